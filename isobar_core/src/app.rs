@@ -2,6 +2,7 @@ use bytes::Bytes;
 use fs;
 use futures::unsync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use futures::{future, Async, Future, IntoFuture, Stream};
+use highlight::{Grammar, GrammarRegistry};
 use never::Never;
 use notify_cell::{NotifyCell, NotifyCellObserver};
 use project::LocalProject;
@@ -34,6 +35,7 @@ pub struct App {
     workspaces: HashMap<WorkspaceId, WorkspaceEntry>,
     next_window_id: WindowId,
     windows: HashMap<WindowId, Window>,
+    grammars: Rc<RefCell<GrammarRegistry>>,
     updates: NotifyCell<()>,
 }
 
@@ -121,6 +123,7 @@ impl App {
             workspaces: HashMap::new(),
             next_window_id: 1,
             windows: HashMap::new(),
+            grammars: GrammarRegistry::new().into_shared(),
             updates: NotifyCell::new(()),
         }.into_shared();
 
@@ -152,6 +155,11 @@ impl App {
         self.headless
     }
 
+    /// Makes the grammar available for highlighting buffers opened from now on.
+    pub fn add_grammar(&mut self, grammar: Grammar) {
+        self.grammars.borrow_mut().add(grammar);
+    }
+
     pub fn open_local_workspace<T: 'static + fs::LocalTree>(&mut self, roots: Vec<T>) {
         let file_provider = self.file_provider.clone();
         let workspace = LocalWorkspace::new(LocalProject::new(file_provider, roots)).into_shared();
@@ -172,6 +180,7 @@ impl App {
             let workspace_view_handle = window.add_view(WorkspaceView::new(
                 self.foreground.clone(),
                 workspace.clone(),
+                self.grammars.clone(),
             ));
             window.set_root_view(workspace_view_handle);
             let window_id = self.next_window_id;
@@ -426,8 +435,10 @@ impl server::Service for AppService {
                 if let Some(workspace) = app.workspaces.get(&workspace_id) {
                     match workspace {
                         &WorkspaceEntry::Local(ref workspace) => {
-                            let service_handle =
-                                connection.add_service(WorkspaceService::new(workspace.clone()));
+                            let service_handle = connection.add_service(WorkspaceService::new(
+                                workspace.clone(),
+                                app.background.clone(),
+                            ));
                             Ok(ServiceResponse::OpenedWorkspace(
                                 service_handle.service_id(),
                             ))
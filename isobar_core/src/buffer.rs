@@ -16,6 +16,11 @@ use std::mem;
 use std::ops::{Add, AddAssign, Range, Sub};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
 use ForegroundExecutor;
 use IntoShared;
 use UserId;
@@ -27,7 +32,7 @@ pub type SelectionSetId = usize;
 type SelectionSetVersion = usize;
 pub type BufferId = usize;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Version(
     #[serde(serialize_with = "serialize_arc", deserialize_with = "deserialize_arc")]
     Arc<HashMap<ReplicaId, LocalTimestamp>>,
@@ -39,6 +44,7 @@ pub enum Error {
     InvalidAnchor,
     InvalidOperation,
     SelectionSetNotFound,
+    NoOpenTransaction,
     IoError(String),
     RpcError(RpcError),
 }
@@ -60,12 +66,22 @@ pub struct Buffer {
     next_local_selection_set_id: SelectionSetId,
     selections: HashMap<(ReplicaId, SelectionSetId), SelectionSet, BuildHasherDefault<SeaHasher>>,
     file: Option<Box<fs::File>>,
+    history: History,
 }
 
 pub struct BufferSnapshot {
     fragments: Tree<Fragment>,
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    /// The replaced range, in the coordinates of the buffer as of the version passed to
+    /// `changes_since`.
+    pub old_range: Range<Point>,
+    /// The range of the replacement, in the current coordinates of the buffer.
+    pub new_range: Range<Point>,
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Hash)]
 pub struct Point {
     pub row: u32,
@@ -112,6 +128,39 @@ pub struct SelectionSetState {
     selections: Vec<Selection>,
 }
 
+// Undo and redo are local to each replica. Reverting a transaction produces ordinary edits that
+// delete the text it inserted and restore the text that only it deleted, so concurrent edits by
+// other replicas are left alone.
+struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    current_transaction: Option<Transaction>,
+    transaction_depth: usize,
+    group_interval: Duration,
+}
+
+struct Transaction {
+    edit_ids: HashSet<EditId>,
+    selections_before: Option<(SelectionSetId, Vec<Selection>)>,
+    selections_after: Option<(SelectionSetId, Vec<Selection>)>,
+    // Edits made outside of an explicit transaction have no timestamps and are never grouped.
+    start: Option<Duration>,
+    end: Option<Duration>,
+}
+
+// Replaces `range` with copies of fragments that a transaction deleted in order to revert part of
+// that transaction.
+struct Reversion {
+    range: Range<usize>,
+    restored: Vec<RestoredFragment>,
+}
+
+struct RestoredFragment {
+    insertion_id: EditId,
+    range: Range<usize>,
+    text: Vec<u16>,
+}
+
 pub struct Iter<'a> {
     fragment_cursor: tree::Cursor<'a, Fragment>,
     fragment_offset: usize,
@@ -243,14 +292,22 @@ impl Version {
     }
 
     fn include(&mut self, insertion: &Insertion) {
+        self.include_edit(insertion.id);
+    }
+
+    fn include_edit(&mut self, id: EditId) {
         let map = Arc::make_mut(&mut self.0);
-        let value = map.entry(insertion.id.replica_id).or_insert(0);
-        *value = cmp::max(*value, insertion.id.timestamp);
+        let value = map.entry(id.replica_id).or_insert(0);
+        *value = cmp::max(*value, id.timestamp);
     }
 
     fn includes(&self, insertion: &Insertion) -> bool {
-        if let Some(timestamp) = self.0.get(&insertion.id.replica_id) {
-            *timestamp >= insertion.id.timestamp
+        self.includes_edit(insertion.id)
+    }
+
+    fn includes_edit(&self, id: EditId) -> bool {
+        if let Some(timestamp) = self.0.get(&id.replica_id) {
+            *timestamp >= id.timestamp
         } else {
             false
         }
@@ -578,6 +635,7 @@ impl Buffer {
             selections: HashMap::default(),
             next_local_selection_set_id: 0,
             file: None,
+            history: History::new(),
         }
     }
 
@@ -638,6 +696,7 @@ impl Buffer {
             selections: selection_sets,
             next_local_selection_set_id: 0,
             file: None,
+            history: History::new(),
         }.into_shared();
 
         let buffer_weak = Rc::downgrade(&buffer);
@@ -774,6 +833,128 @@ impl Buffer {
     }
 
     pub fn edit<'a, I, T>(&mut self, old_ranges: I, new_text: T) -> Vec<Arc<Operation>>
+    where
+        I: IntoIterator<Item = &'a Range<usize>>,
+        T: Into<Text>,
+    {
+        let ops = self.edit_without_history(old_ranges, new_text);
+        if !ops.is_empty() {
+            let edit_ids = ops.iter().map(|op| op.edit_id());
+            if let Some(ref mut transaction) = self.history.current_transaction {
+                transaction.edit_ids.extend(edit_ids);
+            } else {
+                self.history.push(Transaction {
+                    edit_ids: edit_ids.collect(),
+                    selections_before: None,
+                    selections_after: None,
+                    start: None,
+                    end: None,
+                });
+            }
+            self.history.redo_stack.clear();
+        }
+        ops
+    }
+
+    /// Groups the edits made until the matching call to `end_transaction` so that they are undone
+    /// together. When a selection set is given, undoing the transaction restores the selections
+    /// it had when the transaction started. Transactions can be nested, in which case only the
+    /// outermost one takes effect.
+    pub fn start_transaction(
+        &mut self,
+        selection_set_id: Option<SelectionSetId>,
+    ) -> Result<(), Error> {
+        self.start_transaction_at(selection_set_id, now())
+    }
+
+    fn start_transaction_at(
+        &mut self,
+        selection_set_id: Option<SelectionSetId>,
+        now: Duration,
+    ) -> Result<(), Error> {
+        if self.history.transaction_depth == 0 {
+            let selections_before = self.transaction_selections(selection_set_id)?;
+            self.history.current_transaction = Some(Transaction {
+                edit_ids: HashSet::new(),
+                selections_before,
+                selections_after: None,
+                start: Some(now),
+                end: None,
+            });
+        }
+        self.history.transaction_depth += 1;
+        Ok(())
+    }
+
+    /// Ends a transaction. Redoing it restores the selections the given set has now. A
+    /// transaction that starts within the group interval of the end of the previous one is
+    /// merged into it, so that a burst of typing is undone at once.
+    pub fn end_transaction(
+        &mut self,
+        selection_set_id: Option<SelectionSetId>,
+    ) -> Result<(), Error> {
+        self.end_transaction_at(selection_set_id, now())
+    }
+
+    fn end_transaction_at(
+        &mut self,
+        selection_set_id: Option<SelectionSetId>,
+        now: Duration,
+    ) -> Result<(), Error> {
+        if self.history.transaction_depth == 0 {
+            return Err(Error::NoOpenTransaction);
+        }
+
+        let selections_after = self.transaction_selections(selection_set_id)?;
+        self.history.transaction_depth -= 1;
+        if self.history.transaction_depth == 0 {
+            let mut transaction = self.history.current_transaction.take().unwrap();
+            if !transaction.edit_ids.is_empty() {
+                transaction.selections_after = selections_after;
+                transaction.end = Some(now);
+                self.history.push(transaction);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_undo_group_interval(&mut self, interval: Duration) {
+        self.history.group_interval = interval;
+    }
+
+    /// Reverts the most recent transaction, skipping the ones whose effects have since been
+    /// entirely overwritten by other replicas. Does nothing while a transaction is open.
+    pub fn undo(&mut self) -> Vec<Arc<Operation>> {
+        if self.history.transaction_depth > 0 {
+            return Vec::new();
+        }
+
+        while let Some(transaction) = self.history.undo_stack.pop() {
+            let (ops, inverse) = self.revert(transaction);
+            if !ops.is_empty() {
+                self.history.redo_stack.push(inverse);
+                return ops;
+            }
+        }
+        Vec::new()
+    }
+
+    pub fn redo(&mut self) -> Vec<Arc<Operation>> {
+        if self.history.transaction_depth > 0 {
+            return Vec::new();
+        }
+
+        while let Some(transaction) = self.history.redo_stack.pop() {
+            let (ops, inverse) = self.revert(transaction);
+            if !ops.is_empty() {
+                self.history.undo_stack.push(inverse);
+                return ops;
+            }
+        }
+        Vec::new()
+    }
+
+    fn edit_without_history<'a, I, T>(&mut self, old_ranges: I, new_text: T) -> Vec<Arc<Operation>>
     where
         I: IntoIterator<Item = &'a Range<usize>>,
         T: Into<Text>,
@@ -801,6 +982,129 @@ impl Buffer {
         ops
     }
 
+    // Returns the operations that revert the transaction, along with a transaction that reverts
+    // them in turn.
+    fn revert(&mut self, transaction: Transaction) -> (Vec<Arc<Operation>>, Transaction) {
+        let reversions = self.reversions(&transaction.edit_ids);
+
+        // Apply the reversions back to front so that the offsets of the earlier ones stay valid.
+        // Each restored fragment gets an insertion of its own, so that transactions which
+        // inserted the original text can later revert the copy as well.
+        let mut ops = Vec::new();
+        let mut restorations = Vec::new();
+        for reversion in reversions.iter().rev() {
+            let mut old_range = reversion.range.clone();
+            if reversion.restored.is_empty() {
+                ops.extend(self.edit_without_history(iter::once(&old_range), ""));
+            }
+            for fragment in &reversion.restored {
+                let fragment_ops =
+                    self.edit_without_history(iter::once(&old_range), fragment.text.clone());
+                restorations.push((fragment, fragment_ops[0].edit_id()));
+                ops.extend(fragment_ops);
+                let offset = old_range.start + fragment.text.len();
+                old_range = offset..offset;
+            }
+        }
+
+        for &(fragment, new_insertion_id) in &restorations {
+            for entry in self.history
+                .undo_stack
+                .iter_mut()
+                .chain(self.history.redo_stack.iter_mut())
+            {
+                if entry.edit_ids.contains(&fragment.insertion_id) {
+                    entry.edit_ids.insert(new_insertion_id);
+                }
+            }
+        }
+
+        // Anchors into restored text still refer to the deleted fragments, so they are moved to
+        // the copies of those fragments that were just inserted.
+        let selections_before = transaction.selections_before.map(|(set_id, selections)| {
+            let selections = selections
+                .into_iter()
+                .map(|selection| Selection {
+                    start: restored_anchor(selection.start, &restorations),
+                    end: restored_anchor(selection.end, &restorations),
+                    reversed: selection.reversed,
+                    goal_column: selection.goal_column,
+                })
+                .collect::<Vec<_>>();
+            (set_id, selections)
+        });
+        if let Some((set_id, ref selections)) = selections_before {
+            // The selection set may have been removed since the transaction was recorded.
+            let _ = self.mutate_selections(set_id, |_, old_selections| {
+                *old_selections = selections.clone();
+            });
+        }
+
+        let inverse = Transaction {
+            edit_ids: ops.iter().map(|op| op.edit_id()).collect(),
+            selections_before: transaction.selections_after,
+            selections_after: selections_before,
+            start: None,
+            end: None,
+        };
+        (ops, inverse)
+    }
+
+    // Finds the text that was inserted by the given edits and is still visible, along with the
+    // text that was deleted by these edits and nobody else.
+    fn reversions(&self, edit_ids: &HashSet<EditId>) -> Vec<Reversion> {
+        let mut reversions: Vec<Reversion> = Vec::new();
+        let mut offset = 0;
+        for fragment in self.fragments.iter() {
+            let fragment_len = fragment.len();
+            let inserted = edit_ids.contains(&fragment.insertion.id);
+            let restored = !inserted
+                && !fragment.is_visible()
+                && fragment.deletions.iter().all(|id| edit_ids.contains(id));
+
+            if (inserted && fragment.is_visible()) || restored {
+                if reversions
+                    .last()
+                    .map_or(true, |reversion| reversion.range.end != offset)
+                {
+                    reversions.push(Reversion {
+                        range: offset..offset,
+                        restored: Vec::new(),
+                    });
+                }
+
+                let reversion = reversions.last_mut().unwrap();
+                if inserted {
+                    reversion.range.end += fragment_len;
+                } else {
+                    reversion.restored.push(RestoredFragment {
+                        insertion_id: fragment.insertion.id,
+                        range: fragment.start_offset..fragment.end_offset,
+                        text: fragment.insertion.text.code_units
+                            [fragment.start_offset..fragment.end_offset]
+                            .to_vec(),
+                    });
+                }
+            }
+
+            offset += fragment_len;
+        }
+        reversions
+    }
+
+    fn transaction_selections(
+        &self,
+        selection_set_id: Option<SelectionSetId>,
+    ) -> Result<Option<(SelectionSetId, Vec<Selection>)>, Error> {
+        if let Some(set_id) = selection_set_id {
+            let selections = self.selections(set_id)
+                .map_err(|_| Error::SelectionSetNotFound)?;
+            Ok(Some((set_id, selections.to_vec())))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn add_selection_set(
         &mut self,
         user_id: UserId,
@@ -948,6 +1252,56 @@ impl Buffer {
         self.updates.observe()
     }
 
+    /// Describes how the text has changed since the given version, which can be obtained by
+    /// cloning the buffer's `version` field. Changes are returned in the order they appear in the
+    /// buffer and adjacent insertions and deletions are coalesced.
+    pub fn changes_since(&self, version: &Version) -> Vec<Change> {
+        let mut changes: Vec<Change> = Vec::new();
+        let mut old_point = Point::zero();
+        let mut new_point = Point::zero();
+        for fragment in self.fragments.iter() {
+            if fragment.start_offset == fragment.end_offset {
+                continue;
+            }
+
+            let was_visible = version.includes(&fragment.insertion)
+                && !fragment
+                    .deletions
+                    .iter()
+                    .any(|deletion_id| version.includes_edit(*deletion_id));
+            let is_visible = fragment.is_visible();
+            if !was_visible && !is_visible {
+                continue;
+            }
+
+            let extent = fragment.extent_2d();
+            if was_visible && is_visible {
+                old_point += extent;
+                new_point += extent;
+                continue;
+            }
+
+            let is_adjacent = changes.last().map_or(false, |change| {
+                change.old_range.end == old_point && change.new_range.end == new_point
+            });
+            if !is_adjacent {
+                changes.push(Change {
+                    old_range: old_point..old_point,
+                    new_range: new_point..new_point,
+                });
+            }
+            let change = changes.last_mut().unwrap();
+            if was_visible {
+                old_point += extent;
+                change.old_range.end = old_point;
+            } else {
+                new_point += extent;
+                change.new_range.end = new_point;
+            }
+        }
+        changes
+    }
+
     fn broadcast_op(&mut self, op: &Arc<Operation>) {
         for i in (0..self.operation_txs.len()).rev() {
             if self.operation_txs[i].unbounded_send(op.clone()).is_err() {
@@ -982,6 +1336,7 @@ impl Buffer {
                 *timestamp,
             )?,
         }
+        self.version.include_edit(op.edit_id());
         self.anchor_cache.borrow_mut().clear();
         self.offset_cache.borrow_mut().clear();
         self.updates.set(());
@@ -1767,7 +2122,6 @@ impl Point {
         Point { row, column }
     }
 
-    #[cfg(test)]
     pub fn zero() -> Self {
         Point::new(0, 0)
     }
@@ -2412,6 +2766,13 @@ impl Fragment {
         self.deletions.is_empty()
     }
 
+    // Unlike the extent in the fragment's summary, this ignores whether the fragment is visible.
+    fn extent_2d(&self) -> Point {
+        let text = &self.insertion.text;
+        text.point_for_offset(self.end_offset).unwrap()
+            - &text.point_for_offset(self.start_offset).unwrap()
+    }
+
     fn point_for_offset(&self, offset: usize) -> Result<Point, Error> {
         let text = &self.insertion.text;
         let offset_in_insertion = self.start_offset + offset;
@@ -2581,6 +2942,82 @@ impl Operation {
             Operation::Edit { ref id, .. } => id.replica_id,
         }
     }
+
+    fn edit_id(&self) -> EditId {
+        match *self {
+            Operation::Edit { ref id, .. } => *id,
+        }
+    }
+}
+
+impl History {
+    fn new() -> Self {
+        History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            current_transaction: None,
+            transaction_depth: 0,
+            group_interval: Duration::from_millis(300),
+        }
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        if let Some(last_transaction) = self.undo_stack.last_mut() {
+            if let (Some(last_end), Some(start)) = (last_transaction.end, transaction.start) {
+                if start >= last_end && start - last_end < self.group_interval {
+                    last_transaction.edit_ids.extend(transaction.edit_ids);
+                    last_transaction.selections_after = transaction.selections_after;
+                    last_transaction.end = transaction.end;
+                    return;
+                }
+            }
+        }
+        self.undo_stack.push(transaction);
+    }
+}
+
+fn restored_anchor(anchor: Anchor, restorations: &[(&RestoredFragment, EditId)]) -> Anchor {
+    if let AnchorInner::Middle {
+        insertion_id,
+        offset,
+        ref bias,
+    } = anchor.0
+    {
+        for &(fragment, new_insertion_id) in restorations {
+            let contains_anchor = match bias {
+                &AnchorBias::Left => fragment.range.start < offset && offset <= fragment.range.end,
+                &AnchorBias::Right => fragment.range.start <= offset && offset < fragment.range.end,
+            };
+
+            if fragment.insertion_id == insertion_id && contains_anchor {
+                return Anchor(AnchorInner::Middle {
+                    insertion_id: new_insertion_id,
+                    offset: offset - fragment.range.start,
+                    bias: bias.clone(),
+                });
+            }
+        }
+    }
+    anchor
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> Duration {
+    Duration::from_millis(date_now() as u64)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_namespace = Date)]
+extern "C" {
+    #[wasm_bindgen(js_name = now)]
+    fn date_now() -> f64;
 }
 
 fn should_insert_before(
@@ -2660,6 +3097,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_changes_since() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "abc\ndef\nghi");
+        let version = buffer.version.clone();
+        assert_eq!(buffer.changes_since(&version), vec![]);
+
+        buffer.edit(&[1..2], "XY\nZ");
+        buffer.edit(&[10..12], "");
+        assert_eq!(buffer.to_string(), "aXY\nZc\ndefhi");
+        assert_eq!(
+            buffer.changes_since(&version),
+            vec![
+                Change {
+                    old_range: Point::new(0, 1)..Point::new(0, 2),
+                    new_range: Point::new(0, 1)..Point::new(1, 1),
+                },
+                Change {
+                    old_range: Point::new(1, 3)..Point::new(2, 1),
+                    new_range: Point::new(2, 3)..Point::new(2, 3),
+                },
+            ]
+        );
+
+        let version = buffer.version.clone();
+        buffer.edit(&[12..12], "\n");
+        assert_eq!(
+            buffer.changes_since(&version),
+            vec![Change {
+                old_range: Point::new(2, 5)..Point::new(2, 5),
+                new_range: Point::new(2, 5)..Point::new(3, 0),
+            }]
+        );
+    }
+
     #[test]
     fn test_len_for_row() {
         let mut buffer = Buffer::new(0);
@@ -3147,6 +3619,139 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_undo_redo() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "abc");
+        buffer.edit(&[3..3], "def");
+        buffer.edit(&[1..4], "");
+        assert_eq!(buffer.to_string(), "aef");
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abcdef");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abc");
+        buffer.redo();
+        assert_eq!(buffer.to_string(), "abcdef");
+        buffer.redo();
+        assert_eq!(buffer.to_string(), "aef");
+        assert!(buffer.redo().is_empty());
+
+        // Undoing an insertion also removes text that was restored by undoing a later deletion.
+        buffer.undo();
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abc");
+
+        // Editing discards the redo stack.
+        buffer.edit(&[3..3], "ghi");
+        assert!(buffer.redo().is_empty());
+        assert_eq!(buffer.to_string(), "abcghi");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abc");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "");
+        assert!(buffer.undo().is_empty());
+    }
+
+    #[test]
+    fn test_undo_transactions() {
+        let mut buffer = Buffer::new(0);
+        let now = Duration::from_secs(100);
+        buffer.set_undo_group_interval(Duration::from_millis(300));
+
+        buffer.start_transaction_at(None, now).unwrap();
+        buffer.edit(&[0..0], "a");
+        buffer.start_transaction_at(None, now).unwrap();
+        buffer.edit(&[1..1], "b");
+        buffer.end_transaction_at(None, now).unwrap();
+        buffer.edit(&[2..2], "c");
+        assert!(buffer.undo().is_empty());
+        buffer.end_transaction_at(None, now).unwrap();
+        assert_eq!(
+            buffer.end_transaction_at(None, now),
+            Err(Error::NoOpenTransaction)
+        );
+
+        // Transactions that start shortly after the previous one ended are grouped with it.
+        buffer.start_transaction_at(None, now + Duration::from_millis(200)).unwrap();
+        buffer.edit(&[3..3], "d");
+        buffer.end_transaction_at(None, now + Duration::from_millis(200)).unwrap();
+        buffer.start_transaction_at(None, now + Duration::from_millis(600)).unwrap();
+        buffer.edit(&[4..4], "e");
+        buffer.end_transaction_at(None, now + Duration::from_millis(600)).unwrap();
+        assert_eq!(buffer.to_string(), "abcde");
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abcd");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "");
+        buffer.redo();
+        assert_eq!(buffer.to_string(), "abcd");
+
+        // Undo restores the selections from the start of the transaction and redo restores the
+        // ones from its end.
+        let sels = vec![empty_selection(&buffer, 4)];
+        let set_id = buffer.add_selection_set(0, sels);
+        buffer.start_transaction_at(Some(set_id), now + Duration::from_secs(1)).unwrap();
+        buffer.edit(&[1..3], "");
+        buffer
+            .mutate_selections(set_id, |buffer, selections| {
+                *selections = vec![empty_selection(buffer, 1)];
+            })
+            .unwrap();
+        buffer.end_transaction_at(Some(set_id), now + Duration::from_secs(1)).unwrap();
+        assert_eq!(buffer.to_string(), "ad");
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abcd");
+        assert_eq!(selection_offsets(&buffer, set_id), vec![4..4]);
+        buffer.redo();
+        assert_eq!(buffer.to_string(), "ad");
+        assert_eq!(selection_offsets(&buffer, set_id), vec![1..1]);
+    }
+
+    #[test]
+    fn test_undo_concurrent_edits() {
+        let mut buffer_1 = Buffer::new(0);
+        buffer_1.replica_id = 1;
+        let mut buffer_2 = Buffer::new(0);
+        buffer_2.replica_id = 2;
+
+        for op in buffer_1.edit(&[0..0], "abc") {
+            buffer_2.integrate_op(op).unwrap();
+        }
+        for op in buffer_2.edit(&[3..3], "def") {
+            buffer_1.integrate_op(op).unwrap();
+        }
+        for op in buffer_1.edit(&[1..2], "") {
+            buffer_2.integrate_op(op).unwrap();
+        }
+        assert_eq!(buffer_2.to_string(), "acdef");
+
+        let ops_1 = buffer_1.undo();
+        let ops_2 = buffer_2.edit(&[5..5], "g");
+        for op in ops_1 {
+            buffer_2.integrate_op(op).unwrap();
+        }
+        for op in ops_2 {
+            buffer_1.integrate_op(op).unwrap();
+        }
+        assert_eq!(buffer_1.to_string(), "abcdefg");
+        assert_eq!(buffer_2.to_string(), "abcdefg");
+
+        // Each replica only reverts its own edits.
+        for op in buffer_1.undo() {
+            buffer_2.integrate_op(op).unwrap();
+        }
+        assert_eq!(buffer_1.to_string(), "defg");
+        assert_eq!(buffer_2.to_string(), "defg");
+        for op in buffer_2.undo() {
+            buffer_1.integrate_op(op).unwrap();
+        }
+        assert_eq!(buffer_1.to_string(), "def");
+        assert_eq!(buffer_2.to_string(), "def");
+    }
+
     #[test]
     fn test_edit_replication() {
         let local_buffer = Buffer::new(0).into_shared();
@@ -3331,6 +3936,18 @@ mod tests {
         selections
     }
 
+    fn selection_offsets(buffer: &Buffer, set_id: SelectionSetId) -> Vec<Range<usize>> {
+        buffer
+            .selections(set_id)
+            .unwrap()
+            .iter()
+            .map(|selection| {
+                buffer.offset_for_anchor(&selection.start).unwrap()
+                    ..buffer.offset_for_anchor(&selection.end).unwrap()
+            })
+            .collect()
+    }
+
     fn empty_selection(buffer: &Buffer, offset: usize) -> Selection {
         let anchor = buffer.anchor_before_offset(offset).unwrap();
         Selection {
@@ -1,9 +1,13 @@
 use buffer::{self, Buffer, BufferId, Point, Selection, SelectionSetId};
+use display_map::DisplayMap;
+use folding;
 use futures::{Future, Poll, Stream};
+use highlight::{Highlighter, Token, Tokenizer};
 use movement;
 use notify_cell::NotifyCell;
+use search::{self, SearchQuery};
 use serde_json;
-use std::cell::{Cell, Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::cmp::{self, Ordering};
 use std::ops::Range;
 use std::rc::Rc;
@@ -24,12 +28,17 @@ pub struct BufferView {
     height: Option<f64>,
     width: Option<f64>,
     line_height: f64,
+    char_width: Option<f64>,
+    gutter_width: f64,
     scroll_top: f64,
     vertical_margin: u32,
     horizontal_margin: u32,
     vertical_autoscroll: Option<AutoScrollRequest>,
     horizontal_autoscroll: Cell<Option<Range<buffer::Anchor>>>,
     delegate: Option<WeakViewHandle<BufferViewDelegate>>,
+    search: Option<Search>,
+    highlighter: RefCell<Option<Highlighter>>,
+    display_map: RefCell<DisplayMap>,
 }
 
 #[derive(Debug, Eq, PartialEq, Serialize)]
@@ -41,6 +50,12 @@ struct SelectionProps {
     pub remote: bool,
 }
 
+#[derive(Debug, Eq, PartialEq, Serialize)]
+struct SearchMatchProps {
+    pub start: Point,
+    pub end: Point,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 enum BufferViewAction {
@@ -50,12 +65,31 @@ enum BufferViewAction {
     SetDimensions {
         width: u64,
         height: u64,
+        #[serde(default)]
+        char_width: Option<f64>,
+        #[serde(default)]
+        gutter_width: Option<f64>,
     },
     Edit {
         text: String,
     },
     Backspace,
     Delete,
+    Undo,
+    Redo,
+    SetSearchQuery {
+        query: SearchQuery,
+    },
+    ClearSearch,
+    SelectNextMatch,
+    SelectPreviousMatch,
+    SelectAllMatches,
+    ReplaceNextMatch {
+        replacement: String,
+    },
+    ReplaceAllMatches {
+        replacement: String,
+    },
     MoveUp,
     MoveDown,
     MoveLeft,
@@ -80,6 +114,10 @@ enum BufferViewAction {
     SelectLine,
     AddSelectionAbove,
     AddSelectionBelow,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
     SetCursorPosition {
         row: u32,
         column: u32,
@@ -92,6 +130,20 @@ struct AutoScrollRequest {
     center: bool,
 }
 
+// Matches are cached along with the buffer version they were found in, so navigating between
+// them doesn't scan the buffer again. They are found again the first time they are needed after
+// the buffer changes, which includes rendering, so they never go stale.
+struct Search {
+    query: SearchQuery,
+    matcher: search::Matcher,
+    matches: RefCell<SearchMatches>,
+}
+
+struct SearchMatches {
+    version: Option<buffer::Version>,
+    ranges: Vec<Range<usize>>,
+}
+
 impl BufferView {
     pub fn new(
         buffer: Rc<RefCell<Buffer>>,
@@ -115,6 +167,7 @@ impl BufferView {
 
         let updates_tx = NotifyCell::new(());
         let updates_rx = Box::new(updates_tx.observe().select(buffer.borrow().updates()));
+        let display_map = DisplayMap::new(&buffer.borrow(), None);
         Self {
             user_id,
             updates_tx,
@@ -125,12 +178,17 @@ impl BufferView {
             height: None,
             width: None,
             line_height: 10.0,
+            char_width: None,
+            gutter_width: 0.0,
             scroll_top: 0.0,
             vertical_margin: 2,
             horizontal_margin: 4,
             vertical_autoscroll: None,
             horizontal_autoscroll: Cell::new(None),
             delegate,
+            search: None,
+            highlighter: RefCell::new(None),
+            display_map: RefCell::new(display_map),
         }
     }
 
@@ -158,6 +216,26 @@ impl BufferView {
         self
     }
 
+    /// Sets the width of a character, which is used along with the width of the view to determine
+    /// the column at which lines are wrapped.
+    pub fn set_char_width(&mut self, char_width: f64) -> &mut Self {
+        debug_assert!(char_width > 0_f64);
+        self.char_width = Some(char_width);
+        self.autoscroll_to_cursor(false);
+        self.updated();
+        self
+    }
+
+    /// Sets the width taken by the gutter on the left side of the view, which isn't available to
+    /// the text.
+    pub fn set_gutter_width(&mut self, gutter_width: f64) -> &mut Self {
+        debug_assert!(gutter_width >= 0_f64);
+        self.gutter_width = gutter_width;
+        self.autoscroll_to_cursor(false);
+        self.updated();
+        self
+    }
+
     pub fn set_scroll_top(&mut self, scroll_top: f64) -> &mut Self {
         debug_assert!(scroll_top >= 0_f64);
         self.scroll_top = scroll_top;
@@ -168,7 +246,7 @@ impl BufferView {
     }
 
     fn scroll_top(&self) -> f64 {
-        let max_scroll_top = f64::from(self.buffer.borrow().max_point().row) * self.line_height;
+        let max_scroll_top = f64::from(self.display_map().max_point().row) * self.line_height;
         self.scroll_top.min(max_scroll_top)
     }
 
@@ -176,6 +254,38 @@ impl BufferView {
         self.scroll_top() + self.height.unwrap_or(0.0)
     }
 
+    // Lines are wrapped once they reach the width of the view, leaving room for the cursor at the
+    // end of the line. Until the width of a character is known, lines aren't wrapped.
+    fn wrap_column(&self) -> Option<u32> {
+        match (self.width, self.char_width) {
+            (Some(width), Some(char_width)) => {
+                let columns = ((width - self.gutter_width) / char_width).floor() - 1_f64;
+                Some(columns.max(1_f64) as u32)
+            }
+            _ => None,
+        }
+    }
+
+    fn display_map(&self) -> Ref<DisplayMap> {
+        self.display_map_mut();
+        self.display_map.borrow()
+    }
+
+    fn display_map_mut(&self) -> RefMut<DisplayMap> {
+        let buffer = self.buffer.borrow();
+        let mut display_map = self.display_map.borrow_mut();
+        display_map.set_wrap_column(&buffer, self.wrap_column());
+        display_map.refresh(&buffer);
+        display_map
+    }
+
+    pub fn set_tokenizer(&mut self, tokenizer: Rc<Tokenizer>) -> &mut Self {
+        let highlighter = Highlighter::new(tokenizer, &self.buffer.borrow());
+        self.highlighter.replace(Some(highlighter));
+        self.updated();
+        self
+    }
+
     pub fn save(&self) -> Option<Box<Future<Item = (), Error = buffer::Error>>> {
         self.buffer.borrow().save()
     }
@@ -193,6 +303,9 @@ impl BufferView {
             }
 
             let mut buffer = self.buffer.borrow_mut();
+            buffer
+                .start_transaction(Some(self.selection_set_id))
+                .unwrap();
             buffer.edit(&offset_ranges, text);
 
             let text_char_length = text.chars().count();
@@ -219,12 +332,25 @@ impl BufferView {
                         .collect();
                 })
                 .unwrap();
+            buffer.end_transaction(Some(self.selection_set_id)).unwrap();
         }
 
         self.autoscroll_to_cursor(false);
         self.updated();
     }
 
+    pub fn undo(&mut self) {
+        self.buffer.borrow_mut().undo();
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
+    pub fn redo(&mut self) {
+        self.buffer.borrow_mut().redo();
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
     pub fn backspace(&mut self) {
         if self.all_selections_are_empty() {
             self.select_left();
@@ -246,6 +372,233 @@ impl BufferView {
             .all(|selection| selection.is_empty(&buffer))
     }
 
+    pub fn search_query(&self) -> Option<&SearchQuery> {
+        self.search.as_ref().map(|search| &search.query)
+    }
+
+    /// Highlights the matches of the given query and returns how many there are. An invalid query
+    /// clears the current search.
+    pub fn set_search_query(&mut self, query: SearchQuery) -> Result<usize, search::Error> {
+        let matcher = query.matcher();
+        let match_count = match matcher {
+            Ok(matcher) => {
+                self.search = Some(Search {
+                    query,
+                    matcher,
+                    matches: RefCell::new(SearchMatches {
+                        version: None,
+                        ranges: Vec::new(),
+                    }),
+                });
+                Ok(self.search_matches().map_or(0, |matches| matches.len()))
+            }
+            Err(error) => {
+                self.search = None;
+                Err(error)
+            }
+        };
+        self.updated();
+        match_count
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+        self.updated();
+    }
+
+    pub fn select_next_match(&mut self) {
+        let offset = self.last_selection_offsets().end;
+        let next_match = self.search_matches().and_then(|matches| {
+            let index = match matches.binary_search_by(|probe| probe.start.cmp(&offset)) {
+                Ok(index) => index,
+                Err(index) => index,
+            };
+            matches.get(index).or(matches.first()).cloned()
+        });
+        if let Some(range) = next_match {
+            self.select_offset_range(range);
+        }
+    }
+
+    pub fn select_previous_match(&mut self) {
+        let offset = self.last_selection_offsets().start;
+        let previous_match = self.search_matches().and_then(|matches| {
+            let index = match matches.binary_search_by(|probe| probe.end.cmp(&offset)) {
+                Ok(index) => index + 1,
+                Err(index) => index,
+            };
+            if index > 0 {
+                matches.get(index - 1).cloned()
+            } else {
+                matches.last().cloned()
+            }
+        });
+        if let Some(range) = previous_match {
+            self.select_offset_range(range);
+        }
+    }
+
+    pub fn select_all_matches(&mut self) {
+        let matches = self.search_matches().map_or(Vec::new(), |matches| matches.to_vec());
+        if matches.is_empty() {
+            return;
+        }
+
+        self.buffer
+            .borrow_mut()
+            .mutate_selections(self.selection_set_id, |buffer, selections| {
+                *selections = matches
+                    .iter()
+                    .map(|range| Selection {
+                        start: buffer.anchor_before_offset(range.start).unwrap(),
+                        end: buffer.anchor_before_offset(range.end).unwrap(),
+                        reversed: false,
+                        goal_column: None,
+                    })
+                    .collect();
+            })
+            .unwrap();
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
+    /// Replaces the match under the last selection, if there is one, and selects the next match.
+    pub fn replace_next_match(&mut self, replacement: &str) {
+        let selected_range = self.last_selection_offsets();
+        let is_selected = self.search_matches().map_or(false, |matches| {
+            matches
+                .binary_search_by(|probe| probe.start.cmp(&selected_range.start))
+                .ok()
+                .map_or(false, |index| matches[index] == selected_range)
+        });
+        let replacement = if !is_selected {
+            None
+        } else if self.search_expands(replacement) {
+            self.search_replacements(replacement)
+                .into_iter()
+                .find(|(range, _)| *range == selected_range)
+        } else {
+            Some((selected_range, replacement.to_string()))
+        };
+
+        if let Some((range, new_text)) = replacement {
+            let mut buffer = self.buffer.borrow_mut();
+            buffer
+                .start_transaction(Some(self.selection_set_id))
+                .unwrap();
+            buffer.edit(&[range.clone()], new_text.as_str());
+            buffer
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    let offset = range.start + new_text.encode_utf16().count();
+                    let anchor = buffer.anchor_before_offset(offset).unwrap();
+                    selections.clear();
+                    selections.push(Selection {
+                        start: anchor.clone(),
+                        end: anchor,
+                        reversed: false,
+                        goal_column: None,
+                    });
+                })
+                .unwrap();
+            buffer.end_transaction(Some(self.selection_set_id)).unwrap();
+        }
+
+        self.select_next_match();
+        self.updated();
+    }
+
+    /// Replaces every match in a single transaction, so that it can be undone at once.
+    pub fn replace_all_matches(&mut self, replacement: &str) {
+        let replacements = self.search_replacements(replacement);
+        if replacements.is_empty() {
+            return;
+        }
+
+        {
+            let mut buffer = self.buffer.borrow_mut();
+            buffer
+                .start_transaction(Some(self.selection_set_id))
+                .unwrap();
+            for (range, new_text) in replacements.iter().rev() {
+                buffer.edit(&[range.clone()], new_text.as_str());
+            }
+            buffer.end_transaction(Some(self.selection_set_id)).unwrap();
+        }
+
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
+    // The buffer only needs to be scanned again to find replacements that refer to capture groups.
+    // Otherwise, every cached match is replaced with the same text.
+    fn search_replacements(&self, replacement: &str) -> Vec<(Range<usize>, String)> {
+        if self.search_expands(replacement) {
+            let text = self.buffer.borrow().to_u16_chars();
+            self.search
+                .as_ref()
+                .unwrap()
+                .matcher
+                .replacements(&text, replacement)
+        } else {
+            self.search_matches().map_or(Vec::new(), |matches| {
+                matches
+                    .iter()
+                    .map(|range| (range.clone(), replacement.to_string()))
+                    .collect()
+            })
+        }
+    }
+
+    fn search_expands(&self, replacement: &str) -> bool {
+        self.search
+            .as_ref()
+            .map_or(false, |search| search.matcher.expands(replacement))
+    }
+
+    // Returns the offset ranges of the current search's matches, finding them again if the buffer
+    // has changed since they were last found.
+    fn search_matches(&self) -> Option<Ref<Vec<Range<usize>>>> {
+        self.search.as_ref().map(|search| {
+            {
+                let buffer = self.buffer.borrow();
+                let mut matches = search.matches.borrow_mut();
+                if matches.version.as_ref() != Some(&buffer.version) {
+                    matches.ranges = search.matcher.find(&buffer.to_u16_chars());
+                    matches.version = Some(buffer.version.clone());
+                }
+            }
+            Ref::map(search.matches.borrow(), |matches| &matches.ranges)
+        })
+    }
+
+    fn last_selection_offsets(&self) -> Range<usize> {
+        let buffer = self.buffer.borrow();
+        let selection = buffer
+            .selections(self.selection_set_id)
+            .unwrap()
+            .last()
+            .unwrap();
+        buffer.offset_for_anchor(&selection.start).unwrap()
+            ..buffer.offset_for_anchor(&selection.end).unwrap()
+    }
+
+    fn select_offset_range(&mut self, range: Range<usize>) {
+        self.buffer
+            .borrow_mut()
+            .mutate_selections(self.selection_set_id, |buffer, selections| {
+                selections.clear();
+                selections.push(Selection {
+                    start: buffer.anchor_before_offset(range.start).unwrap(),
+                    end: buffer.anchor_before_offset(range.end).unwrap(),
+                    reversed: false,
+                    goal_column: None,
+                });
+            })
+            .unwrap();
+        self.autoscroll_to_selection(false);
+        self.updated();
+    }
+
     pub fn set_selected_anchor_range(
         &mut self,
         range: Range<buffer::Anchor>,
@@ -427,162 +780,190 @@ impl BufferView {
     }
 
     pub fn move_left(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let start = buffer.point_for_anchor(&selection.start).unwrap();
-                    let end = buffer.point_for_anchor(&selection.end).unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let start = buffer.point_for_anchor(&selection.start).unwrap();
+                        let end = buffer.point_for_anchor(&selection.end).unwrap();
 
-                    if start != end {
-                        selection.end = selection.start.clone();
-                    } else {
-                        let cursor = buffer
-                            .anchor_before_point(movement::left(&buffer, start))
-                            .unwrap();
-                        selection.start = cursor.clone();
-                        selection.end = cursor;
+                        if start != end {
+                            selection.end = selection.start.clone();
+                        } else {
+                            let cursor = buffer
+                                .anchor_before_point(movement::left(&display_map, start))
+                                .unwrap();
+                            selection.start = cursor.clone();
+                            selection.end = cursor;
+                        }
+                        selection.reversed = false;
+                        selection.goal_column = None;
                     }
-                    selection.reversed = false;
-                    selection.goal_column = None;
-                }
-            })
-            .unwrap();
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn select_left(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let head = buffer.point_for_anchor(selection.head()).unwrap();
-                    let cursor = buffer
-                        .anchor_before_point(movement::left(&buffer, head))
-                        .unwrap();
-                    selection.set_head(&buffer, cursor);
-                    selection.goal_column = None;
-                }
-            })
-            .unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let head = buffer.point_for_anchor(selection.head()).unwrap();
+                        let cursor = buffer
+                            .anchor_before_point(movement::left(&display_map, head))
+                            .unwrap();
+                        selection.set_head(&buffer, cursor);
+                        selection.goal_column = None;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn move_right(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let start = buffer.point_for_anchor(&selection.start).unwrap();
-                    let end = buffer.point_for_anchor(&selection.end).unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let start = buffer.point_for_anchor(&selection.start).unwrap();
+                        let end = buffer.point_for_anchor(&selection.end).unwrap();
 
-                    if start != end {
-                        selection.start = selection.end.clone();
-                    } else {
-                        let cursor = buffer
-                            .anchor_before_point(movement::right(&buffer, end))
-                            .unwrap();
-                        selection.start = cursor.clone();
-                        selection.end = cursor;
+                        if start != end {
+                            selection.start = selection.end.clone();
+                        } else {
+                            let cursor = buffer
+                                .anchor_before_point(movement::right(&buffer, &display_map, end))
+                                .unwrap();
+                            selection.start = cursor.clone();
+                            selection.end = cursor;
+                        }
+                        selection.reversed = false;
+                        selection.goal_column = None;
                     }
-                    selection.reversed = false;
-                    selection.goal_column = None;
-                }
-            })
-            .unwrap();
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn select_right(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let head = buffer.point_for_anchor(selection.head()).unwrap();
-                    let cursor = buffer
-                        .anchor_before_point(movement::right(&buffer, head))
-                        .unwrap();
-                    selection.set_head(&buffer, cursor);
-                    selection.goal_column = None;
-                }
-            })
-            .unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let head = buffer.point_for_anchor(selection.head()).unwrap();
+                        let cursor = buffer
+                            .anchor_before_point(movement::right(&buffer, &display_map, head))
+                            .unwrap();
+                        selection.set_head(&buffer, cursor);
+                        selection.goal_column = None;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn move_up(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let start = buffer.point_for_anchor(&selection.start).unwrap();
-                    let end = buffer.point_for_anchor(&selection.end).unwrap();
-                    if start != end {
-                        selection.goal_column = None;
-                    }
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let start = buffer.point_for_anchor(&selection.start).unwrap();
+                        let end = buffer.point_for_anchor(&selection.end).unwrap();
+                        if start != end {
+                            selection.goal_column = None;
+                        }
 
-                    let (start, goal_column) = movement::up(&buffer, start, selection.goal_column);
-                    let cursor = buffer.anchor_before_point(start).unwrap();
-                    selection.start = cursor.clone();
-                    selection.end = cursor;
-                    selection.goal_column = goal_column;
-                    selection.reversed = false;
-                }
-            })
-            .unwrap();
+                        let (start, goal_column) =
+                            movement::up(&display_map, start, selection.goal_column);
+                        let cursor = buffer.anchor_before_point(start).unwrap();
+                        selection.start = cursor.clone();
+                        selection.end = cursor;
+                        selection.goal_column = goal_column;
+                        selection.reversed = false;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn select_up(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let head = buffer.point_for_anchor(selection.head()).unwrap();
-                    let (head, goal_column) = movement::up(&buffer, head, selection.goal_column);
-                    selection.set_head(&buffer, buffer.anchor_before_point(head).unwrap());
-                    selection.goal_column = goal_column;
-                }
-            })
-            .unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let head = buffer.point_for_anchor(selection.head()).unwrap();
+                        let (head, goal_column) =
+                            movement::up(&display_map, head, selection.goal_column);
+                        selection.set_head(&buffer, buffer.anchor_before_point(head).unwrap());
+                        selection.goal_column = goal_column;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn move_down(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let start = buffer.point_for_anchor(&selection.start).unwrap();
-                    let end = buffer.point_for_anchor(&selection.end).unwrap();
-                    if start != end {
-                        selection.goal_column = None;
-                    }
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let start = buffer.point_for_anchor(&selection.start).unwrap();
+                        let end = buffer.point_for_anchor(&selection.end).unwrap();
+                        if start != end {
+                            selection.goal_column = None;
+                        }
 
-                    let (start, goal_column) = movement::down(&buffer, end, selection.goal_column);
-                    let cursor = buffer.anchor_before_point(start).unwrap();
-                    selection.start = cursor.clone();
-                    selection.end = cursor;
-                    selection.goal_column = goal_column;
-                    selection.reversed = false;
-                }
-            })
-            .unwrap();
+                        let (start, goal_column) =
+                            movement::down(&display_map, end, selection.goal_column);
+                        let cursor = buffer.anchor_before_point(start).unwrap();
+                        selection.start = cursor.clone();
+                        selection.end = cursor;
+                        selection.goal_column = goal_column;
+                        selection.reversed = false;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
     pub fn select_down(&mut self) {
-        self.buffer
-            .borrow_mut()
-            .mutate_selections(self.selection_set_id, |buffer, selections| {
-                for selection in selections.iter_mut() {
-                    let head = buffer.point_for_anchor(selection.head()).unwrap();
-                    let (head, goal_column) = movement::down(&buffer, head, selection.goal_column);
-                    selection.set_head(&buffer, buffer.anchor_before_point(head).unwrap());
-                    selection.goal_column = goal_column;
-                }
-            })
-            .unwrap();
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let head = buffer.point_for_anchor(selection.head()).unwrap();
+                        let (head, goal_column) =
+                            movement::down(&display_map, head, selection.goal_column);
+                        selection.set_head(&buffer, buffer.anchor_before_point(head).unwrap());
+                        selection.goal_column = goal_column;
+                    }
+                })
+                .unwrap();
+        }
         self.autoscroll_to_cursor(false);
     }
 
@@ -817,6 +1198,100 @@ impl BufferView {
         self.autoscroll_to_cursor(false);
     }
 
+    /// Folds the innermost foldable region containing each cursor.
+    pub fn fold_at_cursors(&mut self) {
+        {
+            let buffer = self.buffer.borrow();
+            let tokens = self.fold_tokens(&buffer);
+            let ranges = buffer
+                .selections(self.selection_set_id)
+                .unwrap()
+                .iter()
+                .filter_map(|selection| {
+                    let head = buffer.point_for_anchor(selection.head()).unwrap();
+                    folding::enclosing_range(&buffer, head.row, tokens.as_ref().map(|t| &t[..]))
+                })
+                .collect::<Vec<_>>();
+            self.display_map_mut().fold(&buffer, ranges);
+        }
+        self.clip_selections_to_folds();
+    }
+
+    /// Unfolds the folds containing each cursor.
+    pub fn unfold_at_cursors(&mut self) {
+        {
+            let buffer = self.buffer.borrow();
+            let rows = buffer
+                .selections(self.selection_set_id)
+                .unwrap()
+                .iter()
+                .map(|selection| buffer.point_for_anchor(selection.head()).unwrap().row)
+                .collect::<Vec<_>>();
+            self.display_map_mut().unfold(&buffer, &rows);
+        }
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
+    pub fn fold_all(&mut self) {
+        {
+            let buffer = self.buffer.borrow();
+            let tokens = self.fold_tokens(&buffer);
+            let ranges = (0..buffer.max_point().row + 1)
+                .filter_map(|row| {
+                    folding::foldable_range(&buffer, row, tokens.as_ref().map(|t| &t[..]))
+                })
+                .collect::<Vec<_>>();
+            self.display_map_mut().fold(&buffer, ranges);
+        }
+        self.clip_selections_to_folds();
+    }
+
+    pub fn unfold_all(&mut self) {
+        {
+            let buffer = self.buffer.borrow();
+            self.display_map_mut().unfold_all(&buffer);
+        }
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
+    // Views with a tokenizer fold by syntax, which requires the tokens of every row.
+    fn fold_tokens(&self, buffer: &Buffer) -> Option<Vec<Vec<Token>>> {
+        let mut highlighter = self.highlighter.borrow_mut();
+        let tokens = highlighter
+            .as_mut()
+            .map(|highlighter| highlighter.tokens(buffer, 0..buffer.max_point().row + 1));
+        tokens
+    }
+
+    // Moves the selection endpoints that were hidden by new folds to the start of their fold.
+    fn clip_selections_to_folds(&mut self) {
+        {
+            let display_map = self.display_map();
+            self.buffer
+                .borrow_mut()
+                .mutate_selections(self.selection_set_id, |buffer, selections| {
+                    for selection in selections.iter_mut() {
+                        let start = buffer.point_for_anchor(&selection.start).unwrap();
+                        let clipped_start = display_map.clip_point(start);
+                        if clipped_start != start {
+                            selection.start = buffer.anchor_before_point(clipped_start).unwrap();
+                        }
+
+                        let end = buffer.point_for_anchor(&selection.end).unwrap();
+                        let clipped_end = display_map.clip_point(end);
+                        if clipped_end != end {
+                            selection.end = buffer.anchor_before_point(clipped_end).unwrap();
+                        }
+                    }
+                })
+                .unwrap();
+        }
+        self.autoscroll_to_cursor(false);
+        self.updated();
+    }
+
     pub fn selections(&self) -> Ref<[Selection]> {
         Ref::map(self.buffer.borrow(), |buffer| {
             buffer.selections(self.selection_set_id).unwrap()
@@ -827,16 +1302,19 @@ impl BufferView {
         self.buffer.borrow().id()
     }
 
-    fn render_selections(&self, range: Range<Point>) -> Vec<SelectionProps> {
+    fn render_selections(&self, display_map: &DisplayMap, rows: Range<u32>) -> Vec<SelectionProps> {
         let buffer = self.buffer.borrow();
+        let range = self.buffer_range_for_display_rows(display_map, rows);
         let mut rendered_selections = Vec::new();
 
         for (user_id, selections) in buffer.remote_selections() {
             for selection in self.query_selections(selections, &range) {
+                let start = buffer.point_for_anchor(&selection.start).unwrap();
+                let end = buffer.point_for_anchor(&selection.end).unwrap();
                 rendered_selections.push(SelectionProps {
                     user_id,
-                    start: buffer.point_for_anchor(&selection.start).unwrap(),
-                    end: buffer.point_for_anchor(&selection.end).unwrap(),
+                    start: display_map.to_display_point(start),
+                    end: display_map.to_display_point(end),
                     reversed: selection.reversed,
                     remote: true,
                 });
@@ -846,10 +1324,12 @@ impl BufferView {
         for selection in
             self.query_selections(&buffer.selections(self.selection_set_id).unwrap(), &range)
         {
+            let start = buffer.point_for_anchor(&selection.start).unwrap();
+            let end = buffer.point_for_anchor(&selection.end).unwrap();
             rendered_selections.push(SelectionProps {
                 user_id: self.user_id,
-                start: buffer.point_for_anchor(&selection.start).unwrap(),
-                end: buffer.point_for_anchor(&selection.end).unwrap(),
+                start: display_map.to_display_point(start),
+                end: display_map.to_display_point(end),
                 reversed: selection.reversed,
                 remote: false,
             });
@@ -858,6 +1338,115 @@ impl BufferView {
         rendered_selections
     }
 
+    fn render_search_matches(
+        &self,
+        display_map: &DisplayMap,
+        rows: Range<u32>,
+    ) -> Vec<SearchMatchProps> {
+        let mut rendered_matches = Vec::new();
+        if let Some(matches) = self.search_matches() {
+            let buffer = self.buffer.borrow();
+            let range = self.buffer_range_for_display_rows(display_map, rows);
+            let offset_for_point = |point| {
+                if point > buffer.max_point() {
+                    buffer.len()
+                } else {
+                    let anchor = buffer.anchor_before_point(point).unwrap();
+                    buffer.offset_for_anchor(&anchor).unwrap()
+                }
+            };
+            let point_for_offset = |offset| {
+                let anchor = buffer.anchor_before_offset(offset).unwrap();
+                buffer.point_for_anchor(&anchor).unwrap()
+            };
+
+            // Matches don't overlap, so both their starts and their ends are sorted.
+            let start_offset = offset_for_point(range.start);
+            let end_offset = offset_for_point(range.end);
+            let start_index = match matches.binary_search_by(|probe| probe.end.cmp(&start_offset)) {
+                Ok(index) => index + 1,
+                Err(index) => index,
+            };
+            for search_match in &matches[start_index..] {
+                if search_match.start >= end_offset {
+                    break;
+                }
+
+                let start = display_map.to_display_point(point_for_offset(search_match.start));
+                let end = display_map.to_display_point(point_for_offset(search_match.end));
+                if start < end {
+                    rendered_matches.push(SearchMatchProps { start, end });
+                }
+            }
+        }
+        rendered_matches
+    }
+
+    // Tokens are computed for whole buffer rows, so they are split between the display rows that
+    // each buffer row was wrapped into.
+    fn render_tokens(
+        &self,
+        display_map: &DisplayMap,
+        rows: Range<u32>,
+    ) -> Option<Vec<Vec<Token>>> {
+        let buffer = self.buffer.borrow();
+        let mut highlighter = self.highlighter.borrow_mut();
+        let tokens = highlighter.as_mut().map(|highlighter| {
+            if rows.start >= rows.end {
+                return Vec::new();
+            }
+
+            let start_row = display_map.buffer_columns(rows.start).0;
+            let end_row = display_map.buffer_columns(rows.end - 1).0 + 1;
+            let line_tokens = highlighter.tokens(&buffer, start_row..end_row);
+            rows.clone()
+                .map(|display_row| {
+                    let (buffer_row, columns) = display_map.buffer_columns(display_row);
+                    line_tokens[(buffer_row - start_row) as usize]
+                        .iter()
+                        .filter_map(|token| {
+                            let start = cmp::max(token.start, columns.start);
+                            let end = cmp::min(token.end, columns.end);
+                            if start < end {
+                                Some(Token {
+                                    start: start - columns.start,
+                                    end: end - columns.start,
+                                    scope: token.scope.clone(),
+                                })
+                            } else {
+                                None
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        });
+        tokens
+    }
+
+    // Returns the text shown on the given display row.
+    fn display_line(&self, buffer: &Buffer, display_map: &DisplayMap, row: u32) -> String {
+        let (buffer_row, columns) = display_map.buffer_columns(row);
+        let line = buffer.line(buffer_row).unwrap_or_default();
+        String::from_utf16_lossy(&line[columns.start as usize..columns.end as usize])
+    }
+
+    // The range of buffer points displayed on the given display rows. Its end lies past the end of
+    // the buffer when the rows extend to the last display row.
+    fn buffer_range_for_display_rows(
+        &self,
+        display_map: &DisplayMap,
+        rows: Range<u32>,
+    ) -> Range<Point> {
+        let start = display_map.to_buffer_point(Point::new(rows.start, 0));
+        let end = if rows.end < display_map.row_count() {
+            display_map.to_buffer_point(Point::new(rows.end, 0))
+        } else {
+            Point::new(self.buffer.borrow().max_point().row + 1, 0)
+        };
+        start..end
+    }
+
     fn query_selections<'a>(
         &self,
         selections: &'a [Selection],
@@ -940,7 +1529,11 @@ impl BufferView {
             let buffer = self.buffer.borrow();
             let start = buffer.point_for_anchor(&range.start)?;
             let end = buffer.point_for_anchor(&range.end)?;
-            (start, end)
+            let display_map = self.display_map();
+            (
+                display_map.to_display_point(start),
+                display_map.to_display_point(end),
+            )
         };
         if let Some(height) = self.height {
             let desired_top;
@@ -990,70 +1583,79 @@ impl View for BufferView {
     }
 
     fn render(&self) -> serde_json::Value {
+        let scroll_top = self.scroll_top();
+        let scroll_bottom = self.scroll_bottom();
+        let display_map = self.display_map();
         let buffer = self.buffer.borrow();
-        let start = Point::new((self.scroll_top() / self.line_height).floor() as u32, 0);
-        let end = Point::new((self.scroll_bottom() / self.line_height).ceil() as u32, 0);
+        let start_row = (scroll_top / self.line_height).floor() as u32;
+        let end_row = cmp::min(
+            (scroll_bottom / self.line_height).ceil() as u32,
+            display_map.row_count(),
+        );
 
         let mut lines = Vec::new();
-        let mut cur_line = Vec::new();
-        let mut cur_row = start.row;
-        for c in buffer.iter_starting_at_point(start) {
-            if c == u16::from(b'\n') {
-                lines.push(String::from_utf16_lossy(&cur_line));
-                cur_line = Vec::new();
-                cur_row += 1;
-                if cur_row >= end.row {
-                    break;
-                }
-            } else {
-                cur_line.push(c);
+        let mut line_numbers = Vec::new();
+        let mut buffer_line: Option<(u32, Vec<u16>)> = None;
+        for row in start_row..end_row {
+            let (buffer_row, columns) = display_map.buffer_columns(row);
+            if buffer_line.as_ref().map_or(true, |line| line.0 != buffer_row) {
+                buffer_line = Some((buffer_row, buffer.line(buffer_row).unwrap_or_default()));
             }
-        }
-        if cur_row < end.row {
-            lines.push(String::from_utf16_lossy(&cur_line));
+            let line = &buffer_line.as_ref().unwrap().1;
+            lines.push(String::from_utf16_lossy(
+                &line[columns.start as usize..columns.end as usize],
+            ));
+            // Only the first display row of each buffer row is numbered in the gutter.
+            line_numbers.push(if columns.start == 0 {
+                Some(buffer_row + 1)
+            } else {
+                None
+            });
         }
 
-        let longest_row = buffer.longest_row();
-        let longest_line = if start.row <= longest_row && longest_row <= end.row {
-            lines[(longest_row - start.row) as usize].clone()
-        } else {
-            String::from_utf16_lossy(&buffer.line(buffer.longest_row()).unwrap())
+        let display_line = |row: u32| {
+            if start_row <= row && row < end_row {
+                lines[(row - start_row) as usize].clone()
+            } else {
+                self.display_line(&buffer, &display_map, row)
+            }
         };
 
+        let longest_row = Point::new(buffer.longest_row(), 0);
+        let longest_line = display_line(display_map.to_display_point(longest_row).row);
+
         let horizontal_autoscroll = self.horizontal_autoscroll.take().map(|range| {
             let scroll_start = buffer.point_for_anchor(&range.start).unwrap();
+            let scroll_start = display_map.to_display_point(scroll_start);
             let scroll_end = buffer.point_for_anchor(&range.end).unwrap();
-            let start_line = if start.row <= scroll_start.row && scroll_start.row <= end.row {
-                lines[(scroll_start.row - start.row) as usize].clone()
-            } else {
-                String::from_utf16_lossy(&buffer.line(scroll_start.row).unwrap())
-            };
-            let end_line = if start.row <= scroll_end.row && scroll_end.row <= end.row {
-                lines[(scroll_end.row - start.row) as usize].clone()
-            } else {
-                String::from_utf16_lossy(&buffer.line(scroll_end.row).unwrap())
-            };
+            let scroll_end = display_map.to_display_point(scroll_end);
 
             json!({
                 "start": scroll_start,
-                "start_line": start_line,
+                "start_line": display_line(scroll_start.row),
                 "end": scroll_end,
-                "end_line": end_line,
+                "end_line": display_line(scroll_end.row),
             })
         });
 
         json!({
-            "first_visible_row": start.row,
-            "total_row_count": buffer.max_point().row + 1,
+            "first_visible_row": start_row,
+            "total_row_count": display_map.row_count(),
             "lines": lines,
+            "line_numbers": line_numbers,
             "longest_line": longest_line,
-            "scroll_top": self.scroll_top(),
+            "scroll_top": scroll_top,
             "horizontal_autoscroll": horizontal_autoscroll,
             "horizontal_margin": self.horizontal_margin,
             "height": self.height,
             "width": self.width,
+            "char_width": self.char_width,
+            "gutter_width": self.gutter_width,
             "line_height": self.line_height,
-            "selections": self.render_selections(start..end),
+            "selections": self.render_selections(&display_map, start_row..end_row),
+            "search_matches": self.render_search_matches(&display_map, start_row..end_row),
+            "tokens": self.render_tokens(&display_map, start_row..end_row),
+            "fold_placeholders": display_map.fold_placeholders(start_row..end_row),
         })
     }
 
@@ -1066,13 +1668,41 @@ impl View for BufferView {
                 }
                 self.set_scroll_top(scroll_top);
             }
-            Ok(BufferViewAction::SetDimensions { width, height }) => {
+            Ok(BufferViewAction::SetDimensions {
+                width,
+                height,
+                char_width,
+                gutter_width,
+            }) => {
+                if let Some(char_width) = char_width {
+                    self.set_char_width(char_width);
+                }
+                if let Some(gutter_width) = gutter_width {
+                    self.set_gutter_width(gutter_width);
+                }
                 self.set_width(width as f64);
                 self.set_height(height as f64);
             }
             Ok(BufferViewAction::Edit { text }) => self.edit(text.as_str()),
             Ok(BufferViewAction::Backspace) => self.backspace(),
             Ok(BufferViewAction::Delete) => self.delete(),
+            Ok(BufferViewAction::Undo) => self.undo(),
+            Ok(BufferViewAction::Redo) => self.redo(),
+            Ok(BufferViewAction::SetSearchQuery { query }) => {
+                if let Err(error) = self.set_search_query(query) {
+                    eprintln!("Invalid search query {:?}", error);
+                }
+            }
+            Ok(BufferViewAction::ClearSearch) => self.clear_search(),
+            Ok(BufferViewAction::SelectNextMatch) => self.select_next_match(),
+            Ok(BufferViewAction::SelectPreviousMatch) => self.select_previous_match(),
+            Ok(BufferViewAction::SelectAllMatches) => self.select_all_matches(),
+            Ok(BufferViewAction::ReplaceNextMatch { replacement }) => {
+                self.replace_next_match(replacement.as_str())
+            }
+            Ok(BufferViewAction::ReplaceAllMatches { replacement }) => {
+                self.replace_all_matches(replacement.as_str())
+            }
             Ok(BufferViewAction::MoveUp) => self.move_up(),
             Ok(BufferViewAction::MoveDown) => self.move_down(),
             Ok(BufferViewAction::MoveLeft) => self.move_left(),
@@ -1097,11 +1727,18 @@ impl View for BufferView {
             Ok(BufferViewAction::SelectLine) => self.select_line(),
             Ok(BufferViewAction::AddSelectionAbove) => self.add_selection_above(),
             Ok(BufferViewAction::AddSelectionBelow) => self.add_selection_below(),
+            Ok(BufferViewAction::Fold) => self.fold_at_cursors(),
+            Ok(BufferViewAction::Unfold) => self.unfold_at_cursors(),
+            Ok(BufferViewAction::FoldAll) => self.fold_all(),
+            Ok(BufferViewAction::UnfoldAll) => self.unfold_all(),
             Ok(BufferViewAction::SetCursorPosition {
                 row,
                 column,
                 autoscroll,
-            }) => self.set_cursor_position(Point::new(row, column), autoscroll),
+            }) => {
+                let position = self.display_map().to_buffer_point(Point::new(row, column));
+                self.set_cursor_position(position, autoscroll);
+            }
             Err(action) => eprintln!("Unrecognized action {:?}", action),
         }
     }
@@ -1802,6 +2439,178 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_undo_redo() {
+        use std::time::Duration;
+
+        let mut editor = BufferView::new(Rc::new(RefCell::new(Buffer::new(0))), 0, None);
+        editor.buffer.borrow_mut().edit(&[0..0], "abc\ndef");
+        editor
+            .buffer
+            .borrow_mut()
+            .set_undo_group_interval(Duration::from_millis(0));
+
+        editor.set_cursor_position(Point::new(0, 3), false);
+        editor.edit("x");
+        editor.set_cursor_position(Point::new(1, 0), false);
+        editor.edit("y");
+        assert_eq!(editor.buffer.borrow().to_string(), "abcx\nydef");
+
+        editor.undo();
+        assert_eq!(editor.buffer.borrow().to_string(), "abcx\ndef");
+        assert_eq!(render_selections(&editor), vec![empty_selection(1, 0)]);
+        editor.undo();
+        assert_eq!(editor.buffer.borrow().to_string(), "abc\ndef");
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 3)]);
+        editor.redo();
+        assert_eq!(editor.buffer.borrow().to_string(), "abcx\ndef");
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 4)]);
+
+        // Typing in quick succession is undone at once.
+        editor
+            .buffer
+            .borrow_mut()
+            .set_undo_group_interval(Duration::from_secs(3600));
+        editor.edit("1");
+        editor.edit("2");
+        assert_eq!(editor.buffer.borrow().to_string(), "abcx12\ndef");
+        editor.undo();
+        assert_eq!(editor.buffer.borrow().to_string(), "abcx\ndef");
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 4)]);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut editor = BufferView::new(Rc::new(RefCell::new(Buffer::new(0))), 0, None);
+        editor
+            .buffer
+            .borrow_mut()
+            .edit(&[0..0], "foo bar\nFoo foo\nbaz");
+        assert_eq!(editor.set_search_query(SearchQuery::literal("foo")), Ok(3));
+        assert_eq!(
+            render_search_matches(&editor, 0..3),
+            vec![
+                search_match((0, 0), (0, 3)),
+                search_match((1, 0), (1, 3)),
+                search_match((1, 4), (1, 7)),
+            ]
+        );
+
+        // Matches follow the text they refer to.
+        editor.buffer.borrow_mut().edit(&[0..0], "\n");
+        assert_eq!(
+            render_search_matches(&editor, 0..4),
+            vec![
+                search_match((1, 0), (1, 3)),
+                search_match((2, 0), (2, 3)),
+                search_match((2, 4), (2, 7)),
+            ]
+        );
+        assert_eq!(
+            render_search_matches(&editor, 2..4),
+            vec![search_match((2, 0), (2, 3)), search_match((2, 4), (2, 7))]
+        );
+
+        editor.select_next_match();
+        assert_eq!(render_selections(&editor), vec![selection((1, 0), (1, 3))]);
+        editor.select_next_match();
+        assert_eq!(render_selections(&editor), vec![selection((2, 0), (2, 3))]);
+        editor.select_next_match();
+        assert_eq!(render_selections(&editor), vec![selection((2, 4), (2, 7))]);
+        editor.select_next_match();
+        assert_eq!(render_selections(&editor), vec![selection((1, 0), (1, 3))]);
+        editor.select_previous_match();
+        assert_eq!(render_selections(&editor), vec![selection((2, 4), (2, 7))]);
+        editor.select_previous_match();
+        assert_eq!(render_selections(&editor), vec![selection((2, 0), (2, 3))]);
+
+        editor.select_all_matches();
+        assert_eq!(
+            render_selections(&editor),
+            vec![
+                selection((1, 0), (1, 3)),
+                selection((2, 0), (2, 3)),
+                selection((2, 4), (2, 7)),
+            ]
+        );
+
+        let query = SearchQuery {
+            regex: true,
+            case_sensitive: true,
+            ..SearchQuery::literal("f(o+)")
+        };
+        assert_eq!(editor.set_search_query(query), Ok(2));
+
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("(")
+        };
+        assert!(editor.set_search_query(query).is_err());
+        assert!(editor.search_query().is_none());
+        assert_eq!(
+            render_search_matches(&editor, 0..4),
+            vec![]
+        );
+
+        // Matches are found again after edits that create or break them.
+        assert_eq!(editor.set_search_query(SearchQuery::literal("foo")), Ok(3));
+        editor.buffer.borrow_mut().edit(&[0..0], "foo");
+        editor.buffer.borrow_mut().edit(&[5..6], "");
+        assert_eq!(editor.buffer.borrow().to_string(), "foo\nfo bar\nFoo foo\nbaz");
+        assert_eq!(
+            render_search_matches(&editor, 0..4),
+            vec![
+                search_match((0, 0), (0, 3)),
+                search_match((2, 0), (2, 3)),
+                search_match((2, 4), (2, 7)),
+            ]
+        );
+        editor.select_next_match();
+        assert_eq!(render_selections(&editor), vec![selection((0, 0), (0, 3))]);
+        editor.select_previous_match();
+        assert_eq!(render_selections(&editor), vec![selection((2, 4), (2, 7))]);
+    }
+
+    #[test]
+    fn test_replace() {
+        use std::time::Duration;
+
+        let mut editor = BufferView::new(Rc::new(RefCell::new(Buffer::new(0))), 0, None);
+        editor
+            .buffer
+            .borrow_mut()
+            .edit(&[0..0], "foo bar\nFoo foo\nbaz");
+        editor
+            .buffer
+            .borrow_mut()
+            .set_undo_group_interval(Duration::from_millis(0));
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("(f)oo")
+        };
+        assert_eq!(editor.set_search_query(query), Ok(3));
+
+        // The first replacement only selects a match, because none was selected.
+        editor.replace_next_match("${1}u");
+        assert_eq!(editor.buffer.borrow().to_string(), "foo bar\nFoo foo\nbaz");
+        assert_eq!(render_selections(&editor), vec![selection((0, 0), (0, 3))]);
+        editor.replace_next_match("${1}u");
+        assert_eq!(editor.buffer.borrow().to_string(), "fu bar\nFoo foo\nbaz");
+        assert_eq!(render_selections(&editor), vec![selection((1, 0), (1, 3))]);
+
+        editor.replace_all_matches("${1}u");
+        assert_eq!(editor.buffer.borrow().to_string(), "fu bar\nFu fu\nbaz");
+        assert_eq!(
+            render_search_matches(&editor, 0..3),
+            vec![]
+        );
+
+        editor.undo();
+        assert_eq!(editor.buffer.borrow().to_string(), "fu bar\nFoo foo\nbaz");
+        editor.undo();
+        assert_eq!(editor.buffer.borrow().to_string(), "foo bar\nFoo foo\nbaz");
+    }
+
     #[test]
     fn test_autoscroll() {
         let mut buffer = Buffer::new(0);
@@ -1926,6 +2735,168 @@ mod tests {
         assert_eq!(frame["selections"], json!([selection((2, 3), (2, 3))]));
     }
 
+    #[test]
+    fn test_soft_wrap() {
+        let line_height = 5.0;
+        let mut editor = BufferView::new(Buffer::new(0).into_shared(), 0, None);
+        editor.buffer.borrow_mut().edit(&[0..0], "abc def ghi\njk");
+        editor
+            .set_height(3.0 * line_height)
+            .set_line_height(line_height)
+            .set_width(5.0)
+            .set_char_width(1.0);
+
+        let frame = editor.render();
+        assert_eq!(frame["total_row_count"], 4);
+        assert_eq!(stringify_lines(&frame["lines"]), vec!["abc ", "def ", "ghi"]);
+        assert_eq!(frame["line_numbers"], json!([1, null, null]));
+
+        // Moving vertically steps through the display rows of wrapped lines.
+        editor.set_cursor_position(Point::new(0, 2), false);
+        editor.move_down();
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 6)]);
+        editor.move_down();
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 10)]);
+        editor.move_down();
+        assert_eq!(render_selections(&editor), vec![empty_selection(1, 2)]);
+        assert_eq!(editor.scroll_top(), 2.0 * line_height);
+
+        editor.move_up();
+        assert_eq!(render_selections(&editor), vec![empty_selection(0, 10)]);
+        assert_eq!(editor.scroll_top(), 0.0);
+        assert_eq!(
+            editor.render()["selections"],
+            json!([selection((2, 2), (2, 2))])
+        );
+
+        // Lines are rewrapped when the view is resized.
+        editor.set_width(20.0);
+        let frame = editor.render();
+        assert_eq!(frame["total_row_count"], 2);
+        assert_eq!(stringify_lines(&frame["lines"]), vec!["abc def ghi", "jk"]);
+        assert_eq!(frame["selections"], json!([selection((0, 10), (0, 10))]));
+    }
+
+    #[test]
+    fn test_folding() {
+        let line_height = 5.0;
+        let mut editor = BufferView::new(Buffer::new(0).into_shared(), 0, None);
+        editor
+            .buffer
+            .borrow_mut()
+            .edit(&[0..0], "a\n  b\n    c\n  d\ne\n  f");
+        editor
+            .set_height(10.0 * line_height)
+            .set_line_height(line_height);
+
+        // Folding hides the region around the cursor and moves the cursor out of it.
+        editor.set_cursor_position(Point::new(2, 2), false);
+        editor.fold_at_cursors();
+        let frame = editor.render();
+        assert_eq!(
+            stringify_lines(&frame["lines"]),
+            vec!["a", "  b", "  d", "e", "  f"]
+        );
+        assert_eq!(frame["line_numbers"], json!([1, 2, 4, 5, 6]));
+        assert_eq!(frame["fold_placeholders"], json!([{"row": 1, "column": 3}]));
+        assert_eq!(frame["selections"], json!([selection((1, 3), (1, 3))]));
+
+        // Moving vertically skips folded rows.
+        editor.move_down();
+        assert_eq!(render_selections(&editor), vec![empty_selection(3, 3)]);
+        editor.move_up();
+        assert_eq!(render_selections(&editor), vec![empty_selection(1, 3)]);
+
+        // Moving horizontally skips them too.
+        editor.move_right();
+        assert_eq!(render_selections(&editor), vec![empty_selection(3, 0)]);
+        editor.move_left();
+        assert_eq!(render_selections(&editor), vec![empty_selection(1, 3)]);
+        editor.select_right();
+        assert_eq!(render_selections(&editor), vec![selection((1, 3), (3, 0))]);
+        editor.move_left();
+        assert_eq!(render_selections(&editor), vec![empty_selection(1, 3)]);
+
+        // Folds follow the text they cover.
+        editor.buffer.borrow_mut().edit(&[0..0], "z\n");
+        assert_eq!(
+            stringify_lines(&editor.render()["lines"]),
+            vec!["z", "a", "  b", "  d", "e", "  f"]
+        );
+
+        editor.fold_all();
+        let frame = editor.render();
+        assert_eq!(stringify_lines(&frame["lines"]), vec!["z", "a", "e"]);
+        assert_eq!(
+            frame["fold_placeholders"],
+            json!([{"row": 1, "column": 1}, {"row": 2, "column": 1}])
+        );
+        assert_eq!(frame["selections"], json!([selection((1, 1), (1, 1))]));
+
+        // Unfolding the outer fold reveals the folds nested in it.
+        editor.unfold_at_cursors();
+        assert_eq!(
+            stringify_lines(&editor.render()["lines"]),
+            vec!["z", "a", "  b", "  d", "e"]
+        );
+
+        editor.unfold_all();
+        assert_eq!(
+            stringify_lines(&editor.render()["lines"]),
+            vec!["z", "a", "  b", "    c", "  d", "e", "  f"]
+        );
+    }
+
+    #[test]
+    fn test_render_tokens() {
+        use highlight::TokenizerState;
+
+        // Scopes each line by its first character.
+        struct FirstCharTokenizer;
+
+        impl Tokenizer for FirstCharTokenizer {
+            fn tokenize_line(&self, line: &str, _: &mut TokenizerState) -> Vec<Token> {
+                line.chars()
+                    .next()
+                    .map(|c| Token {
+                        start: 0,
+                        end: line.encode_utf16().count() as u32,
+                        scope: c.to_string(),
+                    })
+                    .into_iter()
+                    .collect()
+            }
+        }
+
+        let line_height = 4.0;
+        let mut editor = BufferView::new(Buffer::new(0).into_shared(), 0, None);
+        editor.buffer.borrow_mut().edit(&[0..0], "abc\n\ndef");
+        editor
+            .set_height(3.0 * line_height)
+            .set_line_height(line_height);
+        assert_eq!(editor.render()["tokens"], json!(null));
+
+        editor.set_tokenizer(Rc::new(FirstCharTokenizer));
+        assert_eq!(
+            editor.render()["tokens"],
+            json!([
+                [{"start": 0, "end": 3, "scope": "a"}],
+                [],
+                [{"start": 0, "end": 3, "scope": "d"}]
+            ])
+        );
+
+        editor.buffer.borrow_mut().edit(&[4..4], "gh");
+        assert_eq!(
+            editor.render()["tokens"],
+            json!([
+                [{"start": 0, "end": 3, "scope": "a"}],
+                [{"start": 0, "end": 2, "scope": "g"}],
+                [{"start": 0, "end": 3, "scope": "d"}]
+            ])
+        );
+    }
+
     #[test]
     fn test_dropping_view_removes_selection_set() {
         let buffer = Buffer::new(0).into_shared();
@@ -1961,6 +2932,17 @@ mod tests {
             .collect()
     }
 
+    fn render_search_matches(editor: &BufferView, rows: Range<u32>) -> Vec<SearchMatchProps> {
+        editor.render_search_matches(&editor.display_map(), rows)
+    }
+
+    fn search_match(start: (u32, u32), end: (u32, u32)) -> SearchMatchProps {
+        SearchMatchProps {
+            start: Point::new(start.0, start.1),
+            end: Point::new(end.0, end.1),
+        }
+    }
+
     fn empty_selection(row: u32, column: u32) -> SelectionProps {
         SelectionProps {
             user_id: 0,
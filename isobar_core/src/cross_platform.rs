@@ -11,10 +11,10 @@ pub enum PathComponent {
     Unix(Vec<u8>),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct Path(Option<PathState>);
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 enum PathState {
     Unix(Vec<u8>),
 }
@@ -68,6 +68,18 @@ impl Path {
         }
     }
 
+    pub fn file_name(&self) -> Option<PathComponent> {
+        self.0.as_ref().map(|path| match path {
+            &PathState::Unix(ref chars) => {
+                let start = chars
+                    .iter()
+                    .rposition(|c| *c == UNIX_MAIN_SEPARATOR)
+                    .map_or(0, |index| index + 1);
+                PathComponent::Unix(chars[start..].to_vec())
+            }
+        })
+    }
+
     #[cfg(unix)]
     pub fn to_path_buf(&self) -> PathBuf {
         use std::os::unix::ffi::OsStrExt;
@@ -117,17 +129,15 @@ impl<'a> From<&'a OsStr> for PathComponent {
     }
 }
 
-#[cfg(test)]
+// Strings are UTF-8 on every platform, so unlike `OsStr` they can be converted without knowing
+// where we're running. This is what allows in-memory trees to be built on the web.
 impl<'a> From<&'a str> for PathComponent {
-    #[cfg(unix)]
     fn from(string: &'a str) -> Self {
         PathComponent::Unix(string.as_bytes().to_owned())
     }
 }
 
-#[cfg(test)]
 impl<'a> From<&'a str> for Path {
-    #[cfg(unix)]
     fn from(string: &'a str) -> Self {
         Path(Some(PathState::Unix(string.as_bytes().to_owned())))
     }
@@ -0,0 +1,551 @@
+use buffer::{Anchor, Buffer, Point, Version};
+use std::cmp;
+use std::ops::{Add, AddAssign, Range};
+use tree::{self, SeekBias, Tree};
+
+/// Splits the lines of a buffer into display rows that are no wider than a wrap column. Lines are
+/// broken after the last whitespace that fits on a row, or at the wrap column itself when there
+/// is none. Edits only rewrap the lines they touch.
+///
+/// Folds hide the rows following the one on which they start, up to and including the one on
+/// which they end. They are stored as anchor ranges, so they follow the text they cover through
+/// local and remote edits.
+pub struct DisplayMap {
+    wrap_column: Option<u32>,
+    version: Version,
+    // Summarizes the number of display rows of each line, so the display row on which a line
+    // starts is found without visiting the lines before it.
+    lines: Tree<WrappedLine>,
+    folds: Vec<Range<Anchor>>,
+    // The runs of buffer rows hidden by folds, sorted and merged where they overlap or touch.
+    folded_rows: Vec<FoldedRows>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct WrappedLine {
+    len: u32,
+    // The columns at which the display rows following the first one start.
+    wraps: Vec<u32>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct LineSummary {
+    buffer_rows: u32,
+    display_rows: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct BufferRow(u32);
+
+// A display row counted as if nothing were folded.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct UnfoldedRow(u32);
+
+struct FoldedRows {
+    buffer_rows: Range<u32>,
+    // The display rows the buffer rows would occupy if they weren't folded.
+    unfolded_rows: Range<u32>,
+}
+
+impl DisplayMap {
+    pub fn new(buffer: &Buffer, wrap_column: Option<u32>) -> Self {
+        let mut display_map = DisplayMap {
+            wrap_column,
+            version: buffer.version.clone(),
+            lines: Tree::new(),
+            folds: Vec::new(),
+            folded_rows: Vec::new(),
+        };
+        display_map.rewrap(buffer);
+        display_map
+    }
+
+    pub fn set_wrap_column(&mut self, buffer: &Buffer, wrap_column: Option<u32>) {
+        if self.wrap_column != wrap_column {
+            self.wrap_column = wrap_column;
+            self.rewrap(buffer);
+        }
+    }
+
+    /// Rewraps the lines touched by the buffer's changes since the last refresh.
+    pub fn refresh(&mut self, buffer: &Buffer) {
+        if buffer.version == self.version {
+            return;
+        }
+
+        let changes = buffer.changes_since(&self.version);
+        self.version = buffer.version.clone();
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes.iter().rev() {
+            let line_count = self.line_count();
+            let old_start_row = cmp::min(change.old_range.start.row, line_count);
+            let old_end_row = cmp::min(change.old_range.end.row + 1, line_count);
+            let wrap_column = self.wrap_column;
+            self.lines.splice(
+                &BufferRow(old_start_row)..&BufferRow(old_end_row),
+                (change.new_range.start.row..change.new_range.end.row + 1).map(|row| {
+                    WrappedLine::new(&buffer.line(row).unwrap_or_default(), wrap_column)
+                }),
+            );
+        }
+        self.layout(buffer);
+    }
+
+    /// Folds the given ranges. The display map must be up to date with the buffer.
+    pub fn fold<I: IntoIterator<Item = Range<Point>>>(&mut self, buffer: &Buffer, ranges: I) {
+        for range in ranges {
+            if range.start.row < range.end.row && !self.is_folded(buffer, &range) {
+                self.folds.push(
+                    buffer.anchor_after_point(range.start).unwrap()
+                        ..buffer.anchor_before_point(range.end).unwrap(),
+                );
+            }
+        }
+        self.layout(buffer);
+    }
+
+    /// Removes the folds that start on or hide any of the given rows. The display map must be up
+    /// to date with the buffer.
+    pub fn unfold(&mut self, buffer: &Buffer, rows: &[u32]) {
+        self.folds.retain(|fold| {
+            let start = buffer.point_for_anchor(&fold.start).unwrap();
+            let end = buffer.point_for_anchor(&fold.end).unwrap();
+            !rows.iter().any(|row| start.row <= *row && *row <= end.row)
+        });
+        self.layout(buffer);
+    }
+
+    pub fn unfold_all(&mut self, buffer: &Buffer) {
+        self.folds.clear();
+        self.layout(buffer);
+    }
+
+    /// Returns the end of each display row in the given range that is followed by folded rows.
+    pub fn fold_placeholders(&self, rows: Range<u32>) -> Vec<Point> {
+        let mut placeholders = Vec::new();
+        let mut hidden_row_count = 0;
+        for folded in &self.folded_rows {
+            let (line, unfolded_row) = self.line(folded.buffer_rows.start - 1);
+            let row = unfolded_row - hidden_row_count + line.wraps.len() as u32;
+            if row >= rows.end {
+                break;
+            }
+            if rows.start <= row {
+                let row_start = line.wraps.last().cloned().unwrap_or(0);
+                placeholders.push(Point::new(row, line.len - row_start));
+            }
+            hidden_row_count += folded.unfolded_rows.end - folded.unfolded_rows.start;
+        }
+        placeholders
+    }
+
+    pub fn row_count(&self) -> u32 {
+        let hidden_row_count: u32 = self
+            .folded_rows
+            .iter()
+            .map(|folded| folded.unfolded_rows.end - folded.unfolded_rows.start)
+            .sum();
+        self.lines.len::<UnfoldedRow>().0 - hidden_row_count
+    }
+
+    pub fn max_point(&self) -> Point {
+        let row = self.row_count() - 1;
+        let (_, columns) = self.buffer_columns(row);
+        Point::new(row, columns.end - columns.start)
+    }
+
+    /// Returns the buffer row displayed on the given display row, along with the range of its
+    /// columns that the display row covers.
+    pub fn buffer_columns(&self, display_row: u32) -> (u32, Range<u32>) {
+        let display_row = cmp::min(display_row, self.row_count() - 1);
+        let unfolded_row = self.to_unfolded_row(display_row);
+        let mut cursor = self.lines.cursor();
+        cursor.seek(&UnfoldedRow(unfolded_row), SeekBias::Right);
+        let line = cursor.item().unwrap();
+        let buffer_row = cursor.start::<BufferRow>().0;
+        let wrap_index = (unfolded_row - cursor.start::<UnfoldedRow>().0) as usize;
+        let start = if wrap_index == 0 {
+            0
+        } else {
+            line.wraps[wrap_index - 1]
+        };
+        let end = line.wraps.get(wrap_index).cloned().unwrap_or(line.len);
+        (buffer_row, start..end)
+    }
+
+    /// Converts a buffer point to a display point. Points on folded rows are displayed at the end
+    /// of the row on which their fold starts.
+    pub fn to_display_point(&self, point: Point) -> Point {
+        let buffer_row = cmp::min(point.row, self.line_count() - 1);
+        let (buffer_row, column) = match self.folded_rows_containing(buffer_row) {
+            Some(folded) => {
+                let row = folded.buffer_rows.start - 1;
+                (row, self.line(row).0.len)
+            }
+            None => (buffer_row, point.column),
+        };
+        let (line, unfolded_row) = self.line(buffer_row);
+        let wrap_index = line.wraps.iter().take_while(|wrap| **wrap <= column).count();
+        let row_start = if wrap_index == 0 {
+            0
+        } else {
+            line.wraps[wrap_index - 1]
+        };
+        Point::new(
+            self.to_folded_row(unfolded_row) + wrap_index as u32,
+            column - row_start,
+        )
+    }
+
+    /// Converts a display point to a buffer point, clipping it to the display row it is on. The
+    /// last column of a row that was wrapped belongs to the next row, so points on such rows are
+    /// clipped to the column before it.
+    pub fn to_buffer_point(&self, point: Point) -> Point {
+        if point.row >= self.row_count() {
+            return self.to_buffer_point(self.max_point());
+        }
+
+        let (buffer_row, columns) = self.buffer_columns(point.row);
+        let max_column = if columns.end < self.line(buffer_row).0.len {
+            columns.end - 1
+        } else {
+            columns.end
+        };
+        Point::new(
+            buffer_row,
+            cmp::min(columns.start + point.column, max_column),
+        )
+    }
+
+    /// Moves points on folded rows to the start of their fold.
+    pub fn clip_point(&self, point: Point) -> Point {
+        self.to_buffer_point(self.to_display_point(point))
+    }
+
+    fn rewrap(&mut self, buffer: &Buffer) {
+        self.version = buffer.version.clone();
+        let wrap_column = self.wrap_column;
+        self.lines = Tree::new();
+        self.lines.extend(
+            (0..buffer.max_point().row + 1)
+                .map(|row| WrappedLine::new(&buffer.line(row).unwrap_or_default(), wrap_column)),
+        );
+        self.layout(buffer);
+    }
+
+    // Finds the rows hidden by folds, merging the folds that overlap. Folds whose text no longer
+    // spans multiple rows are discarded. This only looks at the folds, so its cost doesn't grow
+    // with the length of the buffer.
+    fn layout(&mut self, buffer: &Buffer) {
+        let mut hidden_rows = Vec::new();
+        self.folds.retain(|fold| {
+            let start = buffer.point_for_anchor(&fold.start).unwrap();
+            let end = buffer.point_for_anchor(&fold.end).unwrap();
+            if start.row < end.row {
+                hidden_rows.push(start.row + 1..end.row + 1);
+                true
+            } else {
+                false
+            }
+        });
+        hidden_rows.sort_by_key(|rows| rows.start);
+
+        let mut merged_rows: Vec<Range<u32>> = Vec::new();
+        for rows in hidden_rows {
+            if let Some(last_rows) = merged_rows.last_mut() {
+                if rows.start <= last_rows.end {
+                    last_rows.end = cmp::max(last_rows.end, rows.end);
+                    continue;
+                }
+            }
+            merged_rows.push(rows);
+        }
+
+        let folded_rows = merged_rows
+            .into_iter()
+            .map(|buffer_rows| FoldedRows {
+                unfolded_rows: self.unfolded_row_for_buffer_row(buffer_rows.start)
+                    ..self.unfolded_row_for_buffer_row(buffer_rows.end),
+                buffer_rows,
+            })
+            .collect();
+        self.folded_rows = folded_rows;
+    }
+
+    fn is_folded(&self, buffer: &Buffer, range: &Range<Point>) -> bool {
+        self.folds.iter().any(|fold| {
+            buffer.point_for_anchor(&fold.start).unwrap().row == range.start.row
+                && buffer.point_for_anchor(&fold.end).unwrap().row == range.end.row
+        })
+    }
+
+    fn line_count(&self) -> u32 {
+        self.lines.len::<BufferRow>().0
+    }
+
+    // Returns the line on the given buffer row along with the unfolded row on which it starts.
+    fn line(&self, buffer_row: u32) -> (&WrappedLine, u32) {
+        let mut cursor = self.lines.cursor();
+        cursor.seek(&BufferRow(buffer_row), SeekBias::Right);
+        (cursor.item().unwrap(), cursor.start::<UnfoldedRow>().0)
+    }
+
+    fn unfolded_row_for_buffer_row(&self, buffer_row: u32) -> u32 {
+        let mut cursor = self.lines.cursor();
+        cursor.seek(&BufferRow(buffer_row), SeekBias::Right);
+        cursor.start::<UnfoldedRow>().0
+    }
+
+    fn folded_rows_containing(&self, buffer_row: u32) -> Option<&FoldedRows> {
+        self.folded_rows.iter().find(|folded| {
+            folded.buffer_rows.start <= buffer_row && buffer_row < folded.buffer_rows.end
+        })
+    }
+
+    // Converts the unfolded row of a display row that isn't hidden by a fold.
+    fn to_folded_row(&self, unfolded_row: u32) -> u32 {
+        let hidden_row_count: u32 = self
+            .folded_rows
+            .iter()
+            .take_while(|folded| folded.unfolded_rows.end <= unfolded_row)
+            .map(|folded| folded.unfolded_rows.end - folded.unfolded_rows.start)
+            .sum();
+        unfolded_row - hidden_row_count
+    }
+
+    fn to_unfolded_row(&self, display_row: u32) -> u32 {
+        let mut unfolded_row = display_row;
+        for folded in &self.folded_rows {
+            if folded.unfolded_rows.start <= unfolded_row {
+                unfolded_row += folded.unfolded_rows.end - folded.unfolded_rows.start;
+            } else {
+                break;
+            }
+        }
+        unfolded_row
+    }
+}
+
+impl WrappedLine {
+    fn new(line: &[u16], wrap_column: Option<u32>) -> Self {
+        let mut wraps = Vec::new();
+        if let Some(wrap_column) = wrap_column {
+            let wrap_column = cmp::max(wrap_column, 1) as usize;
+            let mut row_start = 0;
+            while line.len() - row_start > wrap_column {
+                let max_wrap = row_start + wrap_column;
+                let mut wrap = (row_start + 1..max_wrap + 1)
+                    .rev()
+                    .find(|column| is_whitespace(line[column - 1]))
+                    .unwrap_or(max_wrap);
+                if is_low_surrogate(line[wrap]) && wrap > row_start + 1 {
+                    wrap -= 1;
+                }
+                wraps.push(wrap as u32);
+                row_start = wrap;
+            }
+        }
+
+        WrappedLine {
+            len: line.len() as u32,
+            wraps,
+        }
+    }
+}
+
+impl tree::Item for WrappedLine {
+    type Summary = LineSummary;
+
+    fn summarize(&self) -> Self::Summary {
+        LineSummary {
+            buffer_rows: 1,
+            display_rows: self.wraps.len() as u32 + 1,
+        }
+    }
+}
+
+impl<'a> AddAssign<&'a LineSummary> for LineSummary {
+    fn add_assign(&mut self, other: &Self) {
+        self.buffer_rows += other.buffer_rows;
+        self.display_rows += other.display_rows;
+    }
+}
+
+impl tree::Dimension for BufferRow {
+    type Summary = LineSummary;
+
+    fn from_summary(summary: &Self::Summary) -> Self {
+        BufferRow(summary.buffer_rows)
+    }
+}
+
+impl<'a> Add<&'a Self> for BufferRow {
+    type Output = BufferRow;
+
+    fn add(self, other: &'a Self) -> Self::Output {
+        BufferRow(self.0 + other.0)
+    }
+}
+
+impl tree::Dimension for UnfoldedRow {
+    type Summary = LineSummary;
+
+    fn from_summary(summary: &Self::Summary) -> Self {
+        UnfoldedRow(summary.display_rows)
+    }
+}
+
+impl<'a> Add<&'a Self> for UnfoldedRow {
+    type Output = UnfoldedRow;
+
+    fn add(self, other: &'a Self) -> Self::Output {
+        UnfoldedRow(self.0 + other.0)
+    }
+}
+
+fn is_whitespace(c: u16) -> bool {
+    c == u16::from(b' ') || c == u16::from(b'\t')
+}
+
+fn is_low_surrogate(c: u16) -> bool {
+    c >= 0xDC00 && c <= 0xDFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapping() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "abc defg hijklmnop\n\nqrs");
+        let mut display_map = DisplayMap::new(&buffer, None);
+        assert_eq!(display_map.row_count(), 3);
+        assert_eq!(display_map.max_point(), Point::new(2, 3));
+
+        display_map.set_wrap_column(&buffer, Some(6));
+        assert_eq!(
+            display_rows(&display_map),
+            vec![
+                (0, 0..4),
+                (0, 4..9),
+                (0, 9..15),
+                (0, 15..18),
+                (1, 0..0),
+                (2, 0..3),
+            ]
+        );
+        assert_eq!(display_map.max_point(), Point::new(5, 3));
+
+        assert_eq!(display_map.to_display_point(Point::new(0, 3)), Point::new(0, 3));
+        assert_eq!(display_map.to_display_point(Point::new(0, 4)), Point::new(1, 0));
+        assert_eq!(display_map.to_display_point(Point::new(0, 18)), Point::new(3, 3));
+        assert_eq!(display_map.to_display_point(Point::new(2, 1)), Point::new(5, 1));
+
+        assert_eq!(display_map.to_buffer_point(Point::new(1, 2)), Point::new(0, 6));
+        // Points past the end of a wrapped row are clipped to the row.
+        assert_eq!(display_map.to_buffer_point(Point::new(0, 10)), Point::new(0, 3));
+        assert_eq!(display_map.to_buffer_point(Point::new(3, 10)), Point::new(0, 18));
+        assert_eq!(display_map.to_buffer_point(Point::new(4, 10)), Point::new(1, 0));
+        assert_eq!(display_map.to_buffer_point(Point::new(10, 0)), Point::new(2, 3));
+
+        buffer.edit(&[19..19], "tu vwxyz");
+        buffer.edit(&[4..9], "");
+        display_map.refresh(&buffer);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![
+                (0, 0..4),
+                (0, 4..10),
+                (0, 10..13),
+                (1, 0..3),
+                (1, 3..8),
+                (2, 0..3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_folding() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "abc\ndef\nghi\njkl\nmno");
+        let mut display_map = DisplayMap::new(&buffer, None);
+        display_map.fold(&buffer, vec![Point::new(0, 3)..Point::new(2, 3)]);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![(0, 0..3), (3, 0..3), (4, 0..3)]
+        );
+        assert_eq!(display_map.fold_placeholders(0..3), vec![Point::new(0, 3)]);
+        assert_eq!(display_map.fold_placeholders(1..3), vec![]);
+
+        // Points in folded rows are displayed where the fold starts.
+        assert_eq!(display_map.to_display_point(Point::new(1, 1)), Point::new(0, 3));
+        assert_eq!(display_map.to_display_point(Point::new(3, 2)), Point::new(1, 2));
+        assert_eq!(display_map.to_buffer_point(Point::new(1, 1)), Point::new(3, 1));
+
+        // Folds follow the text they cover.
+        buffer.edit(&[7..7], "\n");
+        display_map.refresh(&buffer);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![(0, 0..3), (4, 0..3), (5, 0..3)]
+        );
+
+        // Folds that no longer span multiple rows are discarded.
+        buffer.edit(&[3..12], "");
+        display_map.refresh(&buffer);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![(0, 0..3), (1, 0..3), (2, 0..3)]
+        );
+        buffer.edit(&[3..3], "\n");
+        display_map.refresh(&buffer);
+        assert_eq!(display_map.row_count(), 4);
+
+        display_map.fold(&buffer, vec![Point::new(1, 0)..Point::new(3, 3)]);
+        assert_eq!(display_map.row_count(), 2);
+        display_map.unfold(&buffer, &[2]);
+        assert_eq!(display_map.row_count(), 4);
+    }
+
+    #[test]
+    fn test_folding_wrapped_lines() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "abc def\nghi\njkl mno\npqr");
+        let mut display_map = DisplayMap::new(&buffer, Some(4));
+        display_map.fold(&buffer, vec![Point::new(0, 7)..Point::new(1, 3)]);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![(0, 0..4), (0, 4..7), (2, 0..4), (2, 4..7), (3, 0..3)]
+        );
+        assert_eq!(display_map.fold_placeholders(0..5), vec![Point::new(1, 3)]);
+        assert_eq!(display_map.to_display_point(Point::new(1, 2)), Point::new(1, 3));
+        assert_eq!(display_map.to_display_point(Point::new(2, 5)), Point::new(3, 1));
+        assert_eq!(display_map.to_buffer_point(Point::new(2, 1)), Point::new(2, 1));
+
+        buffer.edit(&[23..23], " stu");
+        display_map.refresh(&buffer);
+        display_map.refresh(&buffer);
+        assert_eq!(
+            display_rows(&display_map),
+            vec![
+                (0, 0..4),
+                (0, 4..7),
+                (2, 0..4),
+                (2, 4..7),
+                (3, 0..4),
+                (3, 4..7),
+            ]
+        );
+        assert_eq!(display_map.to_display_point(Point::new(3, 6)), Point::new(5, 2));
+    }
+
+    fn display_rows(display_map: &DisplayMap) -> Vec<(u32, Range<u32>)> {
+        (0..display_map.row_count())
+            .map(|row| display_map.buffer_columns(row))
+            .collect()
+    }
+}
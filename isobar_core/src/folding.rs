@@ -0,0 +1,228 @@
+use buffer::{Buffer, Point};
+use highlight::Token;
+use std::ops::Range;
+
+/// Returns the range that can be folded starting on the given row, if any. When tokens are
+/// supplied for every row of the buffer, a row opening brackets that are closed on a later row
+/// folds up to the row before the closing bracket, ignoring brackets in strings and comments.
+/// Otherwise, or when no such bracket is found, a row folds the rows that follow it and are
+/// indented further.
+pub fn foldable_range(
+    buffer: &Buffer,
+    row: u32,
+    tokens: Option<&[Vec<Token>]>,
+) -> Option<Range<Point>> {
+    tokens
+        .and_then(|tokens| syntax_range(buffer, row, tokens))
+        .or_else(|| indentation_range(buffer, row))
+}
+
+/// Returns the innermost foldable range that starts on the given row or hides it. Walking back
+/// from the row, only the rows that could start such a range are tried: those indented less than
+/// every row after them, and those opening a bracket that is still open on the given row. Other
+/// rows fold at most up to the row before it.
+pub fn enclosing_range(
+    buffer: &Buffer,
+    row: u32,
+    tokens: Option<&[Vec<Token>]>,
+) -> Option<Range<Point>> {
+    let mut min_indentation = None;
+    let mut depth = 0;
+    for start_row in (0..row + 1).rev() {
+        let line = buffer.line(start_row).ok()?;
+        let mut can_enclose = false;
+        if let Some(indentation) = indentation(&line) {
+            if min_indentation.map_or(true, |min_indentation| indentation < min_indentation) {
+                min_indentation = Some(indentation);
+                can_enclose = true;
+            }
+        }
+        if let Some(tokens) = tokens {
+            for (column, c) in line.iter().enumerate().rev() {
+                if is_code(&tokens[start_row as usize], column as u32) {
+                    if is_close_bracket(*c) {
+                        depth += 1;
+                    } else if is_open_bracket(*c) {
+                        if depth == 0 {
+                            can_enclose = true;
+                        } else {
+                            depth -= 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        if can_enclose {
+            let range = foldable_range(buffer, start_row, tokens);
+            if let Some(range) = range.filter(|range| range.end.row >= row) {
+                return Some(range);
+            }
+        }
+    }
+    None
+}
+
+fn syntax_range(buffer: &Buffer, row: u32, tokens: &[Vec<Token>]) -> Option<Range<Point>> {
+    let line = buffer.line(row).ok()?;
+    let mut open_columns = Vec::new();
+    for (column, c) in line.iter().enumerate() {
+        if is_code(&tokens[row as usize], column as u32) {
+            if is_open_bracket(*c) {
+                open_columns.push(column as u32);
+            } else if is_close_bracket(*c) {
+                open_columns.pop();
+            }
+        }
+    }
+
+    // Fold from the outermost bracket that is left open at the end of the row.
+    let open_column = *open_columns.first()?;
+    let close_row = closing_bracket_row(buffer, row + 1, open_columns.len(), tokens)?;
+    if close_row > row + 1 {
+        let end_row = close_row - 1;
+        let end_column = buffer.len_for_row(end_row).unwrap();
+        Some(Point::new(row, open_column + 1)..Point::new(end_row, end_column))
+    } else {
+        None
+    }
+}
+
+// Returns the row on which the given number of open brackets are closed, starting the search at
+// the given row.
+fn closing_bracket_row(
+    buffer: &Buffer,
+    start_row: u32,
+    mut depth: usize,
+    tokens: &[Vec<Token>],
+) -> Option<u32> {
+    for row in start_row..buffer.max_point().row + 1 {
+        let line = buffer.line(row).unwrap_or_default();
+        for (column, c) in line.iter().enumerate() {
+            if is_code(&tokens[row as usize], column as u32) {
+                if is_open_bracket(*c) {
+                    depth += 1;
+                } else if is_close_bracket(*c) {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(row);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+fn indentation_range(buffer: &Buffer, row: u32) -> Option<Range<Point>> {
+    let row_indentation = indentation(&buffer.line(row).ok()?)?;
+    let mut end_row = None;
+    for next_row in row + 1..buffer.max_point().row + 1 {
+        // Blank lines belong to the fold only if more indented lines follow them.
+        match indentation(&buffer.line(next_row).unwrap_or_default()) {
+            Some(next_indentation) if next_indentation > row_indentation => {
+                end_row = Some(next_row)
+            }
+            Some(_) => break,
+            None => {}
+        }
+    }
+
+    end_row.map(|end_row| {
+        let start = Point::new(row, buffer.len_for_row(row).unwrap());
+        let end = Point::new(end_row, buffer.len_for_row(end_row).unwrap());
+        start..end
+    })
+}
+
+// The number of whitespace characters at the start of the line, or `None` for blank lines.
+fn indentation(line: &[u16]) -> Option<usize> {
+    line.iter().position(|c| *c != u16::from(b' ') && *c != u16::from(b'\t'))
+}
+
+fn is_code(tokens: &[Token], column: u32) -> bool {
+    !tokens.iter().any(|token| {
+        token.start <= column
+            && column < token.end
+            && (token.scope.starts_with("string") || token.scope.starts_with("comment"))
+    })
+}
+
+fn is_open_bracket(c: u16) -> bool {
+    c == u16::from(b'{') || c == u16::from(b'[') || c == u16::from(b'(')
+}
+
+fn is_close_bracket(c: u16) -> bool {
+    c == u16::from(b'}') || c == u16::from(b']') || c == u16::from(b')')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indentation_range() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "a\n  b\n\n    c\n  d\n\ne\n  f");
+        assert_eq!(
+            foldable_range(&buffer, 0, None),
+            Some(Point::new(0, 1)..Point::new(4, 3))
+        );
+        assert_eq!(
+            foldable_range(&buffer, 1, None),
+            Some(Point::new(1, 3)..Point::new(3, 5))
+        );
+        assert_eq!(foldable_range(&buffer, 2, None), None);
+        assert_eq!(foldable_range(&buffer, 4, None), None);
+        assert_eq!(
+            foldable_range(&buffer, 6, None),
+            Some(Point::new(6, 1)..Point::new(7, 3))
+        );
+
+        assert_eq!(
+            enclosing_range(&buffer, 3, None),
+            Some(Point::new(1, 3)..Point::new(3, 5))
+        );
+        assert_eq!(
+            enclosing_range(&buffer, 4, None),
+            Some(Point::new(0, 1)..Point::new(4, 3))
+        );
+        assert_eq!(enclosing_range(&buffer, 5, None), None);
+    }
+
+    #[test]
+    fn test_syntax_range() {
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "f(a, {\n\"}\"\nb\n}) {\nc\n}\n{\n}");
+        let mut tokens = vec![Vec::new(); 8];
+        tokens[1].push(Token {
+            start: 0,
+            end: 3,
+            scope: "string.quoted".to_string(),
+        });
+
+        assert_eq!(
+            foldable_range(&buffer, 0, Some(&tokens[..])),
+            Some(Point::new(0, 2)..Point::new(2, 1))
+        );
+        assert_eq!(
+            foldable_range(&buffer, 3, Some(&tokens[..])),
+            Some(Point::new(3, 4)..Point::new(4, 1))
+        );
+        // Brackets closed on the following row leave nothing to fold.
+        assert_eq!(foldable_range(&buffer, 6, Some(&tokens[..])), None);
+
+        assert_eq!(
+            enclosing_range(&buffer, 2, Some(&tokens[..])),
+            Some(Point::new(0, 2)..Point::new(2, 1))
+        );
+        assert_eq!(
+            enclosing_range(&buffer, 4, Some(&tokens[..])),
+            Some(Point::new(3, 4)..Point::new(4, 1))
+        );
+        assert_eq!(enclosing_range(&buffer, 5, Some(&tokens[..])), None);
+
+        // Without tokens, rows are folded by indentation.
+        assert_eq!(foldable_range(&buffer, 0, None), None);
+    }
+}
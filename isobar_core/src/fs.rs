@@ -31,6 +31,13 @@ pub trait LocalTree: Tree {
 pub trait FileProvider {
     fn open(&self, path: &cross_platform::Path)
         -> Box<Future<Item = Box<File>, Error = io::Error>>;
+    fn content_reader(&self) -> Arc<ContentReader>;
+}
+
+// Project-wide searches read many files on a background thread, so they can't go through the
+// asynchronous `File` interface.
+pub trait ContentReader: Send + Sync {
+    fn read(&self, path: &cross_platform::Path) -> Result<String, io::Error>;
 }
 
 pub trait File {
@@ -401,6 +408,8 @@ pub(crate) mod tests {
         content: String,
     }
 
+    struct TestContentReader(HashMap<PathBuf, String>);
+
     struct NextTick(bool);
 
     impl TestTree {
@@ -531,6 +540,25 @@ pub(crate) mod tests {
                     .into_future()
             }))
         }
+
+        fn content_reader(&self) -> Arc<ContentReader> {
+            let files = self.0
+                .borrow()
+                .files
+                .iter()
+                .map(|(path, file)| (path.clone(), file.0.borrow().content.clone()))
+                .collect();
+            Arc::new(TestContentReader(files))
+        }
+    }
+
+    impl ContentReader for TestContentReader {
+        fn read(&self, path: &cross_platform::Path) -> Result<String, io::Error> {
+            self.0
+                .get(&path.to_path_buf())
+                .cloned()
+                .ok_or(io::Error::new(io::ErrorKind::NotFound, "Path not found"))
+        }
     }
 
     impl File for TestFile {
@@ -0,0 +1,788 @@
+use buffer::{Buffer, Version};
+use cross_platform;
+use regex::{CaptureLocations, Regex, RegexBuilder};
+use search::Utf16Offsets;
+use serde_json;
+use std::cmp;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+pub trait Tokenizer {
+    /// Splits a line, which doesn't include its trailing newline, into scoped tokens. The state
+    /// describes the constructs left open by the previous lines and must be updated to describe
+    /// the ones left open at the end of this line.
+    fn tokenize_line(&self, line: &str, state: &mut TokenizerState) -> Vec<Token>;
+}
+
+/// The stack of constructs, such as block comments or strings, that are open at a line boundary.
+/// Tokenizers decide what the entries mean.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenizerState(pub Vec<usize>);
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Token {
+    /// The first column of the token, in UTF-16 code units.
+    pub start: u32,
+    /// The column following the last one of the token, in UTF-16 code units.
+    pub end: u32,
+    pub scope: String,
+}
+
+/// Caches the tokens of a buffer's lines. Lines are tokenized lazily when they are requested and
+/// edits only invalidate the lines they touch, along with any following lines whose starting
+/// state changes as a result.
+pub struct Highlighter {
+    tokenizer: Rc<Tokenizer>,
+    version: Version,
+    lines: Vec<HighlightedLine>,
+    // Every line before this row has been tokenized starting from the right state.
+    valid_rows: usize,
+}
+
+struct HighlightedLine {
+    start_state: TokenizerState,
+    end_state: TokenizerState,
+    tokens: Vec<Token>,
+    dirty: bool,
+}
+
+/// A TextMate grammar, as found in `.tmLanguage.json` files. Patterns that can't be compiled by
+/// the `regex` crate, such as ones relying on look-around or backreferences, are ignored along
+/// with includes that refer to other grammars.
+pub struct Grammar {
+    name: String,
+    scope_name: String,
+    file_types: Vec<String>,
+    rules: Vec<Rule>,
+    // The patterns that can match inside each rule, with includes expanded. The root rule lists
+    // the top-level patterns of the grammar.
+    rule_patterns: Vec<Vec<RuleId>>,
+}
+
+#[derive(Default)]
+pub struct GrammarRegistry {
+    grammars: Vec<Rc<Grammar>>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    InvalidGrammar(String),
+}
+
+type RuleId = usize;
+
+const ROOT_RULE_ID: RuleId = 0;
+
+enum Rule {
+    Match {
+        regex: Regex,
+        name: Option<String>,
+        captures: Vec<(usize, String)>,
+    },
+    BeginEnd {
+        begin: Regex,
+        end: Regex,
+        name: Option<String>,
+        content_name: Option<String>,
+        begin_captures: Vec<(usize, String)>,
+        end_captures: Vec<(usize, String)>,
+    },
+    Include(RuleId),
+    Patterns,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawGrammar {
+    #[serde(default)]
+    name: String,
+    scope_name: String,
+    #[serde(default)]
+    file_types: Vec<String>,
+    #[serde(default)]
+    patterns: Vec<RawRule>,
+    #[serde(default)]
+    repository: HashMap<String, RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRule {
+    #[serde(rename = "match")]
+    match_pattern: Option<String>,
+    begin: Option<String>,
+    end: Option<String>,
+    name: Option<String>,
+    content_name: Option<String>,
+    #[serde(default)]
+    captures: HashMap<String, RawCapture>,
+    begin_captures: Option<HashMap<String, RawCapture>>,
+    end_captures: Option<HashMap<String, RawCapture>>,
+    #[serde(default)]
+    patterns: Vec<RawRule>,
+    include: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawCapture {
+    name: Option<String>,
+}
+
+struct GrammarCompiler<'a> {
+    rules: Vec<Option<Rule>>,
+    children: Vec<Vec<RuleId>>,
+    repository_ids: HashMap<&'a str, RuleId>,
+}
+
+// Converts the byte ranges matched in a line into tokens measured in UTF-16 code units.
+struct TokenBuilder<'a> {
+    offsets: Utf16Offsets<'a>,
+    line_len: u32,
+    tokens: Vec<Token>,
+}
+
+impl Highlighter {
+    pub fn new(tokenizer: Rc<Tokenizer>, buffer: &Buffer) -> Self {
+        Highlighter {
+            tokenizer,
+            version: buffer.version.clone(),
+            lines: Vec::new(),
+            valid_rows: 0,
+        }
+    }
+
+    /// Returns the tokens of each row in the given range, tokenizing them if needed.
+    pub fn tokens(&mut self, buffer: &Buffer, rows: Range<u32>) -> Vec<Vec<Token>> {
+        self.refresh(buffer);
+
+        let end_row = cmp::min(rows.end, buffer.max_point().row + 1) as usize;
+        let start_row = cmp::min(rows.start as usize, end_row);
+        let mut row = self.valid_rows;
+        while row < end_row {
+            let start_state = if row == 0 {
+                TokenizerState::default()
+            } else {
+                self.lines[row - 1].end_state.clone()
+            };
+            if row == self.lines.len() {
+                self.lines.push(HighlightedLine::dirty());
+            }
+
+            let line = &mut self.lines[row];
+            if line.dirty || line.start_state != start_state {
+                let text = buffer.line(row as u32).unwrap_or_default();
+                let text = String::from_utf16_lossy(&text);
+                let mut end_state = start_state.clone();
+                line.tokens = self.tokenizer.tokenize_line(&text, &mut end_state);
+                line.start_state = start_state;
+                line.end_state = end_state;
+                line.dirty = false;
+            }
+            row += 1;
+        }
+        self.valid_rows = cmp::max(self.valid_rows, end_row);
+
+        self.lines[start_row..end_row]
+            .iter()
+            .map(|line| line.tokens.clone())
+            .collect()
+    }
+
+    // Replaces the cached lines touched by the buffer's changes with dirty ones.
+    fn refresh(&mut self, buffer: &Buffer) {
+        let changes = buffer.changes_since(&self.version);
+        self.version = buffer.version.clone();
+        for change in changes.iter().rev() {
+            let old_start_row = change.old_range.start.row as usize;
+            if old_start_row >= self.lines.len() {
+                continue;
+            }
+
+            let old_end_row = cmp::min(change.old_range.end.row as usize + 1, self.lines.len());
+            let new_row_count =
+                (change.new_range.end.row - change.new_range.start.row) as usize + 1;
+            self.lines.splice(
+                old_start_row..old_end_row,
+                (0..new_row_count).map(|_| HighlightedLine::dirty()),
+            );
+            self.valid_rows = cmp::min(self.valid_rows, old_start_row);
+        }
+    }
+}
+
+impl HighlightedLine {
+    fn dirty() -> Self {
+        HighlightedLine {
+            start_state: TokenizerState::default(),
+            end_state: TokenizerState::default(),
+            tokens: Vec::new(),
+            dirty: true,
+        }
+    }
+}
+
+impl Grammar {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let raw: RawGrammar =
+            serde_json::from_str(json).map_err(|error| Error::InvalidGrammar(error.to_string()))?;
+
+        let mut compiler = GrammarCompiler {
+            rules: vec![Some(Rule::Patterns)],
+            children: vec![Vec::new()],
+            repository_ids: HashMap::new(),
+        };
+        for key in raw.repository.keys() {
+            let id = compiler.reserve();
+            compiler.repository_ids.insert(key.as_str(), id);
+        }
+        compiler.children[ROOT_RULE_ID] = compiler.compile_rules(&raw.patterns);
+        for (key, raw_rule) in &raw.repository {
+            let id = compiler.repository_ids[key.as_str()];
+            compiler.compile_rule_into(id, raw_rule);
+        }
+
+        let GrammarCompiler {
+            rules, children, ..
+        } = compiler;
+        let rules = rules
+            .into_iter()
+            .map(|rule| rule.unwrap_or(Rule::Patterns))
+            .collect::<Vec<_>>();
+        let rule_patterns = (0..rules.len())
+            .map(|id| {
+                let mut patterns = Vec::new();
+                let mut visited = vec![false; rules.len()];
+                visited[id] = true;
+                for child_id in &children[id] {
+                    expand_rule(&rules, &children, *child_id, &mut visited, &mut patterns);
+                }
+                patterns
+            })
+            .collect();
+
+        Ok(Grammar {
+            name: raw.name,
+            scope_name: raw.scope_name,
+            file_types: raw.file_types,
+            rules,
+            rule_patterns,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn scope_name(&self) -> &str {
+        &self.scope_name
+    }
+
+    fn matches_file_name(&self, file_name: &str) -> bool {
+        self.file_types.iter().any(|file_type| {
+            file_name == file_type || file_name.ends_with(&format!(".{}", file_type))
+        })
+    }
+
+    // The scope of text that isn't matched by any pattern of the innermost open rule.
+    fn context_scope(&self, stack: &[RuleId]) -> Option<&str> {
+        stack
+            .iter()
+            .rev()
+            .filter_map(|id| match self.rules[*id] {
+                Rule::BeginEnd {
+                    ref name,
+                    ref content_name,
+                    ..
+                } => content_name
+                    .as_ref()
+                    .or(name.as_ref())
+                    .map(|scope| scope.as_str()),
+                _ => None,
+            })
+            .next()
+    }
+
+    // Finds the earliest match at or after `position` among the end pattern of the innermost open
+    // rule and the patterns it contains. The end pattern wins ties, followed by the patterns in
+    // the order they are declared.
+    fn next_match(
+        &self,
+        stack: &[RuleId],
+        text: &str,
+        position: usize,
+    ) -> Option<(RuleId, bool, CaptureLocations)> {
+        let context_id = stack.last().cloned().unwrap_or(ROOT_RULE_ID);
+        let mut candidates = Vec::new();
+        if let Rule::BeginEnd { ref end, .. } = self.rules[context_id] {
+            candidates.push((context_id, true, end));
+        }
+        for id in &self.rule_patterns[context_id] {
+            match self.rules[*id] {
+                Rule::Match { ref regex, .. } => candidates.push((*id, false, regex)),
+                Rule::BeginEnd { ref begin, .. } => candidates.push((*id, false, begin)),
+                _ => {}
+            }
+        }
+
+        let mut best: Option<(usize, RuleId, bool, &Regex)> = None;
+        for (id, is_end, regex) in candidates {
+            if let Some(found) = regex.find_at(text, position) {
+                if best.map_or(true, |(start, _, _, _)| found.start() < start) {
+                    best = Some((found.start(), id, is_end, regex));
+                }
+            }
+        }
+        best.map(|(_, id, is_end, regex)| {
+            let mut locations = regex.capture_locations();
+            regex.captures_read_at(&mut locations, text, position);
+            (id, is_end, locations)
+        })
+    }
+}
+
+impl Tokenizer for Grammar {
+    fn tokenize_line(&self, line: &str, state: &mut TokenizerState) -> Vec<Token> {
+        // Patterns commonly rely on the newline to close constructs like line comments.
+        let text = format!("{}\n", line);
+        let mut builder = TokenBuilder::new(&text, line.encode_utf16().count() as u32);
+        let mut position = 0;
+        // The rules that matched the empty string at `empty_match_position`, each with whether it
+        // was their end pattern that matched.
+        let mut empty_match_position = 0;
+        let mut empty_matches = Vec::new();
+        while position < text.len() {
+            let (id, is_end, locations) = match self.next_match(&state.0, &text, position) {
+                Some(next_match) => next_match,
+                None => {
+                    builder.push(position..text.len(), self.context_scope(&state.0));
+                    break;
+                }
+            };
+            let (start, end) = locations.get(0).unwrap();
+
+            // Avoid looping forever on patterns that match the empty string by skipping a
+            // character the second time one matches at the same position. This also stops rules
+            // whose begin and end patterns both match the empty string from being entered and
+            // left over and over.
+            if start == end {
+                if start != empty_match_position {
+                    empty_match_position = start;
+                    empty_matches.clear();
+                }
+                if empty_matches.contains(&(id, is_end)) {
+                    let next_position = text[position..]
+                        .chars()
+                        .next()
+                        .map_or(text.len(), |c| position + c.len_utf8());
+                    builder.push(position..next_position, self.context_scope(&state.0));
+                    position = next_position;
+                    continue;
+                }
+                empty_matches.push((id, is_end));
+            }
+
+            builder.push(position..start, self.context_scope(&state.0));
+            match self.rules[id] {
+                Rule::Match {
+                    ref name,
+                    ref captures,
+                    ..
+                } => {
+                    let scope = name.as_ref()
+                        .map(|name| name.as_str())
+                        .or(self.context_scope(&state.0));
+                    builder.push_captures(&locations, scope, captures);
+                }
+                Rule::BeginEnd {
+                    ref name,
+                    ref begin_captures,
+                    ref end_captures,
+                    ..
+                } => {
+                    if is_end {
+                        state.0.pop();
+                    }
+                    let scope = name.as_ref()
+                        .map(|name| name.as_str())
+                        .or(self.context_scope(&state.0));
+                    if is_end {
+                        builder.push_captures(&locations, scope, end_captures);
+                    } else {
+                        builder.push_captures(&locations, scope, begin_captures);
+                        state.0.push(id);
+                    }
+                }
+                _ => unreachable!(),
+            }
+            position = end;
+        }
+        builder.tokens
+    }
+}
+
+impl GrammarRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, grammar: Grammar) {
+        self.grammars.push(Rc::new(grammar));
+    }
+
+    /// Returns the grammar whose file types match the given path. Grammars added later take
+    /// precedence.
+    pub fn grammar_for_path(&self, path: &cross_platform::Path) -> Option<Rc<Grammar>> {
+        let file_name = path.file_name()?;
+        let file_name = file_name.to_string_lossy();
+        self.grammars
+            .iter()
+            .rev()
+            .find(|grammar| grammar.matches_file_name(&file_name))
+            .cloned()
+    }
+}
+
+impl<'a> GrammarCompiler<'a> {
+    fn reserve(&mut self) -> RuleId {
+        self.rules.push(None);
+        self.children.push(Vec::new());
+        self.rules.len() - 1
+    }
+
+    fn compile_rules(&mut self, raw_rules: &[RawRule]) -> Vec<RuleId> {
+        raw_rules
+            .iter()
+            .filter_map(|raw_rule| {
+                let id = self.reserve();
+                if self.compile_rule_into(id, raw_rule) {
+                    Some(id)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn compile_rule_into(&mut self, id: RuleId, raw_rule: &RawRule) -> bool {
+        let rule = if let Some(ref include) = raw_rule.include {
+            if include == "$self" || include == "$base" {
+                Some(Rule::Include(ROOT_RULE_ID))
+            } else if include.starts_with('#') {
+                self.repository_ids
+                    .get(&include[1..])
+                    .map(|target_id| Rule::Include(*target_id))
+            } else {
+                None
+            }
+        } else if let Some(ref pattern) = raw_rule.match_pattern {
+            compile_regex(pattern).map(|regex| Rule::Match {
+                regex,
+                name: raw_rule.name.clone(),
+                captures: compile_captures(&raw_rule.captures),
+            })
+        } else if let (&Some(ref begin), &Some(ref end)) = (&raw_rule.begin, &raw_rule.end) {
+            let begin = compile_regex(begin);
+            let end = compile_regex(end);
+            if let (Some(begin), Some(end)) = (begin, end) {
+                self.children[id] = self.compile_rules(&raw_rule.patterns);
+                Some(Rule::BeginEnd {
+                    begin,
+                    end,
+                    name: raw_rule.name.clone(),
+                    content_name: raw_rule.content_name.clone(),
+                    begin_captures: compile_captures(
+                        raw_rule.begin_captures.as_ref().unwrap_or(&raw_rule.captures),
+                    ),
+                    end_captures: compile_captures(
+                        raw_rule.end_captures.as_ref().unwrap_or(&raw_rule.captures),
+                    ),
+                })
+            } else {
+                None
+            }
+        } else {
+            self.children[id] = self.compile_rules(&raw_rule.patterns);
+            Some(Rule::Patterns)
+        };
+
+        let compiled = rule.is_some();
+        self.rules[id] = rule;
+        compiled
+    }
+}
+
+impl<'a> TokenBuilder<'a> {
+    fn new(text: &'a str, line_len: u32) -> Self {
+        TokenBuilder {
+            offsets: Utf16Offsets::new(text),
+            line_len,
+            tokens: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, range: Range<usize>, scope: Option<&str>) {
+        let start = cmp::min(self.offsets.to_utf16(range.start) as u32, self.line_len);
+        let end = cmp::min(self.offsets.to_utf16(range.end) as u32, self.line_len);
+        let scope = match scope {
+            Some(scope) if start < end => scope,
+            _ => return,
+        };
+
+        if let Some(last_token) = self.tokens.last_mut() {
+            if last_token.end == start && last_token.scope == scope {
+                last_token.end = end;
+                return;
+            }
+        }
+        self.tokens.push(Token {
+            start,
+            end,
+            scope: scope.to_string(),
+        });
+    }
+
+    // Pushes a match, giving its captured groups their own scope. Groups nested in or overlapping
+    // with a preceding group are ignored.
+    fn push_captures(
+        &mut self,
+        locations: &CaptureLocations,
+        scope: Option<&str>,
+        captures: &[(usize, String)],
+    ) {
+        let (start, end) = locations.get(0).unwrap();
+        let mut scope = scope;
+        let mut position = start;
+        for &(index, ref name) in captures {
+            if index == 0 {
+                scope = Some(name.as_str());
+            } else if let Some((capture_start, capture_end)) = locations.get(index) {
+                if capture_start >= position && capture_start < capture_end {
+                    self.push(position..capture_start, scope);
+                    self.push(capture_start..capture_end, Some(name.as_str()));
+                    position = capture_end;
+                }
+            }
+        }
+        self.push(position..end, scope);
+    }
+}
+
+fn compile_regex(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern).multi_line(true).build().ok()
+}
+
+fn compile_captures(raw_captures: &HashMap<String, RawCapture>) -> Vec<(usize, String)> {
+    let mut captures = raw_captures
+        .iter()
+        .filter_map(|(index, capture)| {
+            let index = index.parse().ok()?;
+            let name = capture.name.clone()?;
+            Some((index, name))
+        })
+        .collect::<Vec<_>>();
+    captures.sort();
+    captures
+}
+
+fn expand_rule(
+    rules: &[Rule],
+    children: &[Vec<RuleId>],
+    id: RuleId,
+    visited: &mut Vec<bool>,
+    patterns: &mut Vec<RuleId>,
+) {
+    match rules[id] {
+        Rule::Match { .. } | Rule::BeginEnd { .. } => patterns.push(id),
+        Rule::Include(target_id) => {
+            if !visited[id] {
+                visited[id] = true;
+                expand_rule(rules, children, target_id, visited, patterns);
+            }
+        }
+        Rule::Patterns => {
+            if !visited[id] {
+                visited[id] = true;
+                for child_id in &children[id] {
+                    expand_rule(rules, children, *child_id, visited, patterns);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_tokenize_with_grammar() {
+        let grammar = test_grammar();
+        let mut state = TokenizerState::default();
+        assert_eq!(
+            grammar.tokenize_line(r#"fn foo() "a\"b" // hi"#, &mut state),
+            vec![
+                token(0..2, "keyword"),
+                token(3..6, "entity.name"),
+                token(9..11, "string"),
+                token(11..13, "constant.escape"),
+                token(13..15, "string"),
+                token(16..21, "comment.line"),
+            ]
+        );
+        assert_eq!(state, TokenizerState::default());
+
+        assert_eq!(
+            grammar.tokenize_line("a /* ✓", &mut state),
+            vec![token(2..6, "comment.block")]
+        );
+        assert_ne!(state, TokenizerState::default());
+        assert_eq!(
+            grammar.tokenize_line("b */ fn c", &mut state),
+            vec![
+                token(0..4, "comment.block"),
+                token(5..7, "keyword"),
+                token(8..9, "entity.name"),
+            ]
+        );
+        assert_eq!(state, TokenizerState::default());
+
+        assert!(Grammar::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_empty_begin_and_end() {
+        let grammar = Grammar::from_json(
+            &json!({
+                "scopeName": "source.test",
+                "patterns": [{ "begin": "^", "end": "$", "name": "line" }]
+            }).to_string(),
+        ).unwrap();
+        let mut state = TokenizerState::default();
+        assert_eq!(grammar.tokenize_line("", &mut state), vec![]);
+        assert_eq!(state, TokenizerState::default());
+    }
+
+    #[test]
+    fn test_highlighter() {
+        let tokenizer = Rc::new(CountingTokenizer {
+            grammar: test_grammar(),
+            tokenized_lines: Cell::new(0),
+        });
+        let mut buffer = Buffer::new(0);
+        buffer.edit(&[0..0], "a /* b\nc\nd */ e\nf");
+        let mut highlighter = Highlighter::new(tokenizer.clone(), &buffer);
+
+        assert_eq!(
+            highlighter.tokens(&buffer, 1..3),
+            vec![
+                vec![token(0..1, "comment.block")],
+                vec![token(0..4, "comment.block")],
+            ]
+        );
+        assert_eq!(tokenizer.tokenized_lines.get(), 3);
+        assert_eq!(highlighter.tokens(&buffer, 0..10).len(), 4);
+        assert_eq!(tokenizer.tokenized_lines.get(), 4);
+
+        // Removing the start of the comment retokenizes the lines it used to cover, but stops once
+        // a line's starting state is unaffected.
+        buffer.edit(&[2..4], "");
+        assert_eq!(
+            highlighter.tokens(&buffer, 0..4),
+            vec![vec![], vec![], vec![], vec![]]
+        );
+        assert_eq!(tokenizer.tokenized_lines.get(), 7);
+
+        // Inserting lines shifts the tokens of the following lines without retokenizing them.
+        buffer.edit(&[0..0], "/* x\ny */\n");
+        assert_eq!(
+            highlighter.tokens(&buffer, 0..6),
+            vec![
+                vec![token(0..4, "comment.block")],
+                vec![token(0..4, "comment.block")],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+            ]
+        );
+        assert_eq!(tokenizer.tokenized_lines.get(), 10);
+    }
+
+    #[test]
+    fn test_grammar_registry() {
+        let mut registry = GrammarRegistry::new();
+        registry.add(test_grammar());
+        assert!(
+            registry
+                .grammar_for_path(&cross_platform::Path::from("src/main.test"))
+                .is_some()
+        );
+        assert!(
+            registry
+                .grammar_for_path(&cross_platform::Path::from("test"))
+                .is_some()
+        );
+        assert!(
+            registry
+                .grammar_for_path(&cross_platform::Path::from("src/latest"))
+                .is_none()
+        );
+    }
+
+    struct CountingTokenizer {
+        grammar: Grammar,
+        tokenized_lines: Cell<usize>,
+    }
+
+    impl Tokenizer for CountingTokenizer {
+        fn tokenize_line(&self, line: &str, state: &mut TokenizerState) -> Vec<Token> {
+            self.tokenized_lines.set(self.tokenized_lines.get() + 1);
+            self.grammar.tokenize_line(line, state)
+        }
+    }
+
+    fn test_grammar() -> Grammar {
+        Grammar::from_json(
+            &json!({
+                "name": "Test",
+                "scopeName": "source.test",
+                "fileTypes": ["test"],
+                "patterns": [
+                    { "include": "#comments" },
+                    {
+                        "match": r"\b(fn)\s+(\w+)",
+                        "captures": {
+                            "1": { "name": "keyword" },
+                            "2": { "name": "entity.name" }
+                        }
+                    },
+                    {
+                        "begin": "\"",
+                        "end": "\"",
+                        "name": "string",
+                        "patterns": [{ "match": r"\\.", "name": "constant.escape" }]
+                    },
+                    { "match": "(?<=x)y", "name": "unsupported" }
+                ],
+                "repository": {
+                    "comments": {
+                        "patterns": [
+                            { "match": "//.*$", "name": "comment.line" },
+                            { "begin": r"/\*", "end": r"\*/", "name": "comment.block" }
+                        ]
+                    }
+                }
+            }).to_string(),
+        ).unwrap()
+    }
+
+    fn token(columns: Range<u32>, scope: &str) -> Token {
+        Token {
+            start: columns.start,
+            end: columns.end,
+            scope: scope.to_string(),
+        }
+    }
+}
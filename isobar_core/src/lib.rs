@@ -6,6 +6,7 @@ extern crate bytes;
 extern crate lazy_static;
 extern crate futures;
 extern crate parking_lot;
+extern crate regex;
 extern crate seahash;
 extern crate serde;
 #[macro_use]
@@ -29,12 +30,16 @@ pub mod buffer;
 pub mod buffer_view;
 pub mod cross_platform;
 pub mod fs;
+pub mod highlight;
 pub mod notify_cell;
 pub mod rpc;
+pub mod search;
 pub mod window;
 pub mod workspace;
 
+mod display_map;
 mod file_finder;
+mod folding;
 mod fuzzy;
 mod movement;
 mod never;
@@ -1,8 +1,40 @@
 use buffer::{Buffer, Point};
+use display_map::DisplayMap;
 use std::char::decode_utf16;
-use std::cmp;
 
-pub fn left(buffer: &Buffer, mut point: Point) -> Point {
+/// Moves one column to the left, wrapping to the end of the previous display row at the start
+/// of a line. Rows hidden by folds are skipped.
+pub fn left(display_map: &DisplayMap, mut point: Point) -> Point {
+    if point.column > 0 {
+        point.column -= 1;
+    } else {
+        let display_row = display_map.to_display_point(point).row;
+        if display_row > 0 {
+            let (row, columns) = display_map.buffer_columns(display_row - 1);
+            point = Point::new(row, columns.end);
+        }
+    }
+    point
+}
+
+/// Moves one column to the right, wrapping to the start of the next display row at the end of a
+/// line. Rows hidden by folds are skipped.
+pub fn right(buffer: &Buffer, display_map: &DisplayMap, mut point: Point) -> Point {
+    let max_column = buffer.len_for_row(point.row).unwrap();
+    if point.column < max_column {
+        point.column += 1;
+    } else {
+        let display_row = display_map.to_display_point(point).row;
+        if display_row + 1 < display_map.row_count() {
+            point = display_map.to_buffer_point(Point::new(display_row + 1, 0));
+        }
+    }
+    point
+}
+
+// Word movement works on buffer rows, so it steps over folds and soft wraps like any other
+// line boundary.
+fn left_in_buffer(buffer: &Buffer, mut point: Point) -> Point {
     if point.column > 0 {
         point.column -= 1;
     } else if point.row > 0 {
@@ -12,7 +44,7 @@ pub fn left(buffer: &Buffer, mut point: Point) -> Point {
     point
 }
 
-pub fn right(buffer: &Buffer, mut point: Point) -> Point {
+fn right_in_buffer(buffer: &Buffer, mut point: Point) -> Point {
     let max_column = buffer.len_for_row(point.row).unwrap();
     if point.column < max_column {
         point.column += 1;
@@ -23,39 +55,43 @@ pub fn right(buffer: &Buffer, mut point: Point) -> Point {
     point
 }
 
-pub fn up(buffer: &Buffer, mut point: Point, goal_column: Option<u32>) -> (Point, Option<u32>) {
+pub fn up(display_map: &DisplayMap, point: Point, goal_column: Option<u32>) -> (Point, Option<u32>) {
+    let mut point = display_map.to_display_point(point);
     let goal_column = goal_column.or(Some(point.column));
     if point.row > 0 {
         point.row -= 1;
-        point.column = cmp::min(goal_column.unwrap(), buffer.len_for_row(point.row).unwrap());
+        point.column = goal_column.unwrap();
+        (display_map.to_buffer_point(point), goal_column)
     } else {
-        point = Point::new(0, 0);
+        (Point::new(0, 0), goal_column)
     }
-
-    (point, goal_column)
 }
 
-pub fn down(buffer: &Buffer, mut point: Point, goal_column: Option<u32>) -> (Point, Option<u32>) {
+pub fn down(
+    display_map: &DisplayMap,
+    point: Point,
+    goal_column: Option<u32>,
+) -> (Point, Option<u32>) {
+    let mut point = display_map.to_display_point(point);
     let goal_column = goal_column.or(Some(point.column));
-    let max_point = buffer.max_point();
+    let max_point = display_map.max_point();
     if point.row < max_point.row {
         point.row += 1;
-        point.column = cmp::min(goal_column.unwrap(), buffer.len_for_row(point.row).unwrap())
+        point.column = goal_column.unwrap();
+        (display_map.to_buffer_point(point), goal_column)
     } else {
-        point = max_point;
+        (display_map.to_buffer_point(max_point), goal_column)
     }
-
-    (point, goal_column)
 }
 
 pub fn beginning_of_word(buffer: &Buffer, mut point: Point) -> Point {
     // TODO: remove this once the iterator returns char instances.
     let mut iter = decode_utf16(buffer.backward_iter_starting_at_point(point)).map(|c| c.unwrap());
     let skip_alphanumeric = iter.next().map_or(false, |c| c.is_alphanumeric());
-    point = left(buffer, point);
+    point = left_in_buffer(buffer, point);
     for character in iter {
         if skip_alphanumeric == character.is_alphanumeric() {
-            point = left(buffer, point);
+            point = left_in_buffer(buffer, point);
         } else {
             break;
         }
@@ -67,10 +103,10 @@ pub fn end_of_word(buffer: &Buffer, mut point: Point) -> Point {
     // TODO: remove this once the iterator returns char instances.
     let mut iter = decode_utf16(buffer.iter_starting_at_point(point)).map(|c| c.unwrap());
     let skip_alphanumeric = iter.next().map_or(false, |c| c.is_alphanumeric());
-    point = right(buffer, point);
+    point = right_in_buffer(buffer, point);
     for character in iter {
         if skip_alphanumeric == character.is_alphanumeric() {
-            point = right(buffer, point);
+            point = right_in_buffer(buffer, point);
         } else {
             break;
         }
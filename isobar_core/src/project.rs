@@ -1,23 +1,31 @@
 use buffer::{self, Buffer, BufferId};
 use cross_platform;
 use fs;
-use futures::{future, Async, Future, Poll};
+use futures::{future, task, Async, Future, Poll, Stream};
 use fuzzy;
 use never::Never;
 use notify_cell::{NotifyCell, NotifyCellObserver, WeakNotifyCell};
 use rpc;
+use search::{self, SearchQuery};
 use std::cell::{Cell, RefCell};
 use std::cmp;
 use std::collections::{BinaryHeap, HashMap};
 use std::error;
 use std::io;
+use std::mem;
+use std::ops::Range;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
+use BackgroundExecutor;
 use ForegroundExecutor;
 use IntoShared;
 
 pub type TreeId = usize;
 
+// Text searches yield after searching this many files, so that their progress gets reported and
+// they can be cancelled long before they finish.
+const TEXT_SEARCH_FILES_PER_POLL: usize = 32;
+
 pub trait Project {
     fn open_path(
         &self,
@@ -34,6 +42,17 @@ pub trait Project {
         max_results: usize,
         include_ignored: bool,
     ) -> (PathSearch, NotifyCellObserver<PathSearchStatus>);
+    fn search_text(
+        &self,
+        query: &SearchQuery,
+        options: TextSearchOptions,
+    ) -> Result<
+        (
+            Box<Future<Item = (), Error = ()> + Send>,
+            NotifyCellObserver<TextSearchStatus>,
+        ),
+        Error,
+    >;
 }
 
 struct BufferWeakSet {
@@ -56,9 +75,15 @@ pub struct RemoteProject {
 
 pub struct ProjectService {
     project: Rc<RefCell<LocalProject>>,
+    background: BackgroundExecutor,
     tree_services: HashMap<TreeId, rpc::server::ServiceHandle>,
 }
 
+pub struct TextSearchService {
+    updates: NotifyCellObserver<TextSearchStatus>,
+    sent_results: usize,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct RpcState {
     trees: HashMap<TreeId, rpc::ServiceId>,
@@ -73,11 +98,24 @@ pub enum RpcRequest {
     OpenBuffer {
         buffer_id: BufferId,
     },
+    SearchText {
+        query: SearchQuery,
+        options: TextSearchOptions,
+    },
 }
 
 #[derive(Deserialize, Serialize)]
 pub enum RpcResponse {
     OpenedBuffer(Result<rpc::ServiceId, Error>),
+    StartedTextSearch(Result<rpc::ServiceId, Error>),
+}
+
+/// Carries only the results found since the previous update, so that results aren't sent to
+/// remote projects more than once.
+#[derive(Deserialize, Serialize)]
+pub enum TextSearchUpdate {
+    Searching(Vec<TextSearchResult>),
+    Ready(Vec<TextSearchResult>),
 }
 
 pub struct PathSearch {
@@ -111,6 +149,109 @@ struct StackEntry {
     found_match: bool,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TextSearchOptions {
+    /// The search stops once it has found this many matches.
+    pub max_results: usize,
+    pub include_ignored: bool,
+}
+
+pub struct TextSearch {
+    tree_ids: Vec<TreeId>,
+    roots: Arc<Vec<fs::Entry>>,
+    tree_paths: Vec<cross_platform::Path>,
+    matcher: search::Matcher,
+    options: TextSearchOptions,
+    content_reader: Arc<fs::ContentReader>,
+    results: Vec<TextSearchResult>,
+    reported_results: usize,
+    match_count: usize,
+    next_tree_index: usize,
+    stack: Vec<TextSearchDir>,
+    updates: WeakNotifyCell<TextSearchStatus>,
+}
+
+struct TextSearchDir {
+    tree_index: usize,
+    children: Arc<Vec<fs::Entry>>,
+    child_index: usize,
+    relative_path: cross_platform::Path,
+    display_path: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TextSearchStatus {
+    Pending,
+    Searching(Vec<TextSearchResult>),
+    Ready(Vec<TextSearchResult>),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TextSearchResult {
+    pub tree_id: TreeId,
+    pub relative_path: cross_platform::Path,
+    pub display_path: String,
+    pub matches: Vec<search::TextMatch>,
+}
+
+pub type ReplaceMatchId = usize;
+
+pub struct ProjectReplace {
+    files: Vec<ReplaceFile>,
+}
+
+struct ReplaceFile {
+    tree_id: TreeId,
+    relative_path: cross_platform::Path,
+    display_path: String,
+    buffer: Rc<RefCell<Buffer>>,
+    matches: Vec<ReplaceMatch>,
+}
+
+struct ReplaceMatch {
+    id: ReplaceMatchId,
+    range: Range<buffer::Anchor>,
+    new_text: String,
+    excluded: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReplacePreview {
+    pub tree_id: TreeId,
+    pub relative_path: cross_platform::Path,
+    pub display_path: String,
+    pub lines: Vec<ReplacePreviewLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReplacePreviewLine {
+    pub row: u32,
+    /// The affected lines as they currently read. This spans multiple rows when a match does.
+    pub old_text: String,
+    /// The affected lines with every match that isn't excluded replaced.
+    pub new_text: String,
+    pub matches: Vec<ReplacePreviewMatch>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReplacePreviewMatch {
+    pub id: ReplaceMatchId,
+    /// The columns of `old_text` covered by the match, in UTF-16 code units.
+    pub old_columns: Range<u32>,
+    /// The columns of `new_text` covered by the replacement, in UTF-16 code units.
+    pub new_columns: Range<u32>,
+    pub excluded: bool,
+}
+
+#[derive(Debug)]
+pub struct ReplaceFileResult {
+    pub tree_id: TreeId,
+    pub relative_path: cross_platform::Path,
+    pub display_path: String,
+    pub replacement_count: usize,
+    pub result: Result<(), Error>,
+}
+
 #[derive(Debug)]
 enum MatchMarker {
     ContainsMatch,
@@ -123,6 +264,9 @@ pub enum Error {
     TreeNotFound,
     IoError(String),
     RpcError(rpc::Error),
+    InvalidSearchQuery(search::Error),
+    BufferError(buffer::Error),
+    BufferHasNoFile,
     UnexpectedResponse,
 }
 
@@ -288,6 +432,48 @@ impl Project for LocalProject {
 
         (search, updates_observer)
     }
+
+    fn search_text(
+        &self,
+        query: &SearchQuery,
+        options: TextSearchOptions,
+    ) -> Result<
+        (
+            Box<Future<Item = (), Error = ()> + Send>,
+            NotifyCellObserver<TextSearchStatus>,
+        ),
+        Error,
+    > {
+        let matcher = query.matcher().map_err(Error::InvalidSearchQuery)?;
+        let (updates, updates_observer) = NotifyCell::weak(TextSearchStatus::Pending);
+
+        let mut tree_ids = self.trees.keys().cloned().collect::<Vec<_>>();
+        tree_ids.sort();
+        let mut roots = Vec::new();
+        let mut tree_paths = Vec::new();
+        for id in &tree_ids {
+            let tree = &self.trees[id];
+            roots.push(tree.root().clone());
+            tree_paths.push(tree.path().clone());
+        }
+
+        let search = TextSearch {
+            tree_ids,
+            roots: Arc::new(roots),
+            tree_paths,
+            matcher,
+            options,
+            content_reader: self.file_provider.content_reader(),
+            results: Vec::new(),
+            reported_results: 0,
+            match_count: 0,
+            next_tree_index: 0,
+            stack: Vec::new(),
+            updates,
+        };
+
+        Ok((Box::new(search), updates_observer))
+    }
 }
 
 impl RemoteProject {
@@ -342,6 +528,7 @@ impl Project for RemoteProject {
                                             .map_err(|error| error.into())
                                     })
                             }),
+                            _ => Err(Error::UnexpectedResponse),
                         })
                 }),
         )
@@ -371,6 +558,7 @@ impl Project for RemoteProject {
                                             .map_err(|error| error.into())
                                     })
                             }),
+                            _ => Err(Error::UnexpectedResponse),
                         })
                 }),
         )
@@ -403,12 +591,90 @@ impl Project for RemoteProject {
 
         (search, updates_observer)
     }
+
+    fn search_text(
+        &self,
+        query: &SearchQuery,
+        options: TextSearchOptions,
+    ) -> Result<
+        (
+            Box<Future<Item = (), Error = ()> + Send>,
+            NotifyCellObserver<TextSearchStatus>,
+        ),
+        Error,
+    > {
+        query.matcher().map_err(Error::InvalidSearchQuery)?;
+        let (updates, updates_observer) = NotifyCell::weak(TextSearchStatus::Pending);
+
+        // The search runs on the host. We forward its progress on the foreground until it is
+        // ready or nobody is observing it anymore, at which point dropping the search service
+        // cancels the search on the host as well.
+        let service = self.service.clone();
+        let forward_updates = self.service
+            .borrow()
+            .request(RpcRequest::SearchText {
+                query: query.clone(),
+                options,
+            })
+            .map_err(|_| ())
+            .and_then(move |response| match response {
+                RpcResponse::StartedTextSearch(Ok(service_id)) => service
+                    .borrow()
+                    .take_service::<TextSearchService>(service_id)
+                    .map_err(|_| ()),
+                _ => Err(()),
+            })
+            .and_then(move |search_service| {
+                // Updates only carry new results, which we append to the ones the search had
+                // already found when we started observing it.
+                let results = match search_service.state() {
+                    Ok(TextSearchStatus::Pending) => Ok(Vec::new()),
+                    Ok(TextSearchStatus::Searching(results))
+                    | Ok(TextSearchStatus::Ready(results)) => Ok(results),
+                    Err(_) => Err(()),
+                };
+                let search_updates = results.and_then(|results| {
+                    search_service
+                        .updates()
+                        .map(|search_updates| (results, search_updates))
+                        .map_err(|_| ())
+                });
+                future::result(search_updates)
+                    .and_then(move |(mut results, search_updates)| {
+                        search_updates.for_each(move |update| match update {
+                            TextSearchUpdate::Searching(new_results) => {
+                                results.extend(new_results);
+                                updates
+                                    .try_set(TextSearchStatus::Searching(results.clone()))
+                                    .map_err(|_| ())
+                            }
+                            TextSearchUpdate::Ready(new_results) => {
+                                results.extend(new_results);
+                                let results = mem::replace(&mut results, Vec::new());
+                                let _ = updates.try_set(TextSearchStatus::Ready(results));
+                                // Stop forwarding once the final results have arrived.
+                                Err(())
+                            }
+                        })
+                    })
+                    .then(move |_| {
+                        drop(search_service);
+                        Ok(())
+                    })
+            });
+        self.foreground
+            .execute(Box::new(forward_updates))
+            .unwrap();
+
+        Ok((Box::new(future::ok(())), updates_observer))
+    }
 }
 
 impl ProjectService {
-    pub fn new(project: Rc<RefCell<LocalProject>>) -> Self {
+    pub fn new(project: Rc<RefCell<LocalProject>>, background: BackgroundExecutor) -> Self {
         Self {
             project,
+            background,
             tree_services: HashMap::new(),
         }
     }
@@ -476,6 +742,59 @@ impl rpc::server::Service for ProjectService {
                     },
                 )))
             }
+            RpcRequest::SearchText { query, options } => {
+                let background = &self.background;
+                let result = self.project.borrow().search_text(&query, options).map(
+                    |(search, updates)| {
+                        background.execute(search).unwrap();
+                        connection
+                            .add_service(TextSearchService {
+                                updates,
+                                sent_results: 0,
+                            })
+                            .service_id()
+                    },
+                );
+                Some(Box::new(future::ok(RpcResponse::StartedTextSearch(result))))
+            }
+        }
+    }
+}
+
+impl rpc::server::Service for TextSearchService {
+    type State = TextSearchStatus;
+    type Update = TextSearchUpdate;
+    type Request = Never;
+    type Response = Never;
+
+    fn init(&mut self, _: &rpc::server::Connection) -> Self::State {
+        let status = self.updates.get();
+        self.sent_results = match &status {
+            TextSearchStatus::Pending => 0,
+            TextSearchStatus::Searching(results) | TextSearchStatus::Ready(results) => {
+                results.len()
+            }
+        };
+        status
+    }
+
+    fn poll_update(&mut self, _: &rpc::server::Connection) -> Async<Option<Self::Update>> {
+        loop {
+            let (mut results, is_ready) = match self.updates.poll() {
+                Ok(Async::Ready(Some(TextSearchStatus::Searching(results)))) => (results, false),
+                Ok(Async::Ready(Some(TextSearchStatus::Ready(results)))) => (results, true),
+                Ok(Async::Ready(Some(TextSearchStatus::Pending))) => continue,
+                _ => return Async::NotReady,
+            };
+
+            // Results are only ever appended, so everything past the ones we've sent is new.
+            let new_results = results.split_off(cmp::min(self.sent_results, results.len()));
+            self.sent_results += new_results.len();
+            if is_ready {
+                return Async::Ready(Some(TextSearchUpdate::Ready(new_results)));
+            } else if !new_results.is_empty() {
+                return Async::Ready(Some(TextSearchUpdate::Searching(new_results)));
+            }
         }
     }
 }
@@ -694,6 +1013,362 @@ impl Future for PathSearch {
     }
 }
 
+impl TextSearch {
+    // Searches the next file in depth-first order, returning false once there are no files left
+    // to search or enough matches have been found.
+    fn search_next_file(&mut self) -> Result<bool, ()> {
+        while self.match_count < self.options.max_results {
+            let next_child = self.stack.last_mut().map(|dir| {
+                let child = dir.children.get(dir.child_index).cloned();
+                dir.child_index += 1;
+                child
+            });
+            let child = match next_child {
+                Some(Some(child)) => child,
+                Some(None) => {
+                    self.stack.pop();
+                    continue;
+                }
+                None if self.next_tree_index < self.roots.len() => {
+                    let tree_index = self.next_tree_index;
+                    let root = &self.roots[tree_index];
+                    let display_path = if self.roots.len() == 1 {
+                        String::new()
+                    } else {
+                        root.name_chars().iter().collect()
+                    };
+                    self.stack.push(TextSearchDir {
+                        tree_index,
+                        children: root.children().unwrap(),
+                        child_index: 0,
+                        relative_path: cross_platform::Path::new(),
+                        display_path,
+                    });
+                    self.next_tree_index += 1;
+                    continue;
+                }
+                None => break,
+            };
+            if child.is_ignored() && !self.options.include_ignored {
+                continue;
+            }
+
+            let (tree_index, relative_path, display_path) = {
+                let dir = self.stack.last().unwrap();
+                let mut relative_path = dir.relative_path.clone();
+                relative_path.push(child.name());
+                let mut display_path = dir.display_path.clone();
+                display_path.extend(child.name_chars());
+                (dir.tree_index, relative_path, display_path)
+            };
+            if child.is_dir() {
+                self.stack.push(TextSearchDir {
+                    tree_index,
+                    children: child.children().unwrap(),
+                    child_index: 0,
+                    relative_path,
+                    display_path,
+                });
+            } else {
+                self.search_file(tree_index, relative_path, display_path)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn search_file(
+        &mut self,
+        tree_index: usize,
+        relative_path: cross_platform::Path,
+        display_path: String,
+    ) -> Result<(), ()> {
+        if !self.updates.has_observers() {
+            return Err(());
+        }
+
+        let mut absolute_path = self.tree_paths[tree_index].clone();
+        absolute_path.push_path(&relative_path);
+
+        // Files that can't be read as text, such as binaries, are skipped.
+        if let Ok(content) = self.content_reader.read(&absolute_path) {
+            let mut matches = self.matcher.find_lines(&content);
+            matches.truncate(self.options.max_results - self.match_count);
+            if !matches.is_empty() {
+                self.match_count += matches.len();
+                self.results.push(TextSearchResult {
+                    tree_id: self.tree_ids[tree_index],
+                    relative_path,
+                    display_path,
+                    matches,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Future for TextSearch {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        for _ in 0..TEXT_SEARCH_FILES_PER_POLL {
+            if !self.search_next_file()? {
+                let results = mem::replace(&mut self.results, Vec::new());
+                let _ = self.updates.try_set(TextSearchStatus::Ready(results));
+                return Ok(Async::Ready(()));
+            }
+        }
+
+        // Results are reported once per batch of files rather than once per matching file,
+        // since every report contains all the results found so far.
+        if self.results.len() > self.reported_results {
+            self.reported_results = self.results.len();
+            self.updates
+                .try_set(TextSearchStatus::Searching(self.results.clone()))
+                .map_err(|_| ())?;
+        }
+        task::current().notify();
+        Ok(Async::NotReady)
+    }
+}
+
+impl ProjectReplace {
+    /// Opens a buffer for every file in the given search results and finds the matches that
+    /// would be replaced in each of them. Matches are found in the buffers' current contents, so
+    /// unsaved edits are taken into account, but no file gets more matches than the search found
+    /// in it, so that a search stopped by its `max_results` doesn't replace unseen matches.
+    pub fn new(
+        project: &Project,
+        query: &SearchQuery,
+        replacement: &str,
+        results: &[TextSearchResult],
+    ) -> Box<Future<Item = Self, Error = Error>> {
+        let matcher = match query.matcher() {
+            Ok(matcher) => matcher,
+            Err(error) => return Box::new(future::err(Error::InvalidSearchQuery(error))),
+        };
+        let replacement = replacement.to_string();
+
+        let open_buffers = results
+            .iter()
+            .map(|result| {
+                let tree_id = result.tree_id;
+                let relative_path = result.relative_path.clone();
+                let display_path = result.display_path.clone();
+                let max_matches = result.matches.len();
+                project
+                    .open_path(result.tree_id, &result.relative_path)
+                    .map(move |buffer| (tree_id, relative_path, display_path, max_matches, buffer))
+            })
+            .collect::<Vec<_>>();
+        Box::new(future::join_all(open_buffers).map(move |buffers| {
+            let mut next_match_id = 0;
+            let mut files = Vec::new();
+            for (tree_id, relative_path, display_path, max_matches, buffer) in buffers {
+                let matches = {
+                    let buffer = buffer.borrow();
+                    matcher
+                        .replacements(&buffer.to_u16_chars(), &replacement)
+                        .into_iter()
+                        .take(max_matches)
+                        .map(|(range, new_text)| {
+                            let id = next_match_id;
+                            next_match_id += 1;
+                            ReplaceMatch {
+                                id,
+                                range: buffer.anchor_after_offset(range.start).unwrap()
+                                    ..buffer.anchor_before_offset(range.end).unwrap(),
+                                new_text,
+                                excluded: false,
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                if !matches.is_empty() {
+                    files.push(ReplaceFile {
+                        tree_id,
+                        relative_path,
+                        display_path,
+                        buffer,
+                        matches,
+                    });
+                }
+            }
+            ProjectReplace { files }
+        }))
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.files.iter().map(|file| file.matches.len()).sum()
+    }
+
+    pub fn set_excluded(&mut self, id: ReplaceMatchId, excluded: bool) -> Result<(), ()> {
+        let replace_match = self.files
+            .iter_mut()
+            .flat_map(|file| file.matches.iter_mut())
+            .find(|replace_match| replace_match.id == id)
+            .ok_or(())?;
+        replace_match.excluded = excluded;
+        Ok(())
+    }
+
+    /// Describes every line that would be affected by the replacement, grouped by file.
+    pub fn preview(&self) -> Vec<ReplacePreview> {
+        self.files
+            .iter()
+            .map(|file| ReplacePreview {
+                tree_id: file.tree_id,
+                relative_path: file.relative_path.clone(),
+                display_path: file.display_path.clone(),
+                lines: file.preview_lines(),
+            })
+            .collect()
+    }
+
+    /// Edits the buffers of all affected files, skipping excluded matches, and then saves them in
+    /// a single batch. Each file's edits form a single transaction, so they can be undone in any
+    /// editor that has the file open. The replace is consumed, so its matches can only be applied
+    /// once. The returned future resolves once every save has completed and reports the outcome
+    /// for each file.
+    pub fn apply(self) -> Box<Future<Item = Vec<ReplaceFileResult>, Error = Never>> {
+        let saves = self.files
+            .iter()
+            .filter_map(|file| {
+                let replacement_count = file.apply();
+                if replacement_count == 0 {
+                    return None;
+                }
+
+                let tree_id = file.tree_id;
+                let relative_path = file.relative_path.clone();
+                let display_path = file.display_path.clone();
+                let save = match file.buffer.borrow().save() {
+                    Some(save) => Box::new(save.map_err(Error::BufferError))
+                        as Box<Future<Item = (), Error = Error>>,
+                    None => Box::new(future::err(Error::BufferHasNoFile)),
+                };
+                Some(save.then(move |result| {
+                    Ok::<_, Never>(ReplaceFileResult {
+                        tree_id,
+                        relative_path,
+                        display_path,
+                        replacement_count,
+                        result,
+                    })
+                }))
+            })
+            .collect::<Vec<_>>();
+        Box::new(future::join_all(saves))
+    }
+}
+
+impl ReplaceFile {
+    fn apply(&self) -> usize {
+        let mut buffer = self.buffer.borrow_mut();
+        let replacements = self.matches
+            .iter()
+            .filter(|replace_match| !replace_match.excluded)
+            .map(|replace_match| {
+                let start = buffer.offset_for_anchor(&replace_match.range.start).unwrap();
+                let end = buffer.offset_for_anchor(&replace_match.range.end).unwrap();
+                (start..end, replace_match.new_text.as_str())
+            })
+            .collect::<Vec<_>>();
+
+        if !replacements.is_empty() {
+            buffer.start_transaction(None).unwrap();
+            for (range, new_text) in replacements.iter().rev() {
+                buffer.edit(&[range.clone()], *new_text);
+            }
+            buffer.end_transaction(None).unwrap();
+        }
+        replacements.len()
+    }
+
+    fn preview_lines(&self) -> Vec<ReplacePreviewLine> {
+        let buffer = self.buffer.borrow();
+        let text = buffer.to_u16_chars();
+        let newline = '\n' as u16;
+
+        let mut lines: Vec<(Range<usize>, ReplacePreviewLine)> = Vec::new();
+        let mut row = 0;
+        let mut row_start = 0;
+        for replace_match in &self.matches {
+            let start = buffer.offset_for_anchor(&replace_match.range.start).unwrap();
+            let end = buffer.offset_for_anchor(&replace_match.range.end).unwrap();
+            for offset in row_start..start {
+                if text[offset] == newline {
+                    row += 1;
+                    row_start = offset + 1;
+                }
+            }
+            let line_end = text[end..]
+                .iter()
+                .position(|c| *c == newline)
+                .map_or(text.len(), |index| end + index);
+
+            // Matches that begin on lines already covered by a preceding match extend its line.
+            let extends_previous_line = lines
+                .last()
+                .map_or(false, |&(ref range, _)| range.end >= row_start);
+            if !extends_previous_line {
+                lines.push((
+                    row_start..line_end,
+                    ReplacePreviewLine {
+                        row,
+                        old_text: String::new(),
+                        new_text: String::new(),
+                        matches: Vec::new(),
+                    },
+                ));
+            }
+
+            let &mut (ref mut range, ref mut line) = lines.last_mut().unwrap();
+            range.end = cmp::max(range.end, line_end);
+            line.matches.push(ReplacePreviewMatch {
+                id: replace_match.id,
+                old_columns: (start - range.start) as u32..(end - range.start) as u32,
+                new_columns: 0..0,
+                excluded: replace_match.excluded,
+            });
+        }
+
+        let mut new_texts = self.matches
+            .iter()
+            .map(|replace_match| replace_match.new_text.encode_utf16().collect::<Vec<_>>());
+        lines
+            .into_iter()
+            .map(|(range, mut line)| {
+                let old_text = &text[range];
+                let mut new_text = Vec::new();
+                let mut old_column = 0;
+                for preview_match in &mut line.matches {
+                    let replacement = new_texts.next().unwrap();
+                    let old_start = preview_match.old_columns.start as usize;
+                    let old_end = preview_match.old_columns.end as usize;
+                    new_text.extend_from_slice(&old_text[old_column..old_start]);
+                    let new_start = new_text.len() as u32;
+                    if preview_match.excluded {
+                        new_text.extend_from_slice(&old_text[old_start..old_end]);
+                    } else {
+                        new_text.extend(replacement);
+                    }
+                    preview_match.new_columns = new_start..new_text.len() as u32;
+                    old_column = old_end;
+                }
+                new_text.extend_from_slice(&old_text[old_column..]);
+
+                line.old_text = String::from_utf16_lossy(old_text);
+                line.new_text = String::from_utf16_lossy(&new_text);
+                line
+            })
+            .collect()
+    }
+}
+
 impl Ord for PathSearchResult {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
         self.partial_cmp(other).unwrap_or(cmp::Ordering::Equal)
@@ -726,6 +1401,7 @@ impl From<rpc::Error> for Error {
 mod tests {
     use super::*;
     use fs::tests::{TestFileProvider, TestTree};
+    use fs::FileProvider;
     use tokio_core::reactor;
     use IntoShared;
 
@@ -868,8 +1544,11 @@ mod tests {
 
         let local_project = build_project(file_provider.clone()).into_shared();
         let remote_project = RemoteProject::new(
-            handle,
-            rpc::tests::connect(&mut reactor, ProjectService::new(local_project.clone())),
+            handle.clone(),
+            rpc::tests::connect(
+                &mut reactor,
+                ProjectService::new(local_project.clone(), handle),
+            ),
         ).unwrap();
 
         let (mut local_search, local_observer) =
@@ -911,6 +1590,390 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_text() {
+        let file_provider = Rc::new(TestFileProvider::new());
+        let project = build_project(file_provider.clone());
+        write_text_search_fixtures(&project, &file_provider);
+
+        let options = TextSearchOptions {
+            max_results: 10,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options.clone())
+            .unwrap();
+        assert_eq!(observer.get(), TextSearchStatus::Pending);
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        assert_eq!(
+            summarize_text_results(&observer.get()),
+            Some(vec![
+                (
+                    0,
+                    "subdir-a/file-1".to_string(),
+                    "foo/subdir-a/file-1".to_string(),
+                    vec![(0, 0..3), (1, 4..7)],
+                ),
+                (
+                    1,
+                    "subdir-b/subdir-2/foo".to_string(),
+                    "bar/subdir-b/subdir-2/foo".to_string(),
+                    vec![(0, 1..4)],
+                ),
+            ])
+        );
+
+        let query = SearchQuery {
+            case_sensitive: true,
+            ..SearchQuery::literal("abc")
+        };
+        let options = TextSearchOptions {
+            max_results: 1,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project.search_text(&query, options.clone()).unwrap();
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        assert_eq!(
+            summarize_text_results(&observer.get()),
+            Some(vec![(
+                0,
+                "subdir-a/file-1".to_string(),
+                "foo/subdir-a/file-1".to_string(),
+                vec![(0, 0..3)],
+            )])
+        );
+
+        // Dropping the observer cancels the search.
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options.clone())
+            .unwrap();
+        drop(observer);
+        assert_eq!(search.poll(), Err(()));
+
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("(")
+        };
+        assert!(project.search_text(&query, options).is_err());
+    }
+
+    #[test]
+    fn test_search_text_ignored() {
+        let file_provider = Rc::new(TestFileProvider::new());
+        let root = fs::Entry::dir(cross_platform::PathComponent::from("tree"), false, false);
+        root.insert(fs::Entry::file(
+            cross_platform::PathComponent::from("ignored"),
+            false,
+            true,
+        )).unwrap();
+        root.insert(fs::Entry::file(
+            cross_platform::PathComponent::from("tracked"),
+            false,
+            false,
+        )).unwrap();
+        let project = LocalProject::new(
+            file_provider.clone(),
+            vec![TestTree::new("/Users/someone/tree", root)],
+        );
+        for name in &["ignored", "tracked"] {
+            let path = project
+                .resolve_path(0, &cross_platform::Path::from(*name))
+                .unwrap();
+            file_provider.write_sync(path, "abc");
+        }
+
+        let options = TextSearchOptions {
+            max_results: 10,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options)
+            .unwrap();
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        assert_eq!(
+            summarize_text_results(&observer.get()),
+            Some(vec![(
+                0,
+                "tracked".to_string(),
+                "tracked".to_string(),
+                vec![(0, 0..3)],
+            )])
+        );
+
+        let options = TextSearchOptions {
+            max_results: 10,
+            include_ignored: true,
+        };
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options)
+            .unwrap();
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        assert_eq!(
+            summarize_text_results(&observer.get())
+                .unwrap()
+                .into_iter()
+                .map(|(_, relative_path, _, _)| relative_path)
+                .collect::<Vec<_>>(),
+            vec!["ignored".to_string(), "tracked".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_search_text_progress() {
+        let file_provider = Rc::new(TestFileProvider::new());
+        let root = fs::Entry::dir(cross_platform::PathComponent::from("tree"), false, false);
+        let names = (0..TEXT_SEARCH_FILES_PER_POLL + 1)
+            .map(|i| format!("file-{:02}", i))
+            .collect::<Vec<_>>();
+        for name in &names {
+            root.insert(fs::Entry::file(
+                cross_platform::PathComponent::from(name.as_str()),
+                false,
+                false,
+            )).unwrap();
+        }
+        let project = LocalProject::new(
+            file_provider.clone(),
+            vec![TestTree::new("/Users/someone/tree", root)],
+        );
+        for name in &names {
+            let path = project
+                .resolve_path(0, &cross_platform::Path::from(name.as_str()))
+                .unwrap();
+            file_provider.write_sync(path, "abc");
+        }
+
+        // Results found so far are reported after every batch of files.
+        let options = TextSearchOptions {
+            max_results: 100,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options.clone())
+            .unwrap();
+        let mut statuses = Vec::new();
+        let result = future::poll_fn(|| {
+            let result = search.poll();
+            statuses.push(match observer.get() {
+                TextSearchStatus::Pending => None,
+                TextSearchStatus::Searching(results) => Some((false, results.len())),
+                TextSearchStatus::Ready(results) => Some((true, results.len())),
+            });
+            result
+        }).wait();
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            statuses,
+            vec![
+                Some((false, TEXT_SEARCH_FILES_PER_POLL)),
+                Some((true, TEXT_SEARCH_FILES_PER_POLL + 1)),
+            ]
+        );
+
+        // The search yields between batches, so dropping the observer cancels it before it's
+        // done.
+        let (mut search, observer) = project
+            .search_text(&SearchQuery::literal("abc"), options)
+            .unwrap();
+        let mut observer = Some(observer);
+        let result = future::poll_fn(|| {
+            let result = search.poll();
+            observer.take();
+            result
+        }).wait();
+        assert_eq!(result, Err(()));
+    }
+
+    #[test]
+    fn test_search_text_replication() {
+        use std::time::Duration;
+
+        let mut reactor = reactor::Core::new().unwrap();
+        let handle = Rc::new(reactor.handle());
+        let file_provider = Rc::new(TestFileProvider::new());
+
+        let local_project = build_project(file_provider.clone()).into_shared();
+        write_text_search_fixtures(&local_project.borrow(), &file_provider);
+        let remote_project = RemoteProject::new(
+            handle.clone(),
+            rpc::tests::connect(
+                &mut reactor,
+                ProjectService::new(local_project.clone(), handle),
+            ),
+        ).unwrap();
+
+        let options = TextSearchOptions {
+            max_results: 10,
+            include_ignored: false,
+        };
+        let (mut local_search, local_observer) = local_project
+            .borrow()
+            .search_text(&SearchQuery::literal("abc"), options.clone())
+            .unwrap();
+        assert_eq!(local_search.poll(), Ok(Async::Ready(())));
+
+        let (remote_search, remote_observer) = remote_project
+            .search_text(&SearchQuery::literal("abc"), options)
+            .unwrap();
+        reactor.run(remote_search).unwrap();
+
+        let mut remaining_tries = 10;
+        while summarize_text_results(&remote_observer.get()).is_none() {
+            remaining_tries -= 1;
+            assert!(
+                remaining_tries > 0,
+                "Ran out of patience waiting for the remote search"
+            );
+            reactor.turn(Some(Duration::from_millis(0)));
+        }
+        assert_eq!(remote_observer.get(), local_observer.get());
+    }
+
+    #[test]
+    fn test_replace() {
+        let file_provider = Rc::new(TestFileProvider::new());
+        let project = build_project(file_provider.clone());
+        write_text_search_fixtures(&project, &file_provider);
+
+        // Unsaved edits in open buffers are taken into account and see the replacements, but no
+        // file gets more matches than the search found in it on disk.
+        let relative_path_1 = cross_platform::Path::from("subdir-a/file-1");
+        let relative_path_2 = cross_platform::Path::from("subdir-b/subdir-2/foo");
+        let buffer = project.open_path(0, &relative_path_1).wait().unwrap();
+        buffer.borrow_mut().edit(&[3..3], " abc");
+
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("a(b)c")
+        };
+        let options = TextSearchOptions {
+            max_results: 10,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project.search_text(&query, options).unwrap();
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        let results = match observer.get() {
+            TextSearchStatus::Ready(results) => results,
+            _ => panic!("Expected search to be ready"),
+        };
+
+        let mut replace = ProjectReplace::new(&project, &query, "<$1>", &results)
+            .wait()
+            .unwrap();
+        assert_eq!(replace.match_count(), 3);
+        assert!(replace.set_excluded(1, true).is_ok());
+        assert!(replace.set_excluded(3, true).is_err());
+        assert_eq!(
+            replace.preview(),
+            vec![
+                ReplacePreview {
+                    tree_id: 0,
+                    relative_path: relative_path_1.clone(),
+                    display_path: "foo/subdir-a/file-1".to_string(),
+                    lines: vec![ReplacePreviewLine {
+                        row: 0,
+                        old_text: "abc abc".to_string(),
+                        new_text: "<b> abc".to_string(),
+                        matches: vec![
+                            ReplacePreviewMatch {
+                                id: 0,
+                                old_columns: 0..3,
+                                new_columns: 0..3,
+                                excluded: false,
+                            },
+                            ReplacePreviewMatch {
+                                id: 1,
+                                old_columns: 4..7,
+                                new_columns: 4..7,
+                                excluded: true,
+                            },
+                        ],
+                    }],
+                },
+                ReplacePreview {
+                    tree_id: 1,
+                    relative_path: relative_path_2.clone(),
+                    display_path: "bar/subdir-b/subdir-2/foo".to_string(),
+                    lines: vec![ReplacePreviewLine {
+                        row: 0,
+                        old_text: "_aBc_".to_string(),
+                        new_text: "_<B>_".to_string(),
+                        matches: vec![ReplacePreviewMatch {
+                            id: 2,
+                            old_columns: 1..4,
+                            new_columns: 1..4,
+                            excluded: false,
+                        }],
+                    }],
+                },
+            ]
+        );
+
+        let file_results = replace.apply().wait().unwrap();
+        assert_eq!(
+            file_results
+                .iter()
+                .map(|file_result| (
+                    file_result.tree_id,
+                    file_result.replacement_count,
+                    file_result.result.is_ok(),
+                ))
+                .collect::<Vec<_>>(),
+            vec![(0, 1, true), (1, 1, true)]
+        );
+        assert_eq!(buffer.borrow().to_string(), "<b> abc\ndef ABC");
+        let content_reader = file_provider.content_reader();
+        for &(tree_id, ref relative_path, content) in &[
+            (0, &relative_path_1, "<b> abc\ndef ABC"),
+            (1, &relative_path_2, "_<B>_"),
+        ] {
+            let absolute_path = project.resolve_path(tree_id, relative_path).unwrap();
+            assert_eq!(content_reader.read(&absolute_path).unwrap(), content);
+        }
+
+        // Matches spanning several lines are previewed along with all the lines they touch.
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal(r"c\nd")
+        };
+        let replace = ProjectReplace::new(&project, &query, "-", &results[0..1])
+            .wait()
+            .unwrap();
+        assert_eq!(
+            replace.preview()[0].lines,
+            vec![ReplacePreviewLine {
+                row: 0,
+                old_text: "<b> abc\ndef ABC".to_string(),
+                new_text: "<b> ab-ef ABC".to_string(),
+                matches: vec![ReplacePreviewMatch {
+                    id: 0,
+                    old_columns: 6..9,
+                    new_columns: 6..7,
+                    excluded: false,
+                }],
+            }]
+        );
+
+        // Matches beyond the search's maximum number of results are left alone.
+        let query = SearchQuery::literal("<");
+        let options = TextSearchOptions {
+            max_results: 1,
+            include_ignored: false,
+        };
+        let (mut search, observer) = project.search_text(&query, options).unwrap();
+        assert_eq!(search.poll(), Ok(Async::Ready(())));
+        let results = match observer.get() {
+            TextSearchStatus::Ready(results) => results,
+            _ => panic!("Expected search to be ready"),
+        };
+        let replace = ProjectReplace::new(&project, &query, "[", &results)
+            .wait()
+            .unwrap();
+        assert_eq!(replace.match_count(), 1);
+        replace.apply().wait().unwrap();
+        assert_eq!(buffer.borrow().to_string(), "[b> abc\ndef ABC");
+    }
+
     fn build_project(file_provider: Rc<TestFileProvider>) -> LocalProject {
         let tree_1 = TestTree::from_json(
             "/Users/someone/foo",
@@ -942,6 +2005,44 @@ mod tests {
         LocalProject::new(file_provider, vec![tree_1, tree_2])
     }
 
+    fn write_text_search_fixtures(project: &LocalProject, file_provider: &TestFileProvider) {
+        for &(tree_id, path, content) in &[
+            (0, "subdir-a/file-1", "abc\ndef ABC"),
+            (0, "subdir-a/subdir-1/bar", "xyz"),
+            (1, "subdir-b/subdir-2/foo", "_aBc_"),
+        ] {
+            let absolute_path = project
+                .resolve_path(tree_id, &cross_platform::Path::from(path))
+                .unwrap();
+            file_provider.write_sync(absolute_path, content);
+        }
+    }
+
+    fn summarize_text_results(
+        status: &TextSearchStatus,
+    ) -> Option<Vec<(TreeId, String, String, Vec<(u32, Range<u32>)>)>> {
+        match status {
+            &TextSearchStatus::Ready(ref results) => Some(
+                results
+                    .iter()
+                    .map(|result| {
+                        (
+                            result.tree_id,
+                            result.relative_path.to_string_lossy(),
+                            result.display_path.clone(),
+                            result
+                                .matches
+                                .iter()
+                                .map(|text_match| (text_match.row, text_match.columns.clone()))
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
     fn summarize_results(
         results: &PathSearchStatus,
     ) -> Option<Vec<(TreeId, String, String, Vec<usize>)>> {
@@ -0,0 +1,282 @@
+use regex::{self, Regex, RegexBuilder};
+use std::cmp;
+use std::ops::Range;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SearchQuery {
+    pub text: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Error {
+    EmptyQuery,
+    InvalidRegex(String),
+}
+
+pub struct Matcher {
+    regex: Regex,
+    expand_replacements: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TextMatch {
+    /// The range of the match in UTF-16 code units.
+    pub range: Range<usize>,
+    pub row: u32,
+    /// The line on which the match starts, without its newline.
+    pub line: String,
+    /// The columns of `line` covered by the match, in UTF-16 code units.
+    pub columns: Range<u32>,
+}
+
+// Translates increasing byte offsets in a UTF-8 string into offsets in UTF-16 code units, which
+// is how buffers measure their text.
+pub(crate) struct Utf16Offsets<'a> {
+    text: &'a str,
+    byte_offset: usize,
+    utf16_offset: usize,
+}
+
+impl SearchQuery {
+    pub fn literal<T: Into<String>>(text: T) -> Self {
+        SearchQuery {
+            text: text.into(),
+            regex: false,
+            case_sensitive: false,
+            whole_word: false,
+        }
+    }
+
+    pub fn matcher(&self) -> Result<Matcher, Error> {
+        if self.text.is_empty() {
+            return Err(Error::EmptyQuery);
+        }
+
+        let mut pattern = if self.regex {
+            self.text.clone()
+        } else {
+            regex::escape(&self.text)
+        };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+            .map_err(|error| Error::InvalidRegex(error.to_string()))?;
+        Ok(Matcher {
+            regex,
+            expand_replacements: self.regex,
+        })
+    }
+}
+
+impl Matcher {
+    /// Returns the ranges of the non-empty matches in the given text, measured in UTF-16 code
+    /// units.
+    pub fn find(&self, text: &[u16]) -> Vec<Range<usize>> {
+        let text = String::from_utf16_lossy(text);
+        let mut offsets = Utf16Offsets::new(&text);
+        self.regex
+            .find_iter(&text)
+            .filter(|mat| mat.start() < mat.end())
+            .map(|mat| offsets.to_utf16(mat.start())..offsets.to_utf16(mat.end()))
+            .collect()
+    }
+
+    /// Returns the non-empty matches in the given text along with the lines they start on.
+    pub fn find_lines(&self, text: &str) -> Vec<TextMatch> {
+        let mut offsets = Utf16Offsets::new(text);
+        let mut row = 0;
+        let mut line_start = 0;
+        let mut matches = Vec::new();
+        for mat in self.regex.find_iter(text) {
+            if mat.start() == mat.end() {
+                continue;
+            }
+
+            while let Some(newline_index) = text[line_start..mat.start()].find('\n') {
+                line_start += newline_index + 1;
+                row += 1;
+            }
+            let line_end = text[line_start..]
+                .find('\n')
+                .map_or(text.len(), |newline_index| line_start + newline_index);
+
+            let start_column = text[line_start..mat.start()].encode_utf16().count();
+            let end_column = start_column
+                + text[mat.start()..cmp::min(mat.end(), line_end)]
+                    .encode_utf16()
+                    .count();
+            matches.push(TextMatch {
+                range: offsets.to_utf16(mat.start())..offsets.to_utf16(mat.end()),
+                row,
+                line: text[line_start..line_end].to_string(),
+                columns: start_column as u32..end_column as u32,
+            });
+        }
+        matches
+    }
+
+    /// Whether the given replacement text refers to capture groups that need expanding for each
+    /// match, rather than replacing every match with the same text.
+    pub fn expands(&self, replacement: &str) -> bool {
+        self.expand_replacements && replacement.contains('$')
+    }
+
+    /// Returns each match along with the text that should replace it. For regex queries,
+    /// references to capture groups such as `$1` or `${name}` are expanded.
+    pub fn replacements(&self, text: &[u16], replacement: &str) -> Vec<(Range<usize>, String)> {
+        let text = String::from_utf16_lossy(text);
+        let mut offsets = Utf16Offsets::new(&text);
+        self.regex
+            .captures_iter(&text)
+            .filter_map(|captures| {
+                let mat = captures.get(0).unwrap();
+                if mat.start() == mat.end() {
+                    return None;
+                }
+
+                let range = offsets.to_utf16(mat.start())..offsets.to_utf16(mat.end());
+                let mut new_text = String::new();
+                if self.expand_replacements {
+                    captures.expand(replacement, &mut new_text);
+                } else {
+                    new_text.push_str(replacement);
+                }
+                Some((range, new_text))
+            })
+            .collect()
+    }
+}
+
+impl<'a> Utf16Offsets<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Utf16Offsets {
+            text,
+            byte_offset: 0,
+            utf16_offset: 0,
+        }
+    }
+
+    pub(crate) fn to_utf16(&mut self, byte_offset: usize) -> usize {
+        debug_assert!(byte_offset >= self.byte_offset);
+        self.utf16_offset += self.text[self.byte_offset..byte_offset]
+            .encode_utf16()
+            .count();
+        self.byte_offset = byte_offset;
+        self.utf16_offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let text = utf16("Foo foo\nfood ✓foo");
+
+        let query = SearchQuery::literal("foo");
+        assert_eq!(
+            query.matcher().unwrap().find(&text),
+            vec![0..3, 4..7, 8..11, 14..17]
+        );
+
+        let query = SearchQuery {
+            case_sensitive: true,
+            whole_word: true,
+            ..SearchQuery::literal("foo")
+        };
+        assert_eq!(query.matcher().unwrap().find(&text), vec![4..7, 14..17]);
+
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("^fo+")
+        };
+        assert_eq!(query.matcher().unwrap().find(&text), vec![0..3, 8..11]);
+
+        // Literal queries don't interpret regex syntax.
+        assert_eq!(SearchQuery::literal("o+").matcher().unwrap().find(&text), vec![]);
+        assert_eq!(
+            SearchQuery::literal("o+").matcher().unwrap().find(&utf16("fo+")),
+            vec![1..3]
+        );
+
+        assert_eq!(SearchQuery::literal("").matcher().err(), Some(Error::EmptyQuery));
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal("(")
+        };
+        assert!(query.matcher().is_err());
+    }
+
+    #[test]
+    fn test_find_lines() {
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal(r"b\w*")
+        };
+        assert_eq!(
+            query.matcher().unwrap().find_lines("abc\n✓ bd be\n\nb"),
+            vec![
+                TextMatch {
+                    range: 1..3,
+                    row: 0,
+                    line: "abc".to_string(),
+                    columns: 1..3,
+                },
+                TextMatch {
+                    range: 6..8,
+                    row: 1,
+                    line: "✓ bd be".to_string(),
+                    columns: 2..4,
+                },
+                TextMatch {
+                    range: 9..11,
+                    row: 1,
+                    line: "✓ bd be".to_string(),
+                    columns: 5..7,
+                },
+                TextMatch {
+                    range: 13..14,
+                    row: 3,
+                    line: "b".to_string(),
+                    columns: 0..1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replacements() {
+        let text = utf16("a1 ✓ b22");
+
+        let query = SearchQuery {
+            regex: true,
+            ..SearchQuery::literal(r"([a-z])(\d+)")
+        };
+        assert_eq!(
+            query.matcher().unwrap().replacements(&text, "$2$1"),
+            vec![(0..2, "1a".to_string()), (5..8, "22b".to_string())]
+        );
+
+        let query = SearchQuery::literal("b22");
+        assert_eq!(
+            query.matcher().unwrap().replacements(&text, "$0"),
+            vec![(5..8, "$0".to_string())]
+        );
+    }
+
+    fn utf16(text: &str) -> Vec<u16> {
+        text.encode_utf16().collect()
+    }
+}
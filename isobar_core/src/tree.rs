@@ -201,7 +201,6 @@ impl<'a, T: Item> Tree<T> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn splice<D: Dimension<Summary = T::Summary>, I: IntoIterator<Item = T>>(
         &mut self,
         old_range: Range<&D>,
@@ -239,7 +238,7 @@ impl<'a, T: Item> Tree<T> {
                 let node_end = node_start.clone() + &D::from_summary(summary);
                 if *start <= node_start && node_end <= *end {
                     result.push_tree(self.clone());
-                } else if node_start < *end || *start < node_end {
+                } else if node_start < *end && *start < node_end {
                     let mut child_start = node_start.clone();
                     for ref child in children {
                         child.append_subsequence_recursive(result, child_start.clone(), start, end);
@@ -3,6 +3,7 @@ use buffer_view::{BufferView, BufferViewDelegate};
 use cross_platform;
 use file_finder::{FileFinderView, FileFinderViewDelegate};
 use futures::{Future, Poll, Stream};
+use highlight::{GrammarRegistry, Tokenizer};
 use never::Never;
 use notify_cell::NotifyCell;
 use notify_cell::NotifyCellObserver;
@@ -16,6 +17,7 @@ use std::cell::{Ref, RefCell, RefMut};
 use std::ops::Range;
 use std::rc::Rc;
 use window::{View, ViewHandle, WeakViewHandle, WeakWindowHandle, Window};
+use BackgroundExecutor;
 use ForegroundExecutor;
 use IntoShared;
 use UserId;
@@ -39,6 +41,7 @@ pub struct RemoteWorkspace {
 
 pub struct WorkspaceService {
     workspace: Rc<RefCell<LocalWorkspace>>,
+    background: BackgroundExecutor,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -50,6 +53,7 @@ pub struct ServiceState {
 pub struct WorkspaceView {
     foreground: ForegroundExecutor,
     workspace: Rc<RefCell<Workspace>>,
+    grammars: Rc<RefCell<GrammarRegistry>>,
     active_buffer_view: Option<WeakViewHandle<BufferView>>,
     center_pane: Option<ViewHandle>,
     modal: Option<ViewHandle>,
@@ -125,8 +129,11 @@ impl Workspace for RemoteWorkspace {
 }
 
 impl WorkspaceService {
-    pub fn new(workspace: Rc<RefCell<LocalWorkspace>>) -> Self {
-        Self { workspace }
+    pub fn new(workspace: Rc<RefCell<LocalWorkspace>>, background: BackgroundExecutor) -> Self {
+        Self {
+            workspace,
+            background,
+        }
     }
 }
 
@@ -143,17 +150,25 @@ impl server::Service for WorkspaceService {
         ServiceState {
             user_id,
             project: connection
-                .add_service(ProjectService::new(workspace.project.clone()))
+                .add_service(ProjectService::new(
+                    workspace.project.clone(),
+                    self.background.clone(),
+                ))
                 .service_id(),
         }
     }
 }
 
 impl WorkspaceView {
-    pub fn new(foreground: ForegroundExecutor, workspace: Rc<RefCell<Workspace>>) -> Self {
+    pub fn new(
+        foreground: ForegroundExecutor,
+        workspace: Rc<RefCell<Workspace>>,
+        grammars: Rc<RefCell<GrammarRegistry>>,
+    ) -> Self {
         WorkspaceView {
             workspace,
             foreground,
+            grammars,
             active_buffer_view: None,
             center_pane: None,
             modal: None,
@@ -176,7 +191,7 @@ impl WorkspaceView {
         self.updates.set(());
     }
 
-    fn open_buffer<T>(&self, buffer: T)
+    fn open_buffer<T>(&self, buffer: T, tokenizer: Option<Rc<Tokenizer>>)
     where
         T: 'static + Future<Item = Rc<RefCell<Buffer>>, Error = project::Error>,
     {
@@ -191,6 +206,9 @@ impl WorkspaceView {
                                 let mut buffer_view =
                                     BufferView::new(buffer, user_id, Some(view_handle.clone()));
                                 buffer_view.set_line_height(20.0);
+                                if let Some(tokenizer) = tokenizer {
+                                    buffer_view.set_tokenizer(tokenizer);
+                                }
                                 let buffer_view = window.add_view(buffer_view);
                                 buffer_view.focus().unwrap();
                                 view_handle.map(|view| {
@@ -281,7 +299,11 @@ impl FileFinderViewDelegate for WorkspaceView {
 
     fn did_confirm(&mut self, tree_id: TreeId, path: &cross_platform::Path, _: &mut Window) {
         let workspace = self.workspace.borrow();
-        self.open_buffer(workspace.project().open_path(tree_id, path));
+        let grammar = self.grammars.borrow().grammar_for_path(path);
+        self.open_buffer(
+            workspace.project().open_path(tree_id, path),
+            grammar.map(|grammar| grammar as Rc<Tokenizer>),
+        );
     }
 }
 
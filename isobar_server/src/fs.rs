@@ -23,6 +23,8 @@ pub struct Tree {
 
 pub struct FileProvider;
 
+struct ContentReader;
+
 pub struct File {
     id: isobar_fs::FileId,
     file: Arc<Mutex<fs::File>>,
@@ -155,6 +157,16 @@ impl isobar_fs::FileProvider for FileProvider {
                 .map(|file| Box::new(file) as Box<isobar_fs::File>),
         )
     }
+
+    fn content_reader(&self) -> Arc<isobar_fs::ContentReader> {
+        Arc::new(ContentReader)
+    }
+}
+
+impl isobar_fs::ContentReader for ContentReader {
+    fn read(&self, path: &cross_platform::Path) -> Result<String, io::Error> {
+        fs::read_to_string(path.to_path_buf())
+    }
 }
 
 impl File {
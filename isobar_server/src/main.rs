@@ -19,6 +19,7 @@ extern crate tokio_uds;
 extern crate isobar_core;
 
 use std::env;
+use std::path::Path;
 use futures::Stream;
 use tokio_core::reactor::Core;
 use tokio_io::AsyncRead;
@@ -36,6 +37,9 @@ fn main() {
     let mut core = Core::new().unwrap();
     let handle = core.handle();
     let mut server = Server::new(headless, handle.clone());
+    if let Some(grammars_path) = env::var_os("ISOBAR_GRAMMARS_PATH") {
+        server.load_grammars(Path::new(&grammars_path));
+    }
 
     let _ = std::fs::remove_file(&socket_path);
     let listener = UnixListener::bind(socket_path, &handle).unwrap();
@@ -5,14 +5,16 @@ use futures_cpupool::CpuPool;
 use messages::{IncomingMessage, OutgoingMessage};
 use std::cell::RefCell;
 use std::error::Error;
+use std::fs as std_fs;
 use std::io;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use tokio_core::net::{TcpListener, TcpStream};
 use tokio_core::reactor;
 use tokio_io::codec;
 use isobar_core::app::Command;
+use isobar_core::highlight::Grammar;
 use isobar_core::{self, App, Never, WindowId};
 
 #[derive(Clone)]
@@ -32,6 +34,33 @@ impl Server {
         }
     }
 
+    /// Loads every TextMate grammar in the given directory. Grammars that can't be read are
+    /// reported and skipped.
+    pub fn load_grammars(&self, dir_path: &Path) {
+        let entries = match std_fs::read_dir(dir_path) {
+            Ok(entries) => entries,
+            Err(error) => {
+                eprintln!("Error reading grammars from {:?}: {}", dir_path, error);
+                return;
+            }
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if !path.to_string_lossy().ends_with(".tmLanguage.json") {
+                continue;
+            }
+
+            let grammar = std_fs::read_to_string(&path)
+                .map_err(|error| error.description().to_owned())
+                .and_then(|json| Grammar::from_json(&json).map_err(|error| format!("{:?}", error)));
+            match grammar {
+                Ok(grammar) => self.app.borrow_mut().add_grammar(grammar),
+                Err(error) => eprintln!("Error loading grammar {:?}: {}", path, error),
+            }
+        }
+    }
+
     pub fn accept_connection<'a, S>(&mut self, socket: S)
     where
         S: 'static
@@ -24,7 +24,18 @@ const theme = {
     { r: 206, g: 157, b: 59, a: 1 },
     { r: 216, g: 49, b: 176, a: 1 },
     { r: 235, g: 221, b: 91, a: 1 }
-  ]
+  ],
+  // Keyed by the first segment of a token's scope, e.g. "comment" for "comment.line".
+  syntaxColors: {
+    comment: { r: 128, g: 128, b: 128, a: 255 },
+    constant: { r: 170, g: 90, b: 20, a: 255 },
+    entity: { r: 30, g: 90, b: 200, a: 255 },
+    keyword: { r: 160, g: 40, b: 160, a: 255 },
+    storage: { r: 160, g: 40, b: 160, a: 255 },
+    string: { r: 60, g: 140, b: 60, a: 255 },
+    support: { r: 30, g: 130, b: 160, a: 255 },
+    variable: { r: 180, g: 50, b: 50, a: 255 }
+  }
 };
 
 // TODO: Eventually, the keyBindings should be provided to the view by the server
@@ -83,7 +94,18 @@ const keyBindings = [
   { key: "cmd-up", context: "TextEditor", action: "MoveToTop" },
   { key: "cmd-down", context: "TextEditor", action: "MoveToBottom" },
   { key: "backspace", context: "TextEditor", action: "Backspace" },
-  { key: "delete", context: "TextEditor", action: "Delete" }
+  { key: "delete", context: "TextEditor", action: "Delete" },
+  { key: "cmd-z", context: "TextEditor", action: "Undo" },
+  { key: "ctrl-z", context: "TextEditor", action: "Undo" },
+  { key: "shift-cmd-z", context: "TextEditor", action: "Redo" },
+  { key: "ctrl-y", context: "TextEditor", action: "Redo" },
+  { key: "cmd-g", context: "TextEditor", action: "SelectNextMatch" },
+  { key: "shift-cmd-g", context: "TextEditor", action: "SelectPreviousMatch" },
+  { key: "ctrl-cmd-g", context: "TextEditor", action: "SelectAllMatches" },
+  { key: "ctrl-cmd-[", context: "TextEditor", action: "Fold" },
+  { key: "ctrl-cmd-]", context: "TextEditor", action: "Unfold" },
+  { key: "ctrl-shift-cmd-{", context: "TextEditor", action: "FoldAll" },
+  { key: "ctrl-shift-cmd-}", context: "TextEditor", action: "UnfoldAll" }
 ];
 
 const styletronInstance = new StyletronClient();
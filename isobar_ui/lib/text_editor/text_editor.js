@@ -9,6 +9,7 @@ const { ActionContext, Action } = require("../action_dispatcher");
 
 const CURSOR_BLINK_RESUME_DELAY = 300;
 const CURSOR_BLINK_PERIOD = 800;
+const FOLD_PLACEHOLDER = " \u22ef";
 
 const Root = styled("div", {
   width: "100%",
@@ -86,11 +87,31 @@ class TextEditor extends React.Component {
     this.resizeObserver.disconnect();
   }
 
+  componentDidUpdate() {
+    // The core wraps lines based on the width of characters and of the gutter,
+    // which can only be measured once the text plane is ready and which change
+    // as the number of rows grows.
+    const { width, height, char_width, gutter_width } = this.props;
+    const charWidth = this.getBaseCharacterWidth();
+    const gutterWidth = this.getGutterWidth();
+    if (
+      width != null &&
+      height != null &&
+      charWidth != null &&
+      gutterWidth != null &&
+      (charWidth !== char_width || gutterWidth !== gutter_width)
+    ) {
+      this.componentDidResize({ width, height });
+    }
+  }
+
   componentDidResize(measurements) {
     this.props.dispatch({
       type: "SetDimensions",
       width: measurements.width,
-      height: measurements.height
+      height: measurements.height,
+      char_width: this.getBaseCharacterWidth(),
+      gutter_width: this.getGutterWidth()
     });
   }
 
@@ -120,7 +141,9 @@ class TextEditor extends React.Component {
           selections: this.props.selections,
           firstVisibleRow: this.props.first_visible_row,
           totalRowCount: this.props.total_row_count,
-          lines: this.props.lines,
+          lines: this.getLinesWithFoldPlaceholders(),
+          lineNumbers: this.props.line_numbers,
+          tokens: this.props.tokens,
           ref: textPlane => {
             this.textPlane = textPlane;
           }
@@ -206,10 +229,43 @@ class TextEditor extends React.Component {
         type: "Backspace",
         onWillDispatch: this.pauseCursorBlinking
       }),
-      $(Action, { type: "Delete", onWillDispatch: this.pauseCursorBlinking })
+      $(Action, { type: "Delete", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, { type: "Undo", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, { type: "Redo", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, {
+        type: "SelectNextMatch",
+        onWillDispatch: this.pauseCursorBlinking
+      }),
+      $(Action, {
+        type: "SelectPreviousMatch",
+        onWillDispatch: this.pauseCursorBlinking
+      }),
+      $(Action, {
+        type: "SelectAllMatches",
+        onWillDispatch: this.pauseCursorBlinking
+      }),
+      $(Action, { type: "Fold", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, { type: "Unfold", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, { type: "FoldAll", onWillDispatch: this.pauseCursorBlinking }),
+      $(Action, {
+        type: "UnfoldAll",
+        onWillDispatch: this.pauseCursorBlinking
+      })
     );
   }
 
+  getLinesWithFoldPlaceholders() {
+    const { lines, first_visible_row, fold_placeholders } = this.props;
+    if (!fold_placeholders || fold_placeholders.length === 0) return lines;
+
+    const linesWithPlaceholders = lines.slice();
+    for (let i = 0; i < fold_placeholders.length; i++) {
+      const index = fold_placeholders[i].row - first_visible_row;
+      linesWithPlaceholders[index] += FOLD_PLACEHOLDER;
+    }
+    return linesWithPlaceholders;
+  }
+
   handleMouseDown(event) {
     if (this.canUseTextPlane()) {
       this.handleClick(event);
@@ -29,7 +29,7 @@ class TextPlane extends React.Component {
   async componentDidUpdate() {
     if (this.canvas == null) return;
 
-    const { userColors } = this.context.theme;
+    const { userColors, syntaxColors } = this.context.theme;
     const {
       fontFamily,
       fontSize,
@@ -67,10 +67,13 @@ class TextPlane extends React.Component {
       firstVisibleRow: this.props.firstVisibleRow,
       totalRowCount: this.props.totalRowCount,
       lines: this.props.lines,
+      lineNumbers: this.props.lineNumbers,
+      tokens: this.props.tokens,
       selections: this.props.selections,
       showLocalCursors: this.props.showLocalCursors,
       selectionColors,
       cursorColors,
+      syntaxColors,
       computedLineHeight
     });
   }
@@ -388,10 +391,13 @@ class Renderer {
     firstVisibleRow,
     totalRowCount,
     lines,
+    lineNumbers,
+    tokens,
     selections,
     showLocalCursors,
     selectionColors,
-    cursorColors
+    cursorColors,
+    syntaxColors
   }) {
     const { dpiScale } = this.style;
     const viewportScaleX = 2 / canvasWidth;
@@ -412,8 +418,7 @@ class Renderer {
     const gutterGlyphCount = this.populateGutterGlyphInstances(
       scrollTop,
       firstVisibleRow,
-      firstVisibleRow + lines.length,
-      totalRowCount,
+      lineNumbers,
       textColor
     );
     const lineGlyphCount = this.populateLineGlyphInstances(
@@ -421,8 +426,10 @@ class Renderer {
       firstVisibleRow,
       gutterWidth + paddingLeft,
       lines,
+      tokens,
       selections,
       textColor,
+      syntaxColors,
       xPositions
     );
     const {
@@ -638,16 +645,16 @@ class Renderer {
   populateGutterGlyphInstances(
     scrollTop,
     firstVisibleRow,
-    lastVisibleRow,
-    totalRowCount,
+    lineNumbers,
     textColor
   ) {
     const firstVisibleRowY = firstVisibleRow * this.style.computedLineHeight;
     let glyphCount = 0;
     let y = Math.round((firstVisibleRowY - scrollTop) * this.style.dpiScale);
 
-    for (let row = firstVisibleRow; row < lastVisibleRow; row++) {
-      const text = (row + 1).toString();
+    for (let i = 0; i < lineNumbers.length; i++) {
+      // Rows continuing a wrapped line have no number.
+      const text = lineNumbers[i] == null ? "" : lineNumbers[i].toString();
       let x = 0;
       for (let i = 0; i < text.length; i++) {
         const char = text[i];
@@ -683,8 +690,10 @@ class Renderer {
     firstVisibleRow,
     paddingLeft,
     lines,
+    tokens,
     selections,
     textColor,
+    syntaxColors,
     xPositions
   ) {
     const firstVisibleRowY = firstVisibleRow * this.style.computedLineHeight;
@@ -698,6 +707,8 @@ class Renderer {
       position.row = firstVisibleRow + i;
       let x = paddingLeft;
       const line = lines[i];
+      const lineTokens = (tokens && tokens[i]) || [];
+      let tokenIndex = 0;
 
       for (
         position.column = 0;
@@ -715,13 +726,25 @@ class Renderer {
             Math.round(x * SUBPIXEL_DIVISOR) % SUBPIXEL_DIVISOR;
           const glyph = this.atlas.getGlyph(char, variantIndex);
 
+          while (
+            tokenIndex < lineTokens.length &&
+            lineTokens[tokenIndex].end <= position.column
+          ) {
+            tokenIndex++;
+          }
+          const token = lineTokens[tokenIndex];
+          const color =
+            token && token.start <= position.column
+              ? colorForScope(token.scope, syntaxColors, textColor)
+              : textColor;
+
           this.updateGlyphInstance(
             this.lineGlyphInstances,
             glyphCount++,
             Math.round(x - glyph.variantOffset),
             y,
             glyph,
-            textColor
+            color
           );
 
           x += glyph.subpixelWidth;
@@ -1045,3 +1068,8 @@ function comparePoints(a, b) {
 function keyForPoint(point) {
   return `${point.row}.${point.column}`;
 }
+
+function colorForScope(scope, syntaxColors, defaultColor) {
+  const color = syntaxColors && syntaxColors[scope.split(".")[0]];
+  return color || defaultColor;
+}
@@ -1,2 +1,9 @@
 export let isobar = import("../dist/isobar_wasm");
 export { JsSink } from "./support";
+
+// Runs the whole app in this process, on a tree that only exists in memory.
+// Directories in `files` are objects and files are strings with their contents.
+export async function createInMemoryServer(rootName, files) {
+  const { Server } = await isobar;
+  return Server.new_in_memory(rootName, JSON.stringify(files));
+}
@@ -8,6 +8,8 @@ extern crate serde;
 extern crate serde_derive;
 extern crate serde_json;
 
+mod memory_fs;
+
 use bytes::Bytes;
 use futures::executor::{self, Notify, Spawn};
 use futures::unsync::mpsc;
@@ -249,6 +251,25 @@ impl Sink for JsSink {
 #[wasm_bindgen]
 impl Server {
     pub fn new() -> Self {
+        Self::with_file_provider(FileProvider)
+    }
+
+    /// Runs the app without a server, opening a workspace on a tree that lives in memory. The
+    /// tree's contents are described by a JSON object in which directories are objects and files
+    /// are strings holding their contents.
+    pub fn new_in_memory(root_name: &str, files_json: &str) -> Result<Server, JsValue> {
+        let files = serde_json::from_str(files_json)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let file_provider = memory_fs::FileProvider::new();
+        let tree = memory_fs::Tree::from_json(root_name, &files, &file_provider)
+            .map_err(|error| JsValue::from_str(&error))?;
+
+        let server = Self::with_file_provider(file_provider);
+        server.app.borrow_mut().open_local_workspace(vec![tree]);
+        Ok(server)
+    }
+
+    fn with_file_provider<T: 'static + isobar_core::fs::FileProvider>(file_provider: T) -> Self {
         let foreground_executor = Rc::new(Executor::new());
         // TODO: use a requestIdleCallback-based executor here instead.
         let background_executor = foreground_executor.clone();
@@ -257,7 +278,7 @@ impl Server {
                 false,
                 foreground_executor.clone(),
                 background_executor.clone(),
-                FileProvider,
+                file_provider,
             ),
             executor: Executor::new(),
         }
@@ -358,6 +379,10 @@ impl isobar_core::fs::FileProvider for FileProvider {
     ) -> Box<Future<Item = Box<isobar_core::fs::File>, Error = io::Error>> {
         unimplemented!()
     }
+
+    fn content_reader(&self) -> Arc<isobar_core::fs::ContentReader> {
+        unimplemented!()
+    }
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,189 @@
+use futures::{future, Future, Stream};
+use isobar_core::buffer::BufferSnapshot;
+use isobar_core::cross_platform;
+use isobar_core::fs as isobar_fs;
+use isobar_core::notify_cell::NotifyCell;
+use serde_json;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A tree that lives entirely in memory, for running the app without a server.
+pub struct Tree {
+    path: cross_platform::Path,
+    root: isobar_fs::Entry,
+    updates: NotifyCell<()>,
+}
+
+pub struct FileProvider(Rc<RefCell<FileProviderState>>);
+
+struct FileProviderState {
+    next_file_id: isobar_fs::FileId,
+    files: HashMap<cross_platform::Path, File>,
+}
+
+#[derive(Clone)]
+struct File(Rc<RefCell<FileState>>);
+
+// A snapshot of every file's contents, which can be searched away from the file provider.
+struct ContentReader(HashMap<cross_platform::Path, String>);
+
+struct FileState {
+    id: isobar_fs::FileId,
+    content: String,
+}
+
+impl Tree {
+    /// Builds a tree named `root_name` from a JSON object in which directories are objects and
+    /// files are strings holding their contents, adding each file to `file_provider`.
+    pub fn from_json(
+        root_name: &str,
+        json: &serde_json::Value,
+        file_provider: &FileProvider,
+    ) -> Result<Self, String> {
+        let path = cross_platform::Path::from(root_name);
+        let root = isobar_fs::Entry::dir(root_name.into(), false, false);
+        match json {
+            serde_json::Value::Object(children) => {
+                Self::populate(&root, &path, children, file_provider)?
+            }
+            _ => return Err(format!("The contents of {} must be an object", root_name)),
+        }
+
+        Ok(Self {
+            path,
+            root,
+            updates: NotifyCell::new(()),
+        })
+    }
+
+    fn populate(
+        dir: &isobar_fs::Entry,
+        dir_path: &cross_platform::Path,
+        children: &serde_json::Map<String, serde_json::Value>,
+        file_provider: &FileProvider,
+    ) -> Result<(), String> {
+        for (name, value) in children {
+            let name = cross_platform::PathComponent::from(name.as_str());
+            let mut path = dir_path.clone();
+            path.push(&name);
+
+            match value {
+                serde_json::Value::Object(children) => {
+                    let child_dir = isobar_fs::Entry::dir(name, false, false);
+                    dir.insert(child_dir.clone()).unwrap();
+                    Self::populate(&child_dir, &path, children, file_provider)?;
+                }
+                serde_json::Value::String(content) => {
+                    dir.insert(isobar_fs::Entry::file(name, false, false))
+                        .unwrap();
+                    file_provider.insert(path, content.clone());
+                }
+                _ => {
+                    return Err(format!(
+                        "{} must be an object or a string",
+                        name.to_string_lossy()
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl isobar_fs::Tree for Tree {
+    fn root(&self) -> isobar_fs::Entry {
+        self.root.clone()
+    }
+
+    fn updates(&self) -> Box<Stream<Item = (), Error = ()>> {
+        Box::new(self.updates.observe())
+    }
+}
+
+impl isobar_fs::LocalTree for Tree {
+    fn path(&self) -> &cross_platform::Path {
+        &self.path
+    }
+
+    fn populated(&self) -> Box<Future<Item = (), Error = ()>> {
+        Box::new(future::ok(()))
+    }
+
+    fn as_tree(&self) -> &isobar_fs::Tree {
+        self
+    }
+}
+
+impl FileProvider {
+    pub fn new() -> Self {
+        FileProvider(Rc::new(RefCell::new(FileProviderState {
+            next_file_id: 0,
+            files: HashMap::new(),
+        })))
+    }
+
+    fn insert(&self, path: cross_platform::Path, content: String) {
+        let mut state = self.0.borrow_mut();
+        let id = state.next_file_id;
+        state.next_file_id += 1;
+        state
+            .files
+            .insert(path, File(Rc::new(RefCell::new(FileState { id, content }))));
+    }
+}
+
+impl isobar_fs::FileProvider for FileProvider {
+    fn open(
+        &self,
+        path: &cross_platform::Path,
+    ) -> Box<Future<Item = Box<isobar_fs::File>, Error = io::Error>> {
+        Box::new(future::result(
+            self.0
+                .borrow()
+                .files
+                .get(path)
+                .map(|file| Box::new(file.clone()) as Box<isobar_fs::File>)
+                .ok_or(io::Error::new(io::ErrorKind::NotFound, "Path not found")),
+        ))
+    }
+
+    fn content_reader(&self) -> Arc<isobar_fs::ContentReader> {
+        let files = self.0
+            .borrow()
+            .files
+            .iter()
+            .map(|(path, file)| (path.clone(), file.0.borrow().content.clone()))
+            .collect();
+        Arc::new(ContentReader(files))
+    }
+}
+
+impl isobar_fs::ContentReader for ContentReader {
+    fn read(&self, path: &cross_platform::Path) -> Result<String, io::Error> {
+        self.0
+            .get(path)
+            .cloned()
+            .ok_or(io::Error::new(io::ErrorKind::NotFound, "Path not found"))
+    }
+}
+
+impl isobar_fs::File for File {
+    fn id(&self) -> isobar_fs::FileId {
+        self.0.borrow().id
+    }
+
+    fn read(&self) -> Box<Future<Item = String, Error = io::Error>> {
+        Box::new(future::ok(self.0.borrow().content.clone()))
+    }
+
+    fn write_snapshot(
+        &self,
+        snapshot: BufferSnapshot,
+    ) -> Box<Future<Item = (), Error = io::Error>> {
+        self.0.borrow_mut().content = snapshot.to_string();
+        Box::new(future::ok(()))
+    }
+}
@@ -1,5 +1,9 @@
 import assert from "assert";
-import { isobar as isobarPromise, JsSink } from "../lib/main";
+import {
+  createInMemoryServer,
+  isobar as isobarPromise,
+  JsSink
+} from "../lib/main";
 
 suite("Server", () => {
   let isobar;
@@ -37,4 +41,44 @@ suite("Server", () => {
       sender.send([i++]);
     }, 1);
   });
+
+  test("in-memory workspaces", endTest => {
+    createInMemoryServer("playground", {
+      src: { "main.rs": "fn main() {}\n" },
+      "README.md": "# Playground\n"
+    }).then(server => {
+      const sink = new JsSink({
+        send(message) {
+          const command = parseMessage(message);
+          assert.equal(command.type, "OpenWindow");
+
+          const channel = isobar.Channel.new();
+          server.start_window(
+            command.window_id,
+            channel.take_receiver(),
+            new JsSink({
+              send(message) {
+                const update = parseMessage(message);
+                assert.equal(update.type, "UpdateWindow");
+                assert(update.updated.length > 0);
+                endTest();
+              }
+            })
+          );
+        }
+      });
+      server.start_app(sink);
+    });
+  });
+
+  test("in-memory workspaces with invalid contents", async () => {
+    await assert.rejects(
+      createInMemoryServer("playground", { "a.txt": 1 }),
+      /must be an object or a string/
+    );
+  });
 });
+
+function parseMessage(message) {
+  return JSON.parse(Buffer.from(message).toString());
+}
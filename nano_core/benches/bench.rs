@@ -0,0 +1,274 @@
+// Each benchmark runs against buffers of increasing size, so that the growth of the measured time
+// can be compared with the growth of the buffer. Conversions between points, offsets and anchors
+// should only take a little longer on a buffer with a million lines than on one with ten thousand.
+// Diffing compares character mode, whose time grows quadratically and is only measured on small
+// texts, with line mode on texts of every size.
+// Loading base entries compares building an epoch's trees in one pass with appending them in the
+// chunks a work tree receives from its git provider, and with the old path of pushing one item
+// at a time. With the `parallel` feature, it also compares summarizing the items serially and in
+// parallel.
+
+use criterion::{criterion_group, criterion_main, Bencher, Criterion};
+use nano_core::{diff, time, Buffer, DiffMode, DirEntry, Epoch, FileType, Point};
+#[cfg(feature = "parallel")]
+use rayon::ThreadPoolBuilder;
+use std::ffi::OsString;
+use std::ops::Range;
+use uuid::Uuid;
+
+const LINE_COUNTS: [u32; 3] = [10_000, 100_000, 1_000_000];
+const DIFF_LINE_COUNTS: [u32; 3] = [50, 100, 200];
+const ENTRY_COUNTS: [usize; 3] = [1_000, 10_000, 100_000];
+const ENTRIES_PER_CHUNK: usize = 500;
+const FILES_PER_DIR: usize = 100;
+
+// A prime stride visits rows far apart from each other, so that consecutive lookups share as
+// little of their path through the trees as possible.
+const ROW_STRIDE: u32 = 7919;
+
+struct Replica {
+    buffer: Buffer,
+    local_clock: time::Local,
+    lamport_clock: time::Lamport,
+}
+
+fn positions(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "anchor_before_point",
+        |b: &mut Bencher, lines: &u32| {
+            let replica = Replica::new(*lines);
+            let mut row = 0;
+            b.iter(|| {
+                row = (row + ROW_STRIDE) % lines;
+                replica
+                    .buffer
+                    .anchor_before_point(Point::new(row, 3))
+                    .unwrap()
+            })
+        },
+        LINE_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "point_for_anchor",
+        |b: &mut Bencher, lines: &u32| {
+            let replica = Replica::new(*lines);
+            let anchors = (0..*lines)
+                .map(|row| {
+                    replica
+                        .buffer
+                        .anchor_before_point(Point::new(row, 3))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let mut row = 0;
+            b.iter(|| {
+                row = (row + ROW_STRIDE) % lines;
+                replica
+                    .buffer
+                    .point_for_anchor(&anchors[row as usize])
+                    .unwrap()
+            })
+        },
+        LINE_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "len_for_row",
+        |b: &mut Bencher, lines: &u32| {
+            let replica = Replica::new(*lines);
+            let mut row = 0;
+            b.iter(|| {
+                row = (row + ROW_STRIDE) % lines;
+                replica.buffer.len_for_row(row).unwrap()
+            })
+        },
+        LINE_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "line",
+        |b: &mut Bencher, lines: &u32| {
+            let replica = Replica::new(*lines);
+            let mut row = 0;
+            b.iter(|| {
+                row = (row + ROW_STRIDE) % lines;
+                replica.buffer.line(row).unwrap()
+            })
+        },
+        LINE_COUNTS.to_vec(),
+    );
+}
+
+fn edits(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "edit_in_middle",
+        |b: &mut Bencher, lines: &u32| {
+            let mut replica = Replica::new(*lines);
+            let middle = replica.buffer.len() / 2;
+            b.iter(|| replica.edit(middle..middle + 1, "y"))
+        },
+        LINE_COUNTS.to_vec(),
+    );
+}
+
+fn diffs(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "diff_character",
+        |b: &mut Bencher, lines: &u32| {
+            let (old_text, new_text) = diff_texts(*lines);
+            b.iter(|| diff(&old_text, &new_text, DiffMode::Character).count())
+        },
+        DIFF_LINE_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "diff_line",
+        |b: &mut Bencher, lines: &u32| {
+            let (old_text, new_text) = diff_texts(*lines);
+            b.iter(|| diff(&old_text, &new_text, DiffMode::Line).count())
+        },
+        DIFF_LINE_COUNTS
+            .iter()
+            .chain(LINE_COUNTS.iter())
+            .cloned()
+            .collect(),
+    );
+}
+
+// Returns a text and a copy in which every tenth line has been changed.
+fn diff_texts(lines: u32) -> (String, String) {
+    let mut old_text = String::new();
+    let mut new_text = String::new();
+    for i in 0..lines {
+        old_text.push_str(&format!("Lorem ipsum dolor sit amet {}\n", i));
+        if i % 10 == 0 {
+            new_text.push_str(&format!("Lorem ipsum dolor amet {}!\n", i));
+        } else {
+            new_text.push_str(&format!("Lorem ipsum dolor sit amet {}\n", i));
+        }
+    }
+    (old_text, new_text)
+}
+
+fn base_entries(c: &mut Criterion) {
+    c.bench_function_over_inputs(
+        "append_base_entries_at_once",
+        |b: &mut Bencher, count: &usize| {
+            let entries = dir_entries(*count);
+            b.iter(|| load_base_entries(&entries, entries.len()))
+        },
+        ENTRY_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "append_base_entries_in_chunks",
+        |b: &mut Bencher, count: &usize| {
+            let entries = dir_entries(*count);
+            b.iter(|| load_base_entries(&entries, ENTRIES_PER_CHUNK))
+        },
+        ENTRY_COUNTS.to_vec(),
+    );
+    c.bench_function_over_inputs(
+        "append_base_entries_one_at_a_time",
+        |b: &mut Bencher, count: &usize| {
+            let entries = dir_entries(*count);
+            b.iter(|| load_base_entries(&entries, 1))
+        },
+        ENTRY_COUNTS.to_vec(),
+    );
+}
+
+// Loads the same entries on a thread pool with a single thread and on one with a thread per
+// core, so the difference is the time saved by summarizing items in parallel.
+#[cfg(feature = "parallel")]
+fn parallel_base_entries(c: &mut Criterion) {
+    for (name, thread_count) in &[
+        ("append_base_entries_serially", 1),
+        ("append_base_entries_in_parallel", 0),
+    ] {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(*thread_count)
+            .build()
+            .unwrap();
+        c.bench_function_over_inputs(
+            name,
+            move |b: &mut Bencher, count: &usize| {
+                let entries = dir_entries(*count);
+                b.iter(|| pool.install(|| load_base_entries(&entries, entries.len())))
+            },
+            ENTRY_COUNTS.to_vec(),
+        );
+    }
+}
+
+fn load_base_entries(entries: &[DirEntry], chunk_size: usize) -> Epoch {
+    let mut epoch = Epoch::new(Uuid::from_u128(1), time::Lamport::default(), None);
+    let mut lamport_clock = time::Lamport::new(Uuid::from_u128(1));
+    for chunk in entries.chunks(chunk_size) {
+        epoch
+            .append_base_entries(chunk.iter().cloned(), &mut lamport_clock)
+            .unwrap();
+    }
+    epoch
+}
+
+fn dir_entries(count: usize) -> Vec<DirEntry> {
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        if i % (FILES_PER_DIR + 1) == 0 {
+            entries.push(DirEntry {
+                depth: 1,
+                name: OsString::from(format!("dir-{}", i)),
+                file_type: FileType::Directory,
+            });
+        } else {
+            entries.push(DirEntry {
+                depth: 2,
+                name: OsString::from(format!("file-{}.txt", i)),
+                file_type: FileType::Text,
+            });
+        }
+    }
+    entries
+}
+
+impl Replica {
+    // Builds a buffer whose text is split into many fragments, as it would be after a long
+    // editing session, rather than a single fragment containing the base text.
+    fn new(lines: u32) -> Self {
+        let mut text = String::new();
+        for i in 0..lines {
+            text.push_str(&format!("Lorem ipsum dolor sit amet {}\n", i));
+        }
+
+        let replica_id = Uuid::from_u128(1);
+        let mut replica = Replica {
+            buffer: Buffer::new(text),
+            local_clock: time::Local::new(replica_id),
+            lamport_clock: time::Lamport::new(replica_id),
+        };
+        for row in (0..lines).step_by(100) {
+            let offset = replica.buffer.len() * row as usize / lines as usize;
+            replica.edit(offset..offset + 1, "L");
+        }
+        replica
+    }
+
+    fn edit(&mut self, range: Range<usize>, text: &str) {
+        self.buffer.edit(
+            Some(range),
+            text,
+            &mut self.local_clock,
+            &mut self.lamport_clock,
+        );
+    }
+}
+
+#[cfg(feature = "parallel")]
+criterion_group!(
+    benches,
+    positions,
+    edits,
+    diffs,
+    base_entries,
+    parallel_base_entries
+);
+#[cfg(not(feature = "parallel"))]
+criterion_group!(benches, positions, edits, diffs, base_entries);
+criterion_main!(benches);
@@ -1,3 +1,5 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use smallvec::SmallVec;
 use std::cmp::Ordering;
 use std::fmt;
@@ -9,8 +11,26 @@ const TREE_BASE: usize = 2;
 #[cfg(not(test))]
 const TREE_BASE: usize = 16;
 
-pub trait Item: Clone + Eq + fmt::Debug {
-    type Summary: for<'a> AddAssign<&'a Self::Summary> + Default + Clone + fmt::Debug;
+// Below this many items, summarizing them on other threads costs more than it saves.
+#[cfg(feature = "parallel")]
+const PARALLEL_SUMMARY_THRESHOLD: usize = 4096;
+
+/// Items and summaries are handed to a thread pool when the `parallel` feature is enabled, so
+/// they must be `Send + Sync` in that configuration and nothing extra otherwise.
+#[cfg(feature = "parallel")]
+pub trait MaybeSync: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> MaybeSync for T {}
+
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSync for T {}
+
+pub trait Item: Clone + Eq + fmt::Debug + MaybeSync {
+    type Summary: for<'a> AddAssign<&'a Self::Summary> + Default + Clone + fmt::Debug + MaybeSync;
 
     fn summarize(&self) -> Self::Summary;
 }
@@ -138,7 +158,14 @@ impl<T: Item> Tree<T> {
         }
     }
 
-    #[cfg(test)]
+    /// Counts the items in the tree. This visits every node, so it is only meant for diagnostics.
+    pub fn len(&self) -> usize {
+        match self.0.as_ref() {
+            Node::Internal { child_trees, .. } => child_trees.iter().map(|tree| tree.len()).sum(),
+            Node::Leaf { items, .. } => items.len(),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         match self.0.as_ref() {
             Node::Internal { .. } => false,
@@ -146,31 +173,45 @@ impl<T: Item> Tree<T> {
         }
     }
 
-    pub fn extend<I>(&mut self, iter: I)
+    /// Builds a tree from the bottom up, filling each node evenly rather than pushing the items
+    /// one leaf at a time. The items must already be in order, which for a keyed tree means
+    /// sorted by key. With the `parallel` feature, large inputs are summarized on a thread pool.
+    pub fn from_items<I>(items: I) -> Self
     where
         I: IntoIterator<Item = T>,
     {
-        let mut leaf: Option<Node<T>> = None;
-
-        for item in iter {
-            if leaf.is_some() && leaf.as_ref().unwrap().items().len() == 2 * TREE_BASE {
-                self.push_tree(Tree(Arc::new(leaf.take().unwrap())));
-            }
-
-            if leaf.is_none() {
-                leaf = Some(Node::Leaf::<T> {
-                    summary: T::Summary::default(),
-                    items: SmallVec::new(),
-                });
-            }
-
-            let leaf = leaf.as_mut().unwrap();
-            *leaf.summary_mut() += &item.summarize();
-            leaf.items_mut().push(item);
+        let items = items.into_iter().collect::<Vec<_>>();
+        let summaries = summarize_all(&items);
+
+        let mut items = items.into_iter();
+        let mut summaries = summaries.into_iter();
+        let mut trees = node_sizes(items.len())
+            .map(|size| {
+                let items = items.by_ref().take(size).collect::<SmallVec<_>>();
+                let summary = sum_owned(summaries.by_ref().take(size));
+                Tree(Arc::new(Node::Leaf { summary, items }))
+            })
+            .collect::<Vec<_>>();
+
+        while trees.len() > 1 {
+            let mut child_trees = trees.into_iter();
+            trees = node_sizes(child_trees.len())
+                .map(|size| Self::from_child_trees(child_trees.by_ref().take(size).collect()))
+                .collect();
         }
 
-        if leaf.is_some() {
-            self.push_tree(Tree(Arc::new(leaf.take().unwrap())));
+        trees.pop().unwrap_or_else(Self::new)
+    }
+
+    pub fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let other = Self::from_items(iter);
+        if self.is_empty() {
+            *self = other;
+        } else {
+            self.push_tree(other);
         }
     }
 
@@ -422,20 +463,6 @@ impl<T: Item> Node<T> {
         }
     }
 
-    fn items_mut(&mut self) -> &mut SmallVec<[T; 2 * TREE_BASE]> {
-        match self {
-            Node::Leaf { items, .. } => items,
-            Node::Internal { .. } => panic!("Internal nodes have no items"),
-        }
-    }
-
-    fn summary_mut(&mut self) -> &mut T::Summary {
-        match self {
-            Node::Internal { summary, .. } => summary,
-            Node::Leaf { summary, .. } => summary,
-        }
-    }
-
     fn is_underflowing(&self) -> bool {
         match self {
             Node::Internal { child_trees, .. } => child_trees.len() < TREE_BASE,
@@ -1043,6 +1070,34 @@ where
     sum
 }
 
+// Splits `count` children into as few nodes as possible, spreading them evenly so that no node
+// other than a lone root has fewer than `TREE_BASE` children.
+fn node_sizes(count: usize) -> impl Iterator<Item = usize> {
+    let node_count = (count + 2 * TREE_BASE - 1) / (2 * TREE_BASE);
+    (0..node_count).map(move |index| {
+        let size = count / node_count;
+        if index < count % node_count {
+            size + 1
+        } else {
+            size
+        }
+    })
+}
+
+#[cfg(feature = "parallel")]
+fn summarize_all<T: Item>(items: &[T]) -> Vec<T::Summary> {
+    if items.len() < PARALLEL_SUMMARY_THRESHOLD {
+        items.iter().map(|item| item.summarize()).collect()
+    } else {
+        items.par_iter().map(|item| item.summarize()).collect()
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn summarize_all<T: Item>(items: &[T]) -> Vec<T::Summary> {
+    items.iter().map(|item| item.summarize()).collect()
+}
+
 fn sum_owned<T, I>(iter: I) -> T
 where
     T: Default + for<'a> AddAssign<&'a T>,
@@ -1071,6 +1126,49 @@ mod tests {
         assert_eq!(tree1.items(), (0..20).chain(50..100).collect::<Vec<u8>>());
     }
 
+    #[test]
+    fn test_from_items() {
+        fn assert_balanced(tree: &Tree<u8>, is_root: bool) -> usize {
+            match tree.0.as_ref() {
+                Node::Internal { child_trees, .. } => {
+                    assert!(child_trees.len() <= 2 * TREE_BASE);
+                    assert!(is_root || child_trees.len() >= TREE_BASE);
+                    let heights = child_trees
+                        .iter()
+                        .map(|child| assert_balanced(child, false))
+                        .collect::<Vec<_>>();
+                    assert!(heights.iter().all(|height| *height == heights[0]));
+                    heights[0] + 1
+                }
+                Node::Leaf { items, .. } => {
+                    assert!(items.len() <= 2 * TREE_BASE);
+                    assert!(is_root || items.len() >= TREE_BASE);
+                    0
+                }
+            }
+        }
+
+        for count in 0..100 {
+            let items = (0..count).collect::<Vec<u8>>();
+            let tree = Tree::from_items(items.iter().cloned());
+            assert_eq!(tree.items(), items);
+            assert_eq!(tree.extent::<Count>().0, count as usize);
+            assert_eq!(
+                tree.extent::<Sum>().0,
+                items.iter().map(|item| *item as usize).sum::<usize>()
+            );
+            assert_balanced(&tree, true);
+
+            let mut extended = tree.clone();
+            extended.extend(100..150);
+            extended.push_tree(Tree::from_items(150..200));
+            assert_eq!(
+                extended.items(),
+                (0..count).chain(100..200).collect::<Vec<u8>>()
+            );
+        }
+    }
+
     #[test]
     fn test_random() {
         for seed in 0..100 {
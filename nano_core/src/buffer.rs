@@ -1,6 +1,8 @@
 use crate::btree::{self, SeekBias};
-use crate::operation_queue::{self, OperationQueue};
+use crate::myers;
+use crate::operation_queue::{self, DeferredOperation, OperationQueue};
 use crate::serialization;
+use crate::text_format::TextFormat;
 use crate::time;
 use crate::ReplicaId;
 use crate::UserId;
@@ -9,7 +11,6 @@ use flatbuffers::{FlatBufferBuilder, WIPOffset};
 use lazy_static::lazy_static;
 use serde_derive::{Deserialize, Serialize};
 use smallvec::SmallVec;
-use std::cell::RefCell;
 use std::cmp::{self, Ordering};
 use std::collections::{HashMap, HashSet};
 use std::iter;
@@ -20,6 +21,8 @@ use std::vec;
 
 type SelectionSetVersion = usize;
 
+const TEXT_CHUNK_BYTES: usize = 256;
+
 #[derive(Eq, PartialEq, Debug)]
 pub enum Error {
     OffsetOutOfRange,
@@ -32,24 +35,51 @@ pub enum Error {
 pub struct Buffer {
     fragments: btree::Tree<Fragment>,
     insertion_splits: HashMap<time::Local, btree::Tree<InsertionSplit>>,
-    anchor_cache: RefCell<HashMap<Anchor, (usize, Point)>>,
-    offset_cache: RefCell<HashMap<Point, usize>>,
     pub version: time::Global,
     selections: HashMap<time::Local, SelectionSet>,
     deferred_ops: OperationQueue<Operation>,
     deferred_replicas: HashSet<ReplicaId>,
 }
 
+/// Describes how much a buffer retains. Deleted text is kept to resolve concurrent edits, so a
+/// buffer's history can be much larger than its visible text.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct BufferStats {
+    pub fragments: usize,
+    /// The number of fragments whose text has been deleted.
+    pub tombstones: usize,
+    pub tombstone_bytes: usize,
+    pub visible_bytes: usize,
+    pub insertions: usize,
+    /// The total size of the trees mapping each insertion to the fragments it was split into.
+    pub insertion_splits: usize,
+    pub deferred_ops: usize,
+    /// The number of replicas in the buffer's version vector.
+    pub version_width: usize,
+}
+
 #[derive(Clone, Copy, Deserialize, Eq, PartialEq, Debug, Hash, Serialize)]
 pub struct Point {
     pub row: u32,
     pub column: u32,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+/// A position whose column is measured in UTF-8 bytes rather than the UTF-16 code units used by
+/// `Point`.
+#[derive(Clone, Copy, Deserialize, Eq, PartialEq, Debug, Hash, Serialize)]
+pub struct Utf8Point {
+    pub row: u32,
+    pub column: u32,
+}
+
+pub trait ToPoint {
+    fn to_point(&self, buffer: &Buffer) -> Result<Point, Error>;
+}
+
+#[derive(Clone, Deserialize, Eq, PartialEq, Debug, Hash, Serialize)]
 pub struct Anchor(AnchorInner);
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Clone, Deserialize, Eq, PartialEq, Debug, Hash, Serialize)]
 enum AnchorInner {
     Start,
     End,
@@ -60,13 +90,13 @@ enum AnchorInner {
     },
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Clone, Deserialize, Eq, PartialEq, Debug, Hash, Serialize)]
 enum AnchorBias {
     Left,
     Right,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Selection {
     pub start: Anchor,
     pub end: Anchor,
@@ -83,7 +113,7 @@ struct SelectionSet {
 
 pub struct Iter {
     fragment_cursor: btree::Cursor<Fragment>,
-    fragment_offset: usize,
+    byte_offset: usize,
     reversed: bool,
 }
 
@@ -97,10 +127,21 @@ struct DiffIter {
     diff: vec::IntoIter<Difference>,
 }
 
+/// How `diff` compares two texts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiffMode {
+    /// Finds the fewest changed characters, taking time quadratic in the length of the texts.
+    Character,
+    /// Finds the changed lines first and then the changed characters within them. Unchanged
+    /// lines are never compared character by character, which keeps large texts with few changes
+    /// fast, though changes spanning many lines may be less precise than in character mode.
+    Line,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct Change {
     pub range: Range<Point>,
-    pub code_units: Vec<u16>,
+    pub text: String,
     new_extent: Point,
 }
 
@@ -113,12 +154,24 @@ pub struct Insertion {
     lamport_timestamp: time::Lamport,
 }
 
+// Text is stored as UTF-8, but offsets into it are expressed in UTF-16 code units so that they
+// agree with the offsets in operations. For non-ASCII text, we record where each chunk of roughly
+// `TEXT_CHUNK_BYTES` starts in every unit, which bounds the scan needed to convert between them.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Text {
-    code_units: Vec<u16>,
+    text: String,
+    summary: TextSummary,
+    chunk_starts: Vec<TextSummary>,
     nodes: Vec<LineNode>,
 }
 
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+struct TextSummary {
+    bytes: usize,
+    code_units: usize,
+    chars: usize,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 struct LineNode {
     len: u32,
@@ -153,6 +206,8 @@ struct Fragment {
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct FragmentSummary {
     extent: usize,
+    bytes: usize,
+    chars: usize,
     extent_2d: Point,
     max_fragment_id: FragmentId,
     first_row_len: u32,
@@ -161,6 +216,15 @@ pub struct FragmentSummary {
     max_version: time::Global,
 }
 
+/// The text inserted by a single edit, along with the edits that deleted any part of it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct InsertionRecord {
+    pub id: time::Local,
+    pub lamport_timestamp: time::Lamport,
+    pub len: usize,
+    pub deleted_by: HashSet<time::Local>,
+}
+
 #[derive(Eq, PartialEq, Clone, Debug)]
 struct InsertionSplit {
     extent: usize,
@@ -240,8 +304,6 @@ impl Buffer {
         Self {
             fragments,
             insertion_splits,
-            anchor_cache: RefCell::new(HashMap::default()),
-            offset_cache: RefCell::new(HashMap::default()),
             version: time::Global::new(),
             selections: HashMap::default(),
             deferred_ops: OperationQueue::new(),
@@ -257,6 +319,14 @@ impl Buffer {
         self.fragments.extent::<usize>()
     }
 
+    pub fn len_bytes(&self) -> usize {
+        self.fragments.summary().bytes
+    }
+
+    pub fn len_chars(&self) -> usize {
+        self.fragments.summary().chars
+    }
+
     pub fn len_for_row(&self, row: u32) -> Result<u32, Error> {
         let row_start_offset = self.offset_for_point(Point::new(row, 0))?;
         let row_end_offset = if row >= self.max_point().row {
@@ -276,28 +346,49 @@ impl Buffer {
         self.fragments.extent()
     }
 
-    pub fn line(&self, row: u32) -> Result<Vec<u16>, Error> {
+    pub fn line(&self, row: u32) -> Result<String, Error> {
         let mut iterator = self.iter_at_point(Point::new(row, 0)).peekable();
         if iterator.peek().is_none() {
             Err(Error::OffsetOutOfRange)
         } else {
-            Ok(iterator.take_while(|c| *c != u16::from(b'\n')).collect())
+            Ok(iterator.take_while(|c| *c != '\n').collect())
         }
     }
 
-    pub fn to_u16_chars(&self) -> Vec<u16> {
-        self.iter().collect::<Vec<u16>>()
-    }
-
     #[cfg(test)]
     pub fn to_string(&self) -> String {
-        String::from_utf16_lossy(&self.to_u16_chars())
+        self.iter().collect()
     }
 
     pub fn iter(&self) -> Iter {
         Iter::new(self)
     }
 
+    pub fn encode(&self, format: &TextFormat) -> Vec<u8> {
+        let fragments = self
+            .fragments
+            .items()
+            .into_iter()
+            .filter(|fragment| fragment.is_visible())
+            .collect::<Vec<_>>();
+        format.encode(
+            fragments
+                .iter()
+                .map(|fragment| (fragment.text(), fragment.base_offset())),
+        )
+    }
+
+    /// Returns the text as it was at the given version, which the buffer must have observed.
+    pub fn text_at(&self, version: &time::Global) -> String {
+        let mut text = String::new();
+        for fragment in self.fragments.items() {
+            if fragment.was_visible(version) {
+                text.push_str(fragment.text());
+            }
+        }
+        text
+    }
+
     pub fn iter_at_point(&self, point: Point) -> Iter {
         Iter::at_point(self, point)
     }
@@ -314,6 +405,55 @@ impl Buffer {
         self.deferred_ops.len()
     }
 
+    /// Describes what each deferred operation is waiting on. Operations from the same replica
+    /// are applied in order, so an operation also waits on whatever is holding back the deferred
+    /// operations its replica generated before it.
+    pub fn deferred_ops(&self) -> Vec<DeferredOperation> {
+        let mut missing_by_replica: HashMap<ReplicaId, Vec<time::Local>> = HashMap::new();
+        self.deferred_ops
+            .iter()
+            .map(|op| {
+                let replica_id = op.local_timestamp().replica_id;
+                let mut missing = self.missing_dependencies(&op);
+                if let Some(earlier_missing) = missing_by_replica.get(&replica_id) {
+                    missing.extend(earlier_missing.iter().cloned());
+                }
+                missing.sort();
+                missing.dedup();
+                missing_by_replica.insert(replica_id, missing.clone());
+                DeferredOperation {
+                    lamport_timestamp: op.lamport_timestamp(),
+                    missing,
+                }
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        let mut stats = BufferStats::default();
+        let mut cursor = self.fragments.cursor();
+        cursor.seek(&FragmentId::min_value(), SeekBias::Left);
+        while let Some(fragment) = cursor.item() {
+            let bytes = fragment.text().len();
+            if bytes > 0 {
+                stats.fragments += 1;
+                if fragment.is_visible() {
+                    stats.visible_bytes += bytes;
+                } else {
+                    stats.tombstones += 1;
+                    stats.tombstone_bytes += bytes;
+                }
+            }
+            cursor.next();
+        }
+
+        stats.insertions = self.insertion_splits.len();
+        stats.insertion_splits = self.insertion_splits.values().map(|tree| tree.len()).sum();
+        stats.deferred_ops = self.deferred_ops.len();
+        stats.version_width = self.version.replica_ids().count();
+        stats
+    }
+
     pub fn edit<I, T>(
         &mut self,
         old_ranges: I,
@@ -332,12 +472,13 @@ impl Buffer {
             None
         };
 
-        self.anchor_cache.borrow_mut().clear();
-        self.offset_cache.borrow_mut().clear();
+        let old_ranges = old_ranges
+            .into_iter()
+            .map(|old_range| self.clip_offset(old_range.start)..self.clip_offset(old_range.end))
+            .filter(|old_range| new_text.is_some() || old_range.end > old_range.start)
+            .collect::<Vec<_>>();
         let ops = self.splice_fragments(
-            old_ranges
-                .into_iter()
-                .filter(|old_range| new_text.is_some() || old_range.end > old_range.start),
+            old_ranges.into_iter(),
             new_text.clone(),
             local_clock,
             lamport_clock,
@@ -348,7 +489,7 @@ impl Buffer {
         ops
     }
 
-    pub fn edit_2d<I, T>(
+    pub fn edit_2d<I, P, T>(
         &mut self,
         old_2d_ranges: I,
         new_text: T,
@@ -356,13 +497,20 @@ impl Buffer {
         lamport_clock: &mut time::Lamport,
     ) -> Vec<Operation>
     where
-        I: IntoIterator<Item = Range<Point>>,
+        I: IntoIterator<Item = Range<P>>,
+        P: ToPoint,
         T: Into<Text>,
     {
         let mut old_1d_ranges = SmallVec::<[_; 1]>::new();
         for old_2d_range in old_2d_ranges {
-            let start = self.offset_for_point(old_2d_range.start);
-            let end = self.offset_for_point(old_2d_range.end);
+            let start = old_2d_range
+                .start
+                .to_point(self)
+                .and_then(|point| self.offset_for_point(point));
+            let end = old_2d_range
+                .end
+                .to_point(self)
+                .and_then(|point| self.offset_for_point(point));
             if start.is_ok() && end.is_ok() {
                 old_1d_ranges.push(start.unwrap()..end.unwrap());
             }
@@ -492,24 +640,117 @@ impl Buffer {
         *selections = new_selections;
     }
 
+    /// Applies operations received from other replicas, deferring the ones whose dependencies
+    /// haven't arrived yet. Operations referring to positions that don't exist in this buffer are
+    /// discarded along with any later operations from the same replica, and the offending
+    /// replicas are returned.
     pub fn apply_ops<I: IntoIterator<Item = Operation>>(
         &mut self,
         ops: I,
         local_clock: &mut time::Local,
         lamport_clock: &mut time::Lamport,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<(ReplicaId, Error)>, Error> {
+        let mut rejections = Vec::new();
         let mut deferred_ops = Vec::new();
         for op in ops {
-            if self.can_apply_op(&op) {
-                self.apply_op(op, local_clock, lamport_clock)?;
-            } else {
-                self.deferred_replicas
-                    .insert(op.local_timestamp().replica_id);
-                deferred_ops.push(op);
-            }
+            self.apply_or_defer_op(
+                op,
+                &mut deferred_ops,
+                &mut rejections,
+                local_clock,
+                lamport_clock,
+            )?;
         }
         self.deferred_ops.insert(deferred_ops);
-        self.flush_deferred_ops(local_clock, lamport_clock)?;
+        self.flush_deferred_ops(&mut rejections, local_clock, lamport_clock)?;
+        Ok(rejections)
+    }
+
+    pub fn discard_deferred_ops(&mut self, replica_id: ReplicaId) {
+        let deferred_ops = self
+            .deferred_ops
+            .drain()
+            .filter(|op| op.local_timestamp().replica_id != replica_id)
+            .collect();
+        self.deferred_ops.insert(deferred_ops);
+        self.deferred_replicas.remove(&replica_id);
+    }
+
+    /// Returns a record for every insertion in the buffer, including the base text, ordered by
+    /// Lamport timestamp.
+    pub(crate) fn insertions(&self) -> Vec<InsertionRecord> {
+        let mut records = HashMap::<time::Local, InsertionRecord>::new();
+        let mut cursor = self.fragments.cursor();
+        cursor.seek(&FragmentId::min_value(), SeekBias::Left);
+        while let Some(fragment) = cursor.item() {
+            let record = records
+                .entry(fragment.insertion.id)
+                .or_insert_with(|| InsertionRecord {
+                    id: fragment.insertion.id,
+                    lamport_timestamp: fragment.insertion.lamport_timestamp,
+                    len: 0,
+                    deleted_by: HashSet::new(),
+                });
+            record.len += fragment.extent();
+            record.deleted_by.extend(fragment.deletions.iter().cloned());
+            cursor.next();
+        }
+
+        let mut records = records.into_iter().map(|(_, r)| r).collect::<Vec<_>>();
+        records.sort_by_key(|record| record.lamport_timestamp);
+        records
+    }
+
+    /// Deletes all visible text that wasn't inserted by the given insertion.
+    pub(crate) fn retain_insertion(
+        &mut self,
+        insertion_id: time::Local,
+        local_clock: &mut time::Local,
+        lamport_clock: &mut time::Lamport,
+    ) -> Vec<Operation> {
+        let mut old_ranges: Vec<Range<usize>> = Vec::new();
+        let mut cursor = self.fragments.cursor();
+        cursor.seek(&FragmentId::min_value(), SeekBias::Left);
+        while let Some(fragment) = cursor.item() {
+            if fragment.is_visible() && fragment.insertion.id != insertion_id {
+                let start = cursor.start::<usize>();
+                let end = start + fragment.len();
+                match old_ranges.last_mut() {
+                    Some(range) if range.end == start => range.end = end,
+                    _ => old_ranges.push(start..end),
+                }
+            }
+            cursor.next();
+        }
+
+        self.edit(old_ranges, "", local_clock, lamport_clock)
+    }
+
+    fn apply_or_defer_op(
+        &mut self,
+        op: Operation,
+        deferred_ops: &mut Vec<Operation>,
+        rejections: &mut Vec<(ReplicaId, Error)>,
+        local_clock: &mut time::Local,
+        lamport_clock: &mut time::Lamport,
+    ) -> Result<(), Error> {
+        let replica_id = op.local_timestamp().replica_id;
+        if rejections
+            .iter()
+            .any(|(rejected_id, _)| *rejected_id == replica_id)
+        {
+            return Ok(());
+        }
+
+        if self.can_apply_op(&op) {
+            match self.validate_op(&op) {
+                Ok(()) => self.apply_op(op, local_clock, lamport_clock)?,
+                Err(error) => rejections.push((replica_id, error)),
+            }
+        } else {
+            self.deferred_replicas.insert(replica_id);
+            deferred_ops.push(op);
+        }
         Ok(())
     }
 
@@ -542,8 +783,6 @@ impl Buffer {
                     local_clock,
                     lamport_clock,
                 )?;
-                self.anchor_cache.borrow_mut().clear();
-                self.offset_cache.borrow_mut().clear();
             }
         }
         Ok(())
@@ -672,19 +911,20 @@ impl Buffer {
 
     fn flush_deferred_ops(
         &mut self,
+        rejections: &mut Vec<(ReplicaId, Error)>,
         local_clock: &mut time::Local,
         lamport_clock: &mut time::Lamport,
     ) -> Result<(), Error> {
         self.deferred_replicas.clear();
         let mut deferred_ops = Vec::new();
         for op in self.deferred_ops.drain() {
-            if self.can_apply_op(&op) {
-                self.apply_op(op, local_clock, lamport_clock)?;
-            } else {
-                self.deferred_replicas
-                    .insert(op.local_timestamp().replica_id);
-                deferred_ops.push(op);
-            }
+            self.apply_or_defer_op(
+                op,
+                &mut deferred_ops,
+                rejections,
+                local_clock,
+                lamport_clock,
+            )?;
         }
         self.deferred_ops.insert(deferred_ops);
         Ok(())
@@ -712,6 +952,86 @@ impl Buffer {
         }
     }
 
+    fn missing_dependencies(&self, op: &Operation) -> Vec<time::Local> {
+        match op {
+            Operation::Edit {
+                start_id,
+                end_id,
+                version_in_range,
+                ..
+            } => {
+                let mut missing = self.version.missing(version_in_range);
+                for id in &[*start_id, *end_id] {
+                    if !self.version.observed(*id) {
+                        missing.push(*id);
+                    }
+                }
+                missing
+            }
+        }
+    }
+
+    fn validate_op(&self, op: &Operation) -> Result<(), Error> {
+        match op {
+            Operation::Edit {
+                start_id,
+                start_offset,
+                end_id,
+                end_offset,
+                ..
+            } => {
+                let start_fragment_id = self.resolve_remote_anchor(*start_id, *start_offset)?;
+                let end_fragment_id = self.resolve_remote_anchor(*end_id, *end_offset)?;
+                match start_fragment_id.cmp(&end_fragment_id) {
+                    Ordering::Less => Ok(()),
+                    Ordering::Equal if start_offset <= end_offset => Ok(()),
+                    _ => Err(Error::InvalidAnchor),
+                }
+            }
+        }
+    }
+
+    fn resolve_remote_anchor(
+        &self,
+        edit_id: time::Local,
+        offset: usize,
+    ) -> Result<FragmentId, Error> {
+        let split_tree = self
+            .insertion_splits
+            .get(&edit_id)
+            .ok_or(Error::InvalidAnchor)?;
+        if offset > split_tree.extent::<usize>() {
+            return Err(Error::OffsetOutOfRange);
+        }
+
+        let fragment_id = self.resolve_fragment_id(edit_id, offset)?;
+        let mut cursor = self.fragments.cursor();
+        cursor.seek(&fragment_id, SeekBias::Left);
+        let fragment = cursor.item().ok_or(Error::InvalidAnchor)?;
+        if fragment.insertion.text.is_char_boundary(offset) {
+            Ok(fragment_id)
+        } else {
+            Err(Error::OffsetOutOfRange)
+        }
+    }
+
+    // Rounds an offset falling between the two halves of a surrogate pair down to the start of
+    // the character, so that fragments are never split inside a character.
+    fn clip_offset(&self, offset: usize) -> usize {
+        let mut cursor = self.fragments.cursor();
+        cursor.seek(&offset, SeekBias::Right);
+        if let Some(fragment) = cursor.item() {
+            let fragment_start = cursor.start::<usize>();
+            if fragment.is_visible() && offset > fragment_start {
+                let text = &fragment.insertion.text;
+                let offset_in_insertion = fragment.start_offset + (offset - fragment_start);
+                return fragment_start + text.summary_at(offset_in_insertion).code_units
+                    - fragment.start_offset;
+            }
+        }
+        offset
+    }
+
     fn resolve_fragment_id(
         &self,
         edit_id: time::Local,
@@ -1130,22 +1450,19 @@ impl Buffer {
         let fragment = cursor.item().unwrap();
         let offset_in_fragment = offset - cursor.start::<usize>();
         let offset_in_insertion = fragment.start_offset + offset_in_fragment;
-        let point = cursor.start::<Point>() + &fragment.point_for_offset(offset_in_fragment)?;
-        let anchor = Anchor(AnchorInner::Middle {
+        Ok(Anchor(AnchorInner::Middle {
             insertion_id: fragment.insertion.id,
             offset: offset_in_insertion,
             bias,
-        });
-        self.cache_position(Some(anchor.clone()), offset, point);
-        Ok(anchor)
+        }))
     }
 
-    pub fn anchor_before_point(&self, point: Point) -> Result<Anchor, Error> {
-        self.anchor_for_point(point, AnchorBias::Left)
+    pub fn anchor_before_point<P: ToPoint>(&self, point: P) -> Result<Anchor, Error> {
+        self.anchor_for_point(point.to_point(self)?, AnchorBias::Left)
     }
 
-    pub fn anchor_after_point(&self, point: Point) -> Result<Anchor, Error> {
-        self.anchor_for_point(point, AnchorBias::Right)
+    pub fn anchor_after_point<P: ToPoint>(&self, point: P) -> Result<Anchor, Error> {
+        self.anchor_for_point(point.to_point(self)?, AnchorBias::Right)
     }
 
     fn anchor_for_point(&self, point: Point, bias: AnchorBias) -> Result<Anchor, Error> {
@@ -1177,14 +1494,11 @@ impl Buffer {
         let fragment = cursor.item().unwrap();
         let offset_in_fragment = fragment.offset_for_point(point - &cursor.start::<Point>())?;
         let offset_in_insertion = fragment.start_offset + offset_in_fragment;
-        let anchor = Anchor(AnchorInner::Middle {
+        Ok(Anchor(AnchorInner::Middle {
             insertion_id: fragment.insertion.id,
             offset: offset_in_insertion,
             bias,
-        });
-        let offset = cursor.start::<usize>() + offset_in_fragment;
-        self.cache_position(Some(anchor.clone()), offset, point);
-        Ok(anchor)
+        }))
     }
 
     pub fn offset_for_anchor(&self, anchor: &Anchor) -> Result<usize, Error> {
@@ -1195,6 +1509,9 @@ impl Buffer {
         Ok(self.position_for_anchor(anchor)?.1)
     }
 
+    // Positions aren't cached. The splits of each insertion are indexed by their offset in it, and
+    // the fragment tree's summaries index each fragment's ID, offset and point, so resolving an
+    // anchor or a point takes a logarithmic number of steps and needs no invalidation on edits.
     fn position_for_anchor(&self, anchor: &Anchor) -> Result<(usize, Point), Error> {
         match &anchor.0 {
             &AnchorInner::Start => Ok((0, Point { row: 0, column: 0 })),
@@ -1204,98 +1521,97 @@ impl Buffer {
                 offset,
                 ref bias,
             } => {
-                let cached_position = {
-                    let anchor_cache = self.anchor_cache.try_borrow().ok();
-                    anchor_cache
-                        .as_ref()
-                        .and_then(|cache| cache.get(anchor).cloned())
+                let seek_bias = match bias {
+                    &AnchorBias::Left => SeekBias::Left,
+                    &AnchorBias::Right => SeekBias::Right,
                 };
 
-                if let Some(cached_position) = cached_position {
-                    Ok(cached_position)
+                let splits = self
+                    .insertion_splits
+                    .get(&insertion_id)
+                    .ok_or(Error::InvalidAnchor)?;
+                let mut splits_cursor = splits.cursor();
+                splits_cursor.seek(&offset, seek_bias);
+                let split = splits_cursor.item().ok_or(Error::InvalidAnchor)?;
+
+                let mut fragments_cursor = self.fragments.cursor();
+                fragments_cursor.seek(&split.fragment_id, SeekBias::Left);
+                let fragment = fragments_cursor.item().ok_or(Error::InvalidAnchor)?;
+                let overshoot = if fragment.is_visible() {
+                    offset - fragment.start_offset
                 } else {
-                    let seek_bias = match bias {
-                        &AnchorBias::Left => SeekBias::Left,
-                        &AnchorBias::Right => SeekBias::Right,
-                    };
-
-                    let splits = self
-                        .insertion_splits
-                        .get(&insertion_id)
-                        .ok_or(Error::InvalidAnchor)?;
-                    let mut splits_cursor = splits.cursor();
-                    splits_cursor.seek(&offset, seek_bias);
-                    splits_cursor
-                        .item()
-                        .ok_or(Error::InvalidAnchor)
-                        .and_then(|split| {
-                            let mut fragments_cursor = self.fragments.cursor();
-                            fragments_cursor.seek(&split.fragment_id, SeekBias::Left);
-                            fragments_cursor
-                                .item()
-                                .ok_or(Error::InvalidAnchor)
-                                .and_then(|fragment| {
-                                    let overshoot = if fragment.is_visible() {
-                                        offset - fragment.start_offset
-                                    } else {
-                                        0
-                                    };
-                                    let offset = fragments_cursor.start::<usize>() + overshoot;
-                                    let point = fragments_cursor.start::<Point>()
-                                        + &fragment.point_for_offset(overshoot)?;
-                                    self.cache_position(Some(anchor.clone()), offset, point);
-                                    Ok((offset, point))
-                                })
-                        })
-                }
+                    0
+                };
+                let offset = fragments_cursor.start::<usize>() + overshoot;
+                let point =
+                    fragments_cursor.start::<Point>() + &fragment.point_for_offset(overshoot)?;
+                Ok((offset, point))
             }
         }
     }
 
     fn offset_for_point(&self, point: Point) -> Result<usize, Error> {
-        let cached_offset = {
-            let offset_cache = self.offset_cache.try_borrow().ok();
-            offset_cache
-                .as_ref()
-                .and_then(|cache| cache.get(&point).cloned())
-        };
+        let mut fragments_cursor = self.fragments.cursor();
+        fragments_cursor.seek(&point, SeekBias::Left);
+        let fragment = fragments_cursor.item().ok_or(Error::OffsetOutOfRange)?;
+        let overshoot = fragment
+            .offset_for_point(point - &fragments_cursor.start::<Point>())
+            .unwrap();
+        Ok(fragments_cursor.start::<usize>() + overshoot)
+    }
 
-        if let Some(cached_offset) = cached_offset {
-            Ok(cached_offset)
+    pub fn point_for_utf8_point(&self, point: Utf8Point) -> Result<Point, Error> {
+        if point.row > self.max_point().row {
+            return Err(Error::OffsetOutOfRange);
+        }
+
+        let mut column = 0;
+        let mut byte_column = 0;
+        for ch in self.iter_at_point(Point::new(point.row, 0)) {
+            if byte_column >= point.column as usize || ch == '\n' {
+                break;
+            }
+            column += ch.len_utf16() as u32;
+            byte_column += ch.len_utf8();
+        }
+
+        if byte_column == point.column as usize {
+            Ok(Point::new(point.row, column))
         } else {
-            let mut fragments_cursor = self.fragments.cursor();
-            fragments_cursor.seek(&point, SeekBias::Left);
-            fragments_cursor
-                .item()
-                .ok_or(Error::OffsetOutOfRange)
-                .map(|fragment| {
-                    let overshoot = fragment
-                        .offset_for_point(point - &fragments_cursor.start::<Point>())
-                        .unwrap();
-                    let offset = &fragments_cursor.start::<usize>() + &overshoot;
-                    self.cache_position(None, offset, point);
-                    offset
-                })
+            Err(Error::OffsetOutOfRange)
         }
     }
 
-    pub fn cmp_anchors(&self, a: &Anchor, b: &Anchor) -> Result<Ordering, Error> {
-        let a_offset = self.offset_for_anchor(a)?;
-        let b_offset = self.offset_for_anchor(b)?;
-        Ok(a_offset.cmp(&b_offset))
-    }
+    pub fn utf8_point_for_point(&self, point: Point) -> Result<Utf8Point, Error> {
+        if point.row > self.max_point().row {
+            return Err(Error::OffsetOutOfRange);
+        }
 
-    fn cache_position(&self, anchor: Option<Anchor>, offset: usize, point: Point) {
-        anchor.map(|anchor| {
-            if let Ok(mut anchor_cache) = self.anchor_cache.try_borrow_mut() {
-                anchor_cache.insert(anchor, (offset, point));
+        let mut column = 0;
+        let mut byte_column = 0;
+        for ch in self.iter_at_point(Point::new(point.row, 0)) {
+            if column >= point.column || ch == '\n' {
+                break;
             }
-        });
+            column += ch.len_utf16() as u32;
+            byte_column += ch.len_utf8();
+        }
 
-        if let Ok(mut offset_cache) = self.offset_cache.try_borrow_mut() {
-            offset_cache.insert(point, offset);
+        if column == point.column {
+            Ok(Utf8Point {
+                row: point.row,
+                column: byte_column as u32,
+            })
+        } else {
+            Err(Error::OffsetOutOfRange)
         }
     }
+
+    pub fn cmp_anchors(&self, a: &Anchor, b: &Anchor) -> Result<Ordering, Error> {
+        let a_offset = self.offset_for_anchor(a)?;
+        let b_offset = self.offset_for_anchor(b)?;
+        Ok(a_offset.cmp(&b_offset))
+    }
 }
 
 impl Point {
@@ -1312,6 +1628,24 @@ impl Point {
     }
 }
 
+impl ToPoint for Point {
+    fn to_point(&self, _: &Buffer) -> Result<Point, Error> {
+        Ok(*self)
+    }
+}
+
+impl Utf8Point {
+    pub fn new(row: u32, column: u32) -> Self {
+        Utf8Point { row, column }
+    }
+}
+
+impl ToPoint for Utf8Point {
+    fn to_point(&self, buffer: &Buffer) -> Result<Point, Error> {
+        buffer.point_for_utf8_point(*self)
+    }
+}
+
 impl btree::Dimension<FragmentSummary> for Point {
     fn from_summary(summary: &FragmentSummary) -> Self {
         summary.extent_2d
@@ -1344,6 +1678,19 @@ impl<'a> Sub<&'a Self> for Point {
     }
 }
 
+impl<'a> AddAssign<&'a Self> for BufferStats {
+    fn add_assign(&mut self, other: &'a Self) {
+        self.fragments += other.fragments;
+        self.tombstones += other.tombstones;
+        self.tombstone_bytes += other.tombstone_bytes;
+        self.visible_bytes += other.visible_bytes;
+        self.insertions += other.insertions;
+        self.insertion_splits += other.insertion_splits;
+        self.deferred_ops += other.deferred_ops;
+        self.version_width = cmp::max(self.version_width, other.version_width);
+    }
+}
+
 impl<'a> AddAssign<&'a Self> for Point {
     fn add_assign(&mut self, other: &'a Self) {
         if other.row == 0 {
@@ -1384,7 +1731,7 @@ impl Iter {
         fragment_cursor.seek(&0, SeekBias::Right);
         Self {
             fragment_cursor,
-            fragment_offset: 0,
+            byte_offset: 0,
             reversed: false,
         }
     }
@@ -1392,16 +1739,17 @@ impl Iter {
     fn at_point(buffer: &Buffer, point: Point) -> Self {
         let mut fragment_cursor = buffer.fragments.cursor();
         fragment_cursor.seek(&point, SeekBias::Right);
-        let fragment_offset = if let Some(fragment) = fragment_cursor.item() {
+        let byte_offset = if let Some(fragment) = fragment_cursor.item() {
             let point_in_fragment = point - &fragment_cursor.start::<Point>();
-            fragment.offset_for_point(point_in_fragment).unwrap()
+            let offset_in_fragment = fragment.offset_for_point(point_in_fragment).unwrap();
+            fragment.byte_offset(offset_in_fragment)
         } else {
             0
         };
 
         Self {
             fragment_cursor,
-            fragment_offset,
+            byte_offset,
             reversed: false,
         }
     }
@@ -1412,30 +1760,33 @@ impl Iter {
     }
 
     pub fn into_string(self) -> String {
-        String::from_utf16_lossy(&self.collect::<Vec<u16>>())
+        self.collect()
     }
 }
 
 impl Iterator for Iter {
-    type Item = u16;
+    type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.reversed {
             if let Some(fragment) = self.fragment_cursor.item() {
-                if self.fragment_offset > 0 {
-                    self.fragment_offset -= 1;
-                    if let Some(c) = fragment.code_unit(self.fragment_offset) {
-                        return Some(c);
-                    }
+                let text = fragment.visible_text();
+                if let Some(c) = text
+                    .get(..self.byte_offset)
+                    .and_then(|prefix| prefix.chars().next_back())
+                {
+                    self.byte_offset -= c.len_utf8();
+                    return Some(c);
                 }
             }
 
             loop {
                 self.fragment_cursor.prev();
                 if let Some(fragment) = self.fragment_cursor.item() {
-                    if fragment.len() > 0 {
-                        self.fragment_offset = fragment.len() - 1;
-                        return fragment.code_unit(self.fragment_offset);
+                    let text = fragment.visible_text();
+                    if let Some(c) = text.chars().next_back() {
+                        self.byte_offset = text.len() - c.len_utf8();
+                        return Some(c);
                     }
                 } else {
                     break;
@@ -1445,8 +1796,12 @@ impl Iterator for Iter {
             None
         } else {
             if let Some(fragment) = self.fragment_cursor.item() {
-                if let Some(c) = fragment.code_unit(self.fragment_offset) {
-                    self.fragment_offset += 1;
+                let text = fragment.visible_text();
+                if let Some(c) = text
+                    .get(self.byte_offset..)
+                    .and_then(|suffix| suffix.chars().next())
+                {
+                    self.byte_offset += c.len_utf8();
                     return Some(c);
                 }
             }
@@ -1454,8 +1809,8 @@ impl Iterator for Iter {
             loop {
                 self.fragment_cursor.next();
                 if let Some(fragment) = self.fragment_cursor.item() {
-                    if let Some(c) = fragment.code_unit(0) {
-                        self.fragment_offset = 1;
+                    if let Some(c) = fragment.visible_text().chars().next() {
+                        self.byte_offset = c.len_utf8();
                         return Some(c);
                     }
                 } else {
@@ -1479,7 +1834,7 @@ impl<F: Fn(&FragmentSummary) -> bool> Iterator for ChangesIter<F> {
             if !fragment.was_visible(&self.since) && fragment.is_visible() {
                 if let Some(ref mut change) = change {
                     if change.range.start + &change.new_extent == position {
-                        change.code_units.extend(fragment.code_units());
+                        change.text.push_str(fragment.text());
                         change.new_extent += &fragment.extent_2d();
                     } else {
                         break;
@@ -1487,7 +1842,7 @@ impl<F: Fn(&FragmentSummary) -> bool> Iterator for ChangesIter<F> {
                 } else {
                     change = Some(Change {
                         range: position..position,
-                        code_units: Vec::from(fragment.code_units()),
+                        text: String::from(fragment.text()),
                         new_extent: fragment.extent_2d(),
                     });
                 }
@@ -1501,7 +1856,7 @@ impl<F: Fn(&FragmentSummary) -> bool> Iterator for ChangesIter<F> {
                 } else {
                     change = Some(Change {
                         range: position..position + &fragment.extent_2d(),
-                        code_units: Vec::new(),
+                        text: String::new(),
                         new_extent: Point::zero(),
                     });
                 }
@@ -1514,10 +1869,16 @@ impl<F: Fn(&FragmentSummary) -> bool> Iterator for ChangesIter<F> {
     }
 }
 
-pub fn diff(a: &str, b: &str) -> impl Iterator<Item = Change> {
+/// Returns the changes that turn `a` into `b`, with each change's range expressed in the text
+/// obtained by applying the changes before it.
+pub fn diff(a: &str, b: &str, mode: DiffMode) -> impl Iterator<Item = Change> {
+    let diff = match mode {
+        DiffMode::Character => Changeset::new(a, b, "").diffs,
+        DiffMode::Line => myers::diff_lines(a, b),
+    };
     DiffIter {
         position: Point::zero(),
-        diff: Changeset::new(a, b, "").diffs.into_iter(),
+        diff: diff.into_iter(),
     }
 }
 
@@ -1528,52 +1889,34 @@ impl Iterator for DiffIter {
         let mut change: Option<Change> = None;
 
         while let Some(diff) = self.diff.next() {
-            let code_units;
-            let extent;
-            match &diff {
-                Difference::Same(text) | Difference::Rem(text) | Difference::Add(text) => {
-                    code_units = text.encode_utf16().collect::<Vec<_>>();
-
-                    let mut rows = 0;
-                    let mut last_row_len = 0;
-                    for ch in &code_units {
-                        if *ch == b'\n' as u16 {
-                            rows += 1;
-                            last_row_len = 0;
-                        } else {
-                            last_row_len += 1;
-                        }
-                    }
-                    extent = Point::new(rows, last_row_len);
-                }
-            }
-
             match diff {
-                Difference::Same(_) => {
-                    self.position += &extent;
+                Difference::Same(text) => {
+                    self.position += &extent_2d(&text);
                     if change.is_some() {
                         break;
                     }
                 }
-                Difference::Rem(_) => {
+                Difference::Rem(text) => {
+                    let extent = extent_2d(&text);
                     if let Some(change) = change.as_mut() {
                         change.range.end += &extent;
                     } else {
                         change = Some(Change {
                             range: self.position..self.position + &extent,
-                            code_units: Vec::new(),
+                            text: String::new(),
                             new_extent: Point::zero(),
                         });
                     }
                 }
-                Difference::Add(_) => {
+                Difference::Add(text) => {
+                    let extent = extent_2d(&text);
                     if let Some(change) = change.as_mut() {
-                        change.code_units.extend(code_units);
+                        change.text.push_str(&text);
                         change.new_extent += &extent;
                     } else {
                         change = Some(Change {
                             range: self.position..self.position,
-                            code_units,
+                            text,
                             new_extent: extent,
                         });
                     }
@@ -1586,6 +1929,20 @@ impl Iterator for DiffIter {
     }
 }
 
+fn extent_2d(text: &str) -> Point {
+    let mut rows = 0;
+    let mut last_row_len = 0;
+    for ch in text.chars() {
+        if ch == '\n' {
+            rows += 1;
+            last_row_len = 0;
+        } else {
+            last_row_len += ch.len_utf16() as u32;
+        }
+    }
+    Point::new(rows, last_row_len)
+}
+
 impl Selection {
     pub fn head(&self) -> &Anchor {
         if self.reversed {
@@ -1629,7 +1986,7 @@ impl Selection {
 }
 
 impl Text {
-    fn new(code_units: Vec<u16>) -> Self {
+    fn new(text: String) -> Self {
         fn build_tree(index: usize, line_lengths: &[u32], mut tree: &mut [LineNode]) {
             if line_lengths.is_empty() {
                 return;
@@ -1698,15 +2055,29 @@ impl Text {
             };
         }
 
+        let mut summary = TextSummary::default();
+        let mut chunk_starts = Vec::new();
         let mut line_lengths = Vec::new();
         let mut prev_offset = 0;
-        for (offset, code_unit) in code_units.iter().enumerate() {
-            if code_unit == &u16::from(b'\n') {
-                line_lengths.push((offset - prev_offset) as u32);
-                prev_offset = offset + 1;
+        for ch in text.chars() {
+            if summary.bytes >= chunk_starts.len() * TEXT_CHUNK_BYTES {
+                chunk_starts.push(summary);
             }
+            if ch == '\n' {
+                line_lengths.push((summary.code_units - prev_offset) as u32);
+                prev_offset = summary.code_units + 1;
+            }
+            summary.push(ch);
+        }
+        line_lengths.push((summary.code_units - prev_offset) as u32);
+
+        // Every non-ASCII character takes more UTF-8 bytes than UTF-16 code units, so when the two
+        // lengths agree all offsets coincide and we don't need to index the chunks.
+        if summary.bytes == summary.code_units {
+            chunk_starts = Vec::new();
+        } else {
+            chunk_starts.shrink_to_fit();
         }
-        line_lengths.push((code_units.len() - prev_offset) as u32);
 
         let mut nodes = Vec::new();
         nodes.resize(
@@ -1721,11 +2092,58 @@ impl Text {
         );
         build_tree(0, &line_lengths, &mut nodes);
 
-        Self { code_units, nodes }
+        Self {
+            text,
+            summary,
+            chunk_starts,
+            nodes,
+        }
     }
 
-    fn len(&self) -> usize {
-        self.code_units.len()
+    pub(crate) fn len(&self) -> usize {
+        self.summary.code_units
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    fn slice(&self, range: Range<usize>) -> &str {
+        &self.text[self.summary_at(range.start).bytes..self.summary_at(range.end).bytes]
+    }
+
+    // Whether the given offset in UTF-16 code units falls between two characters.
+    fn is_char_boundary(&self, offset: usize) -> bool {
+        self.summary_at(offset).code_units == offset
+    }
+
+    // Returns the summary of the text preceding the given offset in UTF-16 code units. Offsets
+    // falling between the two halves of a surrogate pair are rounded down to the start of the
+    // character, since it can't be split in UTF-8.
+    fn summary_at(&self, offset: usize) -> TextSummary {
+        if self.chunk_starts.is_empty() {
+            return TextSummary {
+                bytes: offset,
+                code_units: offset,
+                chars: offset,
+            };
+        }
+
+        let chunk_index = match self
+            .chunk_starts
+            .binary_search_by_key(&offset, |chunk_start| chunk_start.code_units)
+        {
+            Ok(chunk_index) => return self.chunk_starts[chunk_index],
+            Err(chunk_index) => chunk_index - 1,
+        };
+        let mut summary = self.chunk_starts[chunk_index];
+        for ch in self.text[summary.bytes..].chars() {
+            if summary.code_units + ch.len_utf16() > offset {
+                break;
+            }
+            summary.push(ch);
+        }
+        summary
     }
 
     fn longest_row_in_range(&self, target_range: Range<usize>) -> Result<(u32, u32), Error> {
@@ -1876,19 +2294,39 @@ impl Text {
 
 impl<'a> From<&'a str> for Text {
     fn from(s: &'a str) -> Self {
-        Self::new(s.encode_utf16().collect())
+        Self::new(String::from(s))
     }
 }
 
 impl From<String> for Text {
     fn from(s: String) -> Self {
-        Self::from(s.as_str())
+        Self::new(s)
     }
 }
 
 impl<'a> From<Vec<u16>> for Text {
     fn from(s: Vec<u16>) -> Self {
-        Self::new(s)
+        Self::new(String::from_utf16_lossy(&s))
+    }
+}
+
+impl TextSummary {
+    fn push(&mut self, ch: char) {
+        self.bytes += ch.len_utf8();
+        self.code_units += ch.len_utf16();
+        self.chars += 1;
+    }
+}
+
+impl<'a> Sub<&'a Self> for TextSummary {
+    type Output = TextSummary;
+
+    fn sub(self, other: &'a Self) -> Self::Output {
+        TextSummary {
+            bytes: self.bytes - other.bytes,
+            code_units: self.code_units - other.code_units,
+            chars: self.chars - other.chars,
+        }
     }
 }
 
@@ -1968,16 +2406,37 @@ impl Fragment {
         }
     }
 
-    fn code_unit(&self, offset: usize) -> Option<u16> {
-        if offset < self.len() {
-            Some(self.insertion.text.code_units[self.start_offset + offset].clone())
+    fn text(&self) -> &str {
+        self.insertion
+            .text
+            .slice(self.start_offset..self.end_offset)
+    }
+
+    fn visible_text(&self) -> &str {
+        if self.is_visible() {
+            self.text()
+        } else {
+            ""
+        }
+    }
+
+    fn text_summary(&self) -> TextSummary {
+        let text = &self.insertion.text;
+        text.summary_at(self.end_offset) - &text.summary_at(self.start_offset)
+    }
+
+    fn base_offset(&self) -> Option<usize> {
+        if self.insertion.id == time::Local::default() {
+            let text = &self.insertion.text;
+            Some(text.summary_at(self.start_offset).code_units)
         } else {
             None
         }
     }
 
-    fn code_units(&self) -> &[u16] {
-        &self.insertion.text.code_units[self.start_offset..self.end_offset]
+    fn byte_offset(&self, offset: usize) -> usize {
+        let text = &self.insertion.text;
+        text.summary_at(self.start_offset + offset).bytes - text.summary_at(self.start_offset).bytes
     }
 
     fn len(&self) -> usize {
@@ -2050,8 +2509,11 @@ impl btree::Item for Fragment {
                 .text
                 .longest_row_in_range(self.start_offset as usize..self.end_offset as usize)
                 .unwrap();
+            let text_summary = self.text_summary();
             FragmentSummary {
                 extent: self.len(),
+                bytes: text_summary.bytes,
+                chars: text_summary.chars,
                 extent_2d: fragment_2d_end - &fragment_2d_start,
                 max_fragment_id: self.id.clone(),
                 first_row_len,
@@ -2062,6 +2524,8 @@ impl btree::Item for Fragment {
         } else {
             FragmentSummary {
                 extent: 0,
+                bytes: 0,
+                chars: 0,
                 extent_2d: Point { row: 0, column: 0 },
                 max_fragment_id: self.id.clone(),
                 first_row_len: 0,
@@ -2089,6 +2553,8 @@ impl<'a> AddAssign<&'a FragmentSummary> for FragmentSummary {
         }
 
         self.extent += other.extent;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
         self.extent_2d += &other.extent_2d;
         debug_assert!(self.max_fragment_id <= other.max_fragment_id);
         self.max_fragment_id = other.max_fragment_id.clone();
@@ -2100,6 +2566,8 @@ impl Default for FragmentSummary {
     fn default() -> Self {
         FragmentSummary {
             extent: 0,
+            bytes: 0,
+            chars: 0,
             extent_2d: Point { row: 0, column: 0 },
             max_fragment_id: FragmentId::min_value(),
             first_row_len: 0,
@@ -2145,7 +2613,7 @@ impl btree::Dimension<InsertionSplitSummary> for usize {
 }
 
 impl Operation {
-    fn local_timestamp(&self) -> time::Local {
+    pub(crate) fn local_timestamp(&self) -> time::Local {
         match self {
             Operation::Edit {
                 local_timestamp, ..
@@ -2153,6 +2621,14 @@ impl Operation {
         }
     }
 
+    pub(crate) fn lamport_timestamp(&self) -> time::Lamport {
+        match self {
+            Operation::Edit {
+                lamport_timestamp, ..
+            } => *lamport_timestamp,
+        }
+    }
+
     pub fn to_flatbuf<'fbb>(
         &self,
         builder: &mut FlatBufferBuilder<'fbb>,
@@ -2170,9 +2646,9 @@ impl Operation {
                 local_timestamp,
                 lamport_timestamp
             } => {
-                let new_text = new_text.as_ref().map(|new_text| {
-                    builder.create_string(String::from_utf16_lossy(&new_text.code_units).as_str())
-                });
+                let new_text = new_text
+                    .as_ref()
+                    .map(|new_text| builder.create_string(new_text.as_str()));
                 let version_in_range = Some(version_in_range.to_flatbuf(builder));
                 variant_type = serialization::buffer::OperationVariant::Edit;
                 variant = serialization::buffer::Edit::create(
@@ -2331,7 +2807,7 @@ mod tests {
                 for change in buffer.changes_since(old_buffer.version.clone()) {
                     old_buffer.edit_2d(
                         Some(change.range),
-                        Text::new(change.code_units),
+                        change.text.as_str(),
                         &mut local_clock,
                         &mut lamport_clock,
                     );
@@ -2474,6 +2950,108 @@ mod tests {
         assert_eq!(cursor.into_string(), "    \"isobar_wasm\",\n]\n");
     }
 
+    #[test]
+    fn test_utf8_storage() {
+        let mut buffer = Buffer::new("héllo\nwörld 𝄞!");
+        let replica_id = Uuid::from_u128(1);
+        let mut local_clock = time::Local::new(replica_id);
+        let mut lamport_clock = time::Lamport::new(replica_id);
+        assert_eq!(buffer.len(), 15);
+        assert_eq!(buffer.len_bytes(), 19);
+        assert_eq!(buffer.len_chars(), 14);
+
+        buffer.edit(vec![1..1], "ä", &mut local_clock, &mut lamport_clock);
+        assert_eq!(buffer.len(), 16);
+        assert_eq!(buffer.len_bytes(), 21);
+        assert_eq!(buffer.len_chars(), 15);
+        assert_eq!(buffer.line(0), Ok(String::from("häéllo")));
+        assert_eq!(buffer.line(1), Ok(String::from("wörld 𝄞!")));
+        assert_eq!(
+            buffer.iter_at_point(Point::new(1, 8)).rev().into_string(),
+            "𝄞 dlröw\nolléäh"
+        );
+
+        assert_eq!(
+            buffer.utf8_point_for_point(Point::new(1, 6)),
+            Ok(Utf8Point::new(1, 7))
+        );
+        assert_eq!(
+            buffer.utf8_point_for_point(Point::new(1, 8)),
+            Ok(Utf8Point::new(1, 11))
+        );
+        assert_eq!(
+            buffer.utf8_point_for_point(Point::new(1, 7)),
+            Err(Error::OffsetOutOfRange)
+        );
+        assert_eq!(
+            buffer.point_for_utf8_point(Utf8Point::new(1, 11)),
+            Ok(Point::new(1, 8))
+        );
+        assert_eq!(
+            buffer.point_for_utf8_point(Utf8Point::new(1, 2)),
+            Err(Error::OffsetOutOfRange)
+        );
+
+        let anchor = buffer.anchor_before_point(Utf8Point::new(0, 3)).unwrap();
+        assert_eq!(buffer.offset_for_anchor(&anchor), Ok(2));
+        buffer.edit_2d(
+            Some(Utf8Point::new(1, 7)..Utf8Point::new(1, 11)),
+            "x",
+            &mut local_clock,
+            &mut lamport_clock,
+        );
+        assert_eq!(buffer.to_string(), "häéllo\nwörld x!");
+
+        let text = Text::from("a𝄞b");
+        assert_eq!(text.slice(0..2), "a");
+        assert_eq!(text.slice(2..4), "𝄞b");
+
+        let text = Text::from("é".repeat(300) + "x");
+        assert_eq!(text.slice(299..301), "éx");
+        assert_eq!(text.summary_at(301).bytes, 601);
+    }
+
+    #[test]
+    fn test_edit_inside_surrogate_pair() {
+        let replica_1_id = Uuid::from_u128(1);
+        let mut local_clock_1 = time::Local::new(replica_1_id);
+        let mut lamport_clock_1 = time::Lamport::new(replica_1_id);
+        let mut buffer_1 = Buffer::new("a𝄞b");
+
+        let replica_2_id = Uuid::from_u128(2);
+        let mut local_clock_2 = time::Local::new(replica_2_id);
+        let mut lamport_clock_2 = time::Lamport::new(replica_2_id);
+        let mut buffer_2 = Buffer::new("a𝄞b");
+
+        // Local edits between the halves of a surrogate pair start before the character.
+        buffer_1.edit(vec![2..2], "x", &mut local_clock_1, &mut lamport_clock_1);
+        assert_eq!(buffer_1.to_string(), "ax𝄞b");
+        assert_eq!(buffer_1.len(), 5);
+        assert_eq!(buffer_1.len_bytes(), 7);
+        assert_eq!(buffer_1.len_chars(), 4);
+
+        buffer_1.edit(vec![3..5], "", &mut local_clock_1, &mut lamport_clock_1);
+        assert_eq!(buffer_1.to_string(), "ax");
+        assert_eq!(buffer_1.len(), 2);
+        assert_eq!(buffer_1.len_bytes(), 2);
+        assert_eq!(buffer_1.len_chars(), 2);
+
+        // Remote edits splitting a character are rejected.
+        let mut ops = buffer_2.edit(vec![1..1], "y", &mut local_clock_2, &mut lamport_clock_2);
+        let Operation::Edit {
+            start_offset,
+            end_offset,
+            ..
+        } = &mut ops[0];
+        *start_offset = 2;
+        *end_offset = 2;
+        let rejections = buffer_1
+            .apply_ops(ops, &mut local_clock_1, &mut lamport_clock_1)
+            .unwrap();
+        assert_eq!(rejections, vec![(replica_2_id, Error::OffsetOutOfRange)]);
+        assert_eq!(buffer_1.to_string(), "ax");
+    }
+
     #[test]
     fn test_point_for_offset() {
         let text = Text::from("abc\ndefgh\nijklm\nopq");
@@ -2777,6 +3355,143 @@ mod tests {
         assert!(buffer.is_modified());
     }
 
+    #[test]
+    fn test_stats() {
+        let mut buffer = Buffer::new("abcdef");
+        let replica_id = Uuid::from_u128(1);
+        let mut local_clock = time::Local::new(replica_id);
+        let mut lamport_clock = time::Lamport::new(replica_id);
+
+        buffer.edit(vec![1..3], "", &mut local_clock, &mut lamport_clock);
+        buffer.edit(vec![0..0], "xy", &mut local_clock, &mut lamport_clock);
+        let stats = buffer.stats();
+        assert_eq!(stats.fragments, 4);
+        assert_eq!(stats.tombstones, 1);
+        assert_eq!(stats.tombstone_bytes, 2);
+        assert_eq!(stats.visible_bytes, 6);
+        assert_eq!(stats.insertions, 2);
+        assert!(stats.insertion_splits >= stats.fragments);
+        assert_eq!(stats.deferred_ops, 0);
+        assert_eq!(stats.version_width, 1);
+    }
+
+    #[test]
+    fn test_positions_after_edits() {
+        let replica_id = Uuid::from_u128(1);
+        let mut local_clock = time::Local::new(replica_id);
+        let mut lamport_clock = time::Lamport::new(replica_id);
+        let mut buffer = Buffer::new("abc\ndef\nghi\njkl");
+
+        let before = buffer.anchor_before_point(Point::new(1, 1)).unwrap();
+        let after = buffer.anchor_after_point(Point::new(3, 1)).unwrap();
+        buffer.edit(vec![9..9], "xyz\n", &mut local_clock, &mut lamport_clock);
+        assert_eq!(buffer.point_for_anchor(&before).unwrap(), Point::new(1, 1));
+        assert_eq!(buffer.point_for_anchor(&after).unwrap(), Point::new(4, 1));
+        assert_eq!(buffer.len_for_row(2).unwrap(), 4);
+        assert_eq!(buffer.line(3).unwrap(), "hi");
+
+        // Edits that split the text into many fragments move every anchor after them.
+        let mut buffer = Buffer::new("a\n".repeat(1000));
+        let anchors = (0..1000)
+            .map(|row| buffer.anchor_before_point(Point::new(row, 1)).unwrap())
+            .collect::<Vec<_>>();
+        for row in (0..1000).step_by(10).rev() {
+            buffer.edit(
+                vec![row * 2..row * 2],
+                "b",
+                &mut local_clock,
+                &mut lamport_clock,
+            );
+        }
+        for (row, anchor) in anchors.iter().enumerate() {
+            let column = if row % 10 == 0 { 2 } else { 1 };
+            assert_eq!(
+                buffer.offset_for_anchor(anchor).unwrap(),
+                row * 2 + row / 10 + 2
+            );
+            assert_eq!(
+                buffer.point_for_anchor(anchor).unwrap(),
+                Point::new(row as u32, column)
+            );
+            assert_eq!(
+                buffer.offset_for_point(Point::new(row as u32, column)).unwrap(),
+                row * 2 + row / 10 + 2
+            );
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        for seed in 0..100 {
+            let mut rng = StdRng::from_seed(&[seed]);
+            let old_text = RandomCharIter(rng)
+                .take(rng.gen_range(0, 50))
+                .collect::<String>();
+            let mut new_text = old_text.clone();
+            for _ in 0..rng.gen_range(0, 5) {
+                let end = rng.gen_range(0, new_text.len() + 1);
+                let start = rng.gen_range(0, end + 1);
+                let text = RandomCharIter(rng)
+                    .take(rng.gen_range(0, 10))
+                    .collect::<String>();
+                new_text.replace_range(start..end, &text);
+            }
+
+            for mode in &[DiffMode::Character, DiffMode::Line] {
+                let replica_id = Uuid::from_u128(1);
+                let mut local_clock = time::Local::new(replica_id);
+                let mut lamport_clock = time::Lamport::new(replica_id);
+                let mut buffer = Buffer::new(old_text.as_str());
+                for change in diff(&old_text, &new_text, *mode) {
+                    buffer.edit_2d(
+                        Some(change.range),
+                        change.text,
+                        &mut local_clock,
+                        &mut lamport_clock,
+                    );
+                }
+                assert_eq!(buffer.to_string(), new_text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deferred_ops() {
+        let replica_1_id = Uuid::from_u128(1);
+        let mut local_clock_1 = time::Local::new(replica_1_id);
+        let mut lamport_clock_1 = time::Lamport::new(replica_1_id);
+        let mut buffer_1 = Buffer::new("abc");
+
+        let replica_2_id = Uuid::from_u128(2);
+        let mut local_clock_2 = time::Local::new(replica_2_id);
+        let mut lamport_clock_2 = time::Lamport::new(replica_2_id);
+        let mut buffer_2 = Buffer::new("abc");
+
+        let ops_1 = buffer_1.edit(vec![1..1], "x", &mut local_clock_1, &mut lamport_clock_1);
+        let ops_2 = buffer_1.edit(vec![1..2], "", &mut local_clock_1, &mut lamport_clock_1);
+        let ops_3 = buffer_1.edit(vec![0..1], "", &mut local_clock_1, &mut lamport_clock_1);
+
+        // The third operation only depends on the base text, but it can't be applied before the
+        // second one.
+        buffer_2
+            .apply_ops(
+                ops_2.into_iter().chain(ops_3),
+                &mut local_clock_2,
+                &mut lamport_clock_2,
+            )
+            .unwrap();
+        let deferred_ops = buffer_2.deferred_ops();
+        assert_eq!(deferred_ops.len(), 2);
+        assert_eq!(deferred_ops[0].missing, vec![ops_1[0].local_timestamp()]);
+        assert_eq!(deferred_ops[1].missing, vec![ops_1[0].local_timestamp()]);
+
+        buffer_2
+            .apply_ops(ops_1, &mut local_clock_2, &mut lamport_clock_2)
+            .unwrap();
+        assert!(buffer_2.deferred_ops().is_empty());
+        assert_eq!(buffer_2.to_string(), buffer_1.to_string());
+    }
+
     #[test]
     fn test_random_concurrent_edits() {
         use crate::tests::Network;
@@ -2836,13 +3551,14 @@ mod tests {
                     network.broadcast(replica_id, ops, &mut rng);
                     edit_count -= 1;
                 } else if network.has_unreceived(replica_id) {
-                    buffer
+                    let rejections = buffer
                         .apply_ops(
                             network.receive(replica_id, &mut rng),
                             local_clock,
                             lamport_clock,
                         )
                         .unwrap();
+                    assert_eq!(rejections, Vec::new());
                 }
 
                 if edit_count == 0 && network.is_idle() {
@@ -1,18 +1,24 @@
 use crate::btree::{self, SeekBias};
-use crate::buffer::{self, Buffer, Point, Text};
-use crate::operation_queue::{self, OperationQueue};
+use crate::buffer::{self, Anchor, Buffer, BufferStats, Point, Selection, Text, ToPoint};
+use crate::glob::Glob;
+use crate::merge_policy::{MergeConflict, MergePolicies, MergePolicy};
+use crate::operation_queue::{self, DeferredOperation, OperationQueue};
 use crate::serialization;
+use crate::text_format::TextFormat;
 use crate::time;
+use crate::validation::{malformed, Quarantine};
 use crate::Error;
 use crate::Oid;
 use crate::ReplicaId;
+use crate::UserId;
 use flatbuffers::{FlatBufferBuilder, UnionWIPOffset, WIPOffset};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_derive::{Deserialize, Serialize};
 use smallvec::SmallVec;
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::cmp::{self, Ordering};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
+use std::mem;
 use std::ops::{Add, AddAssign, Range};
 use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
@@ -33,7 +39,12 @@ pub struct Epoch {
     version: time::Global,
     local_clock: time::Local,
     text_files: HashMap<FileId, TextFile>,
+    text_formats: HashMap<FileId, TextFormat>,
     deferred_ops: OperationQueue<Operation>,
+    quarantine: Quarantine,
+    merge_policies: MergePolicies,
+    merge_conflicts: Vec<MergeConflict>,
+    refused_edits: HashSet<time::Lamport>,
 }
 
 pub struct Cursor<'a> {
@@ -43,6 +54,7 @@ pub struct Cursor<'a> {
     child_ref_cursor: btree::Cursor<ChildRefValue>,
     stack: Vec<CursorStackEntry>,
     path: PathBuf,
+    filter: Option<CursorFilterState>,
 }
 
 struct CursorStackEntry {
@@ -50,6 +62,21 @@ struct CursorStackEntry {
     visible: bool,
 }
 
+#[derive(Clone, Debug)]
+pub struct CursorFilter {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    statuses: Vec<FileStatus>,
+    show_deleted: bool,
+}
+
+struct CursorFilterState {
+    filter: CursorFilter,
+    // When unchanged entries are filtered out, the child refs that lead to a change in each
+    // directory. Directories missing from this map are skipped entirely.
+    changed_children: Option<HashMap<FileId, BTreeSet<ChildRefValueKey>>>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct CursorEntry {
     pub file_id: FileId,
@@ -93,6 +120,28 @@ pub enum Operation {
         local_timestamp: time::Local,
         lamport_timestamp: time::Lamport,
     },
+    SetMergePolicy {
+        pattern: String,
+        policy: MergePolicy,
+        local_timestamp: time::Local,
+        lamport_timestamp: time::Lamport,
+    },
+    CopyFiles {
+        files: Vec<CopiedFile>,
+        local_timestamp: time::Local,
+        lamport_timestamp: time::Lamport,
+    },
+}
+
+/// A file created by a copy operation. The first file of a copy is the root of the copied
+/// subtree, and every other file is placed in a directory that precedes it in the same copy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CopiedFile {
+    pub file_id: FileId,
+    pub file_type: FileType,
+    pub parent: (FileId, Arc<OsString>),
+    /// For text files, the file whose text is copied along with the version of that text.
+    pub source: Option<(FileId, time::Global)>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -101,7 +150,7 @@ pub enum FileId {
     New(time::Local),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum FileStatus {
     New,
     Renamed,
@@ -117,6 +166,21 @@ pub enum FileType {
     Text,
 }
 
+/// Describes how much an epoch retains in addition to the buffers of its open text files.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct EpochStats {
+    pub files: usize,
+    pub parent_refs: usize,
+    pub child_refs: usize,
+    pub buffered_files: usize,
+    /// Buffer operations held for text files that haven't been opened yet.
+    pub unopened_file_ops: usize,
+    pub deferred_ops: usize,
+    pub version_width: usize,
+    /// Totals over every buffer, except that `version_width` is that of the widest buffer.
+    pub buffers: BufferStats,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Metadata {
     file_id: FileId,
@@ -152,6 +216,7 @@ pub struct ChildRefValueSummary {
     visible: bool,
     timestamp: time::Lamport,
     visible_count: usize,
+    changed_count: usize,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -171,6 +236,12 @@ pub struct ChildRefKey {
 #[derive(Clone)]
 enum TextFile {
     Deferred(Vec<buffer::Operation>),
+    // A copy that hasn't been opened yet. Its text is taken from the source when it is.
+    Copied {
+        source_id: FileId,
+        version: time::Global,
+        operations: Vec<buffer::Operation>,
+    },
     Buffered(Buffer),
 }
 
@@ -187,7 +258,12 @@ impl Epoch {
             version: time::Global::new(),
             local_clock: time::Local::new(replica_id),
             text_files: HashMap::new(),
+            text_formats: HashMap::new(),
             deferred_ops: OperationQueue::new(),
+            quarantine: Quarantine::default(),
+            merge_policies: MergePolicies::new(),
+            merge_conflicts: Vec::new(),
+            refused_edits: HashSet::new(),
         }
     }
 
@@ -203,7 +279,46 @@ impl Epoch {
         self.version.clone()
     }
 
+    pub fn quarantine(&self) -> &Quarantine {
+        &self.quarantine
+    }
+
+    pub fn quarantine_mut(&mut self) -> &mut Quarantine {
+        &mut self.quarantine
+    }
+
+    pub fn merge_policies(&self) -> &MergePolicies {
+        &self.merge_policies
+    }
+
+    pub fn merge_policy(&self, file_id: FileId) -> MergePolicy {
+        self.path(file_id).map_or(MergePolicy::Merge, |path| {
+            self.merge_policies.policy_for(path)
+        })
+    }
+
+    /// Returns the conflicts detected since this method was last called.
+    pub fn take_merge_conflicts(&mut self) -> Vec<MergeConflict> {
+        mem::replace(&mut self.merge_conflicts, Vec::new())
+    }
+
     pub fn cursor(&self) -> Option<Cursor> {
+        self.build_cursor(None)
+    }
+
+    pub fn filtered_cursor(&self, filter: CursorFilter) -> Option<Cursor> {
+        let changed_children = if filter.skips_unchanged() {
+            Some(self.changed_children())
+        } else {
+            None
+        };
+        self.build_cursor(Some(CursorFilterState {
+            filter,
+            changed_children,
+        }))
+    }
+
+    fn build_cursor(&self, filter: Option<CursorFilterState>) -> Option<Cursor> {
         let metadata_cursor = self.metadata.cursor();
         let parent_ref_cursor = self.parent_refs.cursor();
         let child_ref_cursor = self.child_refs.cursor();
@@ -214,14 +329,68 @@ impl Epoch {
             child_ref_cursor,
             stack: Vec::new(),
             path: PathBuf::new(),
+            filter,
         };
-        if cursor.descend_into(true, ROOT_FILE_ID) {
+        if cursor.descend_into(true, ROOT_FILE_ID) && cursor.advance_to_match() {
             Some(cursor)
         } else {
             None
         }
     }
 
+    fn changed_children(&self) -> HashMap<FileId, BTreeSet<ChildRefValueKey>> {
+        let mut changed_file_ids = self
+            .child_refs
+            .filter(|summary| summary.changed_count > 0)
+            .map(|child_ref| child_ref.child_id)
+            .collect::<Vec<_>>();
+        changed_file_ids.extend(
+            self.text_files
+                .iter()
+                .filter(|(_, text_file)| text_file.is_modified())
+                .map(|(file_id, _)| *file_id),
+        );
+
+        let mut changed_children = HashMap::new();
+        let mut visited = HashSet::new();
+        for mut file_id in changed_file_ids {
+            while file_id != ROOT_FILE_ID && visited.insert(file_id) {
+                if let Some(child_ref_key) = self.child_ref_key(file_id) {
+                    file_id = child_ref_key.parent_id;
+                    changed_children
+                        .entry(file_id)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(child_ref_key);
+                } else {
+                    break;
+                }
+            }
+        }
+        changed_children
+    }
+
+    fn child_ref_key(&self, child_id: FileId) -> Option<ChildRefValueKey> {
+        let mut cursor = self.parent_refs.cursor();
+        cursor.seek(&child_id, SeekBias::Left);
+        let latest_parent_ref = cursor.item().filter(|r| r.child_id == child_id)?;
+        while let Some(parent_ref) = cursor.item() {
+            if parent_ref.child_id != child_id {
+                break;
+            }
+
+            if let Some((parent_id, name)) = parent_ref.parent {
+                return Some(ChildRefValueKey {
+                    parent_id,
+                    name,
+                    visible: latest_parent_ref.parent.is_some(),
+                    timestamp: parent_ref.timestamp,
+                });
+            }
+            cursor.next();
+        }
+        None
+    }
+
     pub fn append_base_entries<I>(
         &mut self,
         entries: I,
@@ -325,9 +494,24 @@ impl Epoch {
         let mut new_epoch = self.clone();
         let mut deferred_ops = Vec::new();
         let mut potential_conflicts = HashSet::new();
+        let mut fixup_ops = Vec::new();
 
         for op in ops {
+            let replica_id = op.local_timestamp().replica_id;
+            if new_epoch.quarantine.contains(replica_id) {
+                continue;
+            }
+            if let Err(error) = new_epoch.quarantine.limits().check_epoch_op(&op) {
+                new_epoch.quarantine_replica(replica_id, error);
+                continue;
+            }
+
             if new_epoch.can_apply_op(&op) {
+                if let Err(error) = new_epoch.validate_op(&op) {
+                    new_epoch.quarantine_replica(replica_id, error);
+                    continue;
+                }
+
                 match &op {
                     Operation::InsertMetadata {
                         file_id, parent, ..
@@ -339,16 +523,18 @@ impl Epoch {
                     Operation::UpdateParent { child_id, .. } => {
                         potential_conflicts.insert(*child_id);
                     }
+                    Operation::CopyFiles { files, .. } => {
+                        potential_conflicts.insert(files[0].file_id);
+                    }
                     _ => {}
                 }
-                new_epoch.apply_op(op, lamport_clock)?;
+                fixup_ops.extend(new_epoch.apply_op(op, lamport_clock)?);
             } else {
                 deferred_ops.push(op);
             }
         }
         new_epoch.deferred_ops.insert(deferred_ops);
 
-        let mut fixup_ops = Vec::new();
         for file_id in &potential_conflicts {
             fixup_ops.extend(new_epoch.fix_conflicts(*file_id, lamport_clock));
         }
@@ -357,11 +543,13 @@ impl Epoch {
         Ok(fixup_ops)
     }
 
+    /// Applies a single operation, returning a fixup operation when a buffer operation leaves
+    /// its file in a state its merge policy doesn't allow.
     pub fn apply_op(
         &mut self,
         op: Operation,
         lamport_clock: &mut time::Lamport,
-    ) -> Result<(), Error> {
+    ) -> Result<Option<Operation>, Error> {
         self.version.observe(op.local_timestamp());
         self.local_clock.observe(op.local_timestamp());
         lamport_clock.observe(op.lamport_timestamp());
@@ -374,23 +562,7 @@ impl Epoch {
                 lamport_timestamp,
                 ..
             } => {
-                if !self.metadata.cursor().seek(&file_id, SeekBias::Left) {
-                    self.metadata.insert(Metadata { file_id, file_type });
-                    if let Some((parent_id, name)) = parent {
-                        self.parent_refs.insert(ParentRefValue {
-                            child_id: file_id,
-                            parent: Some((parent_id, name.clone())),
-                            timestamp: lamport_timestamp,
-                        });
-                        self.child_refs.insert(ChildRefValue {
-                            parent_id,
-                            name,
-                            timestamp: lamport_timestamp,
-                            child_id: file_id,
-                            visible: true,
-                        });
-                    }
-                }
+                self.insert_metadata(file_id, file_type, parent, lamport_timestamp);
             }
             Operation::UpdateParent {
                 child_id,
@@ -492,18 +664,126 @@ impl Epoch {
                 .entry(file_id)
                 .or_insert_with(|| TextFile::Deferred(Vec::new()))
             {
-                TextFile::Deferred(deferred_operations) => {
+                TextFile::Deferred(deferred_operations)
+                | TextFile::Copied {
+                    operations: deferred_operations,
+                    ..
+                } => {
                     deferred_operations.extend(operations);
                 }
                 TextFile::Buffered(buffer) => {
-                    buffer
+                    let rejections = buffer
                         .apply_ops(operations, &mut self.local_clock, lamport_clock)
                         .map_err(|_| Error::InvalidOperation)?;
+                    for (replica_id, error) in rejections {
+                        self.quarantine_replica(replica_id, invalid_edit(error));
+                    }
+                    return Ok(self.enforce_merge_policy(file_id, lamport_clock));
                 }
             },
+            Operation::SetMergePolicy {
+                pattern,
+                policy,
+                lamport_timestamp,
+                ..
+            } => {
+                if let Ok(glob) = Glob::new(&pattern) {
+                    self.merge_policies.set(glob, policy, lamport_timestamp);
+                }
+            }
+            Operation::CopyFiles {
+                files,
+                lamport_timestamp,
+                ..
+            } => {
+                for file in files {
+                    if let Some((source_id, version)) = file.source {
+                        self.text_files
+                            .entry(file.file_id)
+                            .or_insert_with(|| TextFile::Copied {
+                                source_id,
+                                version,
+                                operations: Vec::new(),
+                            });
+                    }
+                    self.insert_metadata(
+                        file.file_id,
+                        file.file_type,
+                        Some(file.parent),
+                        lamport_timestamp,
+                    );
+                }
+            }
         }
 
-        Ok(())
+        Ok(None)
+    }
+
+    fn insert_metadata(
+        &mut self,
+        file_id: FileId,
+        file_type: FileType,
+        parent: Option<(FileId, Arc<OsString>)>,
+        lamport_timestamp: time::Lamport,
+    ) {
+        if !self.metadata.cursor().seek(&file_id, SeekBias::Left) {
+            self.metadata.insert(Metadata { file_id, file_type });
+            if let Some((parent_id, name)) = parent {
+                self.parent_refs.insert(ParentRefValue {
+                    child_id: file_id,
+                    parent: Some((parent_id, name.clone())),
+                    timestamp: lamport_timestamp,
+                });
+                self.child_refs.insert(ChildRefValue {
+                    parent_id,
+                    name,
+                    timestamp: lamport_timestamp,
+                    child_id: file_id,
+                    visible: true,
+                });
+            }
+        }
+    }
+
+    // Deletes any text that the file's merge policy doesn't allow to remain visible, recording
+    // conflicts for newly refused edits.
+    fn enforce_merge_policy(
+        &mut self,
+        file_id: FileId,
+        lamport_clock: &mut time::Lamport,
+    ) -> Option<Operation> {
+        let policy = self.merge_policy(file_id);
+        if policy == MergePolicy::Merge {
+            return None;
+        }
+
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get_mut(&file_id) {
+            let resolution = policy.resolve(&buffer.insertions());
+            for (refused, accepted) in resolution.refused {
+                if self.refused_edits.insert(refused) {
+                    self.merge_conflicts.push(MergeConflict {
+                        file_id,
+                        refused,
+                        accepted,
+                    });
+                }
+            }
+
+            let operations =
+                buffer.retain_insertion(resolution.retained?, &mut self.local_clock, lamport_clock);
+            if !operations.is_empty() {
+                let local_timestamp = self.local_clock.tick();
+                self.version.observe(local_timestamp);
+                return Some(Operation::BufferOperation {
+                    file_id,
+                    operations,
+                    local_timestamp,
+                    lamport_timestamp: lamport_clock.tick(),
+                });
+            }
+        }
+
+        None
     }
 
     fn can_apply_op(&self, op: &Operation) -> bool {
@@ -511,6 +791,274 @@ impl Epoch {
             Operation::InsertMetadata { .. } => true,
             Operation::UpdateParent { child_id, .. } => self.metadata(*child_id).is_ok(),
             Operation::BufferOperation { file_id, .. } => self.metadata(*file_id).is_ok(),
+            Operation::SetMergePolicy { .. } => true,
+            // A copied text can only be reproduced once we've seen every edit it includes.
+            Operation::CopyFiles { files, .. } => files.iter().all(|file| {
+                file.source.as_ref().map_or(true, |(source_id, version)| {
+                    self.metadata(*source_id).is_ok() && *version <= self.version
+                })
+            }),
+        }
+    }
+
+    fn missing_dependencies(&self, op: &Operation) -> Vec<time::Local> {
+        let mut missing = Vec::new();
+        match op {
+            Operation::InsertMetadata { .. } | Operation::SetMergePolicy { .. } => {}
+            Operation::UpdateParent { child_id, .. } => {
+                self.push_missing_file(*child_id, &mut missing);
+            }
+            Operation::BufferOperation { file_id, .. } => {
+                self.push_missing_file(*file_id, &mut missing);
+            }
+            Operation::CopyFiles { files, .. } => {
+                for file in files {
+                    if let Some((source_id, version)) = &file.source {
+                        self.push_missing_file(*source_id, &mut missing);
+                        missing.extend(self.version.missing(version));
+                    }
+                }
+            }
+        }
+        missing
+    }
+
+    // Files from the base commit aren't created by an operation, so a missing base file only
+    // means we're still loading base entries.
+    fn push_missing_file(&self, file_id: FileId, missing: &mut Vec<time::Local>) {
+        if let FileId::New(timestamp) = file_id {
+            if self.metadata(file_id).is_err() {
+                missing.push(timestamp);
+            }
+        }
+    }
+
+    // Checks that applying a remote operation can't leave the tree in a state we aren't able to
+    // repair, such as a cycle that `fix_conflicts` has no earlier move to revert.
+    fn validate_op(&self, op: &Operation) -> Result<(), Error> {
+        match op {
+            Operation::InsertMetadata {
+                file_id,
+                file_type,
+                parent,
+                ..
+            } => {
+                if let Ok(metadata) = self.metadata(*file_id) {
+                    return if metadata.file_type == *file_type {
+                        Ok(())
+                    } else {
+                        Err(malformed("file was inserted twice with different types"))
+                    };
+                }
+
+                if let Some((parent_id, _)) = parent {
+                    self.validate_parent(*file_id, *parent_id)?;
+                    if self.is_ancestor(*file_id, *parent_id) {
+                        return Err(malformed("file would become its own ancestor"));
+                    }
+                } else if *file_type == FileType::Directory {
+                    return Err(malformed("directory was inserted without a parent"));
+                }
+
+                if *file_type == FileType::Text && self.has_children(*file_id) {
+                    return Err(malformed("text file already has children"));
+                }
+            }
+            Operation::UpdateParent {
+                child_id,
+                new_parent,
+                lamport_timestamp,
+                ..
+            } => {
+                if *child_id == ROOT_FILE_ID {
+                    return Err(malformed("the root directory can't be moved"));
+                }
+
+                if let Some((parent_id, _)) = new_parent {
+                    self.validate_parent(*child_id, *parent_id)?;
+                    if self.is_observed_ancestor(
+                        *child_id,
+                        *parent_id,
+                        lamport_timestamp.replica_id,
+                    ) {
+                        return Err(malformed("file would become its own ancestor"));
+                    }
+                    if self.metadata(*child_id)?.file_type == FileType::Directory
+                        && self.child_ref_key(*child_id).is_none()
+                    {
+                        return Err(malformed("directory has no previous location"));
+                    }
+                }
+            }
+            Operation::BufferOperation { file_id, .. } => {
+                if self.metadata(*file_id)?.file_type != FileType::Text {
+                    return Err(malformed("edited file isn't a text file"));
+                }
+            }
+            Operation::SetMergePolicy { .. } => {}
+            Operation::CopyFiles { files, .. } => {
+                if files.is_empty() {
+                    return Err(malformed("copy contains no files"));
+                }
+
+                // Copies only ever create files, which keeps every copied text's source older than
+                // the copy and so keeps chains of sources free of cycles.
+                let copied_ids = files.iter().map(|file| file.file_id).collect::<HashSet<_>>();
+                if copied_ids.len() != files.len() {
+                    return Err(malformed("copy contains a file twice"));
+                }
+
+                let mut copied_dirs = HashSet::new();
+                for (index, file) in files.iter().enumerate() {
+                    if self.metadata(file.file_id).is_ok() {
+                        return Err(malformed("copied file already exists"));
+                    }
+
+                    let parent_id = file.parent.0;
+                    if index == 0 {
+                        self.validate_parent(file.file_id, parent_id)?;
+                        if copied_ids.contains(&parent_id) {
+                            return Err(malformed("file would become its own ancestor"));
+                        }
+                    } else if !copied_dirs.contains(&parent_id) {
+                        return Err(malformed("copied file isn't inside the copy"));
+                    }
+
+                    match (file.file_type, &file.source) {
+                        (FileType::Directory, None) => {
+                            copied_dirs.insert(file.file_id);
+                        }
+                        (FileType::Text, Some((source_id, _))) => {
+                            if copied_ids.contains(source_id) {
+                                return Err(malformed("copy source is inside the copy"));
+                            }
+                            if self.metadata(*source_id)?.file_type != FileType::Text {
+                                return Err(malformed("copy source isn't a text file"));
+                            }
+                        }
+                        _ => return Err(malformed("copied file has an invalid source")),
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Local operations are checked like remote ones so that we never broadcast an operation our
+    // peers would reject, but failures are reported to the caller instead of quarantining.
+    fn check_local_op(&self, op: &Operation) -> Result<(), Error> {
+        self.quarantine.limits().check_epoch_op(op)?;
+        self.validate_op(op)
+    }
+
+    fn validate_parent(&self, child_id: FileId, parent_id: FileId) -> Result<(), Error> {
+        if child_id == parent_id {
+            Err(malformed("file would become its own parent"))
+        } else if self
+            .metadata(parent_id)
+            .map_or(false, |parent| parent.file_type != FileType::Directory)
+        {
+            Err(malformed("parent isn't a directory"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn is_ancestor(&self, ancestor_id: FileId, file_id: FileId) -> bool {
+        self.is_ancestor_through(ancestor_id, file_id, |_| true)
+    }
+
+    // Whether the given replica placed `file_id` under `ancestor_id` knowingly, which is the case
+    // when every parent ref between them comes from the base commit or from the replica itself.
+    // A cycle through any other parent ref may be the result of concurrent moves.
+    fn is_observed_ancestor(
+        &self,
+        ancestor_id: FileId,
+        file_id: FileId,
+        replica_id: ReplicaId,
+    ) -> bool {
+        self.is_ancestor_through(ancestor_id, file_id, |timestamp| {
+            timestamp == time::Lamport::default() || timestamp.replica_id == replica_id
+        })
+    }
+
+    fn is_ancestor_through<F>(
+        &self,
+        ancestor_id: FileId,
+        mut file_id: FileId,
+        can_follow: F,
+    ) -> bool
+    where
+        F: Fn(time::Lamport) -> bool,
+    {
+        let mut visited = HashSet::new();
+        let mut cursor = self.parent_refs.cursor();
+        while visited.insert(file_id) {
+            if file_id == ancestor_id {
+                return true;
+            }
+
+            cursor.seek(&file_id, SeekBias::Left);
+            match cursor.item() {
+                Some(ParentRefValue {
+                    child_id,
+                    timestamp,
+                    parent: Some((parent_id, _)),
+                }) if child_id == file_id && can_follow(timestamp) => file_id = parent_id,
+                _ => break,
+            }
+        }
+        false
+    }
+
+    fn visible_children(&self, parent_id: FileId) -> Vec<(Arc<OsString>, FileId)> {
+        let mut children = Vec::new();
+        let mut cursor = self.child_refs.cursor();
+        cursor.seek(&parent_id, SeekBias::Left);
+        while let Some(child_ref) = cursor.item() {
+            if child_ref.parent_id != parent_id {
+                break;
+            } else if child_ref.visible {
+                children.push((child_ref.name, child_ref.child_id));
+            }
+            cursor.next();
+        }
+        children
+    }
+
+    // The version of a text file's current contents, which is what a copy of it refers to.
+    fn text_version(&self, file_id: FileId) -> time::Global {
+        match self.text_files.get(&file_id) {
+            Some(TextFile::Buffered(buffer)) => buffer.version.clone(),
+            Some(TextFile::Deferred(operations)) | Some(TextFile::Copied { operations, .. }) => {
+                let mut version = time::Global::new();
+                for operation in operations {
+                    version.observe(operation.local_timestamp());
+                }
+                version
+            }
+            None => time::Global::new(),
+        }
+    }
+
+    fn has_children(&self, file_id: FileId) -> bool {
+        let mut cursor = self.child_refs.cursor();
+        cursor.seek(&file_id, SeekBias::Left);
+        cursor
+            .item()
+            .map_or(false, |child_ref| child_ref.parent_id == file_id)
+    }
+
+    pub(crate) fn quarantine_replica(&mut self, replica_id: ReplicaId, error: Error) {
+        self.quarantine.insert(replica_id, error);
+        for text_file in self.text_files.values_mut() {
+            match text_file {
+                TextFile::Deferred(operations) | TextFile::Copied { operations, .. } => {
+                    operations.retain(|op| op.local_timestamp().replica_id != replica_id)
+                }
+                TextFile::Buffered(buffer) => buffer.discard_deferred_ops(replica_id),
+            }
         }
     }
 
@@ -536,6 +1084,7 @@ impl Epoch {
             local_timestamp: new_epoch.local_clock.tick(),
             lamport_timestamp: new_lamport_clock.tick(),
         };
+        new_epoch.check_local_op(&operation)?;
         let fixup_ops = new_epoch
             .apply_ops_internal(Some(operation.clone()), &mut new_lamport_clock)
             .unwrap();
@@ -561,24 +1110,39 @@ impl Epoch {
         (file_id, operation)
     }
 
+    /// Opens a text file with the given base text. The line endings and byte order mark of the
+    /// base text are recorded so that `export_text` can restore them, and the buffer only ever
+    /// contains LF line endings.
+    /// Opens a buffer for the given text file. Edits received before the file was opened are
+    /// applied to its base text, and the returned operations delete any of them that the file's
+    /// merge policy doesn't allow.
     pub fn open_text_file<T>(
         &mut self,
         file_id: FileId,
         base_text: T,
         lamport_clock: &mut time::Lamport,
-    ) -> Result<(), Error>
+    ) -> Result<Vec<Operation>, Error>
     where
-        T: Into<Text>,
+        T: AsRef<str>,
     {
         self.check_file_id(file_id, Some(FileType::Text))?;
+        if let Some(TextFile::Copied { .. }) = self.text_files.get(&file_id) {
+            return self.open_copied_text_file(file_id, base_text.as_ref(), lamport_clock);
+        }
+        let (format, base_text) = TextFormat::detect(base_text.as_ref());
 
+        let mut fixup_ops = Vec::new();
         match self.text_files.remove(&file_id) {
             Some(TextFile::Deferred(operations)) => {
                 let mut buffer = Buffer::new(base_text);
-                buffer
+                let rejections = buffer
                     .apply_ops(operations, &mut self.local_clock, lamport_clock)
                     .map_err(|_| Error::InvalidOperation)?;
                 self.text_files.insert(file_id, TextFile::Buffered(buffer));
+                for (replica_id, error) in rejections {
+                    self.quarantine_replica(replica_id, invalid_edit(error));
+                }
+                fixup_ops.extend(self.enforce_merge_policy(file_id, lamport_clock));
             }
             Some(text_file) => {
                 self.text_files.insert(file_id, text_file);
@@ -588,8 +1152,64 @@ impl Epoch {
                     .insert(file_id, TextFile::Buffered(Buffer::new(base_text)));
             }
         }
+        self.text_formats.entry(file_id).or_insert(format);
 
-        Ok(())
+        Ok(fixup_ops)
+    }
+
+    // A copy shares the base text of the file it was copied from, so we open that file first
+    // and take its text at the copied version.
+    fn open_copied_text_file(
+        &mut self,
+        file_id: FileId,
+        base_text: &str,
+        lamport_clock: &mut time::Lamport,
+    ) -> Result<Vec<Operation>, Error> {
+        // Opening the source recurses through its own source, so make sure that ends first.
+        self.text_source(file_id)?;
+        let (source_id, version) = match self.text_files.get(&file_id) {
+            Some(TextFile::Copied {
+                source_id, version, ..
+            }) => (*source_id, version.clone()),
+            _ => unreachable!(),
+        };
+
+        let mut fixup_ops = self.open_text_file(source_id, base_text, lamport_clock)?;
+        let text = match self.text_files.get(&source_id) {
+            Some(TextFile::Buffered(source)) if version <= source.version => {
+                source.text_at(&version)
+            }
+            _ => return Err(Error::InvalidFileId("copy source is missing edits".into())),
+        };
+        let format = self.text_format(source_id)?.derived();
+
+        if let Some(TextFile::Copied { operations, .. }) = self.text_files.remove(&file_id) {
+            let mut buffer = Buffer::new(text);
+            let rejections = buffer
+                .apply_ops(operations, &mut self.local_clock, lamport_clock)
+                .map_err(|_| Error::InvalidOperation)?;
+            self.text_files.insert(file_id, TextFile::Buffered(buffer));
+            for (replica_id, error) in rejections {
+                self.quarantine_replica(replica_id, invalid_edit(error));
+            }
+            fixup_ops.extend(self.enforce_merge_policy(file_id, lamport_clock));
+        }
+        self.text_formats.entry(file_id).or_insert(format);
+
+        Ok(fixup_ops)
+    }
+
+    /// The file whose base text must be supplied when opening the given file, which differs from
+    /// the file itself for copies that haven't been opened yet.
+    pub fn text_source(&self, mut file_id: FileId) -> Result<FileId, Error> {
+        // Without a cycle, the chain of sources visits each text file at most once.
+        for _ in 0..=self.text_files.len() {
+            match self.text_files.get(&file_id) {
+                Some(TextFile::Copied { source_id, .. }) => file_id = *source_id,
+                _ => return Ok(file_id),
+            }
+        }
+        Err(Error::InvalidFileId("copy sources form a cycle".into()))
     }
 
     pub fn rename<N>(
@@ -613,6 +1233,69 @@ impl Epoch {
             local_timestamp: new_epoch.local_clock.tick(),
             lamport_timestamp: new_lamport_clock.tick(),
         };
+        new_epoch.check_local_op(&operation)?;
+        let fixup_ops = new_epoch
+            .apply_ops_internal(Some(operation.clone()), &mut new_lamport_clock)
+            .unwrap();
+        if fixup_ops.is_empty() {
+            *lamport_clock = new_lamport_clock;
+            *self = new_epoch;
+            Ok(operation)
+        } else {
+            Err(Error::InvalidOperation)
+        }
+    }
+
+    /// Copies a file or a directory along with everything inside it. Rather than carrying the
+    /// text of each copied file, the operation refers to the current version of its source.
+    pub fn copy<N>(
+        &mut self,
+        source_id: FileId,
+        new_parent_id: FileId,
+        new_name: N,
+        lamport_clock: &mut time::Lamport,
+    ) -> Result<Operation, Error>
+    where
+        N: AsRef<OsStr>,
+    {
+        let file_type = self.metadata(source_id)?.file_type;
+        self.check_file_id(new_parent_id, Some(FileType::Directory))?;
+        if self.is_ancestor(source_id, new_parent_id) {
+            return Err(Error::InvalidPath(
+                "a directory can't be copied into itself".into(),
+            ));
+        }
+
+        let mut new_lamport_clock = *lamport_clock;
+        let mut new_epoch = self.clone();
+        let mut files = Vec::new();
+        let new_parent = (new_parent_id, Arc::new(new_name.as_ref().into()));
+        let mut stack = vec![(source_id, file_type, new_parent)];
+        while let Some((source_id, file_type, parent)) = stack.pop() {
+            let file_id = FileId::New(new_epoch.local_clock.tick());
+            let source = if file_type == FileType::Directory {
+                for (name, child_id) in self.visible_children(source_id).into_iter().rev() {
+                    let child_type = self.metadata(child_id)?.file_type;
+                    stack.push((child_id, child_type, (file_id, name)));
+                }
+                None
+            } else {
+                Some((source_id, self.text_version(source_id)))
+            };
+            files.push(CopiedFile {
+                file_id,
+                file_type,
+                parent,
+                source,
+            });
+        }
+
+        let operation = Operation::CopyFiles {
+            files,
+            local_timestamp: new_epoch.local_clock.tick(),
+            lamport_timestamp: new_lamport_clock.tick(),
+        };
+        new_epoch.check_local_op(&operation)?;
         let fixup_ops = new_epoch
             .apply_ops_internal(Some(operation.clone()), &mut new_lamport_clock)
             .unwrap();
@@ -642,7 +1325,27 @@ impl Epoch {
         Ok(operation)
     }
 
-    pub fn edit<I, T>(
+    pub fn edit<I, T>(
+        &mut self,
+        file_id: FileId,
+        old_ranges: I,
+        new_text: T,
+        lamport_clock: &mut time::Lamport,
+    ) -> Result<Operation, Error>
+    where
+        I: IntoIterator<Item = Range<usize>>,
+        T: Into<Text>,
+    {
+        self.edit_buffer(
+            file_id,
+            lamport_clock,
+            |buffer, local_clock, lamport_clock| {
+                buffer.edit(old_ranges, new_text, local_clock, lamport_clock)
+            },
+        )
+    }
+
+    pub fn edit_2d<I, P, T>(
         &mut self,
         file_id: FileId,
         old_ranges: I,
@@ -650,39 +1353,66 @@ impl Epoch {
         lamport_clock: &mut time::Lamport,
     ) -> Result<Operation, Error>
     where
-        I: IntoIterator<Item = Range<usize>>,
+        I: IntoIterator<Item = Range<P>>,
+        P: ToPoint,
         T: Into<Text>,
     {
-        if let Some(TextFile::Buffered(buffer)) = self.text_files.get_mut(&file_id) {
-            let operations =
-                buffer.edit(old_ranges, new_text, &mut self.local_clock, lamport_clock);
-            let local_timestamp = self.local_clock.tick();
-            self.version.observe(local_timestamp);
-            Ok(Operation::BufferOperation {
-                file_id,
-                operations,
-                local_timestamp,
-                lamport_timestamp: lamport_clock.tick(),
-            })
-        } else {
-            Err(Error::InvalidFileId("file has not been opened".into()))
-        }
+        self.edit_buffer(
+            file_id,
+            lamport_clock,
+            |buffer, local_clock, lamport_clock| {
+                buffer.edit_2d(old_ranges, new_text, local_clock, lamport_clock)
+            },
+        )
+    }
+
+    pub fn set_merge_policy(
+        &mut self,
+        pattern: &str,
+        policy: MergePolicy,
+        lamport_clock: &mut time::Lamport,
+    ) -> Result<Operation, Error> {
+        let glob = Glob::new(pattern)?;
+        let operation = Operation::SetMergePolicy {
+            pattern: glob.as_str().into(),
+            policy,
+            local_timestamp: self.local_clock.tick(),
+            lamport_timestamp: lamport_clock.tick(),
+        };
+        self.apply_op(operation.clone(), lamport_clock).unwrap();
+        Ok(operation)
     }
 
-    pub fn edit_2d<I, T>(
+    fn edit_buffer<F>(
         &mut self,
         file_id: FileId,
-        old_ranges: I,
-        new_text: T,
         lamport_clock: &mut time::Lamport,
+        edit: F,
     ) -> Result<Operation, Error>
     where
-        I: IntoIterator<Item = Range<Point>>,
-        T: Into<Text>,
+        F: FnOnce(&mut Buffer, &mut time::Local, &mut time::Lamport) -> Vec<buffer::Operation>,
     {
+        let policy = self.merge_policy(file_id);
         if let Some(TextFile::Buffered(buffer)) = self.text_files.get_mut(&file_id) {
-            let operations =
-                buffer.edit_2d(old_ranges, new_text, &mut self.local_clock, lamport_clock);
+            let operations = if policy == MergePolicy::Merge {
+                edit(buffer, &mut self.local_clock, lamport_clock)
+            } else {
+                // Under other policies, each edit is accepted or refused as a whole, so we
+                // express it as a replacement of the entire file.
+                let mut new_buffer = buffer.clone();
+                edit(
+                    &mut new_buffer,
+                    &mut self.local_clock.clone(),
+                    &mut lamport_clock.clone(),
+                );
+                let old_len = buffer.len();
+                buffer.edit(
+                    Some(0..old_len),
+                    new_buffer.to_string(),
+                    &mut self.local_clock,
+                    lamport_clock,
+                )
+            };
             let local_timestamp = self.local_clock.tick();
             self.version.observe(local_timestamp);
             Ok(Operation::BufferOperation {
@@ -784,6 +1514,22 @@ impl Epoch {
         }
     }
 
+    pub fn text_format(&self, file_id: FileId) -> Result<&TextFormat, Error> {
+        self.text_formats
+            .get(&file_id)
+            .ok_or_else(|| Error::InvalidFileId("file has not been opened".into()))
+    }
+
+    /// Returns the contents of a text file encoded as it would be written to disk, using the line
+    /// endings and byte order mark of its base text.
+    pub fn export_text(&self, file_id: FileId) -> Result<Vec<u8>, Error> {
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get(&file_id) {
+            Ok(buffer.encode(self.text_format(file_id)?))
+        } else {
+            Err(Error::InvalidFileId("file has not been opened".into()))
+        }
+    }
+
     pub fn changes_since(
         &self,
         file_id: FileId,
@@ -796,6 +1542,153 @@ impl Epoch {
         }
     }
 
+    pub fn anchor_before_point(&self, file_id: FileId, point: Point) -> Result<Anchor, Error> {
+        self.buffer(file_id)?
+            .anchor_before_point(point)
+            .map_err(buffer_error)
+    }
+
+    pub fn anchor_after_point(&self, file_id: FileId, point: Point) -> Result<Anchor, Error> {
+        self.buffer(file_id)?
+            .anchor_after_point(point)
+            .map_err(buffer_error)
+    }
+
+    pub fn point_for_anchor(&self, file_id: FileId, anchor: &Anchor) -> Result<Point, Error> {
+        self.buffer(file_id)?
+            .point_for_anchor(anchor)
+            .map_err(buffer_error)
+    }
+
+    /// Adds a set of selections to an open text file, returning the id of the new set. Selection
+    /// sets are local to this replica and are discarded along with the epoch.
+    pub fn add_selection_set(
+        &mut self,
+        file_id: FileId,
+        user_id: UserId,
+        selections: Vec<Selection>,
+    ) -> Result<time::Local, Error> {
+        let selections = self.sort_selections(file_id, selections)?;
+        let local_clock = &mut self.local_clock;
+        match self.text_files.get_mut(&file_id) {
+            Some(TextFile::Buffered(buffer)) => {
+                Ok(buffer.add_selection_set(user_id, selections, local_clock))
+            }
+            _ => Err(Error::InvalidFileId("file has not been opened".into())),
+        }
+    }
+
+    pub fn replace_selections(
+        &mut self,
+        file_id: FileId,
+        set_id: time::Local,
+        selections: Vec<Selection>,
+    ) -> Result<(), Error> {
+        let selections = self.sort_selections(file_id, selections)?;
+        self.buffer_mut(file_id)?
+            .mutate_selections(set_id, |_, old_selections| *old_selections = selections)
+            .map_err(buffer_error)
+    }
+
+    pub fn remove_selection_set(
+        &mut self,
+        file_id: FileId,
+        set_id: time::Local,
+    ) -> Result<(), Error> {
+        self.buffer_mut(file_id)?
+            .remove_selection_set(set_id)
+            .map_err(buffer_error)
+    }
+
+    pub fn selections(
+        &self,
+        file_id: FileId,
+        set_id: time::Local,
+    ) -> Result<Vec<Selection>, Error> {
+        self.buffer(file_id)?
+            .selections(set_id)
+            .map(|selections| selections.to_vec())
+            .map_err(buffer_error)
+    }
+
+    // Selections may come from outside the buffer, so check that their anchors resolve before
+    // ordering them by where they start, as the buffer expects.
+    fn sort_selections(
+        &self,
+        file_id: FileId,
+        selections: Vec<Selection>,
+    ) -> Result<Vec<Selection>, Error> {
+        let buffer = self.buffer(file_id)?;
+        let mut selections = selections
+            .into_iter()
+            .map(|selection| {
+                let start = buffer
+                    .offset_for_anchor(&selection.start)
+                    .map_err(buffer_error)?;
+                let end = buffer
+                    .offset_for_anchor(&selection.end)
+                    .map_err(buffer_error)?;
+                if start <= end {
+                    Ok((start, selection))
+                } else {
+                    Err(Error::InvalidAnchor)
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        selections.sort_by_key(|(start, _)| *start);
+        Ok(selections
+            .into_iter()
+            .map(|(_, selection)| selection)
+            .collect())
+    }
+
+    fn buffer(&self, file_id: FileId) -> Result<&Buffer, Error> {
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get(&file_id) {
+            Ok(buffer)
+        } else {
+            Err(Error::InvalidFileId("file has not been opened".into()))
+        }
+    }
+
+    fn buffer_mut(&mut self, file_id: FileId) -> Result<&mut Buffer, Error> {
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get_mut(&file_id) {
+            Ok(buffer)
+        } else {
+            Err(Error::InvalidFileId("file has not been opened".into()))
+        }
+    }
+
+    pub fn buffer_stats(&self, file_id: FileId) -> Result<BufferStats, Error> {
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get(&file_id) {
+            Ok(buffer.stats())
+        } else {
+            Err(Error::InvalidFileId("file has not been opened".into()))
+        }
+    }
+
+    pub fn stats(&self) -> EpochStats {
+        let mut stats = EpochStats {
+            files: self.metadata.len(),
+            parent_refs: self.parent_refs.len(),
+            child_refs: self.child_refs.len(),
+            deferred_ops: self.deferred_ops.len(),
+            version_width: self.version.replica_ids().count(),
+            ..EpochStats::default()
+        };
+        for text_file in self.text_files.values() {
+            match text_file {
+                TextFile::Deferred(operations) | TextFile::Copied { operations, .. } => {
+                    stats.unopened_file_ops += operations.len();
+                }
+                TextFile::Buffered(buffer) => {
+                    stats.buffered_files += 1;
+                    stats.buffers += &buffer.stats();
+                }
+            }
+        }
+        stats
+    }
+
     pub fn buffer_deferred_ops_len(&self, file_id: FileId) -> Result<usize, Error> {
         if let Some(TextFile::Buffered(buffer)) = self.text_files.get(&file_id) {
             Ok(buffer.deferred_ops_len())
@@ -804,6 +1697,50 @@ impl Epoch {
         }
     }
 
+    pub fn buffer_deferred_ops(&self, file_id: FileId) -> Result<Vec<DeferredOperation>, Error> {
+        if let Some(TextFile::Buffered(buffer)) = self.text_files.get(&file_id) {
+            Ok(buffer.deferred_ops())
+        } else {
+            Err(Error::InvalidFileId("file has not been opened".into()))
+        }
+    }
+
+    /// Describes what each deferred operation on the tree itself is waiting on. Operations on
+    /// open buffers are deferred by the buffer and are described by `buffer_deferred_ops`.
+    pub fn deferred_ops(&self) -> Vec<DeferredOperation> {
+        self.deferred_ops
+            .iter()
+            .map(|op| {
+                let mut missing = self.missing_dependencies(&op);
+                missing.sort();
+                missing.dedup();
+                DeferredOperation {
+                    lamport_timestamp: op.lamport_timestamp(),
+                    missing,
+                }
+            })
+            .collect()
+    }
+
+    /// For every replica that deferred operations are waiting on, the earliest timestamp from
+    /// which its operations may be missing. Receiving every operation that replica generated
+    /// from that timestamp on is enough to apply the operations waiting on it.
+    pub fn missing_ops_since(&self) -> Vec<time::Local> {
+        let mut since = HashMap::new();
+        add_missing_ops_since(&mut since, &self.version, self.deferred_ops());
+        for text_file in self.text_files.values() {
+            if let TextFile::Buffered(buffer) = text_file {
+                add_missing_ops_since(&mut since, &buffer.version, buffer.deferred_ops());
+            }
+        }
+        let mut since = since
+            .into_iter()
+            .map(|(replica_id, value)| time::Local { replica_id, value })
+            .collect::<Vec<_>>();
+        since.sort();
+        since
+    }
+
     pub fn file_type(&self, file_id: FileId) -> Result<FileType, Error> {
         Ok(self.metadata(file_id)?.file_type)
     }
@@ -1053,18 +1990,64 @@ impl<'a> Cursor<'a> {
     pub fn next(&mut self, can_descend: bool) -> bool {
         if !self.stack.is_empty() {
             let entry = self.entry().unwrap();
-            if !can_descend
-                || entry.file_type != FileType::Directory
-                || !self.descend_into(entry.visible, entry.file_id)
-            {
-                while !self.stack.is_empty() && !self.next_sibling() {
-                    self.stack.pop();
-                    self.path.pop();
-                }
+            let can_descend = can_descend && self.filter_can_descend(&entry);
+            self.step(&entry, can_descend);
+        }
+
+        self.advance_to_match()
+    }
+
+    fn step(&mut self, entry: &CursorEntry, can_descend: bool) {
+        if !can_descend
+            || entry.file_type != FileType::Directory
+            || !self.descend_into(entry.visible, entry.file_id)
+        {
+            while !self.stack.is_empty() && !self.next_sibling() {
+                self.stack.pop();
+                self.path.pop();
+            }
+        }
+    }
+
+    fn advance_to_match(&mut self) -> bool {
+        if self.filter.is_none() {
+            return !self.stack.is_empty();
+        }
+
+        while !self.stack.is_empty() {
+            let entry = self.entry().unwrap();
+            if self.filter_matches(&entry) {
+                return true;
             }
+
+            let can_descend = self.filter_can_descend(&entry);
+            self.step(&entry, can_descend);
+        }
+
+        false
+    }
+
+    fn filter_matches(&self, entry: &CursorEntry) -> bool {
+        if let Some(CursorFilterState { filter, .. }) = &self.filter {
+            filter.matches(&self.path, entry)
+        } else {
+            true
         }
+    }
 
-        !self.stack.is_empty()
+    fn filter_can_descend(&self, entry: &CursorEntry) -> bool {
+        if let Some(CursorFilterState {
+            filter,
+            changed_children,
+        }) = &self.filter
+        {
+            filter.can_descend(&self.path, entry)
+                && changed_children
+                    .as_ref()
+                    .map_or(true, |changed| changed.contains_key(&entry.file_id))
+        } else {
+            true
+        }
     }
 
     pub fn entry(&self) -> Result<CursorEntry, Error> {
@@ -1123,39 +2106,81 @@ impl<'a> Cursor<'a> {
     fn descend_into(&mut self, parent_visible: bool, dir_id: FileId) -> bool {
         let mut child_ref_cursor = self.child_ref_cursor.clone();
         child_ref_cursor.seek(&dir_id, SeekBias::Left);
-        if let Some(child_ref) = child_ref_cursor.item() {
-            if child_ref.parent_id == dir_id {
-                self.stack.push(CursorStackEntry {
-                    cursor: child_ref_cursor,
-                    visible: parent_visible,
-                });
-                self.path.push(child_ref.name.as_ref());
-                self.metadata_cursor
-                    .seek(&child_ref.child_id, SeekBias::Left);
-                true
-            } else {
-                false
-            }
+        if let Some(child_ref) = self.seek_to_candidate(&mut child_ref_cursor, dir_id, None) {
+            self.stack.push(CursorStackEntry {
+                cursor: child_ref_cursor,
+                visible: parent_visible,
+            });
+            self.path.push(child_ref.name.as_ref());
+            self.metadata_cursor
+                .seek(&child_ref.child_id, SeekBias::Left);
+            true
         } else {
             false
         }
     }
 
     pub fn next_sibling(&mut self) -> bool {
-        let CursorStackEntry { cursor, .. } = self.stack.last_mut().unwrap();
-        let parent_id = cursor.item().unwrap().parent_id;
-        cursor.next();
-        if let Some(child_ref) = cursor.item() {
-            if child_ref.parent_id == parent_id {
-                self.metadata_cursor
-                    .seek(&child_ref.child_id, SeekBias::Left);
-                self.path.pop();
-                self.path.push(child_ref.name.as_ref());
-                return true;
+        let mut stack_entry = self.stack.pop().unwrap();
+        let prev_child_ref = stack_entry.cursor.item().unwrap();
+        stack_entry.cursor.next();
+        let next_child_ref = self.seek_to_candidate(
+            &mut stack_entry.cursor,
+            prev_child_ref.parent_id,
+            Some(&prev_child_ref),
+        );
+        self.stack.push(stack_entry);
+
+        if let Some(child_ref) = next_child_ref {
+            self.metadata_cursor
+                .seek(&child_ref.child_id, SeekBias::Left);
+            self.path.pop();
+            self.path.push(child_ref.name.as_ref());
+            true
+        } else {
+            false
+        }
+    }
+
+    // Moves the given cursor forward to the next child of `dir_id` that the filter could accept,
+    // skipping removed entries and directories without changes in bulk when possible.
+    fn seek_to_candidate(
+        &self,
+        cursor: &mut btree::Cursor<ChildRefValue>,
+        dir_id: FileId,
+        prev_child_ref: Option<&ChildRefValue>,
+    ) -> Option<ChildRefValue> {
+        use crate::btree::KeyedItem;
+
+        if let Some(CursorFilterState {
+            filter,
+            changed_children,
+        }) = &self.filter
+        {
+            if let Some(changed_children) = changed_children {
+                let prev_key = prev_child_ref.map(|child_ref| child_ref.key());
+                let candidates = changed_children.get(&dir_id)?.iter().filter(|key| {
+                    (filter.show_deleted || key.visible)
+                        && prev_key.as_ref().map_or(true, |prev_key| *key > prev_key)
+                });
+                for key in candidates {
+                    cursor.seek_forward(key, SeekBias::Left);
+                    if let Some(child_ref) = cursor.item() {
+                        if child_ref.key() == *key {
+                            return Some(child_ref);
+                        }
+                    }
+                }
+                return None;
+            } else if !filter.show_deleted && cursor.item().map_or(false, |r| !r.visible) {
+                let visible_index = cursor.start::<usize>();
+                cursor.seek_forward(&visible_index, SeekBias::Right);
             }
         }
 
-        false
+        cursor
+            .item()
+            .filter(|child_ref| child_ref.parent_id == dir_id)
     }
 
     fn is_modified_file(&self, file_id: FileId) -> bool {
@@ -1165,8 +2190,74 @@ impl<'a> Cursor<'a> {
     }
 }
 
+impl CursorFilter {
+    pub fn new() -> Self {
+        CursorFilter {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            statuses: Vec::new(),
+            show_deleted: true,
+        }
+    }
+
+    /// Only yields entries matching one of the included globs. Directories are still traversed
+    /// when they could contain a match.
+    pub fn include(mut self, glob: Glob) -> Self {
+        self.include.push(glob);
+        self
+    }
+
+    /// Skips entries matching any of the excluded globs along with everything beneath them.
+    pub fn exclude(mut self, glob: Glob) -> Self {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// Only yields entries with one of the given statuses. When `Unchanged` isn't among them,
+    /// the cursor only visits directories containing changes.
+    pub fn status(mut self, status: FileStatus) -> Self {
+        if !self.statuses.contains(&status) {
+            self.statuses.push(status);
+        }
+        self
+    }
+
+    pub fn show_deleted(mut self, show_deleted: bool) -> Self {
+        self.show_deleted = show_deleted;
+        self
+    }
+
+    fn skips_unchanged(&self) -> bool {
+        !self.statuses.is_empty() && !self.statuses.contains(&FileStatus::Unchanged)
+    }
+
+    fn matches(&self, path: &Path, entry: &CursorEntry) -> bool {
+        (self.show_deleted || entry.visible)
+            && (self.statuses.is_empty() || self.statuses.contains(&entry.status))
+            && (self.include.is_empty() || self.include.iter().any(|glob| glob.is_match(path)))
+            && !self.exclude.iter().any(|glob| glob.is_match(path))
+    }
+
+    fn can_descend(&self, path: &Path, entry: &CursorEntry) -> bool {
+        entry.file_type == FileType::Directory
+            && (self.show_deleted || entry.visible)
+            && (self.include.is_empty()
+                || self
+                    .include
+                    .iter()
+                    .any(|glob| glob.could_match_descendant(path)))
+            && !self.exclude.iter().any(|glob| glob.is_match(path))
+    }
+}
+
+impl Default for CursorFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Operation {
-    fn local_timestamp(&self) -> time::Local {
+    pub(crate) fn local_timestamp(&self) -> time::Local {
         match self {
             Operation::InsertMetadata {
                 local_timestamp, ..
@@ -1177,6 +2268,12 @@ impl Operation {
             Operation::BufferOperation {
                 local_timestamp, ..
             } => *local_timestamp,
+            Operation::SetMergePolicy {
+                local_timestamp, ..
+            } => *local_timestamp,
+            Operation::CopyFiles {
+                local_timestamp, ..
+            } => *local_timestamp,
         }
     }
 
@@ -1191,6 +2288,12 @@ impl Operation {
             Operation::BufferOperation {
                 lamport_timestamp, ..
             } => *lamport_timestamp,
+            Operation::SetMergePolicy {
+                lamport_timestamp, ..
+            } => *lamport_timestamp,
+            Operation::CopyFiles {
+                lamport_timestamp, ..
+            } => *lamport_timestamp,
         }
     }
 
@@ -1199,8 +2302,10 @@ impl Operation {
         builder: &mut FlatBufferBuilder<'fbb>,
     ) -> (serialization::epoch::Operation, WIPOffset<UnionWIPOffset>) {
         use crate::serialization::epoch::{
-            BufferOperation, BufferOperationArgs, FileId as FileIdType, InsertMetadata,
-            InsertMetadataArgs, Operation as OperationType, UpdateParent, UpdateParentArgs,
+            BufferOperation, BufferOperationArgs, CopiedFile as CopiedFileMessage, CopiedFileArgs,
+            CopyFiles, CopyFilesArgs, FileId as FileIdType, InsertMetadata, InsertMetadataArgs,
+            Operation as OperationType, SetMergePolicy, SetMergePolicyArgs, UpdateParent,
+            UpdateParentArgs,
         };
 
         fn parent_to_flatbuf<'a, 'fbb>(
@@ -1307,6 +2412,81 @@ impl Operation {
                     .as_union_value(),
                 )
             }
+            Operation::SetMergePolicy {
+                pattern,
+                policy,
+                local_timestamp,
+                lamport_timestamp,
+            } => {
+                let pattern = builder.create_string(pattern);
+                (
+                    OperationType::SetMergePolicy,
+                    SetMergePolicy::create(
+                        builder,
+                        &SetMergePolicyArgs {
+                            pattern: Some(pattern),
+                            policy: policy.to_flatbuf(),
+                            local_timestamp: Some(&local_timestamp.to_flatbuf()),
+                            lamport_timestamp: Some(&lamport_timestamp.to_flatbuf()),
+                        },
+                    )
+                    .as_union_value(),
+                )
+            }
+            Operation::CopyFiles {
+                files,
+                local_timestamp,
+                lamport_timestamp,
+            } => {
+                let file_flatbufs = &files
+                    .iter()
+                    .map(|file| {
+                        let (file_id_type, file_id) = file.file_id.to_flatbuf(builder);
+                        let (parent_id_type, parent_id) = file.parent.0.to_flatbuf(builder);
+                        let name_in_parent =
+                            builder.create_string(file.parent.1.to_string_lossy().as_ref());
+                        let (source_id_type, source_id, source_version) =
+                            if let Some((source_id, version)) = file.source.as_ref() {
+                                let (source_id_type, source_id) = source_id.to_flatbuf(builder);
+                                (
+                                    source_id_type,
+                                    Some(source_id),
+                                    Some(version.to_flatbuf(builder)),
+                                )
+                            } else {
+                                (FileIdType::NONE, None, None)
+                            };
+                        CopiedFileMessage::create(
+                            builder,
+                            &CopiedFileArgs {
+                                file_id_type,
+                                file_id: Some(file_id),
+                                file_type: file.file_type.to_flatbuf(),
+                                parent_id_type,
+                                parent_id: Some(parent_id),
+                                name_in_parent: Some(name_in_parent),
+                                source_id_type,
+                                source_id,
+                                source_version,
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let files = builder.create_vector(file_flatbufs);
+
+                (
+                    OperationType::CopyFiles,
+                    CopyFiles::create(
+                        builder,
+                        &CopyFilesArgs {
+                            files: Some(files),
+                            local_timestamp: Some(&local_timestamp.to_flatbuf()),
+                            lamport_timestamp: Some(&lamport_timestamp.to_flatbuf()),
+                        },
+                    )
+                    .as_union_value(),
+                )
+            }
         }
     }
 
@@ -1376,12 +2556,68 @@ impl Operation {
                     }
                 }
 
-                Ok(Some(Operation::BufferOperation {
-                    file_id: FileId::from_flatbuf(
-                        message.file_id_type(),
-                        message.file_id().ok_or(Error::DeserializeError)?,
-                    ),
-                    operations,
+                Ok(Some(Operation::BufferOperation {
+                    file_id: FileId::from_flatbuf(
+                        message.file_id_type(),
+                        message.file_id().ok_or(Error::DeserializeError)?,
+                    ),
+                    operations,
+                    local_timestamp: time::Local::from_flatbuf(
+                        message.local_timestamp().ok_or(Error::DeserializeError)?,
+                    ),
+                    lamport_timestamp: time::Lamport::from_flatbuf(
+                        message.lamport_timestamp().ok_or(Error::DeserializeError)?,
+                    ),
+                }))
+            }
+            serialization::epoch::Operation::SetMergePolicy => {
+                let message = serialization::epoch::SetMergePolicy::init_from_table(message);
+                Ok(Some(Operation::SetMergePolicy {
+                    pattern: message
+                        .pattern()
+                        .ok_or(Error::DeserializeError)?
+                        .to_string(),
+                    policy: MergePolicy::from_flatbuf(&message.policy()),
+                    local_timestamp: time::Local::from_flatbuf(
+                        message.local_timestamp().ok_or(Error::DeserializeError)?,
+                    ),
+                    lamport_timestamp: time::Lamport::from_flatbuf(
+                        message.lamport_timestamp().ok_or(Error::DeserializeError)?,
+                    ),
+                }))
+            }
+            serialization::epoch::Operation::CopyFiles => {
+                let message = serialization::epoch::CopyFiles::init_from_table(message);
+                let file_messages = message.files().ok_or(Error::DeserializeError)?;
+                let mut files = Vec::with_capacity(file_messages.len());
+                for i in 0..file_messages.len() {
+                    let file = file_messages.get(i);
+                    let parent = parent_from_flatbuf(
+                        file.parent_id_type(),
+                        file.parent_id(),
+                        file.name_in_parent(),
+                    )
+                    .ok_or(Error::DeserializeError)?;
+                    let source = match (file.source_id(), file.source_version()) {
+                        (Some(source_id), Some(version)) => Some((
+                            FileId::from_flatbuf(file.source_id_type(), source_id),
+                            time::Global::from_flatbuf(version)?,
+                        )),
+                        _ => None,
+                    };
+                    files.push(CopiedFile {
+                        file_id: FileId::from_flatbuf(
+                            file.file_id_type(),
+                            file.file_id().ok_or(Error::DeserializeError)?,
+                        ),
+                        file_type: FileType::from_flatbuf(&file.file_type()),
+                        parent,
+                        source,
+                    });
+                }
+
+                Ok(Some(Operation::CopyFiles {
+                    files,
                     local_timestamp: time::Local::from_flatbuf(
                         message.local_timestamp().ok_or(Error::DeserializeError)?,
                     ),
@@ -1587,6 +2823,11 @@ impl btree::Item for ChildRefValue {
             visible: self.visible,
             timestamp: self.timestamp,
             visible_count: if self.visible { 1 } else { 0 },
+            changed_count: if self.visible && self.timestamp == time::Lamport::default() {
+                0
+            } else {
+                1
+            },
         }
     }
 }
@@ -1629,6 +2870,7 @@ impl<'a> AddAssign<&'a Self> for ChildRefValueSummary {
         self.visible = other.visible;
         self.timestamp = other.timestamp;
         self.visible_count += other.visible_count;
+        self.changed_count += other.changed_count;
     }
 }
 
@@ -1715,12 +2957,47 @@ impl btree::Dimension<ChildRefValueSummary> for usize {
 impl TextFile {
     fn is_modified(&self) -> bool {
         match self {
-            TextFile::Deferred(ops) => !ops.is_empty(),
+            TextFile::Deferred(ops)
+            | TextFile::Copied {
+                operations: ops, ..
+            } => !ops.is_empty(),
             TextFile::Buffered(buffer) => buffer.is_modified(),
         }
     }
 }
 
+fn buffer_error(error: buffer::Error) -> Error {
+    match error {
+        buffer::Error::OffsetOutOfRange => Error::InvalidPoint,
+        buffer::Error::InvalidAnchor => Error::InvalidAnchor,
+        buffer::Error::InvalidOperation => Error::InvalidOperation,
+        buffer::Error::SelectionSetNotFound => Error::InvalidSelectionSetId,
+    }
+}
+
+fn invalid_edit(error: buffer::Error) -> Error {
+    match error {
+        buffer::Error::OffsetOutOfRange => malformed("edit refers to an offset past its insertion"),
+        _ => malformed("edit refers to an invalid range"),
+    }
+}
+
+// A missing dependency is either a file id, which precedes the operation that created it, or
+// part of a version, in which case we may be missing anything after what `version` observed.
+fn add_missing_ops_since(
+    since: &mut HashMap<ReplicaId, u64>,
+    version: &time::Global,
+    deferred_ops: Vec<DeferredOperation>,
+) {
+    for op in deferred_ops {
+        for timestamp in op.missing {
+            let value = cmp::min(timestamp.value, version.get(timestamp.replica_id) + 1);
+            let entry = since.entry(timestamp.replica_id).or_insert(value);
+            *entry = cmp::min(*entry, value);
+        }
+    }
+}
+
 fn serialize_os_string<S>(os_string: &OsString, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -1739,6 +3016,8 @@ where
 mod tests {
     use super::*;
     use crate::buffer::Point;
+    use crate::text_format::LineEnding;
+    use crate::validation::OperationLimits;
     use rand::{Rng, SeedableRng, StdRng};
     use uuid::Uuid;
 
@@ -2012,6 +3291,163 @@ mod tests {
         assert!(cursor.entry().is_err());
     }
 
+    #[test]
+    fn test_filtered_cursor() {
+        let replica_id = Uuid::nil();
+        let mut epoch = Epoch::with_replica_id(replica_id);
+        let mut lamport_clock = time::Lamport::new(replica_id);
+        epoch
+            .append_base_entries(
+                vec![
+                    DirEntry {
+                        depth: 1,
+                        name: OsString::from("a"),
+                        file_type: FileType::Directory,
+                    },
+                    DirEntry {
+                        depth: 2,
+                        name: OsString::from("b.rs"),
+                        file_type: FileType::Text,
+                    },
+                    DirEntry {
+                        depth: 2,
+                        name: OsString::from("c.md"),
+                        file_type: FileType::Text,
+                    },
+                    DirEntry {
+                        depth: 1,
+                        name: OsString::from("d"),
+                        file_type: FileType::Directory,
+                    },
+                    DirEntry {
+                        depth: 2,
+                        name: OsString::from("e.rs"),
+                        file_type: FileType::Text,
+                    },
+                    DirEntry {
+                        depth: 1,
+                        name: OsString::from("target"),
+                        file_type: FileType::Directory,
+                    },
+                    DirEntry {
+                        depth: 2,
+                        name: OsString::from("f.rs"),
+                        file_type: FileType::Text,
+                    },
+                ],
+                &mut lamport_clock,
+            )
+            .unwrap();
+
+        let a = epoch.file_id("a").unwrap();
+        let b = epoch.file_id("a/b.rs").unwrap();
+        let c = epoch.file_id("a/c.md").unwrap();
+        epoch.open_text_file(b, "abc", &mut lamport_clock).unwrap();
+        epoch.edit(b, Some(0..0), "x", &mut lamport_clock).unwrap();
+        epoch.remove(c, &mut lamport_clock).unwrap();
+        epoch
+            .create_file(a, "g.rs", FileType::Text, &mut lamport_clock)
+            .unwrap();
+
+        let rust_files = CursorFilter::new().include(Glob::new("*.rs").unwrap());
+        assert_eq!(
+            epoch.filtered_paths(rust_files.clone()),
+            vec!["a/b.rs", "a/g.rs", "d/e.rs", "target/f.rs"]
+        );
+        assert_eq!(
+            epoch.filtered_paths(rust_files.exclude(Glob::new("/target").unwrap())),
+            vec!["a/b.rs", "a/g.rs", "d/e.rs"]
+        );
+        assert_eq!(
+            epoch.filtered_paths(CursorFilter::new().include(Glob::new("d/*").unwrap())),
+            vec!["d/e.rs"]
+        );
+
+        let changes = CursorFilter::new()
+            .status(FileStatus::New)
+            .status(FileStatus::Modified)
+            .status(FileStatus::Removed);
+        assert_eq!(
+            epoch.filtered_paths(changes.clone()),
+            vec!["a/b.rs", "a/c.md", "a/g.rs"]
+        );
+        assert_eq!(
+            epoch.filtered_paths(changes.show_deleted(false)),
+            vec!["a/b.rs", "a/g.rs"]
+        );
+        assert_eq!(
+            epoch.filtered_paths(CursorFilter::new().status(FileStatus::Renamed)),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_filtered_cursor_random() {
+        let globs = ["a*", "*b*", "[a-m]*", "/?", "**/c", "*~"];
+        let statuses = [
+            FileStatus::New,
+            FileStatus::Renamed,
+            FileStatus::Removed,
+            FileStatus::Modified,
+            FileStatus::RenamedAndModified,
+            FileStatus::Unchanged,
+        ];
+
+        for seed in 0..100 {
+            println!("SEED: {:?}", seed);
+            let mut rng = StdRng::from_seed(&[seed]);
+            let mut lamport_clock = time::Lamport::new(Uuid::nil());
+
+            let mut base_epoch = Epoch::with_replica_id(Uuid::nil());
+            base_epoch.mutate(&mut rng, &mut lamport_clock, 20);
+            let base_entries = base_epoch
+                .entries()
+                .into_iter()
+                .filter(|entry| entry.visible)
+                .map(DirEntry::from)
+                .collect::<Vec<_>>();
+
+            let mut epoch = Epoch::with_replica_id(Uuid::nil());
+            epoch
+                .append_base_entries(base_entries, &mut lamport_clock)
+                .unwrap();
+            let mutation_count = rng.gen_range(0, 5);
+            epoch.mutate(&mut rng, &mut lamport_clock, mutation_count);
+            for _ in 0..rng.gen_range(0, 3) {
+                if let Some(file_id) = epoch.select_file(&mut rng, Some(FileType::Text), false) {
+                    if epoch
+                        .open_text_file(file_id, "", &mut lamport_clock)
+                        .is_ok()
+                    {
+                        epoch
+                            .edit(file_id, Some(0..0), "x", &mut lamport_clock)
+                            .unwrap();
+                    }
+                }
+            }
+
+            for _ in 0..10 {
+                let mut filter = CursorFilter::new().show_deleted(rng.gen());
+                for _ in 0..rng.gen_range(0, 3) {
+                    filter = filter.include(Glob::new(rng.choose(&globs).unwrap()).unwrap());
+                }
+                for _ in 0..rng.gen_range(0, 2) {
+                    filter = filter.exclude(Glob::new(rng.choose(&globs).unwrap()).unwrap());
+                }
+                for _ in 0..rng.gen_range(0, 3) {
+                    filter = filter.status(*rng.choose(&statuses).unwrap());
+                }
+
+                assert_eq!(
+                    epoch.filtered_paths(filter.clone()),
+                    epoch.brute_force_filtered_paths(&filter),
+                    "filter: {:?}",
+                    filter
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_buffers() {
         let base_entries = vec![
@@ -2026,7 +3462,7 @@ mod tests {
                 file_type: FileType::Text,
             },
         ];
-        let base_text = Text::from("abc");
+        let base_text = "abc";
 
         let replica_id_1 = Uuid::from_u128(1);
         let mut epoch_1 = Epoch::with_replica_id(replica_id_1);
@@ -2070,14 +3506,413 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(changes.len(), 2);
         assert_eq!(changes[0].range, Point::new(0, 1)..Point::new(0, 2));
-        assert_eq!(changes[0].code_units, [b'y' as u16]);
+        assert_eq!(changes[0].text, "y");
         assert_eq!(changes[1].range, Point::new(0, 4)..Point::new(0, 4));
-        assert_eq!(changes[1].code_units, [b'y' as u16]);
+        assert_eq!(changes[1].text, "y");
 
         let dir_id = epoch_1.file_id("dir").unwrap();
         assert!(epoch_1
-            .open_text_file(dir_id, Text::from(""), &mut lamport_clock_1)
+            .open_text_file(dir_id, "", &mut lamport_clock_1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let replica_1 = Uuid::from_u128(1);
+        let replica_2 = Uuid::from_u128(2);
+        let replica_3 = Uuid::from_u128(3);
+        let mut epoch_1 = Epoch::with_replica_id(replica_1);
+        let mut clock_1 = time::Lamport::new(replica_1);
+        let mut epoch_2 = Epoch::with_replica_id(replica_2);
+        let mut clock_2 = time::Lamport::new(replica_2);
+
+        let insert_metadata = |replica_id, value, file_type, parent: Option<(FileId, &str)>| {
+            Operation::InsertMetadata {
+                file_id: FileId::New(time::Local { replica_id, value }),
+                file_type,
+                parent: parent.map(|(parent_id, name)| (parent_id, Arc::new(name.into()))),
+                local_timestamp: time::Local {
+                    replica_id,
+                    value: value + 1,
+                },
+                lamport_timestamp: time::Lamport {
+                    replica_id,
+                    value: value + 1,
+                },
+            }
+        };
+
+        // A file can't be its own parent.
+        let a = FileId::New(time::Local {
+            replica_id: replica_2,
+            value: 1,
+        });
+        epoch_1
+            .apply_ops(
+                vec![insert_metadata(
+                    replica_2,
+                    1,
+                    FileType::Directory,
+                    Some((a, "a")),
+                )],
+                &mut clock_1,
+            )
+            .unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert!(epoch_1.metadata(a).is_err());
+
+        // Operations from a quarantined replica are ignored until it is released, but other
+        // replicas are unaffected.
+        epoch_1
+            .apply_ops(
+                vec![
+                    insert_metadata(replica_2, 3, FileType::Directory, Some((ROOT_FILE_ID, "c"))),
+                    insert_metadata(replica_3, 1, FileType::Directory, Some((ROOT_FILE_ID, "b"))),
+                ],
+                &mut clock_1,
+            )
+            .unwrap();
+        assert_eq!(epoch_1.paths(), vec!["b"]);
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        epoch_1
+            .apply_ops(
+                vec![insert_metadata(
+                    replica_2,
+                    3,
+                    FileType::Directory,
+                    Some((ROOT_FILE_ID, "c")),
+                )],
+                &mut clock_1,
+            )
+            .unwrap();
+        assert_eq!(epoch_1.paths(), vec!["b", "c"]);
+
+        // Inserting files can't create a cycle.
+        let d = FileId::New(time::Local {
+            replica_id: replica_3,
+            value: 3,
+        });
+        let e = FileId::New(time::Local {
+            replica_id: replica_3,
+            value: 5,
+        });
+        epoch_1
+            .apply_ops(
+                vec![
+                    insert_metadata(replica_3, 3, FileType::Directory, Some((e, "d"))),
+                    insert_metadata(replica_3, 5, FileType::Directory, Some((d, "e"))),
+                ],
+                &mut clock_1,
+            )
+            .unwrap();
+        assert!(epoch_1.quarantine().contains(replica_3));
+        assert_eq!(epoch_1.paths(), vec!["b", "c"]);
+
+        // Moving a directory inside itself creates a cycle with no concurrent move to revert.
+        let b = epoch_1.file_id("b").unwrap();
+        let c = epoch_1.file_id("c").unwrap();
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        epoch_1
+            .apply_ops(
+                vec![
+                    Operation::UpdateParent {
+                        child_id: c,
+                        new_parent: Some((b, Arc::new("c".into()))),
+                        local_timestamp: time::Local {
+                            replica_id: replica_2,
+                            value: 5,
+                        },
+                        lamport_timestamp: time::Lamport {
+                            replica_id: replica_2,
+                            value: 5,
+                        },
+                    },
+                    Operation::UpdateParent {
+                        child_id: b,
+                        new_parent: Some((c, Arc::new("b".into()))),
+                        local_timestamp: time::Local {
+                            replica_id: replica_2,
+                            value: 6,
+                        },
+                        lamport_timestamp: time::Lamport {
+                            replica_id: replica_2,
+                            value: 6,
+                        },
+                    },
+                ],
+                &mut clock_1,
+            )
+            .unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert_eq!(epoch_1.paths(), vec!["b", "b/c"]);
+
+        // Remote edits must refer to ranges that exist in the buffer.
+        let file_op = epoch_2
+            .create_file(ROOT_FILE_ID, "f", FileType::Text, &mut clock_2)
+            .unwrap();
+        let f = epoch_2.file_id("f").unwrap();
+        epoch_2.open_text_file(f, "abc", &mut clock_2).unwrap();
+        let mut edit_op = epoch_2.edit(f, Some(1..2), "x", &mut clock_2).unwrap();
+        if let Operation::BufferOperation { operations, .. } = &mut edit_op {
+            let buffer::Operation::Edit { end_offset, .. } = &mut operations[0];
+            *end_offset = 100;
+        }
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        epoch_1.apply_ops(Some(file_op), &mut clock_1).unwrap();
+        epoch_1.open_text_file(f, "abc", &mut clock_1).unwrap();
+        epoch_1.apply_ops(Some(edit_op), &mut clock_1).unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert_eq!(epoch_1.text(f).unwrap().into_string(), "abc");
+
+        // A copy can't replace an existing file, which would let a file that hasn't been opened
+        // take its text from itself.
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        let file_op = epoch_2
+            .create_file(ROOT_FILE_ID, "g", FileType::Text, &mut clock_2)
+            .unwrap();
+        let g = epoch_2.file_id("g").unwrap();
+        epoch_1.apply_ops(Some(file_op), &mut clock_1).unwrap();
+        let copy_op = Operation::CopyFiles {
+            files: vec![CopiedFile {
+                file_id: g,
+                file_type: FileType::Text,
+                parent: (ROOT_FILE_ID, Arc::new("h".into())),
+                source: Some((g, time::Global::new())),
+            }],
+            local_timestamp: epoch_2.local_clock.tick(),
+            lamport_timestamp: clock_2.tick(),
+        };
+        epoch_1.apply_ops(Some(copy_op), &mut clock_1).unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert!(epoch_1.file_id("h").is_err());
+        assert_eq!(epoch_1.text_source(g).unwrap(), g);
+        epoch_1.open_text_file(g, "ghi", &mut clock_1).unwrap();
+        assert_eq!(epoch_1.text(g).unwrap().into_string(), "ghi");
+
+        // Oversized insertions and operations from unknown replicas are rejected when the limits
+        // require it.
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        epoch_1.quarantine_mut().set_limits(OperationLimits {
+            max_text_len: 2,
+            ..OperationLimits::default()
+        });
+        let edit_op = epoch_2.edit(f, Some(0..0), "xyz", &mut clock_2).unwrap();
+        epoch_1.apply_ops(Some(edit_op), &mut clock_1).unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert_eq!(epoch_1.text(f).unwrap().into_string(), "abc");
+
+        assert!(epoch_1.quarantine_mut().release(replica_2));
+        epoch_1.quarantine_mut().set_limits(OperationLimits {
+            known_replicas: Some(vec![replica_1].into_iter().collect()),
+            ..OperationLimits::default()
+        });
+        let edit_op = epoch_2.edit(f, Some(0..0), "w", &mut clock_2).unwrap();
+        epoch_1.apply_ops(Some(edit_op), &mut clock_1).unwrap();
+        assert!(epoch_1.quarantine().contains(replica_2));
+        assert_eq!(epoch_1.text(f).unwrap().into_string(), "abc");
+    }
+
+    #[test]
+    fn test_export_text() {
+        let replica_id = Uuid::from_u128(1);
+        let mut epoch = Epoch::with_replica_id(replica_id);
+        let mut lamport_clock = time::Lamport::new(replica_id);
+        epoch
+            .create_file(ROOT_FILE_ID, "a", FileType::Text, &mut lamport_clock)
+            .unwrap();
+        let file_id = epoch.file_id("a").unwrap();
+        epoch
+            .open_text_file(file_id, "\u{feff}a\r\nb\r\nc\n", &mut lamport_clock)
+            .unwrap();
+        assert_eq!(epoch.text(file_id).unwrap().into_string(), "a\nb\nc\n");
+        assert_eq!(
+            epoch.text_format(file_id).unwrap().line_ending,
+            LineEnding::CrLf
+        );
+
+        epoch
+            .edit(file_id, Some(2..2), "x\n", &mut lamport_clock)
+            .unwrap();
+        assert_eq!(
+            epoch.export_text(file_id).unwrap(),
+            "\u{feff}a\r\nx\r\nb\r\nc\n".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let base_entries = vec![
+            DirEntry {
+                depth: 1,
+                name: OsString::from("Cargo.lock"),
+                file_type: FileType::Text,
+            },
+            DirEntry {
+                depth: 1,
+                name: OsString::from("app.min.js"),
+                file_type: FileType::Text,
+            },
+        ];
+        let replica_id_1 = Uuid::from_u128(1);
+        let mut epoch_1 = Epoch::with_replica_id(replica_id_1);
+        let mut clock_1 = time::Lamport::new(replica_id_1);
+        epoch_1
+            .append_base_entries(base_entries.clone(), &mut clock_1)
+            .unwrap();
+        let replica_id_2 = Uuid::from_u128(2);
+        let mut epoch_2 = Epoch::with_replica_id(replica_id_2);
+        let mut clock_2 = time::Lamport::new(replica_id_2);
+        epoch_2
+            .append_base_entries(base_entries.clone(), &mut clock_2)
+            .unwrap();
+        let replica_id_3 = Uuid::from_u128(3);
+        let mut epoch_3 = Epoch::with_replica_id(replica_id_3);
+        let mut clock_3 = time::Lamport::new(replica_id_3);
+        epoch_3
+            .append_base_entries(base_entries, &mut clock_3)
+            .unwrap();
+
+        let ops = vec![
+            epoch_1
+                .set_merge_policy("*.lock", MergePolicy::LastWriterWins, &mut clock_1)
+                .unwrap(),
+            epoch_1
+                .set_merge_policy("*.min.js", MergePolicy::RejectConcurrent, &mut clock_1)
+                .unwrap(),
+        ];
+        epoch_2.apply_ops(ops.clone(), &mut clock_2).unwrap();
+        epoch_3.apply_ops(ops, &mut clock_3).unwrap();
+        assert!(epoch_1
+            .set_merge_policy("", MergePolicy::Merge, &mut clock_1)
             .is_err());
+
+        let lock_id = epoch_1.file_id("Cargo.lock").unwrap();
+        let js_id = epoch_1.file_id("app.min.js").unwrap();
+        assert_eq!(epoch_2.merge_policy(lock_id), MergePolicy::LastWriterWins);
+        assert_eq!(epoch_2.merge_policy(js_id), MergePolicy::RejectConcurrent);
+        for file_id in &[lock_id, js_id] {
+            epoch_1
+                .open_text_file(*file_id, "abc", &mut clock_1)
+                .unwrap();
+            epoch_2
+                .open_text_file(*file_id, "abc", &mut clock_2)
+                .unwrap();
+        }
+
+        // Replica 2's concurrent edits happen later, so they win under last-writer-wins but are
+        // refused under reject-concurrent.
+        for file_id in &[lock_id, js_id] {
+            clock_2.observe(clock_1);
+            let op_1 = epoch_1.edit(*file_id, Some(0..0), "x", &mut clock_1).unwrap();
+            let op_2 = epoch_2.edit(*file_id, Some(3..3), "y", &mut clock_2).unwrap();
+            epoch_3
+                .apply_ops(vec![op_1.clone(), op_2.clone()], &mut clock_3)
+                .unwrap();
+            let fixup_ops_1 = epoch_1.apply_ops(Some(op_2), &mut clock_1).unwrap();
+            let fixup_ops_2 = epoch_2.apply_ops(Some(op_1), &mut clock_2).unwrap();
+            assert!(epoch_1
+                .apply_ops(fixup_ops_2, &mut clock_1)
+                .unwrap()
+                .is_empty());
+            assert!(epoch_2
+                .apply_ops(fixup_ops_1, &mut clock_2)
+                .unwrap()
+                .is_empty());
+        }
+        assert_eq!(epoch_1.text(lock_id).unwrap().into_string(), "abcy");
+        assert_eq!(epoch_2.text(lock_id).unwrap().into_string(), "abcy");
+        assert_eq!(epoch_1.text(js_id).unwrap().into_string(), "xabc");
+        assert_eq!(epoch_2.text(js_id).unwrap().into_string(), "xabc");
+
+        let conflicts_1 = epoch_1.take_merge_conflicts();
+        let conflicts_2 = epoch_2.take_merge_conflicts();
+        assert_eq!(conflicts_1.len(), 1);
+        assert_eq!(conflicts_1[0].file_id, js_id);
+        assert_eq!(conflicts_1, conflicts_2);
+        assert!(epoch_1.take_merge_conflicts().is_empty());
+
+        // Edits received before a file is opened are held to its policy once it is opened.
+        for file_id in &[lock_id, js_id] {
+            let fixup_ops = epoch_3
+                .open_text_file(*file_id, "abc", &mut clock_3)
+                .unwrap();
+            assert_eq!(fixup_ops.len(), 1);
+            assert!(epoch_1.apply_ops(fixup_ops, &mut clock_1).unwrap().is_empty());
+        }
+        assert_eq!(epoch_3.text(lock_id).unwrap().into_string(), "abcy");
+        assert_eq!(epoch_3.text(js_id).unwrap().into_string(), "xabc");
+        assert_eq!(epoch_1.text(lock_id).unwrap().into_string(), "abcy");
+        assert_eq!(epoch_1.text(js_id).unwrap().into_string(), "xabc");
+        assert_eq!(epoch_3.take_merge_conflicts(), conflicts_1);
+
+        // Edits made after seeing the accepted contents are accepted.
+        let op = epoch_2.edit(js_id, Some(4..4), "z", &mut clock_2);
+        assert!(epoch_1.apply_ops(op, &mut clock_1).unwrap().is_empty());
+        assert_eq!(epoch_1.text(js_id).unwrap().into_string(), "xabcz");
+        assert!(epoch_1.take_merge_conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_deferred_ops() -> Result<(), Error> {
+        let replica_1_id = Uuid::from_u128(1);
+        let mut epoch_1 = Epoch::with_replica_id(replica_1_id);
+        let mut clock_1 = time::Lamport::new(replica_1_id);
+
+        let (file_id, new_file_op) = epoch_1.new_text_file(&mut clock_1);
+        epoch_1.open_text_file(file_id, "", &mut clock_1).unwrap();
+        let edit_1_op = epoch_1.edit(file_id, Some(0..0), "135", &mut clock_1)?;
+        let edit_2_op = epoch_1.edit(file_id, Some(1..1), "2", &mut clock_1)?;
+        let edit_1_timestamp = match &edit_1_op {
+            Operation::BufferOperation { operations, .. } => operations[0].local_timestamp(),
+            _ => panic!("expected a buffer operation"),
+        };
+
+        // Edits to a file we haven't heard of are deferred until the file is created.
+        let replica_2_id = Uuid::from_u128(2);
+        let mut epoch_2 = Epoch::with_replica_id(replica_2_id);
+        let mut clock_2 = time::Lamport::new(replica_2_id);
+        epoch_2.apply_ops(Some(edit_2_op.clone()), &mut clock_2)?;
+        let deferred_ops = epoch_2.deferred_ops();
+        assert_eq!(deferred_ops.len(), 1);
+        assert_eq!(
+            deferred_ops[0].lamport_timestamp,
+            edit_2_op.lamport_timestamp()
+        );
+        assert_eq!(
+            deferred_ops[0].missing,
+            vec![time::Local {
+                replica_id: replica_1_id,
+                value: 1,
+            }]
+        );
+        assert_eq!(
+            epoch_2.missing_ops_since(),
+            vec![time::Local {
+                replica_id: replica_1_id,
+                value: 1,
+            }]
+        );
+
+        // Once the file is open, the edit is deferred by its buffer instead.
+        epoch_2.apply_ops(Some(new_file_op), &mut clock_2)?;
+        epoch_2.open_text_file(file_id, "", &mut clock_2)?;
+        assert!(epoch_2.deferred_ops().is_empty());
+        let deferred_ops = epoch_2.buffer_deferred_ops(file_id)?;
+        assert_eq!(deferred_ops.len(), 1);
+        assert_eq!(deferred_ops[0].missing, vec![edit_1_timestamp]);
+        assert_eq!(
+            epoch_2.missing_ops_since(),
+            vec![time::Local {
+                replica_id: replica_1_id,
+                value: 1,
+            }]
+        );
+
+        epoch_2.apply_ops(Some(edit_1_op), &mut clock_2)?;
+        assert!(epoch_2.buffer_deferred_ops(file_id)?.is_empty());
+        assert!(epoch_2.missing_ops_since().is_empty());
+        assert_eq!(epoch_2.text(file_id)?.into_string(), "1235");
+        Ok(())
     }
 
     #[test]
@@ -2227,6 +4062,7 @@ mod tests {
 
             for i in 0..PEERS {
                 assert!(epochs[i].deferred_ops.is_empty());
+                assert_eq!(epochs[i].quarantine().replicas().count(), 0);
             }
 
             for i in 0..PEERS - 1 {
@@ -2297,6 +4133,36 @@ mod tests {
             paths
         }
 
+        fn filtered_paths(&self, filter: CursorFilter) -> Vec<String> {
+            let mut paths = Vec::new();
+            if let Some(mut cursor) = self.filtered_cursor(filter) {
+                loop {
+                    paths.push(cursor.path().unwrap().to_string_lossy().into_owned());
+                    if !cursor.next(true) {
+                        break;
+                    }
+                }
+            }
+            paths
+        }
+
+        fn brute_force_filtered_paths(&self, filter: &CursorFilter) -> Vec<String> {
+            let mut paths = Vec::new();
+            if let Some(mut cursor) = self.cursor() {
+                loop {
+                    let entry = cursor.entry().unwrap();
+                    let path = cursor.path().unwrap().to_path_buf();
+                    if filter.matches(&path, &entry) {
+                        paths.push(path.to_string_lossy().into_owned());
+                    }
+                    if !cursor.next(filter.can_descend(&path, &entry)) {
+                        break;
+                    }
+                }
+            }
+            paths
+        }
+
         pub fn mutate<T: Rng>(
             &mut self,
             rng: &mut T,
@@ -0,0 +1,304 @@
+use crate::Error;
+use std::ffi::OsStr;
+use std::path::{Component, Path};
+
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: String,
+    components: Vec<PatternComponent>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PatternComponent {
+    AnyDepth,
+    Name(Vec<Token>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyRun,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Parses a glob pattern supporting `*`, `?`, `[...]` classes and `**` for any number of
+    /// directories. As in `.gitignore`, a pattern without a leading or inner slash matches the
+    /// file name at any depth.
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let trimmed = pattern.trim_matches('/');
+        if trimmed.is_empty() {
+            return Err(Error::InvalidGlob("glob pattern is empty".into()));
+        }
+
+        let mut components = Vec::new();
+        if !pattern.trim_end_matches('/').contains('/') {
+            components.push(PatternComponent::AnyDepth);
+        }
+        for component in trimmed.split('/') {
+            if component == "**" {
+                if components.last() != Some(&PatternComponent::AnyDepth) {
+                    components.push(PatternComponent::AnyDepth);
+                }
+            } else if component.is_empty() {
+                return Err(Error::InvalidGlob(
+                    format!("glob {:?} contains an empty path component", pattern).into(),
+                ));
+            } else {
+                components.push(PatternComponent::Name(parse_tokens(pattern, component)?));
+            }
+        }
+
+        Ok(Glob {
+            pattern: pattern.into(),
+            components,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        match path_names(path.as_ref()) {
+            Some(names) => match_components(&self.components, &names),
+            None => false,
+        }
+    }
+
+    /// Returns whether some path nested inside the given directory could match this glob. This is
+    /// used to avoid descending into directories that can't contain any match.
+    pub fn could_match_descendant<P: AsRef<Path>>(&self, dir: P) -> bool {
+        match path_names(dir.as_ref()) {
+            Some(names) => could_contain(&self.components, &names),
+            None => false,
+        }
+    }
+}
+
+fn parse_tokens(pattern: &str, component: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = component.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if tokens.last() != Some(&Token::AnyRun) {
+                    tokens.push(Token::AnyRun);
+                }
+            }
+            '?' => tokens.push(Token::AnyChar),
+            '[' => {
+                let negated = if chars.peek() == Some(&'!') || chars.peek() == Some(&'^') {
+                    chars.next();
+                    true
+                } else {
+                    false
+                };
+
+                let mut ranges = Vec::new();
+                let mut closed = false;
+                while let Some(start) = chars.next() {
+                    if start == ']' && !ranges.is_empty() {
+                        closed = true;
+                        break;
+                    }
+
+                    let mut lookahead = chars.clone();
+                    if lookahead.next() == Some('-')
+                        && lookahead.peek().map_or(false, |c| *c != ']')
+                    {
+                        chars.next();
+                        let end = chars.next().unwrap();
+                        ranges.push((start, end));
+                    } else {
+                        ranges.push((start, start));
+                    }
+                }
+
+                if !closed {
+                    return Err(Error::InvalidGlob(
+                        format!(
+                            "glob {:?} contains an unterminated character class",
+                            pattern
+                        )
+                        .into(),
+                    ));
+                }
+                tokens.push(Token::Class { negated, ranges });
+            }
+            '\\' => tokens.push(Token::Char(chars.next().unwrap_or('\\'))),
+            _ => tokens.push(Token::Char(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn path_names(path: &Path) -> Option<Vec<&OsStr>> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::CurDir | Component::RootDir => {}
+            _ => return None,
+        }
+    }
+    Some(names)
+}
+
+fn match_components(pattern: &[PatternComponent], names: &[&OsStr]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        // A trailing `**` matches everything inside a directory but not the directory itself.
+        Some((PatternComponent::AnyDepth, rest)) if rest.is_empty() => !names.is_empty(),
+        Some((PatternComponent::AnyDepth, rest)) => {
+            (0..=names.len()).any(|skipped| match_components(rest, &names[skipped..]))
+        }
+        Some((PatternComponent::Name(tokens), rest)) => match names.split_first() {
+            Some((name, remaining_names)) => {
+                match_name(tokens, name) && match_components(rest, remaining_names)
+            }
+            None => false,
+        },
+    }
+}
+
+fn could_contain(pattern: &[PatternComponent], names: &[&OsStr]) -> bool {
+    match pattern.split_first() {
+        None => false,
+        Some((PatternComponent::AnyDepth, _)) => true,
+        Some((PatternComponent::Name(tokens), rest)) => match names.split_first() {
+            Some((name, remaining_names)) => {
+                match_name(tokens, name) && could_contain(rest, remaining_names)
+            }
+            None => true,
+        },
+    }
+}
+
+fn match_name(tokens: &[Token], name: &OsStr) -> bool {
+    let chars = name.to_string_lossy().chars().collect::<Vec<_>>();
+    match_tokens(tokens, &chars)
+}
+
+// When a token fails to match, only the most recent star is made to absorb one more character.
+// Any match that would need an earlier star to absorb more can instead leave those characters to
+// the most recent star, so matching takes O(tokens × chars) steps rather than backtracking into
+// every star.
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    let mut token_index = 0;
+    let mut char_index = 0;
+    let mut last_star = None;
+    while char_index < chars.len() {
+        match tokens.get(token_index) {
+            Some(Token::AnyRun) => {
+                token_index += 1;
+                last_star = Some((token_index, char_index));
+                continue;
+            }
+            Some(token) if match_char(token, chars[char_index]) => {
+                token_index += 1;
+                char_index += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match last_star {
+            Some((star_token_index, star_char_index)) => {
+                token_index = star_token_index;
+                char_index = star_char_index + 1;
+                last_star = Some((star_token_index, char_index));
+            }
+            None => return false,
+        }
+    }
+    tokens[token_index..].iter().all(|token| *token == Token::AnyRun)
+}
+
+fn match_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => c == *expected,
+        Token::AnyChar => true,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|(start, end)| *start <= c && c <= *end) != *negated
+        }
+        Token::AnyRun => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_match() {
+        let glob = Glob::new("*.rs").unwrap();
+        assert!(glob.is_match("lib.rs"));
+        assert!(glob.is_match("src/lib.rs"));
+        assert!(glob.is_match("a/b/c/lib.rs"));
+        assert!(!glob.is_match("lib.rs/README"));
+        assert!(!glob.is_match("lib.rsx"));
+
+        let glob = Glob::new("src/*.rs").unwrap();
+        assert!(glob.is_match("src/lib.rs"));
+        assert!(!glob.is_match("lib.rs"));
+        assert!(!glob.is_match("src/a/lib.rs"));
+
+        let glob = Glob::new("src/**/test_?.[a-c!]").unwrap();
+        assert!(glob.is_match("src/test_1.a"));
+        assert!(glob.is_match("src/x/y/test_2.!"));
+        assert!(!glob.is_match("src/x/y/test_22.a"));
+        assert!(!glob.is_match("src/x/y/test_2.d"));
+
+        let glob = Glob::new("src/**").unwrap();
+        assert!(glob.is_match("src/a"));
+        assert!(glob.is_match("src/a/b"));
+        assert!(!glob.is_match("src"));
+
+        let glob = Glob::new("/target").unwrap();
+        assert!(glob.is_match("target"));
+        assert!(!glob.is_match("a/target"));
+
+        let glob = Glob::new("target/").unwrap();
+        assert!(glob.is_match("a/target"));
+
+        let glob = Glob::new("[!.]*").unwrap();
+        assert!(glob.is_match("a/b"));
+        assert!(!glob.is_match("a/.b"));
+
+        // Stars only backtrack to the last one, so failing to match doesn't take exponential time.
+        let glob = Glob::new("*a*a*a*a*a*a*a*a*a*a*a*a*b").unwrap();
+        assert!(!glob.is_match("a".repeat(64)));
+        assert!(glob.is_match(format!("{}b", "a".repeat(64))));
+        let glob = Glob::new("*ab*?c").unwrap();
+        assert!(glob.is_match("xabyabzc"));
+        assert!(glob.is_match("abxc"));
+        assert!(!glob.is_match("abc"));
+
+        assert!(Glob::new("").is_err());
+        assert!(Glob::new("a//b").is_err());
+        assert!(Glob::new("[abc").is_err());
+    }
+
+    #[test]
+    fn test_could_match_descendant() {
+        let glob = Glob::new("src/a/*.rs").unwrap();
+        assert!(glob.could_match_descendant(""));
+        assert!(glob.could_match_descendant("src"));
+        assert!(glob.could_match_descendant("src/a"));
+        assert!(!glob.could_match_descendant("src/b"));
+        assert!(!glob.could_match_descendant("src/a/b"));
+        assert!(!glob.could_match_descendant("docs"));
+
+        let glob = Glob::new("src/**/*.rs").unwrap();
+        assert!(glob.could_match_descendant("src/a/b/c"));
+        assert!(!glob.could_match_descendant("docs"));
+
+        let glob = Glob::new("*.md").unwrap();
+        assert!(glob.could_match_descendant("docs/a"));
+    }
+}
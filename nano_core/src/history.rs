@@ -0,0 +1,178 @@
+use crate::epoch::{self, DirEntry, FileType};
+use crate::work_tree::{GitProvider, OperationEnvelope};
+use crate::{Error, Oid, User};
+use futures::{future, Future, Stream};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Describes a commit whose changes can be replayed into a work tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommitInfo {
+    pub oid: Oid,
+    /// The first parent, or `None` for a root commit.
+    pub parent: Option<Oid>,
+    pub author_name: String,
+    pub author_email: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub message: String,
+    /// The paths of the text files whose contents differ from the first parent.
+    pub changed_paths: Vec<PathBuf>,
+}
+
+/// A commit that was replayed as the operations of its own epoch.
+pub struct ReplayedCommit {
+    pub commit: CommitInfo,
+    pub epoch_id: epoch::Id,
+    pub operations: Vec<OperationEnvelope>,
+}
+
+/// The structural difference between two commits.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct TreeChanges {
+    /// The topmost paths that no longer exist, in depth-first order.
+    pub removed: Vec<PathBuf>,
+    /// The paths that need to be created, in depth-first order.
+    pub created: Vec<(PathBuf, FileType)>,
+}
+
+impl CommitInfo {
+    /// The user that the commit's changes are attributed to. Every replica derives the same id
+    /// from the author's email address.
+    pub fn author(&self) -> User {
+        // FNV-1a, which unlike `DefaultHasher` is stable across builds.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.author_email.to_lowercase().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+
+        User {
+            id: hash,
+            display_name: self.author_name.clone(),
+            color: (hash & 0xff_ffff) as u32,
+        }
+    }
+}
+
+/// Follows first parents from `head`, returning at most `max_commits` commits, oldest first.
+pub(crate) fn load_commits(
+    git: Rc<GitProvider>,
+    head: Oid,
+    max_commits: usize,
+) -> Box<Future<Item = Vec<CommitInfo>, Error = Error>> {
+    Box::new(future::loop_fn(
+        (Some(head), Vec::new()),
+        move |(next, mut commits): (Option<Oid>, Vec<CommitInfo>)| match next {
+            Some(oid) if commits.len() < max_commits => future::Either::A(
+                git.commit_info(oid)
+                    .map_err(|error| Error::IoError(error))
+                    .map(move |commit| {
+                        let parent = commit.parent;
+                        commits.push(commit);
+                        future::Loop::Continue((parent, commits))
+                    }),
+            ),
+            _ => {
+                commits.reverse();
+                future::Either::B(future::ok(future::Loop::Break(commits)))
+            }
+        },
+    ))
+}
+
+/// Loads the paths and file types of every entry in the given commit.
+pub(crate) fn base_paths(
+    git: &GitProvider,
+    oid: Option<Oid>,
+) -> Box<Future<Item = BTreeMap<PathBuf, FileType>, Error = Error>> {
+    if let Some(oid) = oid {
+        Box::new(
+            git.base_entries(oid)
+                .map_err(|error| Error::IoError(error))
+                .collect()
+                .map(|entries| entry_paths(entries)),
+        )
+    } else {
+        Box::new(future::ok(BTreeMap::new()))
+    }
+}
+
+fn entry_paths(entries: Vec<DirEntry>) -> BTreeMap<PathBuf, FileType> {
+    let mut paths = BTreeMap::new();
+    let mut stack = Vec::new();
+    for entry in entries {
+        stack.truncate(entry.depth.saturating_sub(1));
+        stack.push(entry.name);
+        paths.insert(stack.iter().collect(), entry.file_type);
+    }
+    paths
+}
+
+pub(crate) fn tree_changes(
+    old_paths: &BTreeMap<PathBuf, FileType>,
+    new_paths: &BTreeMap<PathBuf, FileType>,
+) -> TreeChanges {
+    let mut changes = TreeChanges::default();
+    let mut removed = HashSet::new();
+    for (path, file_type) in old_paths {
+        let ancestor_removed = path.ancestors().skip(1).any(|a| removed.contains(a));
+        if new_paths.get(path) != Some(file_type) {
+            if !ancestor_removed {
+                changes.removed.push(path.clone());
+            }
+            removed.insert(path.as_path());
+        } else if ancestor_removed {
+            removed.insert(path.as_path());
+        }
+    }
+
+    for (path, file_type) in new_paths {
+        if old_paths.get(path) != Some(file_type) || removed.contains(path.as_path()) {
+            changes.created.push((path.clone(), *file_type));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_changes() {
+        let old_paths = paths(&[
+            ("a", FileType::Directory),
+            ("a/b", FileType::Text),
+            ("a/c", FileType::Text),
+            ("d", FileType::Directory),
+            ("d/e", FileType::Text),
+            ("f", FileType::Text),
+        ]);
+        let new_paths = paths(&[
+            ("a", FileType::Directory),
+            ("a/b", FileType::Text),
+            ("a/g", FileType::Text),
+            ("d", FileType::Text),
+            ("f", FileType::Text),
+        ]);
+        assert_eq!(
+            tree_changes(&old_paths, &new_paths),
+            TreeChanges {
+                removed: vec![PathBuf::from("a/c"), PathBuf::from("d")],
+                created: vec![
+                    (PathBuf::from("a/g"), FileType::Text),
+                    (PathBuf::from("d"), FileType::Text),
+                ],
+            }
+        );
+    }
+
+    fn paths(entries: &[(&str, FileType)]) -> BTreeMap<PathBuf, FileType> {
+        entries
+            .iter()
+            .map(|(path, file_type)| (PathBuf::from(path), *file_type))
+            .collect()
+    }
+}
@@ -1,16 +1,41 @@
 mod btree;
 mod buffer;
 mod epoch;
+mod glob;
+mod history;
+mod merge_policy;
+mod myers;
+mod oid;
 #[allow(non_snake_case, unused_imports)]
 mod operation_queue;
+mod patch;
 mod serialization;
+mod text_format;
 pub mod time;
+mod user;
+mod validation;
 mod work_tree;
 
-pub use crate::buffer::{Buffer, Change, Point};
-pub use crate::epoch::{Cursor, DirEntry, Epoch, FileStatus, FileType, ROOT_FILE_ID};
+pub use crate::buffer::{
+    diff, Anchor, Buffer, BufferStats, Change, DiffMode, Point, Selection, ToPoint, Utf8Point,
+};
+pub use crate::epoch::{
+    Cursor, CursorFilter, DirEntry, Epoch, EpochStats, FileStatus, FileType, ROOT_FILE_ID,
+};
+pub use crate::glob::Glob;
+pub use crate::history::{CommitInfo, ReplayedCommit};
+pub use crate::merge_policy::{MergeConflict, MergePolicies, MergePolicy};
+pub use crate::oid::{HashAlgorithm, Oid};
+pub use crate::operation_queue::DeferredOperation;
+pub use crate::patch::{
+    FilePatch, Hunk, HunkLine, Patch, PatchApplication, PatchRejection,
+};
+pub use crate::text_format::{Encoding, LineEnding, TextFormat};
+pub use crate::user::{User, UserRegistry};
+pub use crate::validation::{OperationLimits, Quarantine};
 pub use crate::work_tree::{
-    BufferId, ChangeObserver, GitProvider, Operation, OperationEnvelope, WorkTree,
+    BufferId, ChangeObserver, GitProvider, Operation, OperationEnvelope, OperationRequest,
+    WorkTree, WorkTreeStats,
 };
 use std::borrow::Cow;
 use std::fmt;
@@ -19,18 +44,24 @@ use uuid::Uuid;
 
 pub type ReplicaId = Uuid;
 pub type UserId = u64;
-pub type Oid = [u8; 20];
 
 #[derive(Debug)]
 pub enum Error {
     IoError(io::Error),
     DeserializeError,
     InvalidPath(Cow<'static, str>),
+    InvalidGlob(Cow<'static, str>),
     InvalidOperations,
     InvalidFileId(Cow<'static, str>),
     InvalidBufferId,
+    InvalidSelectionSetId,
+    InvalidPoint,
+    InvalidAnchor,
     InvalidDirEntry,
+    InvalidOid,
+    InvalidPatch(Cow<'static, str>),
     InvalidOperation,
+    MalformedOperation(Cow<'static, str>),
     CursorExhausted,
 }
 
@@ -0,0 +1,240 @@
+use crate::buffer::InsertionRecord;
+use crate::epoch::FileId;
+use crate::glob::Glob;
+use crate::serialization;
+use crate::time;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Decides how concurrent edits to a text file are reconciled.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum MergePolicy {
+    /// Concurrent edits are merged character by character.
+    Merge,
+    /// Every edit replaces the whole file, and the edit with the latest Lamport timestamp wins.
+    LastWriterWins,
+    /// Every edit replaces the whole file, and edits made without having seen the accepted
+    /// contents are refused. Of several concurrent edits, the earliest is accepted.
+    RejectConcurrent,
+}
+
+/// Maps glob patterns to merge policies. When several patterns match a path, the one whose
+/// policy was set most recently applies.
+#[derive(Clone, Debug, Default)]
+pub struct MergePolicies {
+    rules: HashMap<String, MergeRule>,
+}
+
+#[derive(Clone, Debug)]
+struct MergeRule {
+    glob: Glob,
+    policy: MergePolicy,
+    lamport_timestamp: time::Lamport,
+}
+
+/// Reports that an edit to a file with the `RejectConcurrent` policy was refused.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeConflict {
+    pub file_id: FileId,
+    /// The timestamp of the refused edit.
+    pub refused: time::Lamport,
+    /// The timestamp of the accepted edit that the refused edit didn't build upon.
+    pub accepted: time::Lamport,
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub(crate) struct Resolution {
+    /// The insertion whose text should be the only visible text in the file.
+    pub retained: Option<time::Local>,
+    /// The refused edits, each paired with the accepted edit it conflicted with.
+    pub refused: Vec<(time::Lamport, time::Lamport)>,
+}
+
+impl MergePolicy {
+    /// Decides which edit's text a file should contain, given all of its insertions in Lamport
+    /// order. Because edits under these policies replace the whole file, an edit has seen the
+    /// contents inserted by another one exactly when it deleted them.
+    pub(crate) fn resolve(&self, insertions: &[InsertionRecord]) -> Resolution {
+        let mut resolution = Resolution::default();
+        let insertions = insertions.iter().filter(|insertion| insertion.len > 0);
+        match self {
+            MergePolicy::Merge => {}
+            MergePolicy::LastWriterWins => {
+                resolution.retained = insertions.last().map(|insertion| insertion.id);
+            }
+            MergePolicy::RejectConcurrent => {
+                let mut accepted: Option<&InsertionRecord> = None;
+                for insertion in insertions {
+                    match accepted {
+                        Some(head) if !head.deleted_by.contains(&insertion.id) => {
+                            resolution
+                                .refused
+                                .push((insertion.lamport_timestamp, head.lamport_timestamp));
+                        }
+                        _ => accepted = Some(insertion),
+                    }
+                }
+                resolution.retained = accepted.map(|insertion| insertion.id);
+            }
+        }
+        resolution
+    }
+
+    pub(crate) fn to_flatbuf(&self) -> serialization::epoch::MergePolicy {
+        match self {
+            MergePolicy::Merge => serialization::epoch::MergePolicy::Merge,
+            MergePolicy::LastWriterWins => serialization::epoch::MergePolicy::LastWriterWins,
+            MergePolicy::RejectConcurrent => serialization::epoch::MergePolicy::RejectConcurrent,
+        }
+    }
+
+    pub(crate) fn from_flatbuf(message: &serialization::epoch::MergePolicy) -> Self {
+        match message {
+            serialization::epoch::MergePolicy::Merge => MergePolicy::Merge,
+            serialization::epoch::MergePolicy::LastWriterWins => MergePolicy::LastWriterWins,
+            serialization::epoch::MergePolicy::RejectConcurrent => MergePolicy::RejectConcurrent,
+        }
+    }
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        MergePolicy::Merge
+    }
+}
+
+impl MergePolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns a policy to the given pattern, returning false if a more recent assignment to the
+    /// same pattern was already known.
+    pub fn set(
+        &mut self,
+        glob: Glob,
+        policy: MergePolicy,
+        lamport_timestamp: time::Lamport,
+    ) -> bool {
+        if let Some(rule) = self.rules.get(glob.as_str()) {
+            if rule.lamport_timestamp >= lamport_timestamp {
+                return false;
+            }
+        }
+
+        self.rules.insert(
+            glob.as_str().into(),
+            MergeRule {
+                glob,
+                policy,
+                lamport_timestamp,
+            },
+        );
+        true
+    }
+
+    pub fn policy_for<P: AsRef<Path>>(&self, path: P) -> MergePolicy {
+        let path = path.as_ref();
+        self.rules
+            .values()
+            .filter(|rule| rule.glob.is_match(path))
+            .max_by_key(|rule| rule.lamport_timestamp)
+            .map_or(MergePolicy::Merge, |rule| rule.policy)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, MergePolicy)> {
+        self.rules
+            .iter()
+            .map(|(pattern, rule)| (pattern.as_str(), rule.policy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_policy_for() {
+        let mut clock = time::Lamport::new(Uuid::from_u128(1));
+        let mut policies = MergePolicies::new();
+        assert_eq!(policies.policy_for("a/Cargo.lock"), MergePolicy::Merge);
+
+        let t1 = clock.tick();
+        let t2 = clock.tick();
+        assert!(policies.set(
+            Glob::new("*.lock").unwrap(),
+            MergePolicy::LastWriterWins,
+            t2
+        ));
+        assert!(!policies.set(Glob::new("*.lock").unwrap(), MergePolicy::Merge, t1));
+        assert_eq!(
+            policies.policy_for("a/Cargo.lock"),
+            MergePolicy::LastWriterWins
+        );
+
+        let glob = Glob::new("a/**").unwrap();
+        assert!(policies.set(glob, MergePolicy::RejectConcurrent, clock.tick()));
+        assert_eq!(
+            policies.policy_for("a/Cargo.lock"),
+            MergePolicy::RejectConcurrent
+        );
+        assert_eq!(
+            policies.policy_for("b/Cargo.lock"),
+            MergePolicy::LastWriterWins
+        );
+        assert_eq!(policies.policy_for("b/main.rs"), MergePolicy::Merge);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut local_1 = time::Local::new(Uuid::from_u128(1));
+        let mut lamport_1 = time::Lamport::new(Uuid::from_u128(1));
+        let mut local_2 = time::Local::new(Uuid::from_u128(2));
+        let mut lamport_2 = time::Lamport::new(Uuid::from_u128(2));
+
+        // Replica 1 replaces the base text twice, while replica 2 concurrently replaces it once.
+        let base = insertion(time::Local::default(), time::Lamport::default());
+        let a = insertion(local_1.tick(), lamport_1.tick());
+        let b = insertion(local_2.tick(), lamport_2.tick());
+        let c = insertion(local_1.tick(), lamport_1.tick());
+        let base = deleted_by(base, &[a.id, b.id]);
+        let a = deleted_by(a, &[c.id]);
+        let insertions = vec![base.clone(), a.clone(), b.clone(), c.clone()];
+
+        assert_eq!(
+            MergePolicy::Merge.resolve(&insertions),
+            Resolution::default()
+        );
+        assert_eq!(
+            MergePolicy::LastWriterWins.resolve(&insertions),
+            Resolution {
+                retained: Some(c.id),
+                refused: Vec::new(),
+            }
+        );
+        assert_eq!(
+            MergePolicy::RejectConcurrent.resolve(&insertions),
+            Resolution {
+                retained: Some(c.id),
+                refused: vec![(b.lamport_timestamp, a.lamport_timestamp)],
+            }
+        );
+    }
+
+    fn insertion(id: time::Local, lamport_timestamp: time::Lamport) -> InsertionRecord {
+        InsertionRecord {
+            id,
+            lamport_timestamp,
+            len: 1,
+            deleted_by: HashSet::new(),
+        }
+    }
+
+    fn deleted_by(mut insertion: InsertionRecord, ids: &[time::Local]) -> InsertionRecord {
+        insertion.deleted_by.extend(ids.iter().cloned());
+        insertion
+    }
+}
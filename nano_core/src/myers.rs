@@ -0,0 +1,403 @@
+use difference::Difference;
+use std::collections::HashMap;
+
+// Changed hunks longer than this many bytes on either side are replaced wholesale rather than
+// refined to characters, since refining costs time proportional to the hunk's length multiplied
+// by the number of differing characters.
+const MAX_REFINED_HUNK_LEN: usize = 4096;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Edit {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Compares two texts line by line and then character by character within each run of changed
+/// lines. Lines that appear unchanged are never compared character by character, so the time
+/// taken grows with the number of lines and changes rather than with the square of the length.
+pub fn diff_lines(a: &str, b: &str) -> Vec<Difference> {
+    let a_lines = split_lines(a);
+    let b_lines = split_lines(b);
+
+    // Lines are compared many times, so replace each distinct line with a number up front.
+    let mut ids = HashMap::new();
+    let a_ids = line_ids(&a_lines, &mut ids);
+    let b_ids = line_ids(&b_lines, &mut ids);
+
+    let mut edits = Vec::new();
+    diff_slices(&a_ids, &b_ids, &mut edits);
+
+    let mut differences = Vec::new();
+    let mut a_row = 0;
+    let mut b_row = 0;
+    let mut edits = edits.into_iter().peekable();
+    while let Some(edit) = edits.next() {
+        if let Edit::Same(len) = edit {
+            push_difference(
+                &mut differences,
+                Difference::Same(a_lines[a_row..a_row + len].concat()),
+            );
+            a_row += len;
+            b_row += len;
+        } else {
+            let mut removed = 0;
+            let mut added = 0;
+            let mut edit = Some(edit);
+            while let Some(hunk_edit) = edit {
+                match hunk_edit {
+                    Edit::Removed(len) => removed += len,
+                    Edit::Added(len) => added += len,
+                    Edit::Same(_) => unreachable!(),
+                }
+                edit = match edits.peek() {
+                    Some(Edit::Same(_)) | None => None,
+                    Some(_) => edits.next(),
+                };
+            }
+
+            let old_text = a_lines[a_row..a_row + removed].concat();
+            let new_text = b_lines[b_row..b_row + added].concat();
+            refine_hunk(old_text, new_text, &mut differences);
+            a_row += removed;
+            b_row += added;
+        }
+    }
+    differences
+}
+
+fn refine_hunk(old_text: String, new_text: String, differences: &mut Vec<Difference>) {
+    if old_text.len() > MAX_REFINED_HUNK_LEN || new_text.len() > MAX_REFINED_HUNK_LEN {
+        push_difference(differences, Difference::Rem(old_text));
+        push_difference(differences, Difference::Add(new_text));
+        return;
+    }
+
+    let old_chars = old_text.chars().collect::<Vec<_>>();
+    let new_chars = new_text.chars().collect::<Vec<_>>();
+    let mut edits = Vec::new();
+    diff_slices(&old_chars, &new_chars, &mut edits);
+
+    let mut old_ix = 0;
+    let mut new_ix = 0;
+    for edit in edits {
+        match edit {
+            Edit::Same(len) => {
+                let text = old_chars[old_ix..old_ix + len].iter().collect();
+                push_difference(differences, Difference::Same(text));
+                old_ix += len;
+                new_ix += len;
+            }
+            Edit::Removed(len) => {
+                let text = old_chars[old_ix..old_ix + len].iter().collect();
+                push_difference(differences, Difference::Rem(text));
+                old_ix += len;
+            }
+            Edit::Added(len) => {
+                let text = new_chars[new_ix..new_ix + len].iter().collect();
+                push_difference(differences, Difference::Add(text));
+                new_ix += len;
+            }
+        }
+    }
+}
+
+fn push_difference(differences: &mut Vec<Difference>, difference: Difference) {
+    match (differences.last_mut(), &difference) {
+        (_, Difference::Same(text)) | (_, Difference::Rem(text)) | (_, Difference::Add(text))
+            if text.is_empty() =>
+        {
+            return;
+        }
+        (Some(Difference::Same(last)), Difference::Same(text))
+        | (Some(Difference::Rem(last)), Difference::Rem(text))
+        | (Some(Difference::Add(last)), Difference::Add(text)) => {
+            last.push_str(text);
+            return;
+        }
+        _ => {}
+    }
+    differences.push(difference);
+}
+
+// Appends a shortest sequence of edits turning `a` into `b`, trimming their common prefix and
+// suffix and then recursively splitting the remainder where the optimal path crosses its middle.
+fn diff_slices<T: Eq>(a: &[T], b: &[T], edits: &mut Vec<Edit>) {
+    let prefix_len = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let a = &a[prefix_len..];
+    let b = &b[prefix_len..];
+    let suffix_len = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &a[..a.len() - suffix_len];
+    let b = &b[..b.len() - suffix_len];
+
+    push_edit(edits, Edit::Same(prefix_len));
+    if a.is_empty() || b.is_empty() {
+        push_edit(edits, Edit::Removed(a.len()));
+        push_edit(edits, Edit::Added(b.len()));
+    } else {
+        match find_split(a, b) {
+            Some((x, y)) if (x, y) != (0, 0) && (x, y) != (a.len(), b.len()) => {
+                diff_slices(&a[..x], &b[..y], edits);
+                diff_slices(&a[x..], &b[y..], edits);
+            }
+            _ => {
+                push_edit(edits, Edit::Removed(a.len()));
+                push_edit(edits, Edit::Added(b.len()));
+            }
+        }
+    }
+    push_edit(edits, Edit::Same(suffix_len));
+}
+
+fn push_edit(edits: &mut Vec<Edit>, edit: Edit) {
+    match (edits.last_mut(), edit) {
+        (_, Edit::Same(0)) | (_, Edit::Removed(0)) | (_, Edit::Added(0)) => return,
+        (Some(Edit::Same(last)), Edit::Same(len))
+        | (Some(Edit::Removed(last)), Edit::Removed(len))
+        | (Some(Edit::Added(last)), Edit::Added(len)) => {
+            *last += len;
+            return;
+        }
+        _ => {}
+    }
+    edits.push(edit);
+}
+
+// Walks the shortest edit paths forward from the start and backward from the end, one edit at a
+// time, and returns the point at which they first overlap. This is the middle snake from Myers'
+// "An O(ND) Difference Algorithm and Its Variations", which needs space linear in the input.
+fn find_split<T: Eq>(a: &[T], b: &[T]) -> Option<(usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max_d = (n + m + 1) / 2;
+    let offset = max_d + 1;
+    let len = 2 * offset + 1;
+    let mut forward = vec![-1; len as usize];
+    forward[(offset + 1) as usize] = 0;
+    let mut reverse = forward.clone();
+
+    let delta = n - m;
+    let check_forward = delta % 2 != 0;
+    let mut k1_start = 0;
+    let mut k1_end = 0;
+    let mut k2_start = 0;
+    let mut k2_end = 0;
+    for d in 0..max_d {
+        let mut k1 = -d + k1_start;
+        while k1 <= d - k1_end {
+            let k1_ix = offset + k1;
+            let mut x1 = if k1 == -d
+                || (k1 != d && forward[(k1_ix - 1) as usize] < forward[(k1_ix + 1) as usize])
+            {
+                forward[(k1_ix + 1) as usize]
+            } else {
+                forward[(k1_ix - 1) as usize] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            forward[k1_ix as usize] = x1;
+
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if check_forward {
+                let k2_ix = offset + delta - k1;
+                if k2_ix >= 0 && k2_ix < len && reverse[k2_ix as usize] != -1 {
+                    let x2 = n - reverse[k2_ix as usize];
+                    if x1 >= x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k1 += 2;
+        }
+
+        let mut k2 = -d + k2_start;
+        while k2 <= d - k2_end {
+            let k2_ix = offset + k2;
+            let mut x2 = if k2 == -d
+                || (k2 != d && reverse[(k2_ix - 1) as usize] < reverse[(k2_ix + 1) as usize])
+            {
+                reverse[(k2_ix + 1) as usize]
+            } else {
+                reverse[(k2_ix - 1) as usize] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - x2 - 1) as usize] == b[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            reverse[k2_ix as usize] = x2;
+
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !check_forward {
+                let k1_ix = offset + delta - k2;
+                if k1_ix >= 0 && k1_ix < len && forward[k1_ix as usize] != -1 {
+                    let x1 = forward[k1_ix as usize];
+                    let y1 = offset + x1 - k1_ix;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+            k2 += 2;
+        }
+    }
+
+    None
+}
+
+fn line_ids<'a>(lines: &[&'a str], ids: &mut HashMap<&'a str, usize>) -> Vec<usize> {
+    lines
+        .iter()
+        .map(|line| {
+            let next_id = ids.len();
+            *ids.entry(*line).or_insert(next_id)
+        })
+        .collect()
+}
+
+fn split_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (ix, _) in text.match_indices('\n') {
+        lines.push(&text[start..ix + 1]);
+        start = ix + 1;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use std::cmp;
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(diff_lines("", ""), vec![]);
+        assert_eq!(
+            diff_lines("abc\ndef\nghi\n", "abc\ndxf\nghi\n"),
+            vec![
+                Difference::Same("abc\nd".to_string()),
+                Difference::Rem("e".to_string()),
+                Difference::Add("x".to_string()),
+                Difference::Same("f\nghi\n".to_string()),
+            ]
+        );
+        assert_eq!(
+            diff_lines("abc\nghi", "abc\ndef\nghi"),
+            vec![
+                Difference::Same("abc\n".to_string()),
+                Difference::Add("def\n".to_string()),
+                Difference::Same("ghi".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        for seed in 0..100 {
+            let mut rng = StdRng::from_seed(&[seed]);
+            let a = gen_text(&mut rng);
+            let b = gen_text(&mut rng);
+
+            let mut old_text = String::new();
+            let mut new_text = String::new();
+            for difference in diff_lines(&a, &b) {
+                match difference {
+                    Difference::Same(text) => {
+                        old_text.push_str(&text);
+                        new_text.push_str(&text);
+                    }
+                    Difference::Rem(text) => old_text.push_str(&text),
+                    Difference::Add(text) => new_text.push_str(&text),
+                }
+            }
+            assert_eq!(old_text, a);
+            assert_eq!(new_text, b);
+
+            let a_chars = a.chars().collect::<Vec<_>>();
+            let b_chars = b.chars().collect::<Vec<_>>();
+            let mut edits = Vec::new();
+            diff_slices(&a_chars, &b_chars, &mut edits);
+            let distance = edits
+                .iter()
+                .map(|edit| match edit {
+                    Edit::Same(_) => 0,
+                    Edit::Removed(len) | Edit::Added(len) => *len,
+                })
+                .sum::<usize>();
+            assert_eq!(
+                distance,
+                a.len() + b.len() - 2 * lcs_len(&a_chars, &b_chars)
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_text() {
+        let mut a = String::new();
+        for i in 0..100_000 {
+            a.push_str(&format!("line {}\n", i));
+        }
+        let b = a
+            .replacen("line 10\n", "", 1)
+            .replacen("line 50000\n", "line fifty thousand\n", 1)
+            + "line 100000\n";
+
+        let differences = diff_lines(&a, &b);
+        assert_eq!(
+            differences
+                .iter()
+                .filter(|difference| match difference {
+                    Difference::Same(_) => false,
+                    _ => true,
+                })
+                .count(),
+            4
+        );
+    }
+
+    fn lcs_len<T: Eq>(a: &[T], b: &[T]) -> usize {
+        let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                lengths[i + 1][j + 1] = if a[i] == b[j] {
+                    lengths[i][j] + 1
+                } else {
+                    cmp::max(lengths[i][j + 1], lengths[i + 1][j])
+                };
+            }
+        }
+        lengths[a.len()][b.len()]
+    }
+
+    fn gen_text<T: Rng>(rng: &mut T) -> String {
+        let mut text = String::new();
+        for _ in 0..rng.gen_range(0, 20) {
+            let line_len = rng.gen_range(0, 4);
+            text.extend((0..line_len).map(|_| *rng.choose(&['a', 'b', 'c']).unwrap()));
+            text.push('\n');
+        }
+        if rng.gen() {
+            text.pop();
+        }
+        text
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Error;
+use std::fmt;
+
+/// Identifies a Git object. Repositories use either the SHA-1 or the SHA-256 object format, and
+/// the length of the id reflects which one.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Oid {
+    Sha1([u8; 20]),
+    Sha256([u8; 32]),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl Oid {
+    /// Interprets 20 bytes as a SHA-1 id and 32 bytes as a SHA-256 id.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes.len() {
+            20 => {
+                let mut oid = [0; 20];
+                oid.copy_from_slice(bytes);
+                Ok(Oid::Sha1(oid))
+            }
+            32 => {
+                let mut oid = [0; 32];
+                oid.copy_from_slice(bytes);
+                Ok(Oid::Sha256(oid))
+            }
+            _ => Err(Error::InvalidOid),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Oid::Sha1(bytes) => bytes,
+            Oid::Sha256(bytes) => bytes,
+        }
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self {
+            Oid::Sha1(_) => HashAlgorithm::Sha1,
+            Oid::Sha256(_) => HashAlgorithm::Sha256,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Oid {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.as_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Oid({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let sha1 = Oid::from_bytes(&[0xab; 20]).unwrap();
+        assert_eq!(sha1.algorithm(), HashAlgorithm::Sha1);
+        assert_eq!(sha1.as_bytes(), &[0xab; 20][..]);
+        assert_eq!(sha1.to_string(), "ab".repeat(20));
+
+        let sha256 = Oid::from_bytes(&[0x01; 32]).unwrap();
+        assert_eq!(sha256.algorithm(), HashAlgorithm::Sha256);
+        assert_eq!(sha256.as_bytes(), &[0x01; 32][..]);
+        assert_eq!(sha256.to_string(), "01".repeat(32));
+
+        assert!(Oid::from_bytes(&[]).is_err());
+        assert!(Oid::from_bytes(&[0; 21]).is_err());
+    }
+}
@@ -1,15 +1,26 @@
-use crate::btree::{Cursor, Dimension, Edit, Item, KeyedItem, Tree};
+use crate::btree::{Cursor, Dimension, Edit, Item, KeyedItem, MaybeSync, Tree};
 use crate::time;
+use serde_derive::Serialize;
 use std::fmt::Debug;
 use std::ops::{Add, AddAssign};
 
-pub trait Operation: Clone + Debug + Eq {
+pub trait Operation: Clone + Debug + Eq + MaybeSync {
     fn timestamp(&self) -> time::Lamport;
 }
 
 #[derive(Clone, Debug)]
 pub struct OperationQueue<T: Operation>(Tree<T>);
 
+/// An operation that is waiting for other operations to arrive before it can be applied.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct DeferredOperation {
+    pub lamport_timestamp: time::Lamport,
+    /// Timestamps the operation depends on that haven't been observed yet, either because they
+    /// belong to an operation or because they identify a file that hasn't been created. This is
+    /// empty when the operation is only waiting on the base entries of the epoch to load.
+    pub missing: Vec<time::Local>,
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
 pub struct OperationKey(time::Lamport);
 
@@ -43,6 +54,10 @@ impl<T: Operation> OperationQueue<T> {
         self.0.edit(&mut edits);
     }
 
+    pub fn iter(&self) -> Cursor<T> {
+        self.0.cursor()
+    }
+
     pub fn drain(&mut self) -> Cursor<T> {
         let cursor = self.0.cursor();
         self.0 = Tree::new();
@@ -0,0 +1,519 @@
+use crate::buffer::Utf8Point;
+use crate::work_tree::OperationEnvelope;
+use crate::Error;
+use serde_derive::Serialize;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// A parsed unified diff, as produced by `diff -u`, `git diff` or `git format-patch`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Patch {
+    pub files: Vec<FilePatch>,
+}
+
+/// The changes to a single file. A missing old path means the file is created and a missing new
+/// path means it is removed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilePatch {
+    pub old_path: Option<PathBuf>,
+    pub new_path: Option<PathBuf>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Hunk {
+    /// The 1-based row of the first old line, or the row after which to insert when the hunk
+    /// doesn't replace any lines.
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+/// A line of a hunk, including its newline unless it is the last line of a file that doesn't end
+/// with one.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// The outcome of applying a patch to a work tree.
+pub struct PatchApplication {
+    pub operations: Vec<OperationEnvelope>,
+    pub rejections: Vec<PatchRejection>,
+}
+
+/// A change from a patch that could not be applied.
+#[derive(Clone, Debug, Serialize)]
+pub struct PatchRejection {
+    pub path: PathBuf,
+    /// The rejected hunk, or `None` if the file itself could not be created, renamed or removed.
+    pub hunk: Option<Hunk>,
+    pub reason: Cow<'static, str>,
+    /// The lines found where the hunk was expected to apply.
+    pub found: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct HunkResolution {
+    /// The replacements to perform, in ascending order.
+    pub edits: Vec<(Range<Utf8Point>, String)>,
+    /// The indices of the hunks whose old lines couldn't be found, with the lines found instead.
+    pub rejected: Vec<(usize, Vec<String>)>,
+}
+
+impl Patch {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut text = text.replace("\r\n", "\n");
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let lines = split_lines(&text);
+        let mut files = Vec::new();
+        let mut cur_file: Option<FilePatch> = None;
+        let mut row = 0;
+        while row < lines.len() {
+            let line = lines[row].trim_end_matches('\n');
+            row += 1;
+
+            if line.starts_with("diff --git ") {
+                files.extend(cur_file.take());
+                let (old_path, new_path) = parse_git_header(&line["diff --git ".len()..])?;
+                cur_file = Some(FilePatch {
+                    old_path: Some(old_path),
+                    new_path: Some(new_path),
+                    hunks: Vec::new(),
+                });
+            } else if line.starts_with("--- ")
+                && row < lines.len()
+                && lines[row].starts_with("+++ ")
+            {
+                let old_path = parse_path(&line["--- ".len()..]);
+                let new_path = parse_path(&lines[row].trim_end_matches('\n')["+++ ".len()..]);
+                row += 1;
+
+                match cur_file.as_mut() {
+                    Some(file) if file.hunks.is_empty() => {
+                        file.old_path = old_path;
+                        file.new_path = new_path;
+                    }
+                    _ => {
+                        files.extend(cur_file.take());
+                        cur_file = Some(FilePatch {
+                            old_path,
+                            new_path,
+                            hunks: Vec::new(),
+                        });
+                    }
+                }
+            } else if line.starts_with("@@ ") {
+                let file = cur_file
+                    .as_mut()
+                    .ok_or(Error::InvalidPatch("hunk precedes file header".into()))?;
+                let (hunk, consumed) = parse_hunk(line, &lines[row..])?;
+                file.hunks.push(hunk);
+                row += consumed;
+            } else if let Some(file) = cur_file.as_mut() {
+                if line.starts_with("new file mode ") {
+                    file.old_path = None;
+                } else if line.starts_with("deleted file mode ") {
+                    file.new_path = None;
+                } else if line.starts_with("rename from ") {
+                    file.old_path = Some(PathBuf::from(&line["rename from ".len()..]));
+                } else if line.starts_with("rename to ") {
+                    file.new_path = Some(PathBuf::from(&line["rename to ".len()..]));
+                } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                    return Err(Error::InvalidPatch(
+                        "binary patches are not supported".into(),
+                    ));
+                }
+            }
+        }
+        files.extend(cur_file.take());
+
+        for file in &files {
+            if file.old_path.is_none() && file.new_path.is_none() {
+                return Err(Error::InvalidPatch(
+                    "file patch has neither an old nor a new path".into(),
+                ));
+            }
+        }
+
+        Ok(Patch { files })
+    }
+}
+
+impl Hunk {
+    pub fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+            HunkLine::Added(_) => None,
+        })
+    }
+
+    pub fn new_text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                HunkLine::Context(line) | HunkLine::Added(line) => text.push_str(line),
+                HunkLine::Removed(_) => {}
+            }
+        }
+        text
+    }
+}
+
+impl PatchRejection {
+    pub(crate) fn file<R>(path: PathBuf, reason: R) -> Self
+    where
+        R: Into<Cow<'static, str>>,
+    {
+        PatchRejection {
+            path,
+            hunk: None,
+            reason: reason.into(),
+            found: Vec::new(),
+        }
+    }
+}
+
+/// Locates each hunk in the given text. Like `patch`, a hunk whose lines have moved is searched
+/// for outwards from the position it names, adjusted by the displacement of the previous hunk.
+pub(crate) fn resolve_hunks(text: &str, hunks: &[Hunk]) -> HunkResolution {
+    let lines = split_lines(text);
+    let mut resolution = HunkResolution::default();
+    let mut displacement: isize = 0;
+    let mut min_row = 0;
+    for (hunk_ix, hunk) in hunks.iter().enumerate() {
+        let old_lines = hunk.old_lines().collect::<Vec<_>>();
+        let named_row = if hunk.old_len == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected_row = (named_row as isize + displacement)
+            .max(min_row as isize)
+            .min(lines.len() as isize) as usize;
+
+        if let Some(row) = find_lines(&lines, &old_lines, expected_row, min_row) {
+            let end_row = row + old_lines.len();
+            resolution.edits.push((
+                point_for_row(text, &lines, row)..point_for_row(text, &lines, end_row),
+                hunk.new_text(),
+            ));
+            displacement = row as isize - named_row as isize;
+            min_row = end_row;
+        } else {
+            let start_row = expected_row.min(lines.len().saturating_sub(old_lines.len()));
+            let end_row = (start_row + old_lines.len()).min(lines.len());
+            let found = lines[start_row..end_row]
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            resolution.rejected.push((hunk_ix, found));
+        }
+    }
+    resolution
+}
+
+fn find_lines(
+    lines: &[&str],
+    needle: &[&str],
+    expected_row: usize,
+    min_row: usize,
+) -> Option<usize> {
+    let matches_at = |row: usize| {
+        row >= min_row
+            && row + needle.len() <= lines.len()
+            && lines[row..row + needle.len()] == *needle
+    };
+
+    for distance in 0..=lines.len() {
+        if expected_row >= distance && matches_at(expected_row - distance) {
+            return Some(expected_row - distance);
+        }
+        if matches_at(expected_row + distance) {
+            return Some(expected_row + distance);
+        }
+        if expected_row < min_row + distance && expected_row + distance + needle.len() > lines.len()
+        {
+            break;
+        }
+    }
+    None
+}
+
+fn point_for_row(text: &str, lines: &[&str], row: usize) -> Utf8Point {
+    if row == lines.len() && !text.is_empty() && !text.ends_with('\n') {
+        Utf8Point::new(row as u32 - 1, lines[row - 1].len() as u32)
+    } else {
+        Utf8Point::new(row as u32, 0)
+    }
+}
+
+fn parse_hunk(header: &str, lines: &[&str]) -> Result<(Hunk, usize), Error> {
+    let ranges = header["@@ ".len()..]
+        .splitn(2, " @@")
+        .next()
+        .unwrap_or("")
+        .split(' ')
+        .collect::<Vec<_>>();
+    if ranges.len() != 2 || !ranges[0].starts_with('-') || !ranges[1].starts_with('+') {
+        return Err(Error::InvalidPatch(
+            format!("invalid hunk header {:?}", header).into(),
+        ));
+    }
+    let (old_start, old_len) = parse_range(&ranges[0][1..], header)?;
+    let (new_start, new_len) = parse_range(&ranges[1][1..], header)?;
+
+    let mut hunk = Hunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        lines: Vec::new(),
+    };
+    let mut old_remaining = old_len;
+    let mut new_remaining = new_len;
+    let mut consumed = 0;
+    while consumed < lines.len() {
+        let line = lines[consumed];
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" applies to the preceding line.
+            if let Some(last) = hunk.lines.last_mut() {
+                match last {
+                    HunkLine::Context(text) | HunkLine::Removed(text) | HunkLine::Added(text) => {
+                        if text.ends_with('\n') {
+                            text.pop();
+                        }
+                    }
+                }
+            }
+        } else if old_remaining == 0 && new_remaining == 0 {
+            break;
+        } else if line.starts_with(' ') || line == "\n" {
+            let text = if line == "\n" { line } else { &line[1..] };
+            if old_remaining == 0 || new_remaining == 0 {
+                break;
+            }
+            hunk.lines.push(HunkLine::Context(text.into()));
+            old_remaining -= 1;
+            new_remaining -= 1;
+        } else if line.starts_with('-') && old_remaining > 0 {
+            hunk.lines.push(HunkLine::Removed(line[1..].into()));
+            old_remaining -= 1;
+        } else if line.starts_with('+') && new_remaining > 0 {
+            hunk.lines.push(HunkLine::Added(line[1..].into()));
+            new_remaining -= 1;
+        } else {
+            break;
+        }
+        consumed += 1;
+    }
+
+    if old_remaining == 0 && new_remaining == 0 {
+        Ok((hunk, consumed))
+    } else {
+        Err(Error::InvalidPatch(
+            format!("hunk {:?} is truncated", header).into(),
+        ))
+    }
+}
+
+fn parse_range(range: &str, header: &str) -> Result<(usize, usize), Error> {
+    let mut parts = range.splitn(2, ',');
+    let start = parts.next().and_then(|start| start.parse().ok());
+    let len = parts.next().map_or(Some(1), |len| len.parse().ok());
+    match (start, len) {
+        (Some(start), Some(len)) => Ok((start, len)),
+        _ => Err(Error::InvalidPatch(
+            format!("invalid hunk header {:?}", header).into(),
+        )),
+    }
+}
+
+fn parse_git_header(paths: &str) -> Result<(PathBuf, PathBuf), Error> {
+    // Paths may contain spaces, so prefer the split that yields identical old and new paths.
+    let path_len = paths.len().saturating_sub(5) / 2;
+    if paths.len() > 5 && paths.len() % 2 == 1 && paths.is_char_boundary(path_len + 2) {
+        let (old_path, new_path) = paths.split_at(path_len + 2);
+        if old_path.starts_with("a/")
+            && new_path.starts_with(" b/")
+            && old_path[2..] == new_path[3..]
+        {
+            return Ok((PathBuf::from(&old_path[2..]), PathBuf::from(&new_path[3..])));
+        }
+    }
+
+    let mut parts = paths.splitn(2, " b/");
+    match (parts.next(), parts.next()) {
+        (Some(old_path), Some(new_path)) if old_path.starts_with("a/") => {
+            Ok((PathBuf::from(&old_path[2..]), PathBuf::from(new_path)))
+        }
+        _ => Err(Error::InvalidPatch(
+            format!("invalid diff header {:?}", paths).into(),
+        )),
+    }
+}
+
+/// Parses the path from a `---` or `+++` line, dropping any timestamp and the `a/` or `b/` prefix
+/// that Git adds.
+fn parse_path(path: &str) -> Option<PathBuf> {
+    let path = path.splitn(2, '\t').next().unwrap_or("").trim_end();
+    if path == "/dev/null" {
+        None
+    } else if path.starts_with("a/") || path.starts_with("b/") {
+        Some(PathBuf::from(&path[2..]))
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+fn split_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (ix, _) in text.match_indices('\n') {
+        lines.push(&text[start..ix + 1]);
+        start = ix + 1;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let patch = Patch::parse(
+            "From 1234 Mon Sep 17 00:00:00 2001\n\
+             Subject: [PATCH] Example\n\
+             \n\
+             ---\n\
+             diff --git a/src/a.rs b/src/a.rs\n\
+             index 1111111..2222222 100644\n\
+             --- a/src/a.rs\n\
+             +++ b/src/a.rs\n\
+             @@ -1,3 +1,3 @@ fn main() {\n\
+             \x20one\n\
+             -two\n\
+             +TWO\n\
+             \x20three\n\
+             diff --git a/old name b/new name\n\
+             similarity index 100%\n\
+             rename from old name\n\
+             rename to new name\n\
+             diff --git a/b.txt b/b.txt\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/b.txt\n\
+             @@ -0,0 +1 @@\n\
+             +b\n\
+             \\ No newline at end of file\n\
+             diff --git a/c.txt b/c.txt\n\
+             deleted file mode 100644\n\
+             --- a/c.txt\n\
+             +++ /dev/null\n\
+             @@ -1 +0,0 @@\n\
+             -c\n\
+             -- \n\
+             2.19.0\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            patch.files,
+            vec![
+                FilePatch {
+                    old_path: Some("src/a.rs".into()),
+                    new_path: Some("src/a.rs".into()),
+                    hunks: vec![Hunk {
+                        old_start: 1,
+                        old_len: 3,
+                        new_start: 1,
+                        new_len: 3,
+                        lines: vec![
+                            HunkLine::Context("one\n".into()),
+                            HunkLine::Removed("two\n".into()),
+                            HunkLine::Added("TWO\n".into()),
+                            HunkLine::Context("three\n".into()),
+                        ],
+                    }],
+                },
+                FilePatch {
+                    old_path: Some("old name".into()),
+                    new_path: Some("new name".into()),
+                    hunks: vec![],
+                },
+                FilePatch {
+                    old_path: None,
+                    new_path: Some("b.txt".into()),
+                    hunks: vec![Hunk {
+                        old_start: 0,
+                        old_len: 0,
+                        new_start: 1,
+                        new_len: 1,
+                        lines: vec![HunkLine::Added("b".into())],
+                    }],
+                },
+                FilePatch {
+                    old_path: Some("c.txt".into()),
+                    new_path: None,
+                    hunks: vec![Hunk {
+                        old_start: 1,
+                        old_len: 1,
+                        new_start: 0,
+                        new_len: 0,
+                        lines: vec![HunkLine::Removed("c\n".into())],
+                    }],
+                },
+            ]
+        );
+
+        assert!(Patch::parse("@@ -1 +1 @@\n-a\n+b\n").is_err());
+        assert!(Patch::parse("--- a/a\n+++ b/a\n@@ -1,2 +1,2 @@\n-a\n+b\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_hunks() {
+        let patch = Patch::parse(
+            "--- a/file\n\
+             +++ b/file\n\
+             @@ -2,2 +2,2 @@\n\
+             \x20b\n\
+             -c\n\
+             +C\n\
+             @@ -6,0 +7 @@\n\
+             +x\n\
+             @@ -7 +8 @@\n\
+             -missing\n\
+             +replacement\n\
+             @@ -8 +9 @@\n\
+             -h\n\
+             \\ No newline at end of file\n\
+             +H\n",
+        )
+        .unwrap();
+        let hunks = &patch.files[0].hunks;
+
+        // Two lines were inserted at the top since the patch was made.
+        let text = "0\n0\na\nb\nc\nd\ne\nf\ng\nh";
+        let resolution = resolve_hunks(text, hunks);
+        assert_eq!(
+            resolution.edits,
+            vec![
+                (Utf8Point::new(3, 0)..Utf8Point::new(5, 0), "b\nC\n".into()),
+                (Utf8Point::new(8, 0)..Utf8Point::new(8, 0), "x\n".into()),
+                (Utf8Point::new(9, 0)..Utf8Point::new(9, 1), "H\n".into()),
+            ]
+        );
+        assert_eq!(resolution.rejected, vec![(2, vec!["g\n".into()])]);
+    }
+}
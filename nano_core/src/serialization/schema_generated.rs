@@ -668,6 +668,69 @@ pub fn enum_name_file_type(e: FileType) -> &'static str {
   ENUM_NAMES_FILE_TYPE[index]
 }
 
+#[allow(non_camel_case_types)]
+#[repr(i8)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MergePolicy {
+  Merge = 0,
+  LastWriterWins = 1,
+  RejectConcurrent = 2,
+
+}
+
+const ENUM_MIN_MERGE_POLICY: i8 = 0;
+const ENUM_MAX_MERGE_POLICY: i8 = 2;
+
+impl<'a> flatbuffers::Follow<'a> for MergePolicy {
+  type Inner = Self;
+  #[inline]
+  fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::read_scalar_at::<Self>(buf, loc)
+  }
+}
+
+impl flatbuffers::EndianScalar for MergePolicy {
+  #[inline]
+  fn to_little_endian(self) -> Self {
+    let n = i8::to_le(self as i8);
+    let p = &n as *const i8 as *const MergePolicy;
+    unsafe { *p }
+  }
+  #[inline]
+  fn from_little_endian(self) -> Self {
+    let n = i8::from_le(self as i8);
+    let p = &n as *const i8 as *const MergePolicy;
+    unsafe { *p }
+  }
+}
+
+impl flatbuffers::Push for MergePolicy {
+    type Output = MergePolicy;
+    #[inline]
+    fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+        flatbuffers::emplace_scalar::<MergePolicy>(dst, *self);
+    }
+}
+
+#[allow(non_camel_case_types)]
+const ENUM_VALUES_MERGE_POLICY:[MergePolicy; 3] = [
+  MergePolicy::Merge,
+  MergePolicy::LastWriterWins,
+  MergePolicy::RejectConcurrent
+];
+
+#[allow(non_camel_case_types)]
+const ENUM_NAMES_MERGE_POLICY:[&'static str; 3] = [
+    "Merge",
+    "LastWriterWins",
+    "RejectConcurrent"
+];
+
+pub fn enum_name_merge_policy(e: MergePolicy) -> &'static str {
+  let index: usize = e as usize;
+  ENUM_NAMES_MERGE_POLICY[index]
+}
+
 #[allow(non_camel_case_types)]
 #[repr(u8)]
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -676,11 +739,13 @@ pub enum Operation {
   InsertMetadata = 1,
   UpdateParent = 2,
   BufferOperation = 3,
+  SetMergePolicy = 4,
+  CopyFiles = 5,
 
 }
 
 const ENUM_MIN_OPERATION: u8 = 0;
-const ENUM_MAX_OPERATION: u8 = 3;
+const ENUM_MAX_OPERATION: u8 = 5;
 
 impl<'a> flatbuffers::Follow<'a> for Operation {
   type Inner = Self;
@@ -714,19 +779,23 @@ impl flatbuffers::Push for Operation {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_OPERATION:[Operation; 4] = [
+const ENUM_VALUES_OPERATION:[Operation; 6] = [
   Operation::NONE,
   Operation::InsertMetadata,
   Operation::UpdateParent,
-  Operation::BufferOperation
+  Operation::BufferOperation,
+  Operation::SetMergePolicy,
+  Operation::CopyFiles
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_OPERATION:[&'static str; 4] = [
+const ENUM_NAMES_OPERATION:[&'static str; 6] = [
     "NONE",
     "InsertMetadata",
     "UpdateParent",
-    "BufferOperation"
+    "BufferOperation",
+    "SetMergePolicy",
+    "CopyFiles"
 ];
 
 pub fn enum_name_operation(e: Operation) -> &'static str {
@@ -1419,6 +1488,447 @@ impl<'a: 'b, 'b> BufferOperationBuilder<'a, 'b> {
   }
 }
 
+pub struct SetMergePolicy<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for SetMergePolicy<'a> {
+    type Inner = SetMergePolicy<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> SetMergePolicy<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        SetMergePolicy {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args SetMergePolicyArgs<'args>) -> flatbuffers::WIPOffset<SetMergePolicy<'bldr>> {
+      let mut builder = SetMergePolicyBuilder::new(_fbb);
+      if let Some(x) = args.lamport_timestamp { builder.add_lamport_timestamp(x); }
+      if let Some(x) = args.local_timestamp { builder.add_local_timestamp(x); }
+      if let Some(x) = args.pattern { builder.add_pattern(x); }
+      builder.add_policy(args.policy);
+      builder.finish()
+    }
+
+    pub const VT_PATTERN: flatbuffers::VOffsetT = 4;
+    pub const VT_POLICY: flatbuffers::VOffsetT = 6;
+    pub const VT_LOCAL_TIMESTAMP: flatbuffers::VOffsetT = 8;
+    pub const VT_LAMPORT_TIMESTAMP: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn pattern(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(SetMergePolicy::VT_PATTERN, None)
+  }
+  #[inline]
+  pub fn policy(&self) -> MergePolicy {
+    self._tab.get::<MergePolicy>(SetMergePolicy::VT_POLICY, Some(MergePolicy::Merge)).unwrap()
+  }
+  #[inline]
+  pub fn local_timestamp(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(SetMergePolicy::VT_LOCAL_TIMESTAMP, None)
+  }
+  #[inline]
+  pub fn lamport_timestamp(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(SetMergePolicy::VT_LAMPORT_TIMESTAMP, None)
+  }
+}
+
+pub struct SetMergePolicyArgs<'a> {
+    pub pattern: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub policy: MergePolicy,
+    pub local_timestamp: Option<&'a  super::Timestamp>,
+    pub lamport_timestamp: Option<&'a  super::Timestamp>,
+}
+impl<'a> Default for SetMergePolicyArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        SetMergePolicyArgs {
+            pattern: None,
+            policy: MergePolicy::Merge,
+            local_timestamp: None,
+            lamport_timestamp: None,
+        }
+    }
+}
+pub struct SetMergePolicyBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> SetMergePolicyBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_pattern(&mut self, pattern: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(SetMergePolicy::VT_PATTERN, pattern);
+  }
+  #[inline]
+  pub fn add_policy(&mut self, policy: MergePolicy) {
+    self.fbb_.push_slot::<MergePolicy>(SetMergePolicy::VT_POLICY, policy, MergePolicy::Merge);
+  }
+  #[inline]
+  pub fn add_local_timestamp(&mut self, local_timestamp: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(SetMergePolicy::VT_LOCAL_TIMESTAMP, local_timestamp);
+  }
+  #[inline]
+  pub fn add_lamport_timestamp(&mut self, lamport_timestamp: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(SetMergePolicy::VT_LAMPORT_TIMESTAMP, lamport_timestamp);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> SetMergePolicyBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    SetMergePolicyBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<SetMergePolicy<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum CopiedFileOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct CopiedFile<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for CopiedFile<'a> {
+    type Inner = CopiedFile<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> CopiedFile<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        CopiedFile {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args CopiedFileArgs<'args>) -> flatbuffers::WIPOffset<CopiedFile<'bldr>> {
+      let mut builder = CopiedFileBuilder::new(_fbb);
+      if let Some(x) = args.source_version { builder.add_source_version(x); }
+      if let Some(x) = args.source_id { builder.add_source_id(x); }
+      if let Some(x) = args.name_in_parent { builder.add_name_in_parent(x); }
+      if let Some(x) = args.parent_id { builder.add_parent_id(x); }
+      if let Some(x) = args.file_id { builder.add_file_id(x); }
+      builder.add_source_id_type(args.source_id_type);
+      builder.add_parent_id_type(args.parent_id_type);
+      builder.add_file_type(args.file_type);
+      builder.add_file_id_type(args.file_id_type);
+      builder.finish()
+    }
+
+    pub const VT_FILE_ID_TYPE: flatbuffers::VOffsetT = 4;
+    pub const VT_FILE_ID: flatbuffers::VOffsetT = 6;
+    pub const VT_FILE_TYPE: flatbuffers::VOffsetT = 8;
+    pub const VT_PARENT_ID_TYPE: flatbuffers::VOffsetT = 10;
+    pub const VT_PARENT_ID: flatbuffers::VOffsetT = 12;
+    pub const VT_NAME_IN_PARENT: flatbuffers::VOffsetT = 14;
+    pub const VT_SOURCE_ID_TYPE: flatbuffers::VOffsetT = 16;
+    pub const VT_SOURCE_ID: flatbuffers::VOffsetT = 18;
+    pub const VT_SOURCE_VERSION: flatbuffers::VOffsetT = 20;
+
+  #[inline]
+  pub fn file_id_type(&self) -> FileId {
+    self._tab.get::<FileId>(CopiedFile::VT_FILE_ID_TYPE, Some(FileId::NONE)).unwrap()
+  }
+  #[inline]
+  pub fn file_id(&self) -> Option<flatbuffers::Table<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(CopiedFile::VT_FILE_ID, None)
+  }
+  #[inline]
+  pub fn file_type(&self) -> FileType {
+    self._tab.get::<FileType>(CopiedFile::VT_FILE_TYPE, Some(FileType::Directory)).unwrap()
+  }
+  #[inline]
+  pub fn parent_id_type(&self) -> FileId {
+    self._tab.get::<FileId>(CopiedFile::VT_PARENT_ID_TYPE, Some(FileId::NONE)).unwrap()
+  }
+  #[inline]
+  pub fn parent_id(&self) -> Option<flatbuffers::Table<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(CopiedFile::VT_PARENT_ID, None)
+  }
+  #[inline]
+  pub fn name_in_parent(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(CopiedFile::VT_NAME_IN_PARENT, None)
+  }
+  #[inline]
+  pub fn source_id_type(&self) -> FileId {
+    self._tab.get::<FileId>(CopiedFile::VT_SOURCE_ID_TYPE, Some(FileId::NONE)).unwrap()
+  }
+  #[inline]
+  pub fn source_id(&self) -> Option<flatbuffers::Table<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(CopiedFile::VT_SOURCE_ID, None)
+  }
+  #[inline]
+  pub fn source_version(&self) -> Option<super::GlobalTimestamp<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<super::GlobalTimestamp<'a>>>(CopiedFile::VT_SOURCE_VERSION, None)
+  }
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn file_id_as_base_file_id(&'a self) -> Option<BaseFileId> {
+    if self.file_id_type() == FileId::BaseFileId {
+      self.file_id().map(|u| BaseFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn file_id_as_new_file_id(&'a self) -> Option<NewFileId> {
+    if self.file_id_type() == FileId::NewFileId {
+      self.file_id().map(|u| NewFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn parent_id_as_base_file_id(&'a self) -> Option<BaseFileId> {
+    if self.parent_id_type() == FileId::BaseFileId {
+      self.parent_id().map(|u| BaseFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn parent_id_as_new_file_id(&'a self) -> Option<NewFileId> {
+    if self.parent_id_type() == FileId::NewFileId {
+      self.parent_id().map(|u| NewFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn source_id_as_base_file_id(&'a self) -> Option<BaseFileId> {
+    if self.source_id_type() == FileId::BaseFileId {
+      self.source_id().map(|u| BaseFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn source_id_as_new_file_id(&'a self) -> Option<NewFileId> {
+    if self.source_id_type() == FileId::NewFileId {
+      self.source_id().map(|u| NewFileId::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+}
+
+pub struct CopiedFileArgs<'a> {
+    pub file_id_type: FileId,
+    pub file_id: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+    pub file_type: FileType,
+    pub parent_id_type: FileId,
+    pub parent_id: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+    pub name_in_parent: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub source_id_type: FileId,
+    pub source_id: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+    pub source_version: Option<flatbuffers::WIPOffset<super::GlobalTimestamp<'a >>>,
+}
+impl<'a> Default for CopiedFileArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        CopiedFileArgs {
+            file_id_type: FileId::NONE,
+            file_id: None,
+            file_type: FileType::Directory,
+            parent_id_type: FileId::NONE,
+            parent_id: None,
+            name_in_parent: None,
+            source_id_type: FileId::NONE,
+            source_id: None,
+            source_version: None,
+        }
+    }
+}
+pub struct CopiedFileBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> CopiedFileBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_file_id_type(&mut self, file_id_type: FileId) {
+    self.fbb_.push_slot::<FileId>(CopiedFile::VT_FILE_ID_TYPE, file_id_type, FileId::NONE);
+  }
+  #[inline]
+  pub fn add_file_id(&mut self, file_id: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CopiedFile::VT_FILE_ID, file_id);
+  }
+  #[inline]
+  pub fn add_file_type(&mut self, file_type: FileType) {
+    self.fbb_.push_slot::<FileType>(CopiedFile::VT_FILE_TYPE, file_type, FileType::Directory);
+  }
+  #[inline]
+  pub fn add_parent_id_type(&mut self, parent_id_type: FileId) {
+    self.fbb_.push_slot::<FileId>(CopiedFile::VT_PARENT_ID_TYPE, parent_id_type, FileId::NONE);
+  }
+  #[inline]
+  pub fn add_parent_id(&mut self, parent_id: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CopiedFile::VT_PARENT_ID, parent_id);
+  }
+  #[inline]
+  pub fn add_name_in_parent(&mut self, name_in_parent: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CopiedFile::VT_NAME_IN_PARENT, name_in_parent);
+  }
+  #[inline]
+  pub fn add_source_id_type(&mut self, source_id_type: FileId) {
+    self.fbb_.push_slot::<FileId>(CopiedFile::VT_SOURCE_ID_TYPE, source_id_type, FileId::NONE);
+  }
+  #[inline]
+  pub fn add_source_id(&mut self, source_id: flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CopiedFile::VT_SOURCE_ID, source_id);
+  }
+  #[inline]
+  pub fn add_source_version(&mut self, source_version: flatbuffers::WIPOffset<super::GlobalTimestamp<'b >>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<super::GlobalTimestamp>>(CopiedFile::VT_SOURCE_VERSION, source_version);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> CopiedFileBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    CopiedFileBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<CopiedFile<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum CopyFilesOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct CopyFiles<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for CopyFiles<'a> {
+    type Inner = CopyFiles<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> CopyFiles<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        CopyFiles {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args CopyFilesArgs<'args>) -> flatbuffers::WIPOffset<CopyFiles<'bldr>> {
+      let mut builder = CopyFilesBuilder::new(_fbb);
+      if let Some(x) = args.lamport_timestamp { builder.add_lamport_timestamp(x); }
+      if let Some(x) = args.local_timestamp { builder.add_local_timestamp(x); }
+      if let Some(x) = args.files { builder.add_files(x); }
+      builder.finish()
+    }
+
+    pub const VT_FILES: flatbuffers::VOffsetT = 4;
+    pub const VT_LOCAL_TIMESTAMP: flatbuffers::VOffsetT = 6;
+    pub const VT_LAMPORT_TIMESTAMP: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn files(&self) -> Option<flatbuffers::Vector<flatbuffers::ForwardsUOffset<CopiedFile<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<CopiedFile<'a>>>>>(CopyFiles::VT_FILES, None)
+  }
+  #[inline]
+  pub fn local_timestamp(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(CopyFiles::VT_LOCAL_TIMESTAMP, None)
+  }
+  #[inline]
+  pub fn lamport_timestamp(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(CopyFiles::VT_LAMPORT_TIMESTAMP, None)
+  }
+}
+
+pub struct CopyFilesArgs<'a> {
+    pub files: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<CopiedFile<'a >>>>>,
+    pub local_timestamp: Option<&'a  super::Timestamp>,
+    pub lamport_timestamp: Option<&'a  super::Timestamp>,
+}
+impl<'a> Default for CopyFilesArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        CopyFilesArgs {
+            files: None,
+            local_timestamp: None,
+            lamport_timestamp: None,
+        }
+    }
+}
+pub struct CopyFilesBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> CopyFilesBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_files(&mut self, files: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<CopiedFile<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CopyFiles::VT_FILES, files);
+  }
+  #[inline]
+  pub fn add_local_timestamp(&mut self, local_timestamp: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(CopyFiles::VT_LOCAL_TIMESTAMP, local_timestamp);
+  }
+  #[inline]
+  pub fn add_lamport_timestamp(&mut self, lamport_timestamp: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(CopyFiles::VT_LAMPORT_TIMESTAMP, lamport_timestamp);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> CopyFilesBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    CopyFilesBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<CopyFiles<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 }  // pub mod epoch
 
 pub mod worktree {
@@ -1438,11 +1948,12 @@ pub enum OperationVariant {
   NONE = 0,
   StartEpoch = 1,
   EpochOperation = 2,
+  SetUser = 3,
 
 }
 
 const ENUM_MIN_OPERATION_VARIANT: u8 = 0;
-const ENUM_MAX_OPERATION_VARIANT: u8 = 2;
+const ENUM_MAX_OPERATION_VARIANT: u8 = 3;
 
 impl<'a> flatbuffers::Follow<'a> for OperationVariant {
   type Inner = Self;
@@ -1476,17 +1987,19 @@ impl flatbuffers::Push for OperationVariant {
 }
 
 #[allow(non_camel_case_types)]
-const ENUM_VALUES_OPERATION_VARIANT:[OperationVariant; 3] = [
+const ENUM_VALUES_OPERATION_VARIANT:[OperationVariant; 4] = [
   OperationVariant::NONE,
   OperationVariant::StartEpoch,
-  OperationVariant::EpochOperation
+  OperationVariant::EpochOperation,
+  OperationVariant::SetUser
 ];
 
 #[allow(non_camel_case_types)]
-const ENUM_NAMES_OPERATION_VARIANT:[&'static str; 3] = [
+const ENUM_NAMES_OPERATION_VARIANT:[&'static str; 4] = [
     "NONE",
     "StartEpoch",
-    "EpochOperation"
+    "EpochOperation",
+    "SetUser"
 ];
 
 pub fn enum_name_operation_variant(e: OperationVariant) -> &'static str {
@@ -1664,6 +2177,26 @@ impl<'a> EpochOperation<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn operation_as_set_merge_policy(&'a self) -> Option<super::epoch::SetMergePolicy> {
+    if self.operation_type() == super::epoch::Operation::SetMergePolicy {
+      self.operation().map(|u| super::epoch::SetMergePolicy::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn operation_as_copy_files(&'a self) -> Option<super::epoch::CopyFiles> {
+    if self.operation_type() == super::epoch::Operation::CopyFiles {
+      self.operation().map(|u| super::epoch::CopyFiles::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
 }
 
 pub struct EpochOperationArgs<'a> {
@@ -1713,6 +2246,206 @@ impl<'a: 'b, 'b> EpochOperationBuilder<'a, 'b> {
   }
 }
 
+pub enum UserOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct User<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for User<'a> {
+    type Inner = User<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> User<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        User {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args UserArgs<'args>) -> flatbuffers::WIPOffset<User<'bldr>> {
+      let mut builder = UserBuilder::new(_fbb);
+      builder.add_id(args.id);
+      builder.add_color(args.color);
+      if let Some(x) = args.display_name { builder.add_display_name(x); }
+      builder.finish()
+    }
+
+    pub const VT_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_DISPLAY_NAME: flatbuffers::VOffsetT = 6;
+    pub const VT_COLOR: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn id(&self) -> u64 {
+    self._tab.get::<u64>(User::VT_ID, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn display_name(&self) -> Option<&'a str> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(User::VT_DISPLAY_NAME, None)
+  }
+  #[inline]
+  pub fn color(&self) -> u32 {
+    self._tab.get::<u32>(User::VT_COLOR, Some(0)).unwrap()
+  }
+}
+
+pub struct UserArgs<'a> {
+    pub id: u64,
+    pub display_name: Option<flatbuffers::WIPOffset<&'a  str>>,
+    pub color: u32,
+}
+impl<'a> Default for UserArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        UserArgs {
+            id: 0,
+            display_name: None,
+            color: 0,
+        }
+    }
+}
+pub struct UserBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> UserBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_id(&mut self, id: u64) {
+    self.fbb_.push_slot::<u64>(User::VT_ID, id, 0);
+  }
+  #[inline]
+  pub fn add_display_name(&mut self, display_name: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(User::VT_DISPLAY_NAME, display_name);
+  }
+  #[inline]
+  pub fn add_color(&mut self, color: u32) {
+    self.fbb_.push_slot::<u32>(User::VT_COLOR, color, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> UserBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    UserBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<User<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+pub enum SetUserOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct SetUser<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for SetUser<'a> {
+    type Inner = SetUser<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> SetUser<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        SetUser {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args SetUserArgs<'args>) -> flatbuffers::WIPOffset<SetUser<'bldr>> {
+      let mut builder = SetUserBuilder::new(_fbb);
+      if let Some(x) = args.lamport_timestamp { builder.add_lamport_timestamp(x); }
+      if let Some(x) = args.user { builder.add_user(x); }
+      if let Some(x) = args.epoch_id { builder.add_epoch_id(x); }
+      builder.finish()
+    }
+
+    pub const VT_EPOCH_ID: flatbuffers::VOffsetT = 4;
+    pub const VT_USER: flatbuffers::VOffsetT = 6;
+    pub const VT_LAMPORT_TIMESTAMP: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub fn epoch_id(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(SetUser::VT_EPOCH_ID, None)
+  }
+  #[inline]
+  pub fn user(&self) -> Option<User<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<User<'a>>>(SetUser::VT_USER, None)
+  }
+  #[inline]
+  pub fn lamport_timestamp(&self) -> Option<&'a super::Timestamp> {
+    self._tab.get::<super::Timestamp>(SetUser::VT_LAMPORT_TIMESTAMP, None)
+  }
+}
+
+pub struct SetUserArgs<'a> {
+    pub epoch_id: Option<&'a  super::Timestamp>,
+    pub user: Option<flatbuffers::WIPOffset<User<'a >>>,
+    pub lamport_timestamp: Option<&'a  super::Timestamp>,
+}
+impl<'a> Default for SetUserArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        SetUserArgs {
+            epoch_id: None,
+            user: None,
+            lamport_timestamp: None,
+        }
+    }
+}
+pub struct SetUserBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> SetUserBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_epoch_id(&mut self, epoch_id: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(SetUser::VT_EPOCH_ID, epoch_id);
+  }
+  #[inline]
+  pub fn add_user(&mut self, user: flatbuffers::WIPOffset<User<'b >>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<User>>(SetUser::VT_USER, user);
+  }
+  #[inline]
+  pub fn add_lamport_timestamp(&mut self, lamport_timestamp: &'b  super::Timestamp) {
+    self.fbb_.push_slot_always::<&super::Timestamp>(SetUser::VT_LAMPORT_TIMESTAMP, lamport_timestamp);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> SetUserBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    SetUserBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<SetUser<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 pub enum OperationOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -1778,6 +2511,16 @@ impl<'a> Operation<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn variant_as_set_user(&'a self) -> Option<SetUser> {
+    if self.variant_type() == OperationVariant::SetUser {
+      self.variant().map(|u| SetUser::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
 }
 
 pub struct OperationArgs {
@@ -0,0 +1,179 @@
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Encoding {
+    Utf8,
+    Utf8WithBom,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextFormat {
+    pub line_ending: LineEnding,
+    pub encoding: Encoding,
+    // The offsets, in UTF-16 code units of the normalized base text, of the newlines that didn't
+    // use `line_ending` in the original file.
+    base_exceptions: Arc<Vec<usize>>,
+}
+
+impl TextFormat {
+    /// Detects the line ending style and byte order mark of the given text. Returns the format
+    /// along with the text normalized to LF line endings and stripped of its byte order mark.
+    pub fn detect(text: &str) -> (Self, String) {
+        let (encoding, text) = if text.starts_with('\u{feff}') {
+            (Encoding::Utf8WithBom, &text['\u{feff}'.len_utf8()..])
+        } else {
+            (Encoding::Utf8, text)
+        };
+
+        let mut normalized = String::with_capacity(text.len());
+        let mut lf_offsets = Vec::new();
+        let mut crlf_offsets = Vec::new();
+        let mut offset = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+                crlf_offsets.push(offset);
+                normalized.push('\n');
+                offset += 1;
+            } else {
+                if ch == '\n' {
+                    lf_offsets.push(offset);
+                }
+                normalized.push(ch);
+                offset += ch.len_utf16();
+            }
+        }
+
+        let (line_ending, base_exceptions) = if crlf_offsets.len() > lf_offsets.len() {
+            (LineEnding::CrLf, lf_offsets)
+        } else {
+            (LineEnding::Lf, crlf_offsets)
+        };
+        let format = TextFormat {
+            line_ending,
+            encoding,
+            base_exceptions: Arc::new(base_exceptions),
+        };
+        (format, normalized)
+    }
+
+    /// The format of text derived from a file in this format, such as a copy of it. Offsets into
+    /// the original base text don't apply to the new text, so all of its newlines use the
+    /// predominant line ending.
+    pub fn derived(&self) -> Self {
+        TextFormat {
+            line_ending: self.line_ending,
+            encoding: self.encoding,
+            base_exceptions: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Encodes normalized text, supplied in chunks along with their offset in the base text if
+    /// they were part of it. Newlines from the base text are written exactly as they appeared in
+    /// the original file, and all other newlines use the file's predominant line ending.
+    pub fn encode<'a, I>(&self, chunks: I) -> Vec<u8>
+    where
+        I: IntoIterator<Item = (&'a str, Option<usize>)>,
+    {
+        let mut bytes = Vec::new();
+        if self.encoding == Encoding::Utf8WithBom {
+            bytes.extend_from_slice(UTF8_BOM);
+        }
+
+        for (chunk, base_offset) in chunks {
+            let mut base_offset = base_offset;
+            for (index, line) in chunk.split('\n').enumerate() {
+                if index > 0 {
+                    let line_ending = base_offset.map_or(self.line_ending, |base_offset| {
+                        self.base_line_ending(base_offset)
+                    });
+                    bytes.extend_from_slice(line_ending.as_bytes());
+                    base_offset = base_offset.map(|base_offset| base_offset + 1);
+                }
+                bytes.extend_from_slice(line.as_bytes());
+                base_offset =
+                    base_offset.map(|base_offset| base_offset + line.encode_utf16().count());
+            }
+        }
+
+        bytes
+    }
+
+    fn base_line_ending(&self, offset: usize) -> LineEnding {
+        if self.base_exceptions.binary_search(&offset).is_ok() {
+            match self.line_ending {
+                LineEnding::Lf => LineEnding::CrLf,
+                LineEnding::CrLf => LineEnding::Lf,
+            }
+        } else {
+            self.line_ending
+        }
+    }
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        TextFormat {
+            line_ending: LineEnding::Lf,
+            encoding: Encoding::Utf8,
+            base_exceptions: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl LineEnding {
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let (format, text) = TextFormat::detect("a\nb\nc");
+        assert_eq!(format, TextFormat::default());
+        assert_eq!(text, "a\nb\nc");
+
+        let (format, text) = TextFormat::detect("\u{feff}a\r\nb\r\nc\nd\r");
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+        assert_eq!(format.encoding, Encoding::Utf8WithBom);
+        assert_eq!(*format.base_exceptions, vec![5]);
+        assert_eq!(text, "a\nb\nc\nd\r");
+    }
+
+    #[test]
+    fn test_encode() {
+        let original = "\u{feff}a\r\nb\r\nc\nd\r";
+        let (format, text) = TextFormat::detect(original);
+        assert_eq!(
+            format.encode(Some((text.as_str(), Some(0)))),
+            original.as_bytes()
+        );
+
+        // New text uses the predominant line ending, and untouched base text is unchanged.
+        assert_eq!(
+            format.encode(vec![
+                ("a\nb", Some(0)),
+                ("x\ny", None),
+                ("\nc\nd\r", Some(3))
+            ]),
+            "\u{feff}a\r\nbx\r\ny\r\nc\nd\r".as_bytes()
+        );
+    }
+}
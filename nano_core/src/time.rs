@@ -10,7 +10,9 @@ use std::collections::HashMap;
 use std::ops::{Add, AddAssign};
 use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Ord, PartialOrd)]
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Ord, PartialOrd, Serialize,
+)]
 pub struct Local {
     pub replica_id: ReplicaId,
     pub value: u64,
@@ -124,10 +126,27 @@ impl Global {
         }
     }
 
+    pub fn replica_ids<'a>(&'a self) -> impl 'a + Iterator<Item = ReplicaId> {
+        self.0.keys().cloned()
+    }
+
     pub fn observed(&self, timestamp: Local) -> bool {
         self.get(timestamp.replica_id) >= timestamp.value
     }
 
+    /// The latest timestamp of each replica in `other` that this version hasn't observed.
+    pub fn missing(&self, other: &Self) -> Vec<Local> {
+        other
+            .0
+            .iter()
+            .filter(|(replica_id, value)| **value > self.get(**replica_id))
+            .map(|(replica_id, value)| Local {
+                replica_id: *replica_id,
+                value: *value,
+            })
+            .collect()
+    }
+
     pub fn changed_since(&self, other: &Self) -> bool {
         self.0
             .iter()
@@ -0,0 +1,140 @@
+use crate::epoch;
+use crate::time;
+use crate::ReplicaId;
+use crate::UserId;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct User {
+    pub id: UserId,
+    pub display_name: String,
+    /// An RGB color packed as `0xRRGGBB`.
+    pub color: u32,
+}
+
+/// Maps each replica to the user operating it. Every replica announces its own identity, and the
+/// most recent announcement wins. The identity announced in each epoch is kept as well, because a
+/// replica replaying history announces a different author for every commit.
+#[derive(Clone, Debug, Default)]
+pub struct UserRegistry {
+    users: HashMap<ReplicaId, (User, time::Lamport)>,
+    epoch_users: HashMap<ReplicaId, BTreeMap<epoch::Id, (User, time::Lamport)>>,
+}
+
+impl UserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the identity that the replica that generated `lamport_timestamp` announced in the
+    /// given epoch, returning false if a more recent identity was already known.
+    pub fn set(
+        &mut self,
+        user: User,
+        epoch_id: epoch::Id,
+        lamport_timestamp: time::Lamport,
+    ) -> bool {
+        let replica_id = lamport_timestamp.replica_id;
+        let epoch_users = self.epoch_users.entry(replica_id).or_default();
+        if epoch_users
+            .get(&epoch_id)
+            .map_or(true, |(_, timestamp)| *timestamp < lamport_timestamp)
+        {
+            epoch_users.insert(epoch_id, (user.clone(), lamport_timestamp));
+        }
+
+        if let Some((_, timestamp)) = self.users.get(&replica_id) {
+            if *timestamp >= lamport_timestamp {
+                return false;
+            }
+        }
+
+        self.users.insert(replica_id, (user, lamport_timestamp));
+        true
+    }
+
+    pub fn get(&self, replica_id: ReplicaId) -> Option<&User> {
+        self.users.get(&replica_id).map(|(user, _)| user)
+    }
+
+    /// Returns the identity the replica had in the given epoch, which is the last one it
+    /// announced in that epoch or, failing that, in the closest epoch before it.
+    pub fn get_in_epoch(&self, replica_id: ReplicaId, epoch_id: epoch::Id) -> Option<&User> {
+        self.epoch_users
+            .get(&replica_id)?
+            .range(..=epoch_id)
+            .next_back()
+            .map(|(_, (user, _))| user)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ReplicaId, &User)> {
+        self.users
+            .iter()
+            .map(|(replica_id, (user, _))| (*replica_id, user))
+    }
+
+    /// Returns every known identity along with the timestamp it was announced at.
+    pub fn announcements(&self) -> impl Iterator<Item = (&User, time::Lamport)> {
+        self.users.values().map(|(user, timestamp)| (user, *timestamp))
+    }
+
+    pub fn replicas_for_user<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> impl 'a + Iterator<Item = ReplicaId> {
+        self.iter()
+            .filter(move |(_, user)| user.id == user_id)
+            .map(|(replica_id, _)| replica_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_set() {
+        let replica_1 = Uuid::from_u128(1);
+        let replica_2 = Uuid::from_u128(2);
+        let mut clock_1 = time::Lamport::new(replica_1);
+        let mut clock_2 = time::Lamport::new(replica_2);
+        let alice = User {
+            id: 1,
+            display_name: "Alice".into(),
+            color: 0xff0000,
+        };
+        let bob = User {
+            id: 2,
+            display_name: "Bob".into(),
+            color: 0x0000ff,
+        };
+
+        let mut registry = UserRegistry::new();
+        let epoch_1 = clock_1.tick();
+        let timestamp_1 = clock_1.tick();
+        let timestamp_2 = clock_1.tick();
+        assert!(registry.set(bob.clone(), epoch_1, timestamp_2));
+        assert!(!registry.set(alice.clone(), epoch_1, timestamp_1));
+        assert_eq!(registry.get(replica_1), Some(&bob));
+
+        assert!(registry.set(bob.clone(), epoch_1, clock_2.tick()));
+        assert_eq!(registry.get(replica_2), Some(&bob));
+        let mut replicas = registry.replicas_for_user(bob.id).collect::<Vec<_>>();
+        replicas.sort();
+        assert_eq!(replicas, vec![replica_1, replica_2]);
+
+        let epoch_2 = clock_1.tick();
+        assert!(registry.set(alice.clone(), epoch_2, clock_1.tick()));
+        assert_eq!(registry.get(replica_1), Some(&alice));
+        assert_eq!(registry.replicas_for_user(alice.id).count(), 1);
+
+        // Earlier epochs keep the identity that was announced in them.
+        assert_eq!(registry.get_in_epoch(replica_1, epoch_1), Some(&bob));
+        assert_eq!(registry.get_in_epoch(replica_1, epoch_2), Some(&alice));
+        assert_eq!(registry.get_in_epoch(replica_1, clock_1.tick()), Some(&alice));
+        assert_eq!(registry.get_in_epoch(replica_2, epoch_2), Some(&bob));
+        assert_eq!(registry.get_in_epoch(replica_1, time::Lamport::default()), None);
+    }
+}
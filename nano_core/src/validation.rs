@@ -0,0 +1,243 @@
+use crate::buffer;
+use crate::epoch::{self, FileId};
+use crate::glob::Glob;
+use crate::time;
+use crate::user::User;
+use crate::Error;
+use crate::ReplicaId;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+
+#[derive(Clone, Debug)]
+pub struct OperationLimits {
+    /// The maximum length of a single insertion, in UTF-16 code units.
+    pub max_text_len: usize,
+    /// The maximum length of a file name, in bytes.
+    pub max_name_len: usize,
+    /// When set, operations originating from or referring to any other replica are rejected.
+    pub known_replicas: Option<HashSet<ReplicaId>>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Quarantine {
+    limits: OperationLimits,
+    replicas: HashMap<ReplicaId, Cow<'static, str>>,
+}
+
+impl Default for OperationLimits {
+    fn default() -> Self {
+        OperationLimits {
+            max_text_len: 16 * 1024 * 1024,
+            max_name_len: 255,
+            known_replicas: None,
+        }
+    }
+}
+
+impl OperationLimits {
+    fn check_replica(&self, replica_id: ReplicaId) -> Result<(), Error> {
+        // The nil replica owns the base text of every buffer.
+        if replica_id.is_nil()
+            || self
+                .known_replicas
+                .as_ref()
+                .map_or(true, |known_replicas| known_replicas.contains(&replica_id))
+        {
+            Ok(())
+        } else {
+            Err(malformed(format!("unknown replica {}", replica_id)))
+        }
+    }
+
+    fn check_timestamps(
+        &self,
+        local_timestamp: time::Local,
+        lamport_timestamp: time::Lamport,
+    ) -> Result<(), Error> {
+        if local_timestamp.replica_id != lamport_timestamp.replica_id {
+            Err(malformed(
+                "local and lamport timestamps have different replicas",
+            ))
+        } else if local_timestamp.value == u64::max_value()
+            || lamport_timestamp.value == u64::max_value()
+        {
+            Err(malformed("timestamp would overflow"))
+        } else {
+            self.check_replica(local_timestamp.replica_id)
+        }
+    }
+
+    fn check_name(&self, name: &OsStr) -> Result<(), Error> {
+        let name_str = name.to_string_lossy();
+        if name_str.is_empty() || name_str == "." || name_str == ".." || name_str.contains('/') {
+            Err(malformed(format!("invalid file name {:?}", name)))
+        } else if name.len() > self.max_name_len {
+            Err(malformed("file name is too long"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks the parts of an operation that don't depend on the state of the epoch it will be
+    /// applied to.
+    pub(crate) fn check_epoch_op(&self, op: &epoch::Operation) -> Result<(), Error> {
+        self.check_timestamps(op.local_timestamp(), op.lamport_timestamp())?;
+        let replica_id = op.local_timestamp().replica_id;
+
+        match op {
+            epoch::Operation::InsertMetadata {
+                file_id, parent, ..
+            } => {
+                match file_id {
+                    FileId::New(id) if id.replica_id == replica_id => {}
+                    _ => return Err(malformed("file id wasn't allocated by its creator")),
+                }
+                if let Some((_, name)) = parent {
+                    self.check_name(name)?;
+                }
+            }
+            epoch::Operation::UpdateParent { new_parent, .. } => {
+                if let Some((_, name)) = new_parent {
+                    self.check_name(name)?;
+                }
+            }
+            epoch::Operation::BufferOperation { operations, .. } => {
+                for operation in operations {
+                    self.check_buffer_op(replica_id, operation)?;
+                }
+            }
+            epoch::Operation::CopyFiles { files, .. } => {
+                for file in files {
+                    match file.file_id {
+                        FileId::New(id) if id.replica_id == replica_id => {}
+                        _ => return Err(malformed("file id wasn't allocated by its creator")),
+                    }
+                    self.check_name(&file.parent.1)?;
+                    if let Some((_, version)) = &file.source {
+                        for replica_id in version.replica_ids() {
+                            self.check_replica(replica_id)?;
+                        }
+                    }
+                }
+            }
+            epoch::Operation::SetMergePolicy { pattern, .. } => {
+                if Glob::new(pattern).is_err() {
+                    return Err(malformed(format!(
+                        "invalid merge policy pattern {:?}",
+                        pattern
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks an identity announced by another replica.
+    pub(crate) fn check_user(
+        &self,
+        user: &User,
+        lamport_timestamp: time::Lamport,
+    ) -> Result<(), Error> {
+        if lamport_timestamp.replica_id.is_nil() {
+            Err(malformed("the nil replica can't announce a user"))
+        } else if lamport_timestamp.value == u64::max_value() {
+            Err(malformed("timestamp would overflow"))
+        } else if user.display_name.len() > self.max_name_len {
+            Err(malformed("user name is too long"))
+        } else {
+            self.check_replica(lamport_timestamp.replica_id)
+        }
+    }
+
+    fn check_buffer_op(&self, replica_id: ReplicaId, op: &buffer::Operation) -> Result<(), Error> {
+        match op {
+            buffer::Operation::Edit {
+                start_id,
+                end_id,
+                version_in_range,
+                new_text,
+                local_timestamp,
+                lamport_timestamp,
+                ..
+            } => {
+                self.check_timestamps(*local_timestamp, *lamport_timestamp)?;
+                if local_timestamp.replica_id != replica_id {
+                    return Err(malformed("buffer operation was wrapped by another replica"));
+                }
+
+                self.check_replica(start_id.replica_id)?;
+                self.check_replica(end_id.replica_id)?;
+                for replica_id in version_in_range.replica_ids() {
+                    self.check_replica(replica_id)?;
+                }
+
+                if new_text
+                    .as_ref()
+                    .map_or(false, |text| text.len() > self.max_text_len)
+                {
+                    return Err(malformed("inserted text is too long"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Quarantine {
+    pub fn new(limits: OperationLimits) -> Self {
+        Quarantine {
+            limits,
+            replicas: HashMap::new(),
+        }
+    }
+
+    pub fn limits(&self) -> &OperationLimits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: OperationLimits) {
+        self.limits = limits;
+    }
+
+    pub fn contains(&self, replica_id: ReplicaId) -> bool {
+        self.replicas.contains_key(&replica_id)
+    }
+
+    /// Returns the quarantined replicas along with the reason their first rejected operation was
+    /// considered invalid.
+    pub fn replicas(&self) -> impl Iterator<Item = (ReplicaId, &str)> {
+        self.replicas
+            .iter()
+            .map(|(replica_id, reason)| (*replica_id, reason.as_ref()))
+    }
+
+    pub(crate) fn insert(&mut self, replica_id: ReplicaId, error: Error) {
+        let reason = match error {
+            Error::MalformedOperation(reason) => reason,
+            error => format!("{:?}", error).into(),
+        };
+        self.replicas.entry(replica_id).or_insert(reason);
+    }
+
+    /// Accepts operations from the given replica again. Operations that were discarded while it
+    /// was quarantined are not recovered.
+    pub fn release(&mut self, replica_id: ReplicaId) -> bool {
+        self.replicas.remove(&replica_id).is_some()
+    }
+
+    pub(crate) fn merge(&mut self, other: &Quarantine) {
+        self.limits = other.limits.clone();
+        for (replica_id, reason) in &other.replicas {
+            self.replicas
+                .entry(*replica_id)
+                .or_insert_with(|| reason.clone());
+        }
+    }
+}
+
+pub(crate) fn malformed<T: Into<Cow<'static, str>>>(reason: T) -> Error {
+    Error::MalformedOperation(reason.into())
+}
@@ -1,26 +1,45 @@
-use crate::buffer::{self, Change, Point, Text};
-use crate::epoch::{self, Cursor, DirEntry, Epoch, FileId, FileType};
+use crate::buffer::{self, Anchor, BufferStats, Change, DiffMode, Point, Selection, Text, ToPoint};
+use crate::epoch::{self, Cursor, CursorFilter, DirEntry, Epoch, EpochStats, FileId, FileType};
+use crate::history::{self, CommitInfo, ReplayedCommit};
+use crate::merge_policy::{MergeConflict, MergePolicy};
+use crate::operation_queue::DeferredOperation;
+use crate::patch::{self, FilePatch, Hunk, Patch, PatchApplication, PatchRejection};
 use crate::serialization;
-use crate::{time, Error, Oid, ReplicaId};
+use crate::text_format::TextFormat;
+use crate::user::{User, UserRegistry};
+use crate::validation::OperationLimits;
+use crate::{time, Error, Oid, ReplicaId, UserId};
 use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use futures::unsync::mpsc::{self, UnboundedSender};
 use futures::{future, stream, Async, Future, Poll, Stream};
 use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::cell::{Ref, RefCell, RefMut};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
 use std::mem;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::vec;
 
 pub trait GitProvider {
     fn base_entries(&self, oid: Oid) -> Box<Stream<Item = DirEntry, Error = io::Error>>;
     fn base_text(&self, oid: Oid, path: &Path) -> Box<Future<Item = String, Error = io::Error>>;
+
+    /// Describes a commit. Only needed to replay history with `WorkTree::from_history`.
+    fn commit_info(&self, _oid: Oid) -> Box<Future<Item = CommitInfo, Error = io::Error>> {
+        Box::new(future::err(io::Error::new(
+            io::ErrorKind::Other,
+            "commit history is not available",
+        )))
+    }
 }
 
 pub trait ChangeObserver {
     fn text_changed(&self, buffer_id: BufferId, changes: Box<Iterator<Item = Change>>);
+    fn merge_conflict(&self, _buffer_id: BufferId, _conflict: MergeConflict) {}
 }
 
 pub struct WorkTree {
@@ -29,8 +48,32 @@ pub struct WorkTree {
     next_buffer_id: Rc<RefCell<BufferId>>,
     deferred_ops: Rc<RefCell<HashMap<epoch::Id, Vec<epoch::Operation>>>>,
     lamport_clock: Rc<RefCell<time::Lamport>>,
+    users: Rc<RefCell<UserRegistry>>,
     git: Rc<GitProvider>,
     observer: Option<Rc<ChangeObserver>>,
+    requested_ops: RefCell<HashMap<(epoch::Id, ReplicaId), u64>>,
+    operation_request_txs: RefCell<Vec<UnboundedSender<OperationRequest>>>,
+    fixup_op_txs: Rc<RefCell<Vec<UnboundedSender<OperationEnvelope>>>>,
+}
+
+/// Asks peers to resend operations from another replica, because operations we've received
+/// depend on them and can't be applied until they arrive.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct OperationRequest {
+    pub epoch_id: epoch::Id,
+    pub replica_id: ReplicaId,
+    /// The local timestamp of the earliest operation needed. Every operation the replica
+    /// generated in the epoch from this timestamp on should be resent.
+    pub since: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkTreeStats {
+    pub epoch: EpochStats,
+    /// Operations held until we start the epoch they belong to.
+    pub future_epoch_ops: usize,
+    pub open_buffers: usize,
+    pub users: usize,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,6 +97,11 @@ pub enum Operation {
         epoch_id: epoch::Id,
         operation: epoch::Operation,
     },
+    SetUser {
+        epoch_id: epoch::Id,
+        user: User,
+        lamport_timestamp: time::Lamport,
+    },
 }
 
 #[derive(Copy, Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -105,8 +153,12 @@ impl WorkTree {
             next_buffer_id: Rc::new(RefCell::new(BufferId(0))),
             deferred_ops: Rc::new(RefCell::new(HashMap::new())),
             lamport_clock: Rc::new(RefCell::new(time::Lamport::new(replica_id))),
+            users: Rc::new(RefCell::new(UserRegistry::new())),
             git,
             observer,
+            requested_ops: RefCell::new(HashMap::new()),
+            operation_request_txs: RefCell::new(Vec::new()),
+            fixup_op_txs: Rc::new(RefCell::new(Vec::new())),
         };
 
         let ops = if ops.peek().is_none() {
@@ -118,6 +170,157 @@ impl WorkTree {
         Ok((tree, ops))
     }
 
+    /// Creates a work tree from the `max_commits` most recent commits leading up to `head`, with
+    /// one epoch per commit. Each epoch is based on the commit's first parent and contains the
+    /// commit's changes replayed as operations, preceded by the announcement of its author as
+    /// the user of this replica in that epoch, which `user_in_epoch` reports. The returned tree
+    /// is left in the epoch of `head`.
+    pub fn from_history(
+        replica_id: ReplicaId,
+        head: Oid,
+        max_commits: usize,
+        git: Rc<GitProvider>,
+        observer: Option<Rc<ChangeObserver>>,
+    ) -> Box<Future<Item = (WorkTree, Vec<ReplayedCommit>), Error = Error>> {
+        let (tree, ops) = match Self::new(replica_id, None, Vec::new(), git.clone(), observer) {
+            Ok(result) => result,
+            Err(error) => return Box::new(future::err(error)),
+        };
+
+        Box::new(
+            ops.collect()
+                .join(history::load_commits(git, head, max_commits))
+                .and_then(move |(_, commits)| {
+                    future::loop_fn(
+                        (tree, commits.into_iter(), Vec::new()),
+                        |(tree, mut commits, mut replayed): (
+                            WorkTree,
+                            vec::IntoIter<CommitInfo>,
+                            Vec<ReplayedCommit>,
+                        )| match commits.next() {
+                            Some(commit) => future::Either::A(tree.replay_commit(commit).map(
+                                move |(tree, commit)| {
+                                    replayed.push(commit);
+                                    future::Loop::Continue((tree, commits, replayed))
+                                },
+                            )),
+                            None => {
+                                future::Either::B(future::ok(future::Loop::Break((tree, replayed))))
+                            }
+                        },
+                    )
+                }),
+        )
+    }
+
+    fn replay_commit(
+        mut self,
+        commit: CommitInfo,
+    ) -> Box<Future<Item = (WorkTree, ReplayedCommit), Error = Error>> {
+        let git = self.git.clone();
+        let start_epoch = self.reset(commit.parent).collect();
+        let old_paths = history::base_paths(git.as_ref(), commit.parent);
+        let new_paths = history::base_paths(git.as_ref(), Some(commit.oid));
+        Box::new(start_epoch.join3(old_paths, new_paths).and_then(
+            move |(mut operations, old_paths, new_paths)| {
+                let changes = history::tree_changes(&old_paths, &new_paths);
+                let mut load_texts = Vec::new();
+                for (path, file_type) in &changes.created {
+                    if *file_type == FileType::Text {
+                        load_texts.push((path.clone(), None));
+                    }
+                }
+                for path in &commit.changed_paths {
+                    let is_text = |paths: &BTreeMap<PathBuf, FileType>| {
+                        paths.get(path) == Some(&FileType::Text)
+                    };
+                    let created = changes.created.iter().any(|(created, _)| created == path);
+                    if is_text(&old_paths) && is_text(&new_paths) && !created {
+                        load_texts.push((path.clone(), commit.parent));
+                    }
+                }
+                let load_texts = load_texts
+                    .into_iter()
+                    .map(|(path, base)| {
+                        let old_text: Box<Future<Item = String, Error = io::Error>> = match base {
+                            Some(base) => git.base_text(base, &path),
+                            None => Box::new(future::ok(String::new())),
+                        };
+                        old_text
+                            .join(git.base_text(commit.oid, &path))
+                            .map(move |(old_text, new_text)| (path, old_text, new_text))
+                    })
+                    .collect::<Vec<_>>();
+
+                operations.push(self.set_user(commit.author()));
+                future::result(self.replay_tree_changes(&changes, &mut operations))
+                    .and_then(move |_| {
+                        future::join_all(load_texts).map_err(|error| Error::IoError(error))
+                    })
+                    .and_then(move |texts| {
+                        self.replay_edits(texts, &mut operations)?;
+                        let epoch_id = self.cur_epoch().id;
+                        Ok((
+                            self,
+                            ReplayedCommit {
+                                commit,
+                                epoch_id,
+                                operations,
+                            },
+                        ))
+                    })
+            },
+        ))
+    }
+
+    fn replay_tree_changes(
+        &self,
+        changes: &history::TreeChanges,
+        operations: &mut Vec<OperationEnvelope>,
+    ) -> Result<(), Error> {
+        for path in &changes.removed {
+            operations.push(self.remove(path)?);
+        }
+        for (path, file_type) in &changes.created {
+            operations.push(self.create_file(path, *file_type)?);
+        }
+        Ok(())
+    }
+
+    fn replay_edits(
+        &self,
+        texts: Vec<(PathBuf, String, String)>,
+        operations: &mut Vec<OperationEnvelope>,
+    ) -> Result<(), Error> {
+        let mut cur_epoch = self.cur_epoch_mut();
+        let mut lamport_clock = self.lamport_clock.borrow_mut();
+        for (path, old_text, new_text) in texts {
+            let file_id = cur_epoch.file_id(&path)?;
+            let fixup_ops = cur_epoch.open_text_file(file_id, old_text, &mut lamport_clock)?;
+            operations.extend(OperationEnvelope::wrap_many(
+                cur_epoch.id,
+                cur_epoch.head,
+                fixup_ops,
+            ));
+            let old_text = cur_epoch.text(file_id)?.into_string();
+            let (_, new_text) = TextFormat::detect(&new_text);
+            for change in buffer::diff(&old_text, &new_text, DiffMode::Line) {
+                let operation = cur_epoch.edit_2d(
+                    file_id,
+                    Some(change.range),
+                    change.text,
+                    &mut lamport_clock,
+                )?;
+                operations.push(OperationEnvelope::wrap(
+                    cur_epoch.id,
+                    cur_epoch.head,
+                    operation,
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn head(&self) -> Option<Oid> {
         self.epoch.as_ref().and_then(|e| e.borrow().head)
     }
@@ -147,7 +350,9 @@ impl WorkTree {
         for op in ops {
             match op {
                 Operation::StartEpoch { epoch_id, head } => {
-                    epoch_streams.push(self.start_epoch(epoch_id, head));
+                    if !self.is_quarantined(epoch_id.replica_id) {
+                        epoch_streams.push(self.start_epoch(epoch_id, head));
+                    }
                 }
                 Operation::EpochOperation {
                     epoch_id,
@@ -163,6 +368,13 @@ impl WorkTree {
                         self.defer_epoch_op(epoch_id, operation);
                     }
                 }
+                // Identities outlive the epoch they were announced in, so they are applied
+                // regardless of the epoch we are currently in.
+                Operation::SetUser {
+                    epoch_id,
+                    user,
+                    lamport_timestamp,
+                } => self.apply_user_op(epoch_id, user, lamport_timestamp),
             }
         }
 
@@ -190,9 +402,25 @@ impl WorkTree {
                 }
             }
 
+            for conflict in epoch.take_merge_conflicts() {
+                let buffer_id = self
+                    .buffers
+                    .borrow()
+                    .iter()
+                    .find(|(_, file_id)| **file_id == conflict.file_id)
+                    .map(|(buffer_id, _)| *buffer_id);
+                if let (Some(buffer_id), Some(observer)) = (buffer_id, self.observer.as_ref()) {
+                    drop(epoch);
+                    observer.merge_conflict(buffer_id, conflict);
+                    epoch = epoch_ref.borrow_mut();
+                }
+            }
+
             let fixup_ops_stream = Box::new(stream::iter_ok(OperationEnvelope::wrap_many(
                 epoch.id, epoch.head, fixup_ops,
             )));
+            drop(epoch);
+            self.request_missing_ops();
             Ok(epoch_streams.into_iter().fold(
                 fixup_ops_stream as Box<Stream<Item = OperationEnvelope, Error = Error>>,
                 |acc, stream| Box::new(acc.chain(stream)),
@@ -202,6 +430,51 @@ impl WorkTree {
         }
     }
 
+    fn apply_user_op(&self, epoch_id: epoch::Id, user: User, lamport_timestamp: time::Lamport) {
+        // Only this replica can announce its own identity.
+        let replica_id = lamport_timestamp.replica_id;
+        if replica_id == self.replica_id() || self.is_quarantined(replica_id) {
+            return;
+        }
+
+        let result = match self.epoch.as_ref() {
+            Some(epoch) => epoch
+                .borrow()
+                .quarantine()
+                .limits()
+                .check_user(&user, lamport_timestamp),
+            None => OperationLimits::default().check_user(&user, lamport_timestamp),
+        };
+        match result {
+            Ok(()) => {
+                self.lamport_clock.borrow_mut().observe(lamport_timestamp);
+                self.users.borrow_mut().set(user, epoch_id, lamport_timestamp);
+            }
+            Err(error) => {
+                if let Some(epoch) = self.epoch.as_ref() {
+                    epoch.borrow_mut().quarantine_replica(replica_id, error);
+                }
+            }
+        }
+    }
+
+    // Announces every identity we know of again, so that replicas joining in the given epoch
+    // learn about users who announced themselves before it started.
+    fn user_ops(&self, epoch_id: epoch::Id, head: Option<Oid>) -> Vec<OperationEnvelope> {
+        self.users
+            .borrow()
+            .announcements()
+            .map(|(user, lamport_timestamp)| OperationEnvelope {
+                epoch_head: head,
+                operation: Operation::SetUser {
+                    epoch_id,
+                    user: user.clone(),
+                    lamport_timestamp,
+                },
+            })
+            .collect()
+    }
+
     fn start_epoch(
         &mut self,
         new_epoch_id: epoch::Id,
@@ -243,6 +516,7 @@ impl WorkTree {
                 Box::new(stream::empty())
             };
 
+            let user_ops = stream::iter_ok(self.user_ops(new_epoch_id, new_head));
             if let Some(cur_epoch) = self.epoch.clone() {
                 let switch_epoch = SwitchEpoch::new(
                     new_epoch,
@@ -255,10 +529,10 @@ impl WorkTree {
                 )
                 .then(|fixup_ops| Ok(stream::iter_ok(fixup_ops?)))
                 .flatten_stream();
-                Box::new(load_base_entries.chain(switch_epoch))
+                Box::new(user_ops.chain(load_base_entries).chain(switch_epoch))
             } else {
                 self.epoch = Some(new_epoch.clone());
-                load_base_entries
+                Box::new(user_ops.chain(load_base_entries))
             }
         } else {
             Box::new(stream::empty())
@@ -282,6 +556,69 @@ impl WorkTree {
         }
     }
 
+    /// Replaces the limits that operations received from other replicas must respect. Replicas
+    /// sending operations that exceed these limits or that would corrupt the tree are
+    /// quarantined, and all of their subsequent operations are ignored.
+    pub fn set_operation_limits(&self, limits: OperationLimits) {
+        self.cur_epoch_mut().quarantine_mut().set_limits(limits);
+    }
+
+    pub fn quarantined_replicas(&self) -> Vec<(ReplicaId, String)> {
+        self.cur_epoch()
+            .quarantine()
+            .replicas()
+            .map(|(replica_id, reason)| (replica_id, reason.to_string()))
+            .collect()
+    }
+
+    pub fn release_replica(&self, replica_id: ReplicaId) -> bool {
+        self.cur_epoch_mut().quarantine_mut().release(replica_id)
+    }
+
+    fn is_quarantined(&self, replica_id: ReplicaId) -> bool {
+        self.epoch.as_ref().map_or(false, |epoch| {
+            epoch.borrow().quarantine().contains(replica_id)
+        })
+    }
+
+    /// Announces the identity of the user operating this replica to other replicas.
+    pub fn set_user(&self, user: User) -> OperationEnvelope {
+        let lamport_timestamp = self.lamport_clock.borrow_mut().tick();
+        let cur_epoch = self.cur_epoch();
+        self.users
+            .borrow_mut()
+            .set(user.clone(), cur_epoch.id, lamport_timestamp);
+        OperationEnvelope {
+            epoch_head: cur_epoch.head,
+            operation: Operation::SetUser {
+                epoch_id: cur_epoch.id,
+                user,
+                lamport_timestamp,
+            },
+        }
+    }
+
+    pub fn user(&self, replica_id: ReplicaId) -> Option<User> {
+        self.users.borrow().get(replica_id).cloned()
+    }
+
+    /// Returns the user that operated the given replica in the given epoch, such as the author
+    /// of a commit replayed by `from_history`.
+    pub fn user_in_epoch(&self, replica_id: ReplicaId, epoch_id: epoch::Id) -> Option<User> {
+        self.users
+            .borrow()
+            .get_in_epoch(replica_id, epoch_id)
+            .cloned()
+    }
+
+    pub fn users(&self) -> Vec<(ReplicaId, User)> {
+        self.users
+            .borrow()
+            .iter()
+            .map(|(replica_id, user)| (replica_id, user.clone()))
+            .collect()
+    }
+
     pub fn with_cursor<F>(&self, mut f: F)
     where
         F: FnMut(&mut Cursor),
@@ -291,6 +628,15 @@ impl WorkTree {
         }
     }
 
+    pub fn with_filtered_cursor<F>(&self, filter: CursorFilter, mut f: F)
+    where
+        F: FnMut(&mut Cursor),
+    {
+        if let Some(mut cursor) = self.cur_epoch().filtered_cursor(filter) {
+            f(&mut cursor);
+        }
+    }
+
     pub fn create_file<P>(&self, path: P, file_type: FileType) -> Result<OperationEnvelope, Error>
     where
         P: AsRef<Path>,
@@ -352,6 +698,41 @@ impl WorkTree {
         ))
     }
 
+    /// Copies a file, or a directory along with its contents. Copied text files are only read
+    /// from their source when they are first opened.
+    pub fn copy<P1, P2>(&self, old_path: P1, new_path: P2) -> Result<OperationEnvelope, Error>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let old_path = old_path.as_ref();
+        let new_path = new_path.as_ref();
+
+        let mut cur_epoch = self.cur_epoch_mut();
+        let source_id = cur_epoch.file_id(old_path)?;
+        let new_name = new_path
+            .file_name()
+            .ok_or(Error::InvalidPath("new path has no file name".into()))?;
+        let new_parent_id = if let Some(parent_path) = new_path.parent() {
+            cur_epoch.file_id(parent_path)?
+        } else {
+            epoch::ROOT_FILE_ID
+        };
+
+        let operation = cur_epoch.copy(
+            source_id,
+            new_parent_id,
+            new_name,
+            &mut self.lamport_clock.borrow_mut(),
+        )?;
+
+        Ok(OperationEnvelope::wrap(
+            cur_epoch.id,
+            cur_epoch.head,
+            operation,
+        ))
+    }
+
     pub fn remove<P>(&self, path: P) -> Result<OperationEnvelope, Error>
     where
         P: AsRef<Path>,
@@ -374,6 +755,29 @@ impl WorkTree {
         self.cur_epoch().file_id(path).is_ok()
     }
 
+    /// Assigns a merge policy to all text files matching the given glob pattern.
+    pub fn set_merge_policy(
+        &self,
+        pattern: &str,
+        policy: MergePolicy,
+    ) -> Result<OperationEnvelope, Error> {
+        let mut cur_epoch = self.cur_epoch_mut();
+        let operation =
+            cur_epoch.set_merge_policy(pattern, policy, &mut self.lamport_clock.borrow_mut())?;
+        Ok(OperationEnvelope::wrap(
+            cur_epoch.id,
+            cur_epoch.head,
+            operation,
+        ))
+    }
+
+    pub fn merge_policy<P>(&self, path: P) -> MergePolicy
+    where
+        P: AsRef<Path>,
+    {
+        self.cur_epoch().merge_policies().policy_for(path)
+    }
+
     pub fn open_text_file<P>(&self, path: P) -> Box<Future<Item = BufferId, Error = Error>>
     where
         P: Into<PathBuf>,
@@ -385,6 +789,7 @@ impl WorkTree {
             self.buffers.clone(),
             self.next_buffer_id.clone(),
             self.lamport_clock.clone(),
+            self.fixup_op_txs.clone(),
         )
     }
 
@@ -395,6 +800,7 @@ impl WorkTree {
         buffers: Rc<RefCell<HashMap<BufferId, FileId>>>,
         next_buffer_id: Rc<RefCell<BufferId>>,
         lamport_clock: Rc<RefCell<time::Lamport>>,
+        fixup_op_txs: Rc<RefCell<Vec<UnboundedSender<OperationEnvelope>>>>,
     ) -> Box<Future<Item = BufferId, Error = Error>> {
         if let Some(buffer_id) = Self::existing_buffer(&epoch, &buffers, &path) {
             Box::new(future::ok(buffer_id))
@@ -406,12 +812,14 @@ impl WorkTree {
                         if let Some(buffer_id) = Self::existing_buffer(&epoch, &buffers, &path) {
                             Box::new(future::ok(buffer_id))
                         } else if epoch.borrow().id == epoch_id {
-                            match epoch.borrow_mut().open_text_file(
+                            let result = epoch.borrow_mut().open_text_file(
                                 file_id,
                                 base_text,
                                 &mut lamport_clock.borrow_mut(),
-                            ) {
-                                Ok(()) => {
+                            );
+                            match result {
+                                Ok(fixup_ops) => {
+                                    Self::send_fixup_ops(&fixup_op_txs, &epoch.borrow(), fixup_ops);
                                     let buffer_id = *next_buffer_id.borrow();
                                     next_buffer_id.borrow_mut().0 += 1;
                                     buffers.borrow_mut().insert(buffer_id, file_id);
@@ -427,6 +835,7 @@ impl WorkTree {
                                 buffers,
                                 next_buffer_id,
                                 lamport_clock,
+                                fixup_op_txs,
                             )
                         }
                     },
@@ -435,6 +844,20 @@ impl WorkTree {
         }
     }
 
+    fn send_fixup_ops(
+        txs: &RefCell<Vec<UnboundedSender<OperationEnvelope>>>,
+        epoch: &Epoch,
+        fixup_ops: Vec<epoch::Operation>,
+    ) {
+        let mut txs = txs.borrow_mut();
+        for operation in fixup_ops {
+            txs.retain(|tx| {
+                let envelope = OperationEnvelope::wrap(epoch.id, epoch.head, operation.clone());
+                tx.unbounded_send(envelope).is_ok()
+            });
+        }
+    }
+
     fn existing_buffer(
         epoch: &Rc<RefCell<Epoch>>,
         buffers: &Rc<RefCell<HashMap<BufferId, FileId>>>,
@@ -457,9 +880,12 @@ impl WorkTree {
         git: &GitProvider,
     ) -> Box<Future<Item = (FileId, String), Error = Error>> {
         let epoch = epoch.borrow();
-        match epoch.file_id(&path) {
-            Ok(file_id) => {
-                if let (Some(head), Some(base_path)) = (epoch.head, epoch.base_path(file_id)) {
+        match epoch
+            .file_id(&path)
+            .and_then(|file_id| Ok((file_id, epoch.text_source(file_id)?)))
+        {
+            Ok((file_id, text_source)) => {
+                if let (Some(head), Some(base_path)) = (epoch.head, epoch.base_path(text_source)) {
                     Box::new(
                         git.base_text(head, &base_path)
                             .map_err(|err| Error::IoError(err))
@@ -501,14 +927,15 @@ impl WorkTree {
         ))
     }
 
-    pub fn edit_2d<I, T>(
+    pub fn edit_2d<I, P, T>(
         &self,
         buffer_id: BufferId,
         old_ranges: I,
         new_text: T,
     ) -> Result<OperationEnvelope, Error>
     where
-        I: IntoIterator<Item = Range<Point>>,
+        I: IntoIterator<Item = Range<P>>,
+        P: ToPoint,
         T: Into<Text>,
     {
         let file_id = self.buffer_file_id(buffer_id)?;
@@ -529,6 +956,158 @@ impl WorkTree {
         ))
     }
 
+    /// Applies a unified diff to the current epoch. Files that can't be created, renamed or
+    /// removed and hunks whose lines can't be found are reported instead of failing the patch.
+    pub fn apply_patch(
+        &self,
+        patch: &Patch,
+    ) -> Box<Future<Item = PatchApplication, Error = Error>> {
+        let mut operations = Vec::new();
+        let mut rejections = Vec::new();
+        let mut to_edit = Vec::new();
+        for file in &patch.files {
+            let path = file
+                .new_path
+                .as_ref()
+                .or(file.old_path.as_ref())
+                .cloned()
+                .unwrap_or_default();
+            match self.apply_file_patch(file, &mut operations) {
+                Ok(true) if !file.hunks.is_empty() => to_edit.push((path, file.hunks.clone())),
+                Ok(_) => {}
+                Err(error) => rejections.push(PatchRejection::file(path, error.to_string())),
+            }
+        }
+
+        // Patched files are edited through the epoch rather than opened as buffers, so that
+        // applying a patch doesn't leave buffers open that nobody will close.
+        let epoch = self.epoch.clone().unwrap();
+        let lamport_clock = self.lamport_clock.clone();
+        let epoch_id = epoch.borrow().id;
+        let base_texts = to_edit.into_iter().map(|(path, hunks)| {
+            Self::base_text(&path, &epoch, self.git.as_ref())
+                .then(move |result| Ok::<_, Error>((path, hunks, result)))
+        });
+        Box::new(future::join_all(base_texts).map(move |files| {
+            for (path, hunks, base_text) in files {
+                let result: Result<_, Cow<'static, str>> = match base_text {
+                    Ok(_) if epoch.borrow().id != epoch_id => {
+                        Err("work tree was reset while applying the patch".into())
+                    }
+                    Ok((file_id, base_text)) => {
+                        Self::apply_hunks(&epoch, &lamport_clock, file_id, base_text, &path, &hunks)
+                            .map_err(|error| error.to_string().into())
+                    }
+                    Err(error) => Err(error.to_string().into()),
+                };
+
+                match result {
+                    Ok((hunk_operations, hunk_rejections)) => {
+                        operations.extend(hunk_operations);
+                        rejections.extend(hunk_rejections);
+                    }
+                    Err(reason) => {
+                        rejections.extend(hunks.into_iter().map(|hunk| PatchRejection {
+                            path: path.clone(),
+                            hunk: Some(hunk),
+                            reason: reason.clone(),
+                            found: Vec::new(),
+                        }));
+                    }
+                }
+            }
+
+            PatchApplication {
+                operations,
+                rejections,
+            }
+        }))
+    }
+
+    /// Creates, renames or removes the file targeted by a file patch, returning whether its
+    /// hunks should then be applied.
+    fn apply_file_patch(
+        &self,
+        file: &FilePatch,
+        operations: &mut Vec<OperationEnvelope>,
+    ) -> Result<bool, Error> {
+        match (&file.old_path, &file.new_path) {
+            (None, Some(new_path)) => {
+                if self.exists(new_path) {
+                    return Err(Error::InvalidPath("file already exists".into()));
+                }
+                self.create_parent_dirs(new_path, operations)?;
+                operations.push(self.create_file(new_path, FileType::Text)?);
+                Ok(true)
+            }
+            (Some(old_path), None) => {
+                operations.push(self.remove(old_path)?);
+                Ok(false)
+            }
+            (Some(old_path), Some(new_path)) => {
+                if old_path != new_path {
+                    self.create_parent_dirs(new_path, operations)?;
+                    operations.push(self.rename(old_path, new_path)?);
+                }
+                Ok(true)
+            }
+            (None, None) => Ok(false),
+        }
+    }
+
+    fn create_parent_dirs(
+        &self,
+        path: &Path,
+        operations: &mut Vec<OperationEnvelope>,
+    ) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() && !self.exists(parent) {
+                self.create_parent_dirs(parent, operations)?;
+                operations.push(self.create_file(parent, FileType::Directory)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_hunks(
+        epoch: &RefCell<Epoch>,
+        lamport_clock: &RefCell<time::Lamport>,
+        file_id: FileId,
+        base_text: String,
+        path: &Path,
+        hunks: &[Hunk],
+    ) -> Result<(Vec<OperationEnvelope>, Vec<PatchRejection>), Error> {
+        let mut epoch = epoch.borrow_mut();
+        let fixup_ops =
+            epoch.open_text_file(file_id, base_text, &mut lamport_clock.borrow_mut())?;
+        let mut operations = OperationEnvelope::wrap_many(epoch.id, epoch.head, fixup_ops);
+        let text = epoch.text(file_id)?.into_string();
+        let resolution = patch::resolve_hunks(&text, hunks);
+
+        // Edit from the end of the file so that the remaining ranges stay valid.
+        for (range, new_text) in resolution.edits.into_iter().rev() {
+            let operation = epoch.edit_2d(
+                file_id,
+                Some(range),
+                new_text,
+                &mut lamport_clock.borrow_mut(),
+            )?;
+            operations.push(OperationEnvelope::wrap(epoch.id, epoch.head, operation));
+        }
+
+        let rejections = resolution
+            .rejected
+            .into_iter()
+            .map(|(hunk_ix, found)| PatchRejection {
+                path: path.to_path_buf(),
+                hunk: Some(hunks[hunk_ix].clone()),
+                reason: "hunk lines were not found".into(),
+                found,
+            })
+            .collect();
+        Ok((operations, rejections))
+    }
+
     pub fn path(&self, buffer_id: BufferId) -> Option<PathBuf> {
         self.buffers
             .borrow()
@@ -541,6 +1120,16 @@ impl WorkTree {
         self.cur_epoch().text(file_id)
     }
 
+    pub fn text_format(&self, buffer_id: BufferId) -> Result<TextFormat, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().text_format(file_id).map(Clone::clone)
+    }
+
+    pub fn export_text(&self, buffer_id: BufferId) -> Result<Vec<u8>, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().export_text(file_id)
+    }
+
     pub fn changes_since(
         &self,
         buffer_id: BufferId,
@@ -550,11 +1139,170 @@ impl WorkTree {
         self.cur_epoch().changes_since(file_id, version)
     }
 
+    /// The version of a buffer, which can later be passed to `changes_since` to find out what
+    /// has changed. Versions are only meaningful within the epoch they were obtained in.
+    pub fn buffer_version(&self, buffer_id: BufferId) -> Result<time::Global, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch()
+            .buffer_version(file_id)
+            .ok_or_else(|| Error::InvalidFileId("file has not been opened".into()))
+    }
+
+    pub fn anchor_before_point(&self, buffer_id: BufferId, point: Point) -> Result<Anchor, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().anchor_before_point(file_id, point)
+    }
+
+    pub fn anchor_after_point(&self, buffer_id: BufferId, point: Point) -> Result<Anchor, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().anchor_after_point(file_id, point)
+    }
+
+    pub fn point_for_anchor(&self, buffer_id: BufferId, anchor: &Anchor) -> Result<Point, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().point_for_anchor(file_id, anchor)
+    }
+
+    /// Adds a set of selections to a buffer. Selection sets aren't replicated, and like anchors
+    /// they are forgotten when the work tree moves to a new epoch.
+    pub fn add_selection_set(
+        &self,
+        buffer_id: BufferId,
+        user_id: UserId,
+        selections: Vec<Selection>,
+    ) -> Result<time::Local, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch_mut()
+            .add_selection_set(file_id, user_id, selections)
+    }
+
+    pub fn replace_selections(
+        &self,
+        buffer_id: BufferId,
+        set_id: time::Local,
+        selections: Vec<Selection>,
+    ) -> Result<(), Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch_mut()
+            .replace_selections(file_id, set_id, selections)
+    }
+
+    pub fn remove_selection_set(
+        &self,
+        buffer_id: BufferId,
+        set_id: time::Local,
+    ) -> Result<(), Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch_mut().remove_selection_set(file_id, set_id)
+    }
+
+    pub fn selections(
+        &self,
+        buffer_id: BufferId,
+        set_id: time::Local,
+    ) -> Result<Vec<Selection>, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().selections(file_id, set_id)
+    }
+
     pub fn buffer_deferred_ops_len(&self, buffer_id: BufferId) -> Result<usize, Error> {
         let file_id = self.buffer_file_id(buffer_id)?;
         self.cur_epoch().buffer_deferred_ops_len(file_id)
     }
 
+    pub fn buffer_stats(&self, buffer_id: BufferId) -> Result<BufferStats, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().buffer_stats(file_id)
+    }
+
+    /// Reports the size of the work tree's state, to help diagnose sessions that retain a lot of
+    /// memory.
+    pub fn stats(&self) -> WorkTreeStats {
+        WorkTreeStats {
+            epoch: self.cur_epoch().stats(),
+            future_epoch_ops: self
+                .deferred_ops
+                .borrow()
+                .values()
+                .map(|ops| ops.len())
+                .sum(),
+            open_buffers: self.buffers.borrow().len(),
+            users: self.users.borrow().iter().count(),
+        }
+    }
+
+    pub fn buffer_deferred_ops(
+        &self,
+        buffer_id: BufferId,
+    ) -> Result<Vec<DeferredOperation>, Error> {
+        let file_id = self.buffer_file_id(buffer_id)?;
+        self.cur_epoch().buffer_deferred_ops(file_id)
+    }
+
+    /// Describes the operations on the tree's structure that are waiting on operations we haven't
+    /// received.
+    pub fn deferred_ops(&self) -> Vec<DeferredOperation> {
+        self.cur_epoch().deferred_ops()
+    }
+
+    /// Lists the operations that deferred operations in the current epoch are waiting on. This
+    /// can be used to retry requests from `operation_requests` that peers didn't answer.
+    pub fn missing_operations(&self) -> Vec<OperationRequest> {
+        let epoch = self.cur_epoch();
+        epoch
+            .missing_ops_since()
+            .into_iter()
+            .map(|since| OperationRequest {
+                epoch_id: epoch.id,
+                replica_id: since.replica_id,
+                since: since.value,
+            })
+            .collect()
+    }
+
+    /// Returns a stream of requests for operations that deferred operations are waiting on. The
+    /// stream starts with the operations that are currently missing, and a new request is sent
+    /// whenever applying operations reveals that we're missing earlier ones.
+    pub fn operation_requests(&self) -> impl Stream<Item = OperationRequest, Error = ()> {
+        let (tx, rx) = mpsc::unbounded();
+        for request in self.missing_operations() {
+            let _ = tx.unbounded_send(request);
+        }
+        self.operation_request_txs.borrow_mut().push(tx);
+        rx
+    }
+
+    /// Returns a stream of the operations this replica generates on its own rather than in
+    /// response to a call that returns them, such as the deletions a file's merge policy requires
+    /// once opening it applies edits received earlier. They must be broadcast to other replicas.
+    pub fn fixup_operations(&self) -> impl Stream<Item = OperationEnvelope, Error = ()> {
+        let (tx, rx) = mpsc::unbounded();
+        self.fixup_op_txs.borrow_mut().push(tx);
+        rx
+    }
+
+    fn request_missing_ops(&self) {
+        let requests = self.missing_operations();
+        let mut requested_ops = self.requested_ops.borrow_mut();
+        requested_ops.retain(|(epoch_id, replica_id), _| {
+            requests
+                .iter()
+                .any(|request| request.epoch_id == *epoch_id && request.replica_id == *replica_id)
+        });
+
+        let mut txs = self.operation_request_txs.borrow_mut();
+        for request in requests {
+            let key = (request.epoch_id, request.replica_id);
+            if requested_ops
+                .get(&key)
+                .map_or(true, |since| request.since < *since)
+            {
+                requested_ops.insert(key, request.since);
+                txs.retain(|tx| tx.unbounded_send(request.clone()).is_ok());
+            }
+        }
+    }
+
     fn cur_epoch(&self) -> Ref<Epoch> {
         self.epoch.as_ref().unwrap().borrow()
     }
@@ -629,6 +1377,7 @@ impl Operation {
         match self {
             Operation::StartEpoch { epoch_id, .. } => *epoch_id,
             Operation::EpochOperation { epoch_id, .. } => *epoch_id,
+            Operation::SetUser { epoch_id, .. } => *epoch_id,
         }
     }
 
@@ -653,7 +1402,8 @@ impl Operation {
     ) -> WIPOffset<serialization::worktree::Operation<'fbb>> {
         use crate::serialization::worktree::{
             EpochOperation, EpochOperationArgs, Operation as OperationFlatbuf, OperationArgs,
-            OperationVariant, StartEpoch, StartEpochArgs,
+            OperationVariant, SetUser, SetUserArgs, StartEpoch, StartEpochArgs,
+            User as UserFlatbuf, UserArgs,
         };
 
         let variant_type;
@@ -662,7 +1412,7 @@ impl Operation {
         match self {
             Operation::StartEpoch { epoch_id, head } => {
                 variant_type = OperationVariant::StartEpoch;
-                let head = head.map(|head| builder.create_vector(&head));
+                let head = head.map(|head| builder.create_vector(head.as_bytes()));
                 variant = StartEpoch::create(
                     builder,
                     &StartEpochArgs {
@@ -688,6 +1438,31 @@ impl Operation {
                 )
                 .as_union_value();
             }
+            Operation::SetUser {
+                epoch_id,
+                user,
+                lamport_timestamp,
+            } => {
+                variant_type = OperationVariant::SetUser;
+                let display_name = builder.create_string(&user.display_name);
+                let user = UserFlatbuf::create(
+                    builder,
+                    &UserArgs {
+                        id: user.id,
+                        display_name: Some(display_name),
+                        color: user.color,
+                    },
+                );
+                variant = SetUser::create(
+                    builder,
+                    &SetUserArgs {
+                        epoch_id: Some(&epoch_id.to_flatbuf()),
+                        user: Some(user),
+                        lamport_timestamp: Some(&lamport_timestamp.to_flatbuf()),
+                    },
+                )
+                .as_union_value();
+            }
         }
 
         OperationFlatbuf::create(
@@ -702,7 +1477,9 @@ impl Operation {
     pub fn from_flatbuf<'fbb>(
         message: serialization::worktree::Operation<'fbb>,
     ) -> Result<Option<Self>, Error> {
-        use crate::serialization::worktree::{EpochOperation, OperationVariant, StartEpoch};
+        use crate::serialization::worktree::{
+            EpochOperation, OperationVariant, SetUser, StartEpoch,
+        };
 
         let variant = message.variant().ok_or(Error::DeserializeError)?;
         match message.variant_type() {
@@ -711,11 +1488,10 @@ impl Operation {
                 let epoch_id = message.epoch_id().ok_or(Error::DeserializeError)?;
                 Ok(Some(Operation::StartEpoch {
                     epoch_id: time::Lamport::from_flatbuf(epoch_id),
-                    head: message.head().map(|head| {
-                        let mut oid = [0; 20];
-                        oid.copy_from_slice(head);
-                        oid
-                    }),
+                    head: match message.head() {
+                        Some(head) => Some(Oid::from_bytes(head)?),
+                        None => None,
+                    },
                 }))
             }
             OperationVariant::EpochOperation => {
@@ -733,6 +1509,25 @@ impl Operation {
                     Ok(None)
                 }
             }
+            OperationVariant::SetUser => {
+                let message = SetUser::init_from_table(variant);
+                let epoch_id = message.epoch_id().ok_or(Error::DeserializeError)?;
+                let user = message.user().ok_or(Error::DeserializeError)?;
+                let lamport_timestamp =
+                    message.lamport_timestamp().ok_or(Error::DeserializeError)?;
+                Ok(Some(Operation::SetUser {
+                    epoch_id: time::Lamport::from_flatbuf(epoch_id),
+                    user: User {
+                        id: user.id(),
+                        display_name: user
+                            .display_name()
+                            .ok_or(Error::DeserializeError)?
+                            .to_string(),
+                        color: user.color(),
+                    },
+                    lamport_timestamp: time::Lamport::from_flatbuf(lamport_timestamp),
+                }))
+            }
             OperationVariant::NONE => Ok(None),
         }
     }
@@ -826,13 +1621,19 @@ impl Future for SwitchEpoch {
 
             if is_done {
                 let mut fixup_ops = Vec::new();
+                let quarantine = cur_epoch.quarantine().clone();
+                to_assign.quarantine_mut().merge(&quarantine);
 
                 let mut buffer_mappings = Vec::with_capacity(self.base_text_requests.len());
                 for (buffer_id, request) in self.base_text_requests.drain() {
                     if let Some(request) = request {
                         let base_text = request.future.take_result().unwrap()?;
                         let new_file_id = to_assign.file_id(request.path).unwrap();
-                        to_assign.open_text_file(new_file_id, base_text, &mut lamport_clock)?;
+                        fixup_ops.extend(OperationEnvelope::wrap_many(
+                            to_assign.id,
+                            to_assign.head,
+                            to_assign.open_text_file(new_file_id, base_text, &mut lamport_clock)?,
+                        ));
                         buffer_mappings.push((buffer_id, new_file_id));
                     } else {
                         // TODO: This may be okay for now, but I think we should take a smarter
@@ -876,7 +1677,7 @@ impl Future for SwitchEpoch {
                 for (buffer_id, new_file_id) in buffer_mappings {
                     let old_text = cur_epoch.text(buffers[&buffer_id])?.into_string();
                     let new_text = to_assign.text(new_file_id)?.into_string();
-                    let mut changes = buffer::diff(&old_text, &new_text).peekable();
+                    let mut changes = buffer::diff(&old_text, &new_text, DiffMode::Line).peekable();
                     if changes.peek().is_some() {
                         buffer_changes.push((buffer_id, changes));
                     }
@@ -940,7 +1741,10 @@ impl<F: Future> MaybeDone<F> {
 mod tests {
     use super::*;
     use crate::epoch::CursorEntry;
+    use crate::HashAlgorithm;
     use rand::{Rng, SeedableRng, StdRng};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::{env, fs, process};
     use uuid::Uuid;
 
     #[test]
@@ -1053,6 +1857,7 @@ mod tests {
             for replica_index in 0..PEERS {
                 let tree = &trees[replica_index];
                 let observer = &observers[replica_index];
+                assert_eq!(tree.quarantined_replicas(), Vec::new());
                 for buffer_id in tree.open_buffers() {
                     assert_eq!(
                         observer.text(buffer_id),
@@ -1285,6 +2090,551 @@ mod tests {
         assert!(tree_2.observed(tree_1.version()));
     }
 
+    #[test]
+    fn test_stats() {
+        let tree = WorkTree::empty();
+        tree.create_file("a", FileType::Directory).unwrap();
+        tree.create_file("a/b", FileType::Text).unwrap();
+        let b = tree.open_text_file("a/b").wait().unwrap();
+        tree.edit(b, Some(0..0), "abcdef").unwrap();
+        tree.edit(b, Some(1..3), "").unwrap();
+
+        let buffer_stats = tree.buffer_stats(b).unwrap();
+        assert_eq!(buffer_stats.tombstone_bytes, 2);
+        assert_eq!(buffer_stats.visible_bytes, 4);
+
+        let stats = tree.stats();
+        assert_eq!(stats.epoch.files, 2);
+        assert_eq!(stats.epoch.buffered_files, 1);
+        assert_eq!(stats.epoch.buffers, buffer_stats);
+        assert_eq!(stats.open_buffers, 1);
+        assert_eq!(stats.future_epoch_ops, 0);
+    }
+
+    #[test]
+    fn test_operation_requests() {
+        let git = Rc::new(TestGitProvider::new());
+        let commit = git.commit(&WorkTree::empty());
+        let replica_1 = Uuid::from_u128(1);
+        let replica_2 = Uuid::from_u128(2);
+        let (tree_1, ops_1) =
+            WorkTree::new(replica_1, Some(commit), vec![], git.clone(), None).unwrap();
+        let (mut tree_2, ops_2) = WorkTree::new(
+            replica_2,
+            Some(commit),
+            open_envelopes(ops_1.collect().wait().unwrap()),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+
+        let create_op = tree_1.create_file("a", FileType::Text).unwrap().operation;
+        let a = tree_1.open_text_file("a").wait().unwrap();
+        let edit_op = tree_1.edit(a, Some(0..0), "x").unwrap().operation;
+
+        // The edit can't be applied until we receive the operation that created the file.
+        let requests = tree_2.operation_requests();
+        tree_2
+            .apply_ops(vec![edit_op])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.deferred_ops().len(), 1);
+        let missing = tree_2.missing_operations();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].replica_id, replica_1);
+
+        tree_2
+            .apply_ops(vec![create_op])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert!(tree_2.deferred_ops().is_empty());
+        assert!(tree_2.missing_operations().is_empty());
+        assert!(tree_2.exists("a"));
+
+        drop(tree_2);
+        assert_eq!(requests.collect().wait().unwrap(), missing);
+    }
+
+    #[test]
+    fn test_selections() {
+        let git = Rc::new(TestGitProvider::new());
+        let commit = git.commit(&WorkTree::empty());
+        let (tree_1, ops_1) =
+            WorkTree::new(Uuid::from_u128(1), Some(commit), vec![], git.clone(), None).unwrap();
+        let (mut tree_2, ops_2) = WorkTree::new(
+            Uuid::from_u128(2),
+            Some(commit),
+            open_envelopes(ops_1.collect().wait().unwrap()),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+
+        let create_op = tree_1.create_file("a", FileType::Text).unwrap().operation;
+        let a_1 = tree_1.open_text_file("a").wait().unwrap();
+        let edit_op = tree_1.edit(a_1, Some(0..0), "abc\ndef").unwrap().operation;
+        tree_2
+            .apply_ops(vec![create_op, edit_op])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        let a_2 = tree_2.open_text_file("a").wait().unwrap();
+
+        let start = tree_1.anchor_before_point(a_1, Point::new(0, 1)).unwrap();
+        let end = tree_1.anchor_after_point(a_1, Point::new(1, 2)).unwrap();
+        let cursor = tree_1.anchor_before_point(a_1, Point::new(1, 3)).unwrap();
+        let set_id = tree_1
+            .add_selection_set(
+                a_1,
+                1,
+                vec![
+                    selection(cursor.clone(), cursor.clone()),
+                    selection(start, end),
+                ],
+            )
+            .unwrap();
+        let version = tree_1.buffer_version(a_1).unwrap();
+
+        let remote_edit_op = tree_2.edit(a_2, Some(0..0), "xy\n").unwrap().operation;
+        tree_1
+            .apply_ops(vec![remote_edit_op])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(
+            tree_1
+                .changes_since(a_1, version)
+                .unwrap()
+                .map(|change| (change.range, change.text))
+                .collect::<Vec<_>>(),
+            vec![(Point::new(0, 0)..Point::new(0, 0), "xy\n".to_string())]
+        );
+
+        // Selections are kept in order and follow the text they were anchored to.
+        let selections = tree_1.selections(a_1, set_id).unwrap();
+        let points = selections
+            .iter()
+            .map(|selection| {
+                tree_1.point_for_anchor(a_1, &selection.start).unwrap()
+                    ..tree_1.point_for_anchor(a_1, &selection.end).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            vec![
+                Point::new(1, 1)..Point::new(2, 2),
+                Point::new(2, 3)..Point::new(2, 3),
+            ]
+        );
+
+        tree_1
+            .replace_selections(a_1, set_id, vec![selection(cursor.clone(), cursor)])
+            .unwrap();
+        assert_eq!(tree_1.selections(a_1, set_id).unwrap().len(), 1);
+
+        // Anchors into text this replica hasn't seen are refused rather than resolved.
+        tree_2.edit(a_2, Some(0..0), "z").unwrap();
+        let unknown = tree_2.anchor_before_point(a_2, Point::new(0, 1)).unwrap();
+        assert!(tree_1.point_for_anchor(a_1, &unknown).is_err());
+        assert!(tree_1
+            .replace_selections(a_1, set_id, vec![selection(unknown.clone(), unknown)])
+            .is_err());
+
+        tree_1.remove_selection_set(a_1, set_id).unwrap();
+        assert!(tree_1.selections(a_1, set_id).is_err());
+        assert!(tree_1.remove_selection_set(a_1, set_id).is_err());
+    }
+
+    #[test]
+    fn test_users() {
+        let git = Rc::new(TestGitProvider::new());
+        let commit = git.commit(&WorkTree::empty());
+        let replica_1 = Uuid::from_u128(1);
+        let replica_2 = Uuid::from_u128(2);
+        let (mut tree_1, ops_1) =
+            WorkTree::new(replica_1, Some(commit), vec![], git.clone(), None).unwrap();
+        let (tree_2, ops_2) = WorkTree::new(
+            replica_2,
+            Some(commit),
+            open_envelopes(ops_1.collect().wait().unwrap()),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+
+        let alice = User {
+            id: 1,
+            display_name: "Alice".into(),
+            color: 0xff0000,
+        };
+        let op = tree_1.set_user(alice.clone()).operation;
+        assert_eq!(tree_1.user(replica_1), Some(alice.clone()));
+        tree_2
+            .apply_ops(deserialize_ops(serialize_ops(Some(op))))
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.user(replica_1), Some(alice.clone()));
+        assert_eq!(tree_2.user(replica_2), None);
+
+        let alice_renamed = User {
+            display_name: "Alice B.".into(),
+            ..alice.clone()
+        };
+        let op_1 = tree_1.set_user(alice.clone()).operation;
+        let op_2 = tree_1.set_user(alice_renamed.clone()).operation;
+        tree_2
+            .apply_ops(vec![op_2, op_1])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.users(), vec![(replica_1, alice_renamed.clone())]);
+
+        // Nobody else can announce our identity, and replicas announcing an identity they aren't
+        // allowed to are quarantined.
+        let replica_3 = Uuid::from_u128(3);
+        let mallory = User {
+            id: 3,
+            display_name: "Mallory".into(),
+            color: 0x00ff00,
+        };
+        let epoch_id = tree_2.cur_epoch().id;
+        tree_2.set_operation_limits(OperationLimits {
+            known_replicas: Some(vec![replica_1, replica_2].into_iter().collect()),
+            ..OperationLimits::default()
+        });
+        tree_2
+            .apply_ops(vec![
+                Operation::SetUser {
+                    epoch_id,
+                    user: mallory.clone(),
+                    lamport_timestamp: time::Lamport {
+                        replica_id: replica_2,
+                        value: 100,
+                    },
+                },
+                Operation::SetUser {
+                    epoch_id,
+                    user: mallory,
+                    lamport_timestamp: time::Lamport {
+                        replica_id: replica_3,
+                        value: 100,
+                    },
+                },
+            ])
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.user(replica_2), None);
+        assert_eq!(tree_2.user(replica_3), None);
+        let quarantined = tree_2.quarantined_replicas();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].0, replica_3);
+
+        // Replicas joining a later epoch learn about identities announced before it started.
+        let reset_ops = open_envelopes(tree_1.reset(Some(commit)).collect().wait().unwrap());
+        let (tree_3, _) =
+            WorkTree::new(Uuid::from_u128(4), Some(commit), reset_ops, git.clone(), None).unwrap();
+        assert_eq!(tree_3.users(), vec![(replica_1, alice_renamed)]);
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let git = Rc::new(TestGitProvider::new());
+        let base_tree = WorkTree::empty();
+        base_tree.create_file("a", FileType::Text).unwrap();
+        base_tree.create_file("b", FileType::Text).unwrap();
+        base_tree.create_file("c", FileType::Text).unwrap();
+        let a_base = base_tree.open_text_file("a").wait().unwrap();
+        base_tree
+            .edit(a_base, Some(0..0), "one\ntwo\nthree\nfour\n")
+            .unwrap();
+        let commit = git.commit(&base_tree);
+
+        let (tree_1, ops_1) =
+            WorkTree::new(Uuid::from_u128(1), Some(commit), vec![], git.clone(), None).unwrap();
+        let (mut tree_2, ops_2) = WorkTree::new(
+            Uuid::from_u128(2),
+            Some(commit),
+            open_envelopes(ops_1.collect().wait().unwrap()),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+
+        // Move the lines targeted by the patch down.
+        let a_1 = tree_1.open_text_file("a").wait().unwrap();
+        let edit_op = tree_1.edit(a_1, Some(0..0), "zero\n").unwrap();
+
+        let patch = Patch::parse(
+            "diff --git a/a b/a\n\
+             --- a/a\n\
+             +++ b/a\n\
+             @@ -1,2 +1,2 @@\n\
+             \x20one\n\
+             -two\n\
+             +TWO\n\
+             @@ -4 +4 @@\n\
+             -missing\n\
+             +FOUR\n\
+             diff --git a/b b/b\n\
+             deleted file mode 100644\n\
+             diff --git a/c b/d/e\n\
+             rename from c\n\
+             rename to d/e\n\
+             diff --git a/f b/f\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/f\n\
+             @@ -0,0 +1 @@\n\
+             +new\n\
+             diff --git a/g b/g\n\
+             --- a/g\n\
+             +++ b/g\n\
+             @@ -1 +1 @@\n\
+             -g\n\
+             +G\n",
+        )
+        .unwrap();
+        let application = tree_1.apply_patch(&patch).wait().unwrap();
+        // Only buffers that were already open remain open.
+        assert!(tree_1.open_buffers() == vec![a_1]);
+        assert_eq!(tree_1.text_str(a_1), "zero\none\nTWO\nthree\nfour\n");
+        assert!(!tree_1.exists("b"));
+        assert!(!tree_1.exists("c"));
+        assert!(tree_1.exists("d/e"));
+        let f_1 = tree_1.open_text_file("f").wait().unwrap();
+        assert_eq!(tree_1.text_str(f_1), "new\n");
+
+        let rejections = application
+            .rejections
+            .iter()
+            .map(|rejection| (rejection.path.clone(), rejection.found.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rejections,
+            vec![
+                (PathBuf::from("a"), vec!["four\n".to_string()]),
+                (PathBuf::from("g"), vec![])
+            ]
+        );
+
+        let mut ops = vec![edit_op.operation];
+        ops.extend(open_envelopes(application.operations));
+        tree_2
+            .apply_ops(deserialize_ops(serialize_ops(ops)))
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.entries(), tree_1.entries());
+        let a_2 = tree_2.open_text_file("a").wait().unwrap();
+        assert_eq!(tree_2.text_str(a_2), tree_1.text_str(a_1));
+        let f_2 = tree_2.open_text_file("f").wait().unwrap();
+        assert_eq!(tree_2.text_str(f_2), "new\n");
+    }
+
+    #[test]
+    fn test_copy() {
+        let git = Rc::new(TestGitProvider::new());
+        let base_tree = WorkTree::empty();
+        base_tree.create_file("a", FileType::Directory).unwrap();
+        base_tree.create_file("a/b", FileType::Text).unwrap();
+        base_tree.create_file("a/c", FileType::Directory).unwrap();
+        base_tree.create_file("a/c/d", FileType::Text).unwrap();
+        let b_base = base_tree.open_text_file("a/b").wait().unwrap();
+        base_tree.edit(b_base, Some(0..0), "abc").unwrap();
+        let d_base = base_tree.open_text_file("a/c/d").wait().unwrap();
+        base_tree.edit(d_base, Some(0..0), "def").unwrap();
+        let commit = git.commit(&base_tree);
+
+        let (tree_1, ops_1) =
+            WorkTree::new(Uuid::from_u128(1), Some(commit), vec![], git.clone(), None).unwrap();
+        let (mut tree_2, ops_2) = WorkTree::new(
+            Uuid::from_u128(2),
+            Some(commit),
+            open_envelopes(ops_1.collect().wait().unwrap()),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+
+        let mut ops = Vec::new();
+        let b_1 = tree_1.open_text_file("a/b").wait().unwrap();
+        ops.push(tree_1.edit(b_1, Some(0..0), "x").unwrap().operation);
+        ops.push(tree_1.copy("a", "e").unwrap().operation);
+        ops.push(tree_1.edit(b_1, Some(4..4), "y").unwrap().operation);
+        assert!(tree_1.copy("a", "a/c/a").is_err());
+        assert!(tree_1.exists("a/c/d"));
+        assert!(tree_1.exists("e/c/d"));
+
+        // Copies contain their source's text as it was when the copy was made.
+        let e_b_1 = tree_1.open_text_file("e/b").wait().unwrap();
+        assert_eq!(tree_1.text_str(e_b_1), "xabc");
+        let e_d_1 = tree_1.open_text_file("e/c/d").wait().unwrap();
+        assert_eq!(tree_1.text_str(e_d_1), "def");
+
+        tree_2
+            .apply_ops(deserialize_ops(serialize_ops(ops)))
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(tree_2.dir_entries(), tree_1.dir_entries());
+
+        // A copy of a copy that hasn't been opened yet starts from the original base text.
+        tree_2.copy("e/c/d", "f").unwrap();
+        let f_2 = tree_2.open_text_file("f").wait().unwrap();
+        assert_eq!(tree_2.text_str(f_2), "def");
+        let e_b_2 = tree_2.open_text_file("e/b").wait().unwrap();
+        assert_eq!(tree_2.text_str(e_b_2), "xabc");
+        let b_2 = tree_2.open_text_file("a/b").wait().unwrap();
+        assert_eq!(tree_2.text_str(b_2), "xabcy");
+    }
+
+    #[test]
+    fn test_from_history() {
+        let git = Rc::new(TestGitProvider::new());
+        let base_tree = WorkTree::empty();
+        base_tree.create_file("a", FileType::Text).unwrap();
+        let a_base = base_tree.open_text_file("a").wait().unwrap();
+        base_tree.edit(a_base, Some(0..0), "abc").unwrap();
+        let commit_0 = git.commit(&base_tree);
+        git.describe(commit_0, None, "Alice", &["a"]);
+
+        base_tree.edit(a_base, Some(1..2), "def").unwrap();
+        base_tree.create_file("b", FileType::Directory).unwrap();
+        base_tree.create_file("b/c", FileType::Text).unwrap();
+        let c_base = base_tree.open_text_file("b/c").wait().unwrap();
+        base_tree.edit(c_base, Some(0..0), "ghi").unwrap();
+        let commit_1 = git.commit(&base_tree);
+        git.describe(commit_1, Some(commit_0), "Bob", &["a", "b/c"]);
+
+        base_tree.remove("a").unwrap();
+        base_tree.edit(c_base, Some(0..0), "jkl").unwrap();
+        let commit_2 = git.commit(&base_tree);
+        git.describe(commit_2, Some(commit_1), "Alice", &["b/c"]);
+
+        let replica_1 = Uuid::from_u128(1);
+        let (tree_1, replayed) = WorkTree::from_history(replica_1, commit_2, 2, git.clone(), None)
+            .wait()
+            .unwrap();
+        assert_eq!(
+            replayed.iter().map(|r| r.commit.oid).collect::<Vec<_>>(),
+            vec![commit_1, commit_2]
+        );
+        assert!(replayed[0].epoch_id < replayed[1].epoch_id);
+        assert_ne!(replayed[0].commit.author(), replayed[1].commit.author());
+        assert_eq!(tree_1.head(), Some(commit_1));
+        assert_eq!(tree_1.dir_entries(), git.tree(commit_2).dir_entries());
+        assert_eq!(tree_1.user(replica_1), Some(replayed[1].commit.author()));
+        let authors = replayed
+            .iter()
+            .map(|r| (r.epoch_id, r.commit.author()))
+            .collect::<Vec<_>>();
+        for (epoch_id, author) in &authors {
+            assert_eq!(tree_1.user_in_epoch(replica_1, *epoch_id).as_ref(), Some(author));
+        }
+        let c_1 = tree_1.open_text_file("b/c").wait().unwrap();
+        assert_eq!(tree_1.text_str(c_1), "jklghi");
+
+        // Another replica catches up by applying the replayed operations.
+        let ops = replayed
+            .into_iter()
+            .flat_map(|commit| open_envelopes(commit.operations))
+            .collect::<Vec<_>>();
+        let (tree_2, ops_2) = WorkTree::new(
+            Uuid::from_u128(2),
+            None,
+            deserialize_ops(serialize_ops(ops)),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        ops_2.collect().wait().unwrap();
+        assert_eq!(tree_2.entries(), tree_1.entries());
+        assert_eq!(tree_2.user(replica_1), tree_1.user(replica_1));
+        for (epoch_id, author) in &authors {
+            assert_eq!(tree_2.user_in_epoch(replica_1, *epoch_id).as_ref(), Some(author));
+        }
+        let c_2 = tree_2.open_text_file("b/c").wait().unwrap();
+        assert_eq!(tree_2.text_str(c_2), "jklghi");
+    }
+
+    #[test]
+    fn test_from_history_on_disk() {
+        let git = Rc::new(CliGitProvider::init());
+        let commit_0 = git.commit("Alice", &[("a", Some("abc"))]);
+        let commit_1 = git.commit("Bob", &[("a", Some("adefc")), ("b/c", Some("ghi"))]);
+        let commit_2 = git.commit("Alice", &[("a", None), ("b/c", Some("jklghi"))]);
+
+        let replica_1 = Uuid::from_u128(1);
+        let (tree_1, replayed) = WorkTree::from_history(replica_1, commit_2, 2, git.clone(), None)
+            .wait()
+            .unwrap();
+        assert_eq!(
+            replayed.iter().map(|r| r.commit.oid).collect::<Vec<_>>(),
+            vec![commit_1, commit_2]
+        );
+        assert_eq!(replayed[0].commit.parent, Some(commit_0));
+        assert_eq!(tree_1.head(), Some(commit_1));
+        assert_eq!(
+            tree_1.dir_entries(),
+            git.base_entries(commit_2).collect().wait().unwrap()
+        );
+        let authors = replayed
+            .iter()
+            .map(|r| tree_1.user_in_epoch(replica_1, r.epoch_id).unwrap().display_name)
+            .collect::<Vec<_>>();
+        assert_eq!(authors, vec!["Bob", "Alice"]);
+        let c_1 = tree_1.open_text_file("b/c").wait().unwrap();
+        assert_eq!(tree_1.text_str(c_1), "jklghi");
+    }
+
+    #[test]
+    fn test_sha256_oids() {
+        let git = Rc::new(TestGitProvider::with_algorithm(HashAlgorithm::Sha256));
+        let base_tree = WorkTree::empty();
+        base_tree.create_file("a", FileType::Text).unwrap();
+        let a_base = base_tree.open_text_file("a").wait().unwrap();
+        base_tree.edit(a_base, Some(0..0), "abc").unwrap();
+        let commit = git.commit(&base_tree);
+        assert_eq!(commit.algorithm(), HashAlgorithm::Sha256);
+
+        let (tree_1, ops_1) =
+            WorkTree::new(Uuid::from_u128(1), Some(commit), vec![], git.clone(), None).unwrap();
+        let ops_1 = serialize_ops(open_envelopes(ops_1.collect().wait().unwrap()));
+        let (tree_2, ops_2) = WorkTree::new(
+            Uuid::from_u128(2),
+            Some(commit),
+            deserialize_ops(ops_1),
+            git.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(ops_2.wait().next().is_none());
+        assert_eq!(tree_1.head(), Some(commit));
+        assert_eq!(tree_2.head(), Some(commit));
+        assert_eq!(tree_2.dir_entries(), git.tree(commit).dir_entries());
+
+        let a_2 = tree_2.open_text_file("a").wait().unwrap();
+        assert_eq!(tree_2.text_str(a_2), "abc");
+    }
+
     fn open_envelopes<I: IntoIterator<Item = OperationEnvelope>>(envelopes: I) -> Vec<Operation> {
         envelopes.into_iter().map(|e| e.operation).collect()
     }
@@ -1365,7 +2715,9 @@ mod tests {
 
     struct TestGitProvider {
         commits: RefCell<HashMap<Oid, WorkTree>>,
+        commit_infos: RefCell<HashMap<Oid, CommitInfo>>,
         next_oid: RefCell<u64>,
+        algorithm: HashAlgorithm,
     }
 
     struct TestChangeObserver {
@@ -1376,9 +2728,15 @@ mod tests {
 
     impl TestGitProvider {
         fn new() -> Self {
+            Self::with_algorithm(HashAlgorithm::Sha1)
+        }
+
+        fn with_algorithm(algorithm: HashAlgorithm) -> Self {
             TestGitProvider {
                 commits: RefCell::new(HashMap::new()),
+                commit_infos: RefCell::new(HashMap::new()),
                 next_oid: RefCell::new(0),
+                algorithm,
             }
         }
 
@@ -1395,13 +2753,32 @@ mod tests {
             oid
         }
 
+        fn describe(
+            &self,
+            oid: Oid,
+            parent: Option<Oid>,
+            author_name: &str,
+            changed_paths: &[&str],
+        ) {
+            let commit = CommitInfo {
+                oid,
+                parent,
+                author_name: author_name.into(),
+                author_email: format!("{}@example.com", author_name.to_lowercase()),
+                timestamp: 0,
+                message: String::new(),
+                changed_paths: changed_paths.iter().map(PathBuf::from).collect(),
+            };
+            self.commit_infos.borrow_mut().insert(oid, commit);
+        }
+
         fn tree(&self, oid: Oid) -> Ref<WorkTree> {
             Ref::map(self.commits.borrow(), |commits| commits.get(&oid).unwrap())
         }
 
         fn gen_oid(&self) -> Oid {
             let mut next_oid = self.next_oid.borrow_mut();
-            let mut oid = [0; 20];
+            let mut oid = [0; 32];
             oid[0] = (*next_oid >> 0) as u8;
             oid[1] = (*next_oid >> 8) as u8;
             oid[2] = (*next_oid >> 16) as u8;
@@ -1411,7 +2788,10 @@ mod tests {
             oid[6] = (*next_oid >> 48) as u8;
             oid[7] = (*next_oid >> 56) as u8;
             *next_oid += 1;
-            oid
+            match self.algorithm {
+                HashAlgorithm::Sha1 => Oid::from_bytes(&oid[0..20]).unwrap(),
+                HashAlgorithm::Sha256 => Oid::Sha256(oid),
+            }
         }
     }
 
@@ -1452,6 +2832,167 @@ mod tests {
                     .into_future(),
             )
         }
+
+        fn commit_info(&self, oid: Oid) -> Box<Future<Item = CommitInfo, Error = io::Error>> {
+            match self.commit_infos.borrow().get(&oid) {
+                Some(commit) => Box::new(future::ok(commit.clone())),
+                None => Box::new(future::err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Commit does not exist",
+                ))),
+            }
+        }
+    }
+
+    // Reads commits from a repository on disk through the git command line.
+    struct CliGitProvider {
+        path: PathBuf,
+    }
+
+    impl CliGitProvider {
+        fn init() -> Self {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .subsec_nanos();
+            let path = env::temp_dir().join(format!("nano-history-{}-{}", process::id(), nanos));
+            fs::create_dir_all(&path).unwrap();
+            let provider = CliGitProvider { path };
+            provider.git(&["init", "--quiet"]);
+            provider
+        }
+
+        fn commit(&self, author_name: &str, files: &[(&str, Option<&str>)]) -> Oid {
+            for (path, text) in files {
+                let path = self.path.join(path);
+                match text {
+                    Some(text) => {
+                        fs::create_dir_all(path.parent().unwrap()).unwrap();
+                        fs::write(&path, text).unwrap();
+                    }
+                    None => fs::remove_file(&path).unwrap(),
+                }
+            }
+            self.git(&["add", "--all"]);
+            self.git(&[
+                "-c",
+                &format!("user.name={}", author_name),
+                "-c",
+                &format!("user.email={}@example.com", author_name.to_lowercase()),
+                "commit",
+                "--quiet",
+                "--message",
+                author_name,
+            ]);
+            Self::parse_oid(self.git(&["rev-parse", "HEAD"]).trim())
+        }
+
+        fn git(&self, args: &[&str]) -> String {
+            let output = process::Command::new("git")
+                .args(&["-c", "commit.gpgsign=false"])
+                .args(args)
+                .current_dir(&self.path)
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8(output.stdout).unwrap()
+        }
+
+        fn parse_oid(hex: &str) -> Oid {
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>();
+            Oid::from_bytes(&bytes).unwrap()
+        }
+    }
+
+    impl Drop for CliGitProvider {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    impl GitProvider for CliGitProvider {
+        fn base_entries(&self, oid: Oid) -> Box<Stream<Item = DirEntry, Error = io::Error>> {
+            let entries = self
+                .git(&["ls-tree", "-r", "-t", "-z", &oid.to_string()])
+                .split('\0')
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let (info, path) = line.split_at(line.find('\t').unwrap());
+                    let path = Path::new(&path[1..]);
+                    DirEntry {
+                        depth: path.components().count(),
+                        name: path.file_name().unwrap().to_os_string(),
+                        file_type: if info.contains(" tree ") {
+                            FileType::Directory
+                        } else {
+                            FileType::Text
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            Box::new(stream::iter_ok(entries))
+        }
+
+        fn base_text(
+            &self,
+            oid: Oid,
+            path: &Path,
+        ) -> Box<Future<Item = String, Error = io::Error>> {
+            let object = format!("{}:{}", oid, path.display());
+            Box::new(future::ok(self.git(&["show", &object])))
+        }
+
+        fn commit_info(&self, oid: Oid) -> Box<Future<Item = CommitInfo, Error = io::Error>> {
+            let oid_hex = oid.to_string();
+            let output = self.git(&["show", "-s", "--format=%P%n%an%n%ae%n%at%n%B", &oid_hex]);
+            let mut lines = output.lines();
+            let parent = lines
+                .next()
+                .unwrap()
+                .split(' ')
+                .next()
+                .filter(|parent| !parent.is_empty())
+                .map(Self::parse_oid);
+            let author_name = lines.next().unwrap().to_string();
+            let author_email = lines.next().unwrap().to_string();
+            let timestamp = lines.next().unwrap().parse().unwrap();
+            let message = lines.collect::<Vec<_>>().join("\n");
+            let changed_paths = match parent {
+                Some(parent) => self
+                    .git(&[
+                        "diff-tree",
+                        "-r",
+                        "-z",
+                        "--no-renames",
+                        "--diff-filter=AM",
+                        "--name-only",
+                        &parent.to_string(),
+                        &oid_hex,
+                    ])
+                    .split('\0')
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect(),
+                None => Vec::new(),
+            };
+            Box::new(future::ok(CommitInfo {
+                oid,
+                parent,
+                author_name,
+                author_email,
+                timestamp,
+                message,
+                changed_paths,
+            }))
+        }
     }
 
     impl TestChangeObserver {
@@ -1494,7 +3035,7 @@ mod tests {
                 for change in changes {
                     buffer.edit_2d(
                         Some(change.range),
-                        change.code_units,
+                        change.text,
                         &mut self.local_clock.borrow_mut(),
                         &mut self.lamport_clock.borrow_mut(),
                     );
@@ -1503,6 +3044,15 @@ mod tests {
         }
     }
 
+    fn selection(start: Anchor, end: Anchor) -> Selection {
+        Selection {
+            start,
+            end,
+            reversed: false,
+            goal_column: None,
+        }
+    }
+
     fn gen_text<T: Rng>(rng: &mut T) -> String {
         let text_len = rng.gen_range(0, 50);
         let mut text: String = rng.gen_ascii_chars().take(text_len).collect();
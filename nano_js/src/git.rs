@@ -0,0 +1,708 @@
+use crate::{FileSystem, JsValueExt};
+use futures::{future, stream, Future, Stream};
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+use nano_core as nano;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::str;
+use wasm_bindgen::JsCast;
+
+const TREE_MODE: u32 = 0o040000;
+const SYMLINK_MODE: u32 = 0o120000;
+const SUBMODULE_MODE: u32 = 0o160000;
+/// Git writes delta chains of at most 50 objects by default, so a chain this long most likely
+/// comes from deltas whose bases refer back to each other.
+const MAX_DELTA_DEPTH: usize = 1000;
+
+/// Reads commits, trees and blobs from a repository on disk, in loose files as well as in
+/// packs. Files are read through a `FileSystem` supplied by JavaScript, so the same code serves
+/// Node's `fs` module and in-memory file systems.
+pub struct Repository {
+    fs: FileSystem,
+    git_dir: String,
+    hash_len: usize,
+    packs: RefCell<Option<Rc<Vec<Pack>>>>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+struct Object {
+    kind: ObjectKind,
+    data: Vec<u8>,
+}
+
+struct Pack {
+    data: Vec<u8>,
+    /// The id and offset of every object in the pack, sorted by id.
+    entries: Vec<(Vec<u8>, usize)>,
+    /// The offset of every object in the pack, sorted, so that each object's compressed data can
+    /// be bounded by the start of the next object.
+    offsets: Vec<usize>,
+}
+
+struct TreeEntry {
+    mode: u32,
+    name: String,
+    oid: Vec<u8>,
+}
+
+struct Commit {
+    tree: nano::Oid,
+    parent: Option<nano::Oid>,
+    author_name: String,
+    author_email: String,
+    timestamp: i64,
+    message: String,
+}
+
+impl Repository {
+    /// Opens the repository at `path`, which is either a work tree containing a `.git`
+    /// directory or file, or the git directory itself.
+    pub fn open(fs: FileSystem, path: &str) -> io::Result<Self> {
+        let path = path.trim_end_matches('/');
+        let dot_git = format!("{}/.git", path);
+        let git_dir = if read_dir(&fs, &dot_git)?.is_some() {
+            dot_git
+        } else if let Some(contents) = read_file(&fs, &dot_git)? {
+            // Linked work trees and submodules point to their git directory from a file.
+            let contents = String::from_utf8(contents).map_err(invalid_data)?;
+            let git_dir = contents
+                .trim()
+                .trim_start_matches("gitdir:")
+                .trim()
+                .to_string();
+            if git_dir.starts_with('/') {
+                git_dir
+            } else {
+                format!("{}/{}", path, git_dir)
+            }
+        } else {
+            path.to_string()
+        };
+
+        if read_file(&fs, &format!("{}/HEAD", git_dir))?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a git repository", path),
+            ));
+        }
+
+        let config = read_file(&fs, &format!("{}/config", git_dir))?.unwrap_or_default();
+        let config = String::from_utf8_lossy(&config);
+        let is_sha256 = config.lines().any(|line| {
+            let mut setting = line.split('=').map(|part| part.trim().to_lowercase());
+            setting.next().as_ref().map(String::as_str) == Some("objectformat")
+                && setting.next().as_ref().map(String::as_str) == Some("sha256")
+        });
+
+        Ok(Repository {
+            fs,
+            git_dir,
+            hash_len: if is_sha256 { 32 } else { 20 },
+            packs: RefCell::new(None),
+        })
+    }
+
+    /// The commit that `HEAD` points to, or `None` in a repository without commits.
+    pub fn head(&self) -> io::Result<Option<nano::Oid>> {
+        self.resolve_ref("HEAD", 0)
+    }
+
+    fn resolve_ref(&self, name: &str, depth: usize) -> io::Result<Option<nano::Oid>> {
+        if depth > 10 {
+            return Err(invalid_data(format!("ref {} is nested too deeply", name)));
+        }
+
+        let contents = match self.read_git_file(name)? {
+            Some(contents) => Some(String::from_utf8(contents).map_err(invalid_data)?),
+            None => self.packed_ref(name)?,
+        };
+        match contents {
+            Some(ref contents) if contents.starts_with("ref:") => {
+                self.resolve_ref(contents["ref:".len()..].trim(), depth + 1)
+            }
+            Some(contents) => parse_hex_oid(contents.trim()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn packed_ref(&self, name: &str) -> io::Result<Option<String>> {
+        if let Some(packed_refs) = self.read_git_file("packed-refs")? {
+            let packed_refs = String::from_utf8(packed_refs).map_err(invalid_data)?;
+            for line in packed_refs.lines() {
+                if line.starts_with('#') || line.starts_with('^') {
+                    continue;
+                }
+                let mut fields = line.split(' ');
+                if let (Some(oid), Some(ref_name)) = (fields.next(), fields.next()) {
+                    if ref_name == name {
+                        return Ok(Some(oid.to_string()));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_git_file(&self, path: &str) -> io::Result<Option<Vec<u8>>> {
+        read_file(&self.fs, &format!("{}/{}", self.git_dir, path))
+    }
+
+    fn read_object(&self, oid: &[u8]) -> io::Result<Object> {
+        self.read_object_at_depth(oid, 0)
+    }
+
+    // Reads an object that is the base of `depth` deltas being resolved.
+    fn read_object_at_depth(&self, oid: &[u8], depth: usize) -> io::Result<Object> {
+        let hex_oid = hex::encode(oid);
+        let loose_path = format!("objects/{}/{}", &hex_oid[..2], &hex_oid[2..]);
+        if let Some(compressed) = self.read_git_file(&loose_path)? {
+            let data = inflate(&compressed)?;
+            let header_len = data
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or_else(|| invalid_data(format!("object {} has no header", hex_oid)))?;
+            let kind = {
+                let header = str::from_utf8(&data[..header_len]).map_err(invalid_data)?;
+                object_kind(header.split(' ').next().unwrap_or(""))?
+            };
+            return Ok(Object {
+                kind,
+                data: data[header_len + 1..].to_vec(),
+            });
+        }
+
+        let packs = self.packs()?;
+        for pack in packs.iter() {
+            if let Ok(index) = pack
+                .entries
+                .binary_search_by(|(entry_oid, _)| entry_oid.as_slice().cmp(oid))
+            {
+                return self.read_packed_object(pack, pack.entries[index].1, depth);
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("object {} not found", hex_oid),
+        ))
+    }
+
+    fn read_packed_object(&self, pack: &Pack, offset: usize, depth: usize) -> io::Result<Object> {
+        if depth > MAX_DELTA_DEPTH {
+            return Err(invalid_data(format!(
+                "delta chain at pack offset {} is too deep or cyclic",
+                offset
+            )));
+        }
+
+        let end = match pack.offsets.binary_search(&offset) {
+            Ok(index) if index + 1 < pack.offsets.len() => pack.offsets[index + 1],
+            Ok(_) => pack.data.len() - self.hash_len,
+            Err(_) => return Err(invalid_data(format!("no object at pack offset {}", offset))),
+        };
+
+        let data = &pack.data[..end];
+        let mut position = offset;
+        let mut byte = byte_at(data, &mut position)?;
+        let type_id = (byte >> 4) & 0b111;
+        while byte & 0x80 != 0 {
+            // Skip the uncompressed size, which the compressed data already determines.
+            byte = byte_at(data, &mut position)?;
+        }
+
+        let (base, delta) = match type_id {
+            1 | 2 | 3 | 4 => {
+                let kind = match type_id {
+                    1 => ObjectKind::Commit,
+                    2 => ObjectKind::Tree,
+                    3 => ObjectKind::Blob,
+                    _ => ObjectKind::Tag,
+                };
+                let data = inflate(&data[position..])?;
+                return Ok(Object { kind, data });
+            }
+            6 => {
+                let mut byte = byte_at(data, &mut position)?;
+                let mut distance = (byte & 0x7f) as usize;
+                while byte & 0x80 != 0 {
+                    // Each further byte multiplies the distance by at least 128.
+                    if distance > offset >> 7 {
+                        return Err(invalid_data("delta base precedes the pack"));
+                    }
+                    byte = byte_at(data, &mut position)?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as usize;
+                }
+                let base_offset = offset
+                    .checked_sub(distance)
+                    .ok_or_else(|| invalid_data("delta base precedes the pack"))?;
+                if base_offset >= offset {
+                    // Only a base earlier in the pack guarantees that resolving deltas ends.
+                    return Err(invalid_data(format!(
+                        "delta at pack offset {} does not follow its base",
+                        offset
+                    )));
+                }
+                let base = self.read_packed_object(pack, base_offset, depth + 1)?;
+                (base, inflate(&data[position..])?)
+            }
+            7 => {
+                let base_oid = data
+                    .get(position..position + self.hash_len)
+                    .ok_or_else(|| invalid_data("truncated pack"))?;
+                let base = self.read_object_at_depth(base_oid, depth + 1)?;
+                (base, inflate(&data[position + self.hash_len..])?)
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "unknown pack object type {}",
+                    type_id
+                )))
+            }
+        };
+
+        Ok(Object {
+            kind: base.kind,
+            data: apply_delta(&base.data, &delta)?,
+        })
+    }
+
+    fn packs(&self) -> io::Result<Rc<Vec<Pack>>> {
+        if let Some(packs) = self.packs.borrow().as_ref() {
+            return Ok(packs.clone());
+        }
+
+        let mut packs = Vec::new();
+        let pack_dir = format!("{}/objects/pack", self.git_dir);
+        for name in read_dir(&self.fs, &pack_dir)?.unwrap_or_default() {
+            if name.ends_with(".idx") {
+                let index = read_file(&self.fs, &format!("{}/{}", pack_dir, name))?
+                    .ok_or_else(|| invalid_data(format!("pack index {} disappeared", name)))?;
+                let pack_name = format!("{}.pack", name.trim_end_matches(".idx"));
+                let data = read_file(&self.fs, &format!("{}/{}", pack_dir, pack_name))?
+                    .ok_or_else(|| invalid_data(format!("{} has no pack", name)))?;
+                if !data.starts_with(b"PACK") || data.len() < 12 + self.hash_len {
+                    return Err(invalid_data(format!("{} is not a pack", pack_name)));
+                }
+
+                let entries = parse_pack_index(&index, self.hash_len)?;
+                let mut offsets = entries
+                    .iter()
+                    .map(|(_, offset)| *offset)
+                    .collect::<Vec<_>>();
+                offsets.sort_unstable();
+                packs.push(Pack {
+                    data,
+                    entries,
+                    offsets,
+                });
+            }
+        }
+
+        let packs = Rc::new(packs);
+        *self.packs.borrow_mut() = Some(packs.clone());
+        Ok(packs)
+    }
+
+    fn read_commit(&self, oid: &[u8]) -> io::Result<Commit> {
+        let object = self.read_object(oid)?;
+        if object.kind != ObjectKind::Commit {
+            return Err(invalid_data(format!(
+                "{} is not a commit",
+                hex::encode(oid)
+            )));
+        }
+        let data = String::from_utf8_lossy(&object.data);
+        let (headers, message) = match data.find("\n\n") {
+            Some(index) => (&data[..index], &data[index + 2..]),
+            None => (data.as_ref(), ""),
+        };
+
+        let mut tree = None;
+        let mut parent = None;
+        let mut author_name = String::new();
+        let mut author_email = String::new();
+        let mut timestamp = 0;
+        for header in headers.lines() {
+            if header.starts_with("tree ") {
+                tree = Some(parse_hex_oid(&header["tree ".len()..])?);
+            } else if header.starts_with("parent ") && parent.is_none() {
+                parent = Some(parse_hex_oid(&header["parent ".len()..])?);
+            } else if header.starts_with("author ") {
+                // author Name <email> seconds timezone
+                let author = &header["author ".len()..];
+                if let (Some(email_start), Some(email_end)) = (author.find('<'), author.rfind('>'))
+                {
+                    author_name = author[..email_start].trim().to_string();
+                    author_email = author[email_start + 1..email_end].to_string();
+                    timestamp = author[email_end + 1..]
+                        .split_whitespace()
+                        .next()
+                        .and_then(|seconds| seconds.parse().ok())
+                        .unwrap_or(0);
+                }
+            }
+        }
+
+        Ok(Commit {
+            tree: tree.ok_or_else(|| invalid_data("commit has no tree"))?,
+            parent,
+            author_name,
+            author_email,
+            timestamp,
+            message: message.to_string(),
+        })
+    }
+
+    fn read_tree(&self, oid: &[u8]) -> io::Result<Vec<TreeEntry>> {
+        let object = self.read_object(oid)?;
+        if object.kind != ObjectKind::Tree {
+            return Err(invalid_data(format!("{} is not a tree", hex::encode(oid))));
+        }
+
+        let data = object.data;
+        let mut entries = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            let mode_end = position
+                + data[position..]
+                    .iter()
+                    .position(|byte| *byte == b' ')
+                    .ok_or_else(|| invalid_data("truncated tree entry"))?;
+            let name_end = mode_end
+                + data[mode_end..]
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .ok_or_else(|| invalid_data("truncated tree entry"))?;
+            let mode = str::from_utf8(&data[position..mode_end])
+                .ok()
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+                .ok_or_else(|| invalid_data("invalid tree entry mode"))?;
+            let name = String::from_utf8_lossy(&data[mode_end + 1..name_end]).into_owned();
+            let oid = data
+                .get(name_end + 1..name_end + 1 + self.hash_len)
+                .ok_or_else(|| invalid_data("truncated tree entry"))?
+                .to_vec();
+            position = name_end + 1 + self.hash_len;
+            entries.push(TreeEntry { mode, name, oid });
+        }
+        Ok(entries)
+    }
+
+    // Appends the entries beneath a tree in depth-first order. Submodules and symbolic links
+    // aren't represented in work trees, so they are left out.
+    fn push_dir_entries(
+        &self,
+        tree_oid: &[u8],
+        depth: usize,
+        entries: &mut Vec<nano::DirEntry>,
+    ) -> io::Result<()> {
+        for entry in self.read_tree(tree_oid)? {
+            match entry.mode & 0o170000 {
+                TREE_MODE => {
+                    entries.push(nano::DirEntry {
+                        depth,
+                        name: OsString::from(entry.name),
+                        file_type: nano::FileType::Directory,
+                    });
+                    self.push_dir_entries(&entry.oid, depth + 1, entries)?;
+                }
+                SYMLINK_MODE | SUBMODULE_MODE => {}
+                _ => entries.push(nano::DirEntry {
+                    depth,
+                    name: OsString::from(entry.name),
+                    file_type: nano::FileType::Text,
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    // Maps the path of every file beneath a tree to the id of its blob.
+    fn collect_blobs(
+        &self,
+        tree_oid: &[u8],
+        path: &Path,
+        blobs: &mut HashMap<PathBuf, Vec<u8>>,
+    ) -> io::Result<()> {
+        for entry in self.read_tree(tree_oid)? {
+            let entry_path = path.join(&entry.name);
+            match entry.mode & 0o170000 {
+                TREE_MODE => self.collect_blobs(&entry.oid, &entry_path, blobs)?,
+                SYMLINK_MODE | SUBMODULE_MODE => {}
+                _ => {
+                    blobs.insert(entry_path, entry.oid);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_text(&self, commit_oid: &[u8], path: &Path) -> io::Result<String> {
+        let mut oid = self.read_commit(commit_oid)?.tree.as_bytes().to_vec();
+        let mut mode = TREE_MODE;
+        for component in path.components() {
+            let name = match component {
+                Component::Normal(name) => name.to_string_lossy(),
+                _ => return Err(invalid_data(format!("invalid path {}", path.display()))),
+            };
+            if mode & 0o170000 != TREE_MODE {
+                return Err(not_found(path));
+            }
+            let entry = self
+                .read_tree(&oid)?
+                .into_iter()
+                .find(|entry| entry.name == name)
+                .ok_or_else(|| not_found(path))?;
+            oid = entry.oid;
+            mode = entry.mode;
+        }
+
+        let object = self.read_object(&oid)?;
+        if object.kind != ObjectKind::Blob {
+            return Err(invalid_data(format!("{} is not a file", path.display())));
+        }
+        String::from_utf8(object.data).map_err(invalid_data)
+    }
+}
+
+impl nano::GitProvider for Repository {
+    fn base_entries(
+        &self,
+        oid: nano::Oid,
+    ) -> Box<Stream<Item = nano::DirEntry, Error = io::Error>> {
+        let mut entries = Vec::new();
+        let result = self
+            .read_commit(oid.as_bytes())
+            .and_then(|commit| self.push_dir_entries(commit.tree.as_bytes(), 1, &mut entries));
+        match result {
+            Ok(()) => Box::new(stream::iter_ok(entries)),
+            Err(error) => Box::new(stream::once(Err(error))),
+        }
+    }
+
+    fn base_text(
+        &self,
+        oid: nano::Oid,
+        path: &Path,
+    ) -> Box<Future<Item = String, Error = io::Error>> {
+        Box::new(future::result(self.read_text(oid.as_bytes(), path)))
+    }
+
+    fn commit_info(
+        &self,
+        oid: nano::Oid,
+    ) -> Box<Future<Item = nano::CommitInfo, Error = io::Error>> {
+        let result = self.read_commit(oid.as_bytes()).and_then(|commit| {
+            let mut new_blobs = HashMap::new();
+            self.collect_blobs(commit.tree.as_bytes(), Path::new(""), &mut new_blobs)?;
+            let mut old_blobs = HashMap::new();
+            if let Some(parent) = commit.parent {
+                let parent_tree = self.read_commit(parent.as_bytes())?.tree;
+                self.collect_blobs(parent_tree.as_bytes(), Path::new(""), &mut old_blobs)?;
+            }
+
+            let mut changed_paths = new_blobs
+                .iter()
+                .filter(|(path, oid)| old_blobs.get(*path).map_or(false, |old| old != *oid))
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>();
+            changed_paths.sort();
+
+            Ok(nano::CommitInfo {
+                oid,
+                parent: commit.parent,
+                author_name: commit.author_name,
+                author_email: commit.author_email,
+                timestamp: commit.timestamp,
+                message: commit.message,
+                changed_paths,
+            })
+        });
+        Box::new(future::result(result))
+    }
+}
+
+fn read_file(fs: &FileSystem, path: &str) -> io::Result<Option<Vec<u8>>> {
+    fs.read_file(path).map_err(js_error)
+}
+
+fn read_dir(fs: &FileSystem, path: &str) -> io::Result<Option<Vec<String>>> {
+    let names = fs.read_dir(path).map_err(js_error)?;
+    if names.is_null() || names.is_undefined() {
+        return Ok(None);
+    }
+
+    let names = names
+        .dyn_into::<js_sys::Array>()
+        .map_err(|_| invalid_data(format!("entries of {} must be an array", path)))?;
+    let mut entries = Vec::new();
+    for name in names.values() {
+        let name = name.map_err(js_error)?;
+        entries.push(
+            name.as_string()
+                .ok_or_else(|| invalid_data(format!("entries of {} must be strings", path)))?,
+        );
+    }
+    Ok(Some(entries))
+}
+
+fn parse_pack_index(index: &[u8], hash_len: usize) -> io::Result<Vec<(Vec<u8>, usize)>> {
+    if !index.starts_with(&[0xff, b't', b'O', b'c', 0, 0, 0, 2]) {
+        return Err(invalid_data("only version 2 pack indices are supported"));
+    }
+
+    let count = read_u32(index, 8 + 255 * 4)? as usize;
+    let oids_start = 8 + 256 * 4;
+    let offsets_start = oids_start + count * (hash_len + 4);
+    let large_offsets_start = offsets_start + count * 4;
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let oid_start = oids_start + i * hash_len;
+        let oid = index
+            .get(oid_start..oid_start + hash_len)
+            .ok_or_else(|| invalid_data("truncated pack index"))?;
+        let offset = read_u32(index, offsets_start + i * 4)?;
+        let offset = if offset & 0x8000_0000 == 0 {
+            offset as usize
+        } else {
+            let large_offset_start = large_offsets_start + (offset & 0x7fff_ffff) as usize * 8;
+            let high = read_u32(index, large_offset_start)? as u64;
+            let low = read_u32(index, large_offset_start + 4)? as u64;
+            ((high << 32) | low) as usize
+        };
+        entries.push((oid.to_vec(), offset));
+    }
+    Ok(entries)
+}
+
+// Rebuilds an object from its base and a delta, which is a sequence of instructions to either
+// copy a range of the base or insert literal bytes.
+fn apply_delta(base: &[u8], delta: &[u8]) -> io::Result<Vec<u8>> {
+    let mut position = 0;
+    let base_len = read_varint(delta, &mut position)?;
+    let result_len = read_varint(delta, &mut position)?;
+    if base_len != base.len() {
+        return Err(invalid_data("delta doesn't match the length of its base"));
+    }
+
+    let mut result = Vec::with_capacity(result_len);
+    while position < delta.len() {
+        let instruction = byte_at(delta, &mut position)?;
+        if instruction & 0x80 != 0 {
+            let mut offset = 0;
+            for i in 0..4 {
+                if instruction & (1 << i) != 0 {
+                    offset |= (byte_at(delta, &mut position)? as usize) << (8 * i);
+                }
+            }
+            let mut len = 0;
+            for i in 0..3 {
+                if instruction & (0x10 << i) != 0 {
+                    len |= (byte_at(delta, &mut position)? as usize) << (8 * i);
+                }
+            }
+            if len == 0 {
+                len = 0x10000;
+            }
+            let copied = base
+                .get(offset..offset + len)
+                .ok_or_else(|| invalid_data("delta copies past the end of its base"))?;
+            result.extend_from_slice(copied);
+        } else if instruction != 0 {
+            let len = instruction as usize;
+            let inserted = delta
+                .get(position..position + len)
+                .ok_or_else(|| invalid_data("truncated delta"))?;
+            result.extend_from_slice(inserted);
+            position += len;
+        } else {
+            return Err(invalid_data("invalid delta instruction"));
+        }
+    }
+
+    if result.len() == result_len {
+        Ok(result)
+    } else {
+        Err(invalid_data("delta produced the wrong length"))
+    }
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> io::Result<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = byte_at(data, position)?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn read_u32(data: &[u8], position: usize) -> io::Result<u32> {
+    let bytes = data
+        .get(position..position + 4)
+        .ok_or_else(|| invalid_data("truncated pack index"))?;
+    Ok(bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u32))
+}
+
+fn byte_at(data: &[u8], position: &mut usize) -> io::Result<u8> {
+    let byte = *data
+        .get(*position)
+        .ok_or_else(|| invalid_data("unexpected end of object data"))?;
+    *position += 1;
+    Ok(byte)
+}
+
+fn inflate(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    decompress_to_vec_zlib(compressed)
+        .map_err(|status| invalid_data(format!("invalid compressed data: {:?}", status)))
+}
+
+fn object_kind(name: &str) -> io::Result<ObjectKind> {
+    match name {
+        "commit" => Ok(ObjectKind::Commit),
+        "tree" => Ok(ObjectKind::Tree),
+        "blob" => Ok(ObjectKind::Blob),
+        "tag" => Ok(ObjectKind::Tag),
+        _ => Err(invalid_data(format!("unknown object type {:?}", name))),
+    }
+}
+
+fn parse_hex_oid(hex_oid: &str) -> io::Result<nano::Oid> {
+    let bytes = hex::decode(hex_oid.trim()).map_err(invalid_data)?;
+    nano::Oid::from_bytes(&bytes).map_err(|_| invalid_data(format!("invalid oid {}", hex_oid)))
+}
+
+fn js_error(error: wasm_bindgen::JsValue) -> io::Error {
+    let message = error.into_error_message().unwrap_or_else(|message| message);
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
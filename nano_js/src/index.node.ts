@@ -0,0 +1,33 @@
+import * as fs from "fs";
+import { GitRepository } from "./index";
+import { FileSystem, Path } from "./support";
+
+export * from "./index";
+
+const MISSING_PATH_ERRORS = ["ENOENT", "ENOTDIR", "EISDIR"];
+
+export const nodeFileSystem: FileSystem = {
+  readFile(path: Path): Uint8Array | null {
+    try {
+      return fs.readFileSync(path);
+    } catch (error) {
+      if (MISSING_PATH_ERRORS.includes(error.code)) return null;
+      throw error;
+    }
+  },
+
+  readDir(path: Path): string[] | null {
+    try {
+      return fs.readdirSync(path);
+    } catch (error) {
+      if (MISSING_PATH_ERRORS.includes(error.code)) return null;
+      throw error;
+    }
+  }
+};
+
+// Opens the git repository containing the work tree at `path`, or the git
+// directory at `path` itself, through Node's `fs` module.
+export function openLocalRepository(path: Path): Promise<GitRepository> {
+  return GitRepository.open(path, nodeFileSystem);
+}
@@ -1,23 +1,31 @@
 export {
   BaseEntry,
   Change,
+  FileSystem,
   GitProvider,
   FileType,
+  MergePolicy,
   Oid,
   Path,
   Point,
   Range
 } from "./support";
 import {
+  BaseEntry,
   BufferId,
+  Change,
   ChangeObserver,
   ChangeObserverCallback,
   Disposable,
+  FileSystem,
   GitProvider,
   GitProviderWrapper,
   FileType,
+  MergeConflictCallback,
+  MergePolicy,
   Oid,
   Path,
+  Point,
   Range,
   Tagged
 } from "./support";
@@ -34,6 +42,7 @@ async function init() {
 }
 
 export type Version = Tagged<Uint8Array, "Version">;
+export type BufferVersion = Tagged<Uint8Array, "BufferVersion">;
 export type Operation = Tagged<Uint8Array, "Operation">;
 export type EpochId = Tagged<Uint8Array, "EpochId">;
 export type ReplicaId = Tagged<string, "ReplicaId">;
@@ -45,6 +54,96 @@ export interface OperationEnvelope {
   operation(): Operation;
 }
 
+export interface User {
+  readonly id: number;
+  readonly display_name: string;
+  readonly color: number;
+}
+
+export type HunkLine =
+  | { readonly Context: string }
+  | { readonly Removed: string }
+  | { readonly Added: string };
+
+export interface Hunk {
+  readonly old_start: number;
+  readonly old_len: number;
+  readonly new_start: number;
+  readonly new_len: number;
+  readonly lines: ReadonlyArray<HunkLine>;
+}
+
+export interface PatchRejection {
+  readonly path: Path;
+  readonly hunk: null | Hunk;
+  readonly reason: string;
+  readonly found: ReadonlyArray<string>;
+}
+
+export interface PatchApplication {
+  readonly operations: ReadonlyArray<OperationEnvelope>;
+  readonly rejections: ReadonlyArray<PatchRejection>;
+}
+
+export interface BufferStats {
+  readonly fragments: number;
+  readonly tombstones: number;
+  readonly tombstone_bytes: number;
+  readonly visible_bytes: number;
+  readonly insertions: number;
+  readonly insertion_splits: number;
+  readonly deferred_ops: number;
+  readonly version_width: number;
+}
+
+export interface EpochStats {
+  readonly files: number;
+  readonly parent_refs: number;
+  readonly child_refs: number;
+  readonly buffered_files: number;
+  readonly unopened_file_ops: number;
+  readonly deferred_ops: number;
+  readonly version_width: number;
+  readonly buffers: BufferStats;
+}
+
+export interface WorkTreeStats {
+  readonly epoch: EpochStats;
+  readonly future_epoch_ops: number;
+  readonly open_buffers: number;
+  readonly users: number;
+}
+
+export interface Timestamp {
+  readonly value: number;
+  readonly replica_id: ReplicaId;
+}
+
+export interface DeferredOperation {
+  readonly lamport_timestamp: Timestamp;
+  readonly missing: ReadonlyArray<Timestamp>;
+}
+
+export interface OperationRequest {
+  readonly epoch_id: Timestamp;
+  readonly replica_id: ReplicaId;
+  readonly since: number;
+}
+
+// Anchors are opaque. They can be passed back to the buffer they were created
+// in for as long as the work tree stays in the same epoch.
+export type Anchor = Tagged<{}, "Anchor">;
+export type SelectionSetId = Tagged<Timestamp, "SelectionSetId">;
+
+export interface Selection {
+  readonly start: Anchor;
+  readonly end: Anchor;
+  readonly reversed: boolean;
+  readonly goal_column: number | null;
+}
+
+export type SelectionRange = Range & { readonly reversed: boolean };
+
 export enum FileStatus {
   New = "New",
   Renamed = "Renamed",
@@ -63,6 +162,33 @@ export interface Entry {
   readonly visible: boolean;
 }
 
+// Reads base entries and text straight from a git repository, loose objects and
+// packs alike, so no GitProvider needs to be written in JavaScript.
+export class GitRepository implements GitProvider {
+  private repository: any;
+
+  static async open(path: Path, fs: FileSystem): Promise<GitRepository> {
+    await init();
+    return new GitRepository(nano.GitRepository.open(fs, path));
+  }
+
+  private constructor(repository: any) {
+    this.repository = repository;
+  }
+
+  head(): null | Oid {
+    return this.repository.head();
+  }
+
+  baseEntries(oid: Oid): AsyncIterable<BaseEntry> {
+    return this.repository.baseEntries(oid);
+  }
+
+  baseText(oid: Oid, path: Path): Promise<string> {
+    return this.repository.baseText(oid, path);
+  }
+}
+
 export class WorkTree {
   private tree: any;
   private observer: ChangeObserver;
@@ -104,6 +230,26 @@ export class WorkTree {
     return this.tree.head();
   }
 
+  getStats(): WorkTreeStats {
+    return this.tree.stats();
+  }
+
+  getDeferredOperations(): DeferredOperation[] {
+    return this.tree.deferred_ops();
+  }
+
+  getMissingOperations(): OperationRequest[] {
+    return this.tree.missing_operations();
+  }
+
+  operationRequests(): AsyncIterable<OperationRequest> {
+    return this.tree.operation_requests();
+  }
+
+  fixupOperations(): AsyncIterable<OperationEnvelope> {
+    return this.tree.fixup_operations();
+  }
+
   reset(base: Oid | null): AsyncIterable<OperationEnvelope> {
     return this.tree.reset(base);
   }
@@ -120,6 +266,10 @@ export class WorkTree {
     return this.tree.rename(oldPath, newPath);
   }
 
+  copy(oldPath: Path, newPath: Path): OperationEnvelope {
+    return this.tree.copy(oldPath, newPath);
+  }
+
   remove(path: Path): OperationEnvelope {
     return this.tree.remove(path);
   }
@@ -128,14 +278,50 @@ export class WorkTree {
     return this.tree.exists(path);
   }
 
-  entries(options?: { descendInto?: Path[]; showDeleted?: boolean }): Entry[] {
+  applyPatch(patch: string): Promise<PatchApplication> {
+    return this.tree.apply_patch(patch);
+  }
+
+  setUser(user: User): OperationEnvelope {
+    return this.tree.set_user(user);
+  }
+
+  getUser(replicaId: ReplicaId): User | null {
+    return this.tree.user(replicaId);
+  }
+
+  getUsers(): [ReplicaId, User][] {
+    return this.tree.users();
+  }
+
+  setMergePolicy(pattern: string, policy: MergePolicy): OperationEnvelope {
+    return this.tree.set_merge_policy(pattern, policy);
+  }
+
+  getMergePolicy(path: Path): MergePolicy {
+    return this.tree.merge_policy(path);
+  }
+
+  entries(options?: {
+    descendInto?: Path[];
+    showDeleted?: boolean;
+    include?: string[];
+    exclude?: string[];
+    status?: FileStatus[];
+  }): Entry[] {
     let descendInto = null;
     let showDeleted = false;
+    let include = null;
+    let exclude = null;
+    let status = null;
     if (options) {
       if (options.descendInto) descendInto = options.descendInto;
       if (options.showDeleted) showDeleted = options.showDeleted;
+      if (options.include) include = options.include;
+      if (options.exclude) exclude = options.exclude;
+      if (options.status) status = options.status;
     }
-    return this.tree.entries(descendInto, showDeleted);
+    return this.tree.entries(descendInto, showDeleted, include, exclude, status);
   }
 
   async openTextFile(path: Path): Promise<Buffer> {
@@ -176,7 +362,59 @@ export class Buffer {
     return this.observer.onChange(this.id, callback);
   }
 
+  onMergeConflict(callback: MergeConflictCallback): Disposable {
+    return this.observer.onMergeConflict(this.id, callback);
+  }
+
   getDeferredOperationCount(): number {
     return this.tree.buffer_deferred_ops_len(this.id);
   }
+
+  getDeferredOperations(): DeferredOperation[] {
+    return this.tree.buffer_deferred_ops(this.id);
+  }
+
+  getStats(): BufferStats {
+    return this.tree.buffer_stats(this.id);
+  }
+
+  getVersion(): BufferVersion {
+    return this.tree.buffer_version(this.id);
+  }
+
+  getChangesSince(version: BufferVersion): Change[] {
+    return this.tree.changes_since(this.id, version);
+  }
+
+  anchorBeforePoint(point: Point): Anchor {
+    return this.tree.anchor_before_point(this.id, point);
+  }
+
+  anchorAfterPoint(point: Point): Anchor {
+    return this.tree.anchor_after_point(this.id, point);
+  }
+
+  pointForAnchor(anchor: Anchor): Point {
+    return this.tree.point_for_anchor(this.id, anchor);
+  }
+
+  addSelectionSet(userId: number, selections: Selection[]): SelectionSetId {
+    return this.tree.add_selection_set(this.id, userId, selections);
+  }
+
+  replaceSelections(setId: SelectionSetId, selections: Selection[]): void {
+    this.tree.replace_selections(this.id, setId, selections);
+  }
+
+  removeSelectionSet(setId: SelectionSetId): void {
+    this.tree.remove_selection_set(this.id, setId);
+  }
+
+  getSelections(setId: SelectionSetId): Selection[] {
+    return this.tree.selections(this.id, setId);
+  }
+
+  getSelectionRanges(setId: SelectionSetId): SelectionRange[] {
+    return this.tree.selection_ranges(this.id, setId);
+  }
 }
@@ -1,5 +1,7 @@
 #![feature(macros_in_extern)]
 
+mod git;
+
 use bincode;
 use futures::{Async, Future, Poll, Stream};
 use nano_core as nano;
@@ -51,6 +53,9 @@ pub struct WorkTreeNewResult {
 #[wasm_bindgen]
 pub struct OperationEnvelope(nano::OperationEnvelope);
 
+#[wasm_bindgen]
+pub struct GitRepository(git::Repository);
+
 #[derive(Copy, Clone, Serialize, Deserialize)]
 struct EditRange {
     start: nano::Point,
@@ -64,6 +69,13 @@ struct Change {
     text: String,
 }
 
+#[derive(Serialize)]
+struct SelectionRange {
+    start: nano::Point,
+    end: nano::Point,
+    reversed: bool,
+}
+
 #[derive(Serialize)]
 struct Entry {
     #[serde(rename = "type")]
@@ -92,10 +104,22 @@ extern "C" {
     #[wasm_bindgen(method, js_name = baseText)]
     fn base_text(this: &GitProviderWrapper, head: &str, path: &str) -> js_sys::Promise;
 
+    pub type FileSystem;
+
+    // Returning a vector lets the bindings copy the file's bytes into wasm memory in one call.
+    #[wasm_bindgen(method, catch, js_name = readFile)]
+    fn read_file(this: &FileSystem, path: &str) -> Result<Option<Vec<u8>>, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = readDir)]
+    fn read_dir(this: &FileSystem, path: &str) -> Result<JsValue, JsValue>;
+
     pub type ChangeObserver;
 
     #[wasm_bindgen(method, js_name = textChanged)]
     fn text_changed(this: &ChangeObserver, buffer_id: JsValue, changes: JsValue);
+
+    #[wasm_bindgen(method, js_name = mergeConflict)]
+    fn merge_conflict(this: &ChangeObserver, buffer_id: JsValue);
 }
 
 #[wasm_bindgen]
@@ -206,6 +230,13 @@ impl WorkTree {
             .map_err(|e| e.into_js_err())
     }
 
+    pub fn copy(&self, old_path: String, new_path: String) -> Result<OperationEnvelope, JsValue> {
+        self.0
+            .copy(&old_path, &new_path)
+            .map(|operation| OperationEnvelope::new(operation))
+            .map_err(|e| e.into_js_err())
+    }
+
     pub fn remove(&self, path: String) -> Result<OperationEnvelope, JsValue> {
         self.0
             .remove(&path)
@@ -217,6 +248,61 @@ impl WorkTree {
         self.0.exists(&path)
     }
 
+    pub fn apply_patch(&self, patch: String) -> Result<js_sys::Promise, JsValue> {
+        let patch = nano::Patch::parse(&patch).map_err(|e| e.into_js_err())?;
+        Ok(future_to_promise(
+            self.0
+                .apply_patch(&patch)
+                .map(|application| {
+                    let operations = js_sys::Array::new();
+                    for operation in application.operations {
+                        operations.push(&JsValue::from(OperationEnvelope::new(operation)));
+                    }
+                    let result = JsValue::from(js_sys::Object::new());
+                    js_sys::Reflect::set(&result, &JsValue::from_str("operations"), &operations)
+                        .unwrap();
+                    js_sys::Reflect::set(
+                        &result,
+                        &JsValue::from_str("rejections"),
+                        &JsValue::from_serde(&application.rejections).unwrap(),
+                    )
+                    .unwrap();
+                    result
+                })
+                .map_err(|e| e.into_js_err()),
+        ))
+    }
+
+    pub fn set_user(&self, user: JsValue) -> Result<OperationEnvelope, JsValue> {
+        let user = user.into_serde().map_err(|e| e.into_js_err())?;
+        Ok(OperationEnvelope::new(self.0.set_user(user)))
+    }
+
+    pub fn user(&self, replica_id: JsValue) -> Result<JsValue, JsValue> {
+        let replica_id = replica_id.into_serde().map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&self.0.user(replica_id)).map_err(|e| e.into_js_err())
+    }
+
+    pub fn users(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.users()).map_err(|e| e.into_js_err())
+    }
+
+    pub fn set_merge_policy(
+        &self,
+        pattern: String,
+        policy: JsValue,
+    ) -> Result<OperationEnvelope, JsValue> {
+        let policy = policy.into_serde().map_err(|e| e.into_js_err())?;
+        self.0
+            .set_merge_policy(&pattern, policy)
+            .map(|operation| OperationEnvelope::new(operation))
+            .map_err(|e| e.into_js_err())
+    }
+
+    pub fn merge_policy(&self, path: String) -> JsValue {
+        JsValue::from_serde(&self.0.merge_policy(&path)).unwrap()
+    }
+
     pub fn open_text_file(&mut self, path: String) -> js_sys::Promise {
         future_to_promise(
             self.0
@@ -242,6 +328,11 @@ impl WorkTree {
             .map_err(|e| e.into_js_err())
     }
 
+    pub fn export_text(&self, buffer_id: JsValue) -> Result<Vec<u8>, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        self.0.export_text(buffer_id).map_err(|e| e.into_js_err())
+    }
+
     pub fn buffer_deferred_ops_len(&self, buffer_id: JsValue) -> Result<u32, JsValue> {
         let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
         self.0
@@ -250,6 +341,54 @@ impl WorkTree {
             .map_err(|e| e.into_js_err())
     }
 
+    pub fn buffer_deferred_ops(&self, buffer_id: JsValue) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let deferred_ops = self
+            .0
+            .buffer_deferred_ops(buffer_id)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&deferred_ops).map_err(|e| e.into_js_err())
+    }
+
+    pub fn deferred_ops(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.deferred_ops()).map_err(|e| e.into_js_err())
+    }
+
+    pub fn missing_operations(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.missing_operations()).map_err(|e| e.into_js_err())
+    }
+
+    pub fn operation_requests(&self) -> StreamToAsyncIterator {
+        StreamToAsyncIterator::new(
+            self.0
+                .operation_requests()
+                .map(|request| JsValue::from_serde(&request).unwrap())
+                .map_err(|_| JsValue::NULL),
+        )
+    }
+
+    pub fn fixup_operations(&self) -> StreamToAsyncIterator {
+        StreamToAsyncIterator::new(
+            self.0
+                .fixup_operations()
+                .map(|op| JsValue::from(OperationEnvelope::new(op)))
+                .map_err(|_| JsValue::NULL),
+        )
+    }
+
+    pub fn buffer_stats(&self, buffer_id: JsValue) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let stats = self
+            .0
+            .buffer_stats(buffer_id)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&stats).map_err(|e| e.into_js_err())
+    }
+
+    pub fn stats(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&self.0.stats()).map_err(|e| e.into_js_err())
+    }
+
     pub fn edit(
         &self,
         buffer_id: JsValue,
@@ -269,11 +408,177 @@ impl WorkTree {
             .map_err(|e| e.into_js_err())
     }
 
-    pub fn entries(&self, descend_into: JsValue, show_deleted: bool) -> Result<JsValue, JsValue> {
+    pub fn buffer_version(&self, buffer_id: JsValue) -> Result<Vec<u8>, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let version = self
+            .0
+            .buffer_version(buffer_id)
+            .map_err(|e| e.into_js_err())?;
+        Ok(bincode::serialize(&version).unwrap())
+    }
+
+    pub fn changes_since(
+        &self,
+        buffer_id: JsValue,
+        version_bytes: &[u8],
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let version = bincode::deserialize(&version_bytes).map_err(|e| e.into_js_err())?;
+        let changes = self
+            .0
+            .changes_since(buffer_id, version)
+            .map_err(|e| e.into_js_err())?
+            .map(Change::from)
+            .collect::<Vec<_>>();
+        JsValue::from_serde(&changes).map_err(|e| e.into_js_err())
+    }
+
+    pub fn anchor_before_point(
+        &self,
+        buffer_id: JsValue,
+        point: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let point = point.into_serde().map_err(|e| e.into_js_err())?;
+        let anchor = self
+            .0
+            .anchor_before_point(buffer_id, point)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&anchor).map_err(|e| e.into_js_err())
+    }
+
+    pub fn anchor_after_point(
+        &self,
+        buffer_id: JsValue,
+        point: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let point = point.into_serde().map_err(|e| e.into_js_err())?;
+        let anchor = self
+            .0
+            .anchor_after_point(buffer_id, point)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&anchor).map_err(|e| e.into_js_err())
+    }
+
+    pub fn point_for_anchor(
+        &self,
+        buffer_id: JsValue,
+        anchor: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let anchor = anchor.into_serde().map_err(|e| e.into_js_err())?;
+        let point = self
+            .0
+            .point_for_anchor(buffer_id, &anchor)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&point).map_err(|e| e.into_js_err())
+    }
+
+    pub fn add_selection_set(
+        &self,
+        buffer_id: JsValue,
+        user_id: JsValue,
+        selections: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let user_id = user_id.into_serde().map_err(|e| e.into_js_err())?;
+        let selections = selections.into_serde().map_err(|e| e.into_js_err())?;
+        let set_id = self
+            .0
+            .add_selection_set(buffer_id, user_id, selections)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&set_id).map_err(|e| e.into_js_err())
+    }
+
+    pub fn replace_selections(
+        &self,
+        buffer_id: JsValue,
+        set_id: JsValue,
+        selections: JsValue,
+    ) -> Result<(), JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let set_id = set_id.into_serde().map_err(|e| e.into_js_err())?;
+        let selections = selections.into_serde().map_err(|e| e.into_js_err())?;
+        self.0
+            .replace_selections(buffer_id, set_id, selections)
+            .map_err(|e| e.into_js_err())
+    }
+
+    pub fn remove_selection_set(&self, buffer_id: JsValue, set_id: JsValue) -> Result<(), JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let set_id = set_id.into_serde().map_err(|e| e.into_js_err())?;
+        self.0
+            .remove_selection_set(buffer_id, set_id)
+            .map_err(|e| e.into_js_err())
+    }
+
+    pub fn selections(&self, buffer_id: JsValue, set_id: JsValue) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let set_id = set_id.into_serde().map_err(|e| e.into_js_err())?;
+        let selections = self
+            .0
+            .selections(buffer_id, set_id)
+            .map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&selections).map_err(|e| e.into_js_err())
+    }
+
+    pub fn selection_ranges(
+        &self,
+        buffer_id: JsValue,
+        set_id: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let buffer_id = buffer_id.into_serde().map_err(|e| e.into_js_err())?;
+        let set_id = set_id.into_serde().map_err(|e| e.into_js_err())?;
+        let mut ranges = Vec::new();
+        for selection in self
+            .0
+            .selections(buffer_id, set_id)
+            .map_err(|e| e.into_js_err())?
+        {
+            ranges.push(SelectionRange {
+                start: self
+                    .0
+                    .point_for_anchor(buffer_id, &selection.start)
+                    .map_err(|e| e.into_js_err())?,
+                end: self
+                    .0
+                    .point_for_anchor(buffer_id, &selection.end)
+                    .map_err(|e| e.into_js_err())?,
+                reversed: selection.reversed,
+            });
+        }
+        JsValue::from_serde(&ranges).map_err(|e| e.into_js_err())
+    }
+
+    pub fn entries(
+        &self,
+        descend_into: JsValue,
+        show_deleted: bool,
+        include: JsValue,
+        exclude: JsValue,
+        statuses: JsValue,
+    ) -> Result<JsValue, JsValue> {
         let descend_into: Option<HashSet<PathBuf>> =
             descend_into.into_serde().map_err(|e| e.into_js_err())?;
+        let include: Option<Vec<String>> = include.into_serde().map_err(|e| e.into_js_err())?;
+        let exclude: Option<Vec<String>> = exclude.into_serde().map_err(|e| e.into_js_err())?;
+        let statuses: Option<Vec<nano::FileStatus>> =
+            statuses.into_serde().map_err(|e| e.into_js_err())?;
+
+        let mut filter = nano::CursorFilter::new();
+        for pattern in include.unwrap_or_default() {
+            filter = filter.include(nano::Glob::new(&pattern).map_err(|e| e.into_js_err())?);
+        }
+        for pattern in exclude.unwrap_or_default() {
+            filter = filter.exclude(nano::Glob::new(&pattern).map_err(|e| e.into_js_err())?);
+        }
+        for status in statuses.unwrap_or_default() {
+            filter = filter.status(status);
+        }
+
         let mut entries = Vec::new();
-        self.0.with_cursor(|cursor| loop {
+        self.0.with_filtered_cursor(filter, |cursor| loop {
             let entry = cursor.entry().unwrap();
             let mut descend = false;
             if show_deleted || entry.status != nano::FileStatus::Removed {
@@ -348,6 +653,46 @@ impl OperationEnvelope {
     }
 }
 
+#[wasm_bindgen]
+impl GitRepository {
+    pub fn open(fs: FileSystem, path: &str) -> Result<GitRepository, JsValue> {
+        git::Repository::open(fs, path)
+            .map(GitRepository)
+            .map_err(|e| e.into_js_err())
+    }
+
+    pub fn head(&self) -> Result<JsValue, JsValue> {
+        let head = self.0.head().map_err(|e| e.into_js_err())?;
+        JsValue::from_serde(&head.map(HexOid)).map_err(|e| e.into_js_err())
+    }
+
+    #[wasm_bindgen(js_name = baseEntries)]
+    pub fn base_entries(&self, oid: JsValue) -> Result<StreamToAsyncIterator, JsValue> {
+        use nano::GitProvider;
+
+        let oid = oid.into_serde::<HexOid>().map_err(|e| e.into_js_err())?.0;
+        Ok(StreamToAsyncIterator::new(
+            self.0
+                .base_entries(oid)
+                .map(|entry| JsValue::from_serde(&entry).unwrap())
+                .map_err(|error| error.into_js_err()),
+        ))
+    }
+
+    #[wasm_bindgen(js_name = baseText)]
+    pub fn base_text(&self, oid: JsValue, path: String) -> Result<js_sys::Promise, JsValue> {
+        use nano::GitProvider;
+
+        let oid = oid.into_serde::<HexOid>().map_err(|e| e.into_js_err())?.0;
+        Ok(future_to_promise(
+            self.0
+                .base_text(oid, Path::new(&path))
+                .map(|text| JsValue::from_str(&text))
+                .map_err(|error| error.into_js_err()),
+        ))
+    }
+}
+
 impl<T> AsyncIteratorToStream<T> {
     fn new(iterator: AsyncIteratorWrapper) -> Self {
         AsyncIteratorToStream {
@@ -433,7 +778,7 @@ impl nano::GitProvider for GitProviderWrapper {
         &self,
         oid: nano::Oid,
     ) -> Box<Stream<Item = nano::DirEntry, Error = io::Error>> {
-        let iterator = GitProviderWrapper::base_entries(self, &hex::encode(oid));
+        let iterator = GitProviderWrapper::base_entries(self, &oid.to_string());
         Box::new(
             AsyncIteratorToStream::new(iterator)
                 .map_err(|error: String| io::Error::new(io::ErrorKind::Other, error)),
@@ -448,7 +793,7 @@ impl nano::GitProvider for GitProviderWrapper {
         Box::new(
             JsFuture::from(GitProviderWrapper::base_text(
                 self,
-                &hex::encode(oid),
+                &oid.to_string(),
                 path.to_string_lossy().as_ref(),
             ))
             .then(|value| match value {
@@ -464,19 +809,27 @@ impl nano::GitProvider for GitProviderWrapper {
 
 impl nano::ChangeObserver for ChangeObserver {
     fn text_changed(&self, buffer_id: nano::BufferId, changes: Box<Iterator<Item = nano::Change>>) {
-        let changes = changes
-            .map(|change| Change {
-                start: change.range.start,
-                end: change.range.end,
-                text: String::from_utf16_lossy(&change.code_units),
-            })
-            .collect::<Vec<_>>();
+        let changes = changes.map(Change::from).collect::<Vec<_>>();
         ChangeObserver::text_changed(
             self,
             JsValue::from_serde(&buffer_id).unwrap(),
             JsValue::from_serde(&changes).unwrap(),
         );
     }
+
+    fn merge_conflict(&self, buffer_id: nano::BufferId, _conflict: nano::MergeConflict) {
+        ChangeObserver::merge_conflict(self, JsValue::from_serde(&buffer_id).unwrap());
+    }
+}
+
+impl From<nano::Change> for Change {
+    fn from(change: nano::Change) -> Self {
+        Change {
+            start: change.range.start,
+            end: change.range.end,
+            text: change.text,
+        }
+    }
 }
 
 impl Serialize for HexOid {
@@ -484,7 +837,7 @@ impl Serialize for HexOid {
     where
         S: Serializer,
     {
-        hex::encode(self.0).serialize(serializer)
+        self.0.to_string().serialize(serializer)
     }
 }
 
@@ -496,16 +849,14 @@ impl<'de> Deserialize<'de> for HexOid {
         use serde::de::Error;
         let hex_string = String::deserialize(deserializer)?;
         let bytes = hex::decode(&hex_string).map_err(Error::custom)?;
-        let mut oid = nano::Oid::default();
-        if oid.len() == bytes.len() {
-            oid.copy_from_slice(&bytes);
-            Ok(HexOid(oid))
-        } else {
-            Err(D::Error::custom(format!(
-                "{} cannot be parsed as a valid object id. pass a full 40-character hex string.",
+        let oid = nano::Oid::from_bytes(&bytes).map_err(|_| {
+            D::Error::custom(format!(
+                "{} cannot be parsed as a valid object id. pass a full 40-character (SHA-1) or \
+                 64-character (SHA-256) hex string.",
                 hex_string
-            )))
-        }
+            ))
+        })?;
+        Ok(HexOid(oid))
     }
 }
 
@@ -17,11 +17,24 @@ export enum FileType {
   Text = "Text"
 }
 
+export enum MergePolicy {
+  Merge = "Merge",
+  LastWriterWins = "LastWriterWins",
+  RejectConcurrent = "RejectConcurrent"
+}
+
 export interface GitProvider {
   baseEntries(oid: Oid): AsyncIterable<BaseEntry>;
   baseText(oid: Oid, path: Path): Promise<string>;
 }
 
+// A synchronous view of a file system, used to read a git repository from the
+// Rust side. Both methods return null for paths that don't exist.
+export interface FileSystem {
+  readFile(path: Path): Uint8Array | null;
+  readDir(path: Path): string[] | null;
+}
+
 export class GitProviderWrapper {
   private git: GitProvider;
 
@@ -53,6 +66,7 @@ export class AsyncIteratorWrapper<T> {
 }
 
 export type ChangeObserverCallback = (changes: ReadonlyArray<Change>) => void;
+export type MergeConflictCallback = () => void;
 
 export class ChangeObserver {
   emitter: Emitter;
@@ -65,9 +79,20 @@ export class ChangeObserver {
     return this.emitter.on(`buffer-${bufferId}-change`, callback);
   }
 
+  onMergeConflict(
+    bufferId: BufferId,
+    callback: MergeConflictCallback
+  ): Disposable {
+    return this.emitter.on(`buffer-${bufferId}-merge-conflict`, callback);
+  }
+
   textChanged(bufferId: BufferId, changes: Change[]) {
     this.emitter.emit(`buffer-${bufferId}-change`, changes);
   }
+
+  mergeConflict(bufferId: BufferId) {
+    this.emitter.emit(`buffer-${bufferId}-merge-conflict`, null);
+  }
 }
 
 export interface Disposable {
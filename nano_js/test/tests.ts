@@ -12,9 +12,16 @@ import {
   ReplicaId,
   WorkTree
 } from "../src/index";
+import { openLocalRepository } from "../src/index.node";
 import * as assert from "assert";
+import { execFileSync } from "child_process";
+import * as crypto from "crypto";
+import * as fs from "fs";
+import * as os from "os";
+import * as path from "path";
 import * as uuid from "uuid/v4";
 import * as uuidParse from "uuid-parse";
+import * as zlib from "zlib";
 
 suite("WorkTree", () => {
   test("basic API interaction", async () => {
@@ -189,6 +196,34 @@ suite("WorkTree", () => {
         }
       ]
     );
+    assert.deepEqual(
+      tree1.entries({
+        showDeleted: true,
+        status: [FileStatus.Modified, FileStatus.Removed]
+      }),
+      [
+        {
+          depth: 3,
+          type: FileType.Text,
+          name: "c",
+          path: "a/b/c",
+          status: FileStatus.Modified,
+          visible: true
+        },
+        {
+          depth: 3,
+          type: FileType.Directory,
+          name: "d",
+          path: "a/b/d",
+          status: FileStatus.Removed,
+          visible: false
+        }
+      ]
+    );
+    assert.deepEqual(
+      tree1.entries({ include: ["a/**"], exclude: ["c", "x"] }).map(e => e.path),
+      ["a/b"]
+    );
     assert(tree1.exists("a/b/x"));
     assert(!tree1.exists("a/b/d"));
 
@@ -347,6 +382,54 @@ suite("WorkTree", () => {
     assert.strictEqual(buffer1ChangeCount, 1);
   });
 
+  test("anchors, selections and changes since a version", async () => {
+    const OID = "0".repeat(40);
+    const git = new TestGitProvider();
+    git.commit(OID, [
+      { depth: 1, name: "a", type: FileType.Text, text: "abc\ndef" }
+    ]);
+
+    const [tree1, initOps1] = await WorkTree.create(uuid(), OID, [], git);
+    const [tree2, initOps2] = await WorkTree.create(
+      uuid(),
+      OID,
+      await collectOps(initOps1),
+      git
+    );
+    tree1.applyOps(await collectOps(initOps2));
+
+    const buffer1 = await tree1.openTextFile("a");
+    const buffer2 = await tree2.openTextFile("a");
+    const start = buffer1.anchorBeforePoint(point(0, 1));
+    const end = buffer1.anchorAfterPoint(point(1, 2));
+    const setId = buffer1.addSelectionSet(1, [
+      { start, end, reversed: true, goal_column: null }
+    ]);
+    const version = buffer1.getVersion();
+
+    tree1.applyOps([
+      buffer2.edit([{ start: point(0, 0), end: point(0, 0) }], "xy\n").operation()
+    ]);
+    assert.deepEqual(buffer1.getChangesSince(version), [
+      { start: point(0, 0), end: point(0, 0), text: "xy\n" }
+    ]);
+    assert.deepEqual(buffer1.pointForAnchor(start), point(1, 1));
+    assert.deepEqual(buffer1.getSelectionRanges(setId), [
+      { start: point(1, 1), end: point(2, 2), reversed: true }
+    ]);
+
+    const cursor = buffer1.anchorBeforePoint(point(2, 3));
+    buffer1.replaceSelections(setId, [
+      { start: cursor, end: cursor, reversed: false, goal_column: null }
+    ]);
+    assert.deepEqual(buffer1.getSelections(setId), [
+      { start: cursor, end: cursor, reversed: false, goal_column: null }
+    ]);
+
+    buffer1.removeSelectionSet(setId);
+    assert.throws(() => buffer1.getSelections(setId), /InvalidSelectionSetId/);
+  });
+
   test("throwing error when retrieving base entries", async () => {
     const git = {
       async *baseEntries(): AsyncIterable<BaseEntry> {
@@ -381,6 +464,156 @@ suite("WorkTree", () => {
   });
 });
 
+suite("GitRepository", () => {
+  test("reading a local repository", async () => {
+    const repoPath = fs.mkdtempSync(path.join(os.tmpdir(), "nano-git-"));
+    const git = (...args: string[]) =>
+      execFileSync("git", args, { cwd: repoPath, encoding: "utf8" }).trim();
+    git("init", "--quiet");
+    git("config", "user.name", "Test");
+    git("config", "user.email", "test@example.com");
+    fs.mkdirSync(path.join(repoPath, "a"));
+    fs.mkdirSync(path.join(repoPath, "a", "b"));
+    fs.writeFileSync(
+      path.join(repoPath, "a", "b", "c"),
+      "abc\n".repeat(100)
+    );
+    fs.writeFileSync(path.join(repoPath, "d"), "d\n");
+    git("add", "--all");
+    git("commit", "--quiet", "--message", "first");
+    fs.writeFileSync(
+      path.join(repoPath, "a", "b", "c"),
+      "abc\n".repeat(101)
+    );
+    git("commit", "--quiet", "--all", "--message", "second");
+    const head = git("rev-parse", "HEAD");
+
+    const expectedEntries = [
+      { depth: 1, name: "a", type: FileType.Directory },
+      { depth: 2, name: "b", type: FileType.Directory },
+      { depth: 3, name: "c", type: FileType.Text },
+      { depth: 1, name: "d", type: FileType.Text }
+    ];
+
+    const looseRepo = await openLocalRepository(repoPath);
+    assert.strictEqual(looseRepo.head(), head);
+    assert.deepStrictEqual(
+      await collect(looseRepo.baseEntries(head)),
+      expectedEntries
+    );
+    assert.strictEqual(
+      await looseRepo.baseText(head, "a/b/c"),
+      "abc\n".repeat(101)
+    );
+    await assert.rejects(looseRepo.baseText(head, "a/e"), /does not exist/);
+
+    // Packing stores the second version of "a/b/c" as a delta.
+    git("repack", "-a", "-d", "--quiet");
+    git("pack-refs", "--all");
+    const packedRepo = await openLocalRepository(repoPath);
+    assert.strictEqual(packedRepo.head(), head);
+    assert.deepStrictEqual(
+      await collect(packedRepo.baseEntries(head)),
+      expectedEntries
+    );
+    assert.strictEqual(
+      await packedRepo.baseText(head, "a/b/c"),
+      "abc\n".repeat(101)
+    );
+
+    const [tree, initOps] = await WorkTree.create(
+      uuid(),
+      head,
+      [],
+      packedRepo
+    );
+    await collectOps(initOps);
+    const buffer = await tree.openTextFile("d");
+    assert.strictEqual(buffer.getText(), "d\n");
+
+    // A delta that names itself as its base is rejected rather than resolved
+    // forever.
+    const packDir = path.join(repoPath, ".git", "objects", "pack");
+    const packName = fs
+      .readdirSync(packDir)
+      .find(name => name.endsWith(".pack"))!;
+    const packPath = path.join(packDir, packName);
+    const [deltaOid, , , , deltaOffset] = git("verify-pack", "-v", packPath)
+      .split("\n")
+      .map(line => line.split(/\s+/))
+      .find(fields => fields.length === 7)!;
+    const deltaCommit =
+      git("rev-parse", "HEAD:a/b/c") === deltaOid
+        ? head
+        : git("rev-parse", "HEAD~1");
+    const pack = fs.readFileSync(packPath);
+    let position = Number(deltaOffset);
+    while (pack[position] & 0x80) position++;
+    pack[position + 1] = 0;
+    fs.chmodSync(packPath, 0o644);
+    fs.writeFileSync(packPath, pack);
+    const corruptRepo = await openLocalRepository(repoPath);
+    await assert.rejects(
+      corruptRepo.baseText(deltaCommit, "a/b/c"),
+      /does not follow its base/
+    );
+  });
+
+  test("reading a thin pack whose deltas refer to each other", async () => {
+    const repoPath = fs.mkdtempSync(path.join(os.tmpdir(), "nano-git-"));
+    const packDir = path.join(repoPath, "objects", "pack");
+    fs.mkdirSync(path.join(repoPath, "objects"));
+    fs.mkdirSync(packDir);
+    fs.writeFileSync(path.join(repoPath, "HEAD"), "ref: refs/heads/master\n");
+
+    // Object 1 is a delta against itself, while objects 2 and 3 are deltas
+    // against each other. None of them can be resolved.
+    const oid = (byte: number) => Buffer.alloc(20, byte);
+    const baseOids = [oid(1), oid(3), oid(2)];
+    const delta = zlib.deflateSync(Buffer.from([0, 1, 1, "x".charCodeAt(0)]));
+    const objects = baseOids.map(baseOid =>
+      Buffer.concat([Buffer.from([0x74]), baseOid, delta])
+    );
+    const header = Buffer.from("PACK\0\0\0\x02\0\0\0\x03", "binary");
+    const packData = Buffer.concat([header, ...objects]);
+    const pack = Buffer.concat([
+      packData,
+      crypto.createHash("sha1").update(packData).digest()
+    ]);
+
+    const offsets = objects.map((_, i) =>
+      objects.slice(0, i).reduce((offset, object) => offset + object.length, 12)
+    );
+    const fanout = Buffer.alloc(256 * 4);
+    for (let byte = 0; byte < 256; byte++) {
+      fanout.writeUInt32BE(Math.min(byte, 3), byte * 4);
+    }
+    const offsetTable = Buffer.alloc(3 * 4);
+    offsets.forEach((offset, i) => offsetTable.writeUInt32BE(offset, i * 4));
+    const index = Buffer.concat([
+      Buffer.from([0xff, 0x74, 0x4f, 0x63, 0, 0, 0, 2]),
+      fanout,
+      oid(1),
+      oid(2),
+      oid(3),
+      Buffer.alloc(3 * 4),
+      offsetTable,
+      pack.slice(pack.length - 20),
+      Buffer.alloc(20)
+    ]);
+    fs.writeFileSync(path.join(packDir, "pack-test.pack"), pack);
+    fs.writeFileSync(path.join(packDir, "pack-test.idx"), index);
+
+    const repo = await openLocalRepository(repoPath);
+    for (const byte of [1, 2, 3]) {
+      await assert.rejects(
+        repo.baseText(oid(byte).toString("hex"), "a"),
+        /too deep or cyclic/
+      );
+    }
+  });
+});
+
 type BaseEntry =
   | NanoBaseEntry & { type: FileType.Directory }
   | NanoBaseEntry & { type: FileType.Text; text: string };
@@ -18,7 +18,7 @@ const baseConfig = {
 
 const libConfig = {
   ...baseConfig,
-  entry: "./src/index.ts",
+  entry: "./src/index.node.ts",
   mode: "production",
   devtool: "source-map",
   output: {